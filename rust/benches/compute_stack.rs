@@ -0,0 +1,151 @@
+/*!
+ * Measures how much recomputing a three-level graph allocates: 1,000 cells, read by 100
+ * deriveds, read by 10 deriveds at the top. Every iteration writes to every cell and reads the
+ * top of the graph again, so every derived recomputes.
+ *
+ * Besides the timings, each benchmark prints the allocations made while reading the top of the
+ * graph on a fresh thread, the first time the graph is computed and once the compute stack has
+ * storage to reuse. The writes to the cells aren't counted, and what a recompute still
+ * allocates is mostly the record of what invalidated each derived.
+ *
+ * Run with `cargo bench --bench compute_stack -- --nocapture` to see the allocation counts.
+ */
+
+#![feature(test)]
+
+extern crate test;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use everafter::{Cell, ComputeStack, Derived, Timeline};
+use test::{black_box, Bencher};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const WIDTH: usize = 10;
+
+struct Graph {
+    // keeps the timeline alive for the length of the benchmark
+    _timeline: Timeline,
+    cells: Vec<Cell<usize>>,
+    tops: Vec<Derived<usize>>,
+    value: usize,
+}
+
+impl Graph {
+    fn new() -> Graph {
+        let timeline = Timeline::new();
+
+        let cells: Vec<Cell<usize>> = (0..WIDTH * WIDTH * WIDTH)
+            .map(|i| timeline.cell(i))
+            .collect();
+
+        let middles: Vec<Derived<usize>> = cells
+            .chunks(WIDTH)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+                timeline.derived(move || chunk.iter().map(Cell::get).sum())
+            })
+            .collect();
+
+        let tops: Vec<Derived<usize>> = middles
+            .chunks(WIDTH)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+                timeline.derived(move || chunk.iter().map(Derived::get).sum())
+            })
+            .collect();
+
+        Graph {
+            _timeline: timeline,
+            cells,
+            tops,
+            value: 0,
+        }
+    }
+
+    fn recompute(&mut self) -> usize {
+        self.value += 1;
+
+        for cell in &self.cells {
+            cell.set(self.value);
+        }
+
+        self.tops.iter().map(Derived::get).sum()
+    }
+}
+
+fn counted<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    let result = f();
+    (result, ALLOCATIONS.load(Ordering::SeqCst) - before)
+}
+
+fn report(name: &str, graph: &mut Graph) {
+    let (_, first) = counted(|| black_box(graph.tops.iter().map(Derived::get).sum::<usize>()));
+
+    // the first recompute hands the storage of the first computation's dependencies to the pool
+    graph.recompute();
+
+    graph.value += 1;
+
+    for cell in &graph.cells {
+        cell.set(graph.value);
+    }
+
+    let (_, again) = counted(|| black_box(graph.tops.iter().map(Derived::get).sum::<usize>()));
+
+    eprintln!(
+        "{}: {} allocations to compute the graph, {} to recompute it",
+        name, first, again
+    );
+}
+
+#[bench]
+fn recompute(b: &mut Bencher) {
+    thread::spawn(|| report("recompute", &mut Graph::new()))
+        .join()
+        .unwrap();
+
+    let mut graph = Graph::new();
+    b.iter(|| black_box(graph.recompute()));
+}
+
+#[bench]
+fn recompute_reserved(b: &mut Bencher) {
+    thread::spawn(|| {
+        ComputeStack::reserve(WIDTH * WIDTH + WIDTH);
+        report("recompute_reserved", &mut Graph::new())
+    })
+    .join()
+    .unwrap();
+
+    ComputeStack::reserve(WIDTH * WIDTH + WIDTH);
+    let mut graph = Graph::new();
+    b.iter(|| black_box(graph.recompute()));
+}
@@ -0,0 +1,181 @@
+/*!
+ * Times the paths every read and write goes through: reading a cell inside and outside a
+ * `ComputeStack` frame, reading a derived that is up to date, recomputing one, and propagating a
+ * write through graphs of different shapes built with the `everafter::bench` helpers. The
+ * `map_*` benchmarks do the same as the `derived_*` ones with `ReactiveValue::map`, which has no
+ * dependencies of its own to record and validate.
+ *
+ * Each `propagate_*` benchmark writes a new value to every input and reads every output, so every
+ * derived in the graph validates and recomputes. Each `validate_*` benchmark only reads the
+ * outputs, so it measures how long it takes to find out that nothing changed.
+ *
+ * Run with `cargo bench --features bench-helpers --bench hot_paths`. A baseline, in ns/iter:
+ *
+ * ```text
+ * cell_read_untracked      5,580    (100 reads)
+ * cell_read_tracked        6,482    (100 reads in one frame)
+ * derived_read_clean          70
+ * derived_recompute          757
+ * map_read_clean              53
+ * map_recompute              253
+ *
+ *                      propagate   validate
+ * chain (100)            118,420         72
+ * chain (1,000)        1,454,804         72
+ * fan-out (1 → 100)       68,664      6,679
+ * fan-in (100 → 1)        30,069         69
+ * diamond (10 × 10)      134,166        709
+ * ```
+ *
+ * Propagating through a chain grows linearly with its length. It used to grow with the square of
+ * the length, because every level copied the invalidation chain of the level below it and
+ * searched the whole `ComputeStack` for cycles.
+ */
+
+#![feature(test)]
+
+extern crate test;
+
+use everafter::{bench::Graph, ComputeStack, ReactiveValue, Timeline};
+use test::{black_box, Bencher};
+
+const READS: usize = 100;
+
+#[bench]
+fn cell_read_untracked(b: &mut Bencher) {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1u64);
+
+    b.iter(|| (0..READS).map(|_| black_box(cell.get())).sum::<u64>());
+}
+
+#[bench]
+fn cell_read_tracked(b: &mut Bencher) {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1u64);
+
+    b.iter(|| ComputeStack::track(|| (0..READS).map(|_| black_box(cell.get())).sum::<u64>()));
+}
+
+#[bench]
+fn derived_read_clean(b: &mut Bencher) {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1u64);
+
+    let derived = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() + 1)
+    };
+
+    derived.get();
+    b.iter(|| black_box(derived.get()));
+}
+
+#[bench]
+fn derived_recompute(b: &mut Bencher) {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1u64);
+
+    let derived = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() + 1)
+    };
+
+    let mut value = 0;
+    b.iter(|| {
+        value += 1;
+        cell.set(value);
+        black_box(derived.get())
+    });
+}
+
+#[bench]
+fn map_read_clean(b: &mut Bencher) {
+    let timeline = Timeline::new();
+    let mapped = timeline.cell(1u64).map(|value: &u64| value + 1);
+
+    mapped.get();
+    b.iter(|| black_box(mapped.get()));
+}
+
+#[bench]
+fn map_recompute(b: &mut Bencher) {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1u64);
+    let mapped = cell.clone().map(|value: &u64| value + 1);
+
+    let mut value = 0;
+    b.iter(|| {
+        value += 1;
+        cell.set(value);
+        black_box(mapped.get())
+    });
+}
+
+fn propagate(b: &mut Bencher, graph: Graph) {
+    graph.read_outputs();
+
+    let mut value = 0;
+    b.iter(|| {
+        value += 1;
+        graph.set_inputs(value);
+        black_box(graph.read_outputs())
+    });
+}
+
+fn validate(b: &mut Bencher, graph: Graph) {
+    graph.set_inputs(1);
+    graph.read_outputs();
+
+    b.iter(|| black_box(graph.read_outputs()));
+}
+
+#[bench]
+fn propagate_chain_1k(b: &mut Bencher) {
+    propagate(b, Timeline::new().build_chain(1000));
+}
+
+#[bench]
+fn propagate_chain(b: &mut Bencher) {
+    propagate(b, Timeline::new().build_chain(100));
+}
+
+#[bench]
+fn propagate_fan_out(b: &mut Bencher) {
+    propagate(b, Timeline::new().build_fan_out(100));
+}
+
+#[bench]
+fn propagate_fan_in(b: &mut Bencher) {
+    propagate(b, Timeline::new().build_fan_in(100));
+}
+
+#[bench]
+fn propagate_diamond(b: &mut Bencher) {
+    propagate(b, Timeline::new().build_diamond(10, 10));
+}
+
+#[bench]
+fn validate_chain_1k(b: &mut Bencher) {
+    validate(b, Timeline::new().build_chain(1000));
+}
+
+#[bench]
+fn validate_chain(b: &mut Bencher) {
+    validate(b, Timeline::new().build_chain(100));
+}
+
+#[bench]
+fn validate_fan_out(b: &mut Bencher) {
+    validate(b, Timeline::new().build_fan_out(100));
+}
+
+#[bench]
+fn validate_fan_in(b: &mut Bencher) {
+    validate(b, Timeline::new().build_fan_in(100));
+}
+
+#[bench]
+fn validate_diamond(b: &mut Bencher) {
+    validate(b, Timeline::new().build_diamond(10, 10));
+}
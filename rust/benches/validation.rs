@@ -0,0 +1,79 @@
+/*!
+ * Compares `Derived::is_stale` in lazy and eager validation mode on a graph of 10,000 cells,
+ * read by 100 deriveds that are themselves read by one derived at the top.
+ *
+ * Run with `cargo bench --bench validation`.
+ */
+
+#![feature(test)]
+
+extern crate test;
+
+use everafter::{Cell, Derived, Timeline, ValidationMode};
+use test::{black_box, Bencher};
+
+const WIDTH: usize = 100;
+
+struct Graph {
+    // keeps the timeline alive for the length of the benchmark
+    _timeline: Timeline,
+    cells: Vec<Cell<usize>>,
+    top: Derived<usize>,
+}
+
+fn graph(mode: ValidationMode) -> Graph {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(mode);
+
+    let cells: Vec<Cell<usize>> = (0..WIDTH * WIDTH).map(|i| timeline.cell(i)).collect();
+    let rows: Vec<Derived<usize>> = cells
+        .chunks(WIDTH)
+        .map(|row| {
+            let row = row.to_vec();
+            timeline.derived(move || row.iter().map(Cell::get).sum())
+        })
+        .collect();
+
+    let top = timeline.derived(move || rows.iter().map(Derived::get).sum());
+    top.get();
+
+    Graph {
+        _timeline: timeline,
+        cells,
+        top,
+    }
+}
+
+#[bench]
+fn is_stale_lazy(b: &mut Bencher) {
+    let graph = graph(ValidationMode::Lazy);
+    b.iter(|| black_box(graph.top.is_stale()));
+}
+
+#[bench]
+fn is_stale_eager(b: &mut Bencher) {
+    let graph = graph(ValidationMode::Eager);
+    b.iter(|| black_box(graph.top.is_stale()));
+}
+
+#[bench]
+fn write_lazy(b: &mut Bencher) {
+    let graph = graph(ValidationMode::Lazy);
+    let mut value = 0;
+
+    b.iter(|| {
+        value += 1;
+        graph.cells[0].set(value);
+    });
+}
+
+#[bench]
+fn write_eager(b: &mut Bencher) {
+    let graph = graph(ValidationMode::Eager);
+    let mut value = 0;
+
+    b.iter(|| {
+        value += 1;
+        graph.cells[0].set(value);
+    });
+}
@@ -0,0 +1,70 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{spanned::Spanned, Error, FnArg, ImplItemMethod, Result, ReturnType, Visibility};
+
+pub(super) fn expand(args: TokenStream, method: ImplItemMethod) -> Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(Error::new(args.span(), "#[cached] doesn't take arguments"));
+    }
+
+    let sig = &method.sig;
+
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => {
+            return Err(Error::new(
+                sig.span(),
+                "#[cached] methods must take `&self`",
+            ))
+        }
+    }
+
+    if let Some(argument) = sig.inputs.iter().nth(1) {
+        return Err(Error::new(
+            argument.span(),
+            "#[cached] methods are cached once per value, so they can't take arguments besides \
+             `&self`",
+        ));
+    }
+
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new(
+            sig.generics.span(),
+            "#[cached] methods can't be generic",
+        ));
+    }
+
+    if let ReturnType::Default = sig.output {
+        return Err(Error::new(
+            sig.span(),
+            "#[cached] methods must return the value to cache",
+        ));
+    }
+
+    let name = &sig.ident;
+    let uncached_name = format_ident!("__everafter_uncached_{}", name);
+
+    let mut uncached = method.clone();
+    uncached.attrs = vec![];
+    uncached.vis = Visibility::Inherited;
+    uncached.sig.ident = uncached_name.clone();
+
+    let attrs = &method.attrs;
+    let vis = &method.vis;
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            self.__everafter_cached_methods()
+                .derived(stringify!(#name), || {
+                    let this = ::std::clone::Clone::clone(self);
+                    move || this.#uncached_name()
+                })
+                .get()
+        }
+
+        #[doc(hidden)]
+        #uncached
+    })
+}
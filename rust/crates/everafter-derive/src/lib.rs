@@ -0,0 +1,91 @@
+/*!
+ * `#[derive(Reactive)]` and `#[cached]`, which generate the `Cell` and `Derived` plumbing of a
+ * struct of reactive values. The generated code only uses `everafter`'s public API.
+ *
+ * Fields marked `#[track]` are declared as `Cell<T>`, and get a tracked getter that returns the
+ * value and a setter that writes it. Methods marked `#[cached]` are memoized as deriveds, one per
+ * value, which are stored in the struct's `CachedMethods` field.
+ *
+ * ```
+ * use everafter::{CachedMethods, Cell, Timeline};
+ * use everafter_derive::{cached, Reactive};
+ *
+ * #[derive(Clone, Reactive)]
+ * struct Person {
+ *     #[track]
+ *     first: Cell<String>,
+ *     #[track]
+ *     last: Cell<String>,
+ *     cached: CachedMethods,
+ * }
+ *
+ * impl Person {
+ *     #[cached]
+ *     fn full_name(&self) -> String {
+ *         format!("{} {}", self.first(), self.last())
+ *     }
+ * }
+ *
+ * let timeline = Timeline::new();
+ * let person = Person {
+ *     first: timeline.cell("Yehuda".to_string()),
+ *     last: timeline.cell("Katz".to_string()),
+ *     cached: timeline.cached_methods(),
+ * };
+ *
+ * assert_eq!(person.full_name(), "Yehuda Katz");
+ *
+ * person.set_first("Y.".to_string());
+ * assert_eq!(person.full_name(), "Y. Katz");
+ * ```
+ *
+ * Tracked values are stored in the timeline, so they can't borrow anything:
+ *
+ * ```compile_fail
+ * use everafter::Cell;
+ * use everafter_derive::Reactive;
+ *
+ * #[derive(Reactive)]
+ * struct Borrowed<'a> {
+ *     #[track]
+ *     name: Cell<&'a str>,
+ * }
+ * ```
+ */
+
+mod cached;
+mod reactive;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ImplItemMethod};
+
+/**
+ * Generate a getter and a setter for every `#[track]` field, and give `#[cached]` methods access
+ * to the struct's `CachedMethods` field, if it has one.
+ *
+ * The getter of a field `name: Cell<T>` is `fn name(&self) -> T`, which reads the cell with
+ * `Cell::get`. The setter is `fn set_name(&self, value: T)`, which writes it with `Cell::set`.
+ * Cells are written through shared references like everywhere else, so setters take `&self`.
+ */
+#[proc_macro_derive(Reactive, attributes(track))]
+pub fn derive_reactive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    reactive::expand(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/**
+ * Memoize a `&self` method as a derived. The derived is created the first time the method is
+ * called on a value, and holds a clone of the value, so the struct has to be `Clone + 'static`
+ * and derive `Reactive` with a `CachedMethods` field.
+ */
+#[proc_macro_attribute]
+pub fn cached(args: TokenStream, input: TokenStream) -> TokenStream {
+    let method = parse_macro_input!(input as ImplItemMethod);
+
+    cached::expand(args.into(), method)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
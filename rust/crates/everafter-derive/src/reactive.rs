@@ -0,0 +1,151 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    spanned::Spanned, Data, DataStruct, DeriveInput, Error, Fields, GenericArgument, Ident,
+    PathArguments, PathSegment, Result, Type,
+};
+
+pub(super) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "Reactive can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let mut accessors = vec![];
+    let mut cached_methods: Option<&Ident> = None;
+
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named fields have names");
+
+        if last_segment(&field.ty).is_some_and(|segment| segment.ident == "CachedMethods") {
+            if cached_methods.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "a Reactive struct can only have one CachedMethods field",
+                ));
+            }
+
+            cached_methods = Some(ident);
+        }
+
+        if !field.attrs.iter().any(|attr| attr.path.is_ident("track")) {
+            continue;
+        }
+
+        let value = cell_value(&field.ty).ok_or_else(|| {
+            Error::new(
+                field.ty.span(),
+                format!(
+                    "#[track] fields must be declared as `Cell<T>`, but `{}` isn't a `Cell`",
+                    ident
+                ),
+            )
+        })?;
+
+        if let Some(lifetime) = borrowed_lifetime(value.to_token_stream()) {
+            return Err(Error::new(
+                lifetime.span(),
+                format!(
+                    "the #[track] field `{}` borrows for `'{}`, but tracked values must be \
+                     'static because they are stored in the timeline",
+                    ident, lifetime
+                ),
+            ));
+        }
+
+        let vis = &field.vis;
+        let setter = format_ident!("set_{}", ident);
+
+        accessors.push(quote! {
+            #vis fn #ident(&self) -> #value {
+                self.#ident.get()
+            }
+
+            #vis fn #setter(&self, value: #value) {
+                self.#ident.set(value)
+            }
+        });
+    }
+
+    let cached_methods = cached_methods.map(|field| {
+        quote! {
+            #[doc(hidden)]
+            fn __everafter_cached_methods(&self) -> &::everafter::CachedMethods {
+                &self.#field
+            }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#accessors)*
+            #cached_methods
+        }
+    })
+}
+
+fn last_segment(ty: &Type) -> Option<&PathSegment> {
+    match ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    }
+}
+
+/**
+ * The `T` in a field declared as `Cell<T>`.
+ */
+fn cell_value(ty: &Type) -> Option<&Type> {
+    let segment = last_segment(ty)?;
+
+    if segment.ident != "Cell" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first()? {
+                GenericArgument::Type(value) => Some(value),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/**
+ * The first lifetime other than `'static` in `tokens`, without its quote.
+ */
+fn borrowed_lifetime(tokens: TokenStream) -> Option<Ident> {
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                if let Some(TokenTree::Ident(lifetime)) = tokens.peek() {
+                    if lifetime != "static" {
+                        return Some(lifetime.clone());
+                    }
+                }
+            }
+            TokenTree::Group(group) => {
+                if let Some(lifetime) = borrowed_lifetime(group.stream()) {
+                    return Some(lifetime);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
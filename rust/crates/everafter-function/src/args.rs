@@ -8,8 +8,8 @@ use syn::{
  * macro expansions.
  */
 pub(super) struct FunctionArgs {
-    pub(super) arg: Vec<Box<Pat>>,
-    pub(super) ty: Vec<Box<Type>>,
+    pub(super) arg: Vec<Pat>,
+    pub(super) ty: Vec<Type>,
 }
 
 impl Parse for FunctionArgs {
@@ -33,8 +33,8 @@ impl Parse for FunctionArgs {
                     ))
                 }
                 FnArg::Typed(arg) => {
-                    arg_vec.push(arg.pat);
-                    ty_vec.push(arg.ty)
+                    arg_vec.push(*arg.pat);
+                    ty_vec.push(*arg.ty)
                 }
             }
         }
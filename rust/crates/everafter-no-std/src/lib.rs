@@ -0,0 +1,25 @@
+/*!
+ * Checks that `everafter` builds without `std`: this crate is `no_std` itself, so it only
+ * compiles while the crate's core does. Build it on its own with `cargo build -p
+ * everafter-no-std`, since building the whole workspace turns `std` on for every member.
+ */
+#![no_std]
+
+use everafter::{Local, Revision};
+
+/**
+ * Advance the revision kept in `latest`, the way a timeline counts its writes.
+ */
+pub fn advance(latest: &'static impl Local<Revision>) -> Revision {
+    latest.with(|latest| {
+        *latest = latest.increment();
+        *latest
+    })
+}
+
+/**
+ * How many times `advance` ran since `since`.
+ */
+pub fn advanced_since(latest: &'static impl Local<Revision>, since: Revision) -> u64 {
+    latest.with(|latest| latest.distance(&since))
+}
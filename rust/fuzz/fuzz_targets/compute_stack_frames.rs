@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(
+    init: everafter::fuzz::quiet_deliberate_panics(),
+    |data: &[u8]| everafter::fuzz::compute_stack_frames(data)
+);
@@ -0,0 +1,157 @@
+/*!
+ * Graphs of common shapes for benchmarks. This module only exists with the `bench-helpers`
+ * feature, so the benchmarks in `benches/` can build the same graphs without reaching into the
+ * crate's internals.
+ *
+ * Every graph is a layer of cells feeding a layer of outputs, with deriveds in between. Each
+ * derived adds up the values it reads, so writing a new value to an input changes every output
+ * that depends on it.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let chain = timeline.build_chain(100);
+ *
+ * assert_eq!(chain.outputs[0].get(), 0);
+ * chain.inputs[0].set(1);
+ * assert_eq!(chain.outputs[0].get(), 1);
+ * ```
+ */
+
+use crate::{
+    reactive::{Cell, Derived},
+    timeline::Timeline,
+};
+
+/**
+ * A graph built by one of the `Timeline::build_*` helpers.
+ */
+#[derive(Debug, Clone)]
+pub struct Graph {
+    /**
+     * The cells at the bottom of the graph.
+     */
+    pub inputs: Vec<Cell<u64>>,
+    /**
+     * The deriveds at the top of the graph, which nothing else reads.
+     */
+    pub outputs: Vec<Derived<u64>>,
+}
+
+impl Graph {
+    /**
+     * Write `value` to every input.
+     */
+    pub fn set_inputs(&self, value: u64) {
+        for input in &self.inputs {
+            input.set(value);
+        }
+    }
+
+    /**
+     * Read every output and add them up.
+     */
+    pub fn read_outputs(&self) -> u64 {
+        self.outputs
+            .iter()
+            .map(Derived::get)
+            .fold(0, u64::wrapping_add)
+    }
+}
+
+impl Timeline {
+    /**
+     * One cell read by a chain of `length` deriveds, each reading the one before it.
+     */
+    pub fn build_chain(&self, length: usize) -> Graph {
+        assert!(length > 0, "a chain needs at least one derived");
+
+        let input = self.cell(0);
+        let mut last = {
+            let input = input.clone();
+            self.derived(move || input.get())
+        };
+
+        for _ in 1..length {
+            let previous = last.clone();
+            last = self.derived(move || previous.get());
+        }
+
+        Graph {
+            inputs: vec![input],
+            outputs: vec![last],
+        }
+    }
+
+    /**
+     * One cell read by `width` deriveds.
+     */
+    pub fn build_fan_out(&self, width: usize) -> Graph {
+        let input = self.cell(0);
+
+        let outputs = (0..width)
+            .map(|_| {
+                let input = input.clone();
+                self.derived(move || input.get())
+            })
+            .collect();
+
+        Graph {
+            inputs: vec![input],
+            outputs,
+        }
+    }
+
+    /**
+     * `width` cells read by one derived.
+     */
+    pub fn build_fan_in(&self, width: usize) -> Graph {
+        let inputs: Vec<Cell<u64>> = (0..width).map(|_| self.cell(0)).collect();
+
+        let output = {
+            let inputs = inputs.clone();
+            self.derived(move || inputs.iter().map(Cell::get).sum())
+        };
+
+        Graph {
+            inputs,
+            outputs: vec![output],
+        }
+    }
+
+    /**
+     * A lattice of diamonds: `width` cells, followed by `depth` layers of `width` deriveds. Each
+     * derived reads the node below it and the one after that, wrapping around at the end of the
+     * layer, so every layer reads the one below it twice over.
+     */
+    pub fn build_diamond(&self, width: usize, depth: usize) -> Graph {
+        assert!(width > 0, "a lattice needs at least one node per layer");
+
+        let inputs: Vec<Cell<u64>> = (0..width).map(|_| self.cell(0)).collect();
+
+        let mut layer: Vec<Derived<u64>> = inputs
+            .iter()
+            .map(|input| {
+                let input = input.clone();
+                self.derived(move || input.get())
+            })
+            .collect();
+
+        for _ in 0..depth {
+            layer = (0..width)
+                .map(|i| {
+                    let left = layer[i].clone();
+                    let right = layer[(i + 1) % width].clone();
+                    // every layer doubles the values below it, so deep lattices wrap around
+                    self.derived(move || left.get().wrapping_add(right.get()))
+                })
+                .collect();
+        }
+
+        Graph {
+            inputs,
+            outputs: layer,
+        }
+    }
+}
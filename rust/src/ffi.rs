@@ -0,0 +1,318 @@
+/*!
+ * A C interface to timelines, `i64` cells and deriveds computed by the host, with the `ffi`
+ * feature. Values are reached through handles, which are numbers rather than pointers: a handle
+ * that was freed, or that names another kind of value, is reported with a status instead of
+ * being dereferenced, and no panic unwinds into the host.
+ *
+ * A derived's computation is a host function, which reads cells and other deriveds through
+ * `everafter_cell_get` and `everafter_derived_get`. Those reads are tracked like reads from Rust,
+ * so the derived recomputes after one of the values it read changed.
+ *
+ * Handles belong to the thread that created them. The handle `0` is never valid, so hosts can
+ * use it as a null handle.
+ */
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ffi::c_void,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use crate::{
+    reactive::{Cell, Derived},
+    timeline::Timeline,
+};
+
+pub type EverafterHandle = u64;
+
+/**
+ * A host function that computes a derived's value. It's called with the `user_data` the
+ * derived was created with.
+ */
+pub type EverafterCompute = extern "C" fn(user_data: *mut c_void) -> i64;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EverafterStatus {
+    Ok = 0,
+    // the handle `0` was passed
+    NullHandle = 1,
+    // the handle was freed, or never handed out on this thread
+    InvalidHandle = 2,
+    // the handle names another kind of value than the function takes
+    WrongKind = 3,
+    // a pointer to write the result to was null
+    NullPointer = 4,
+    // the operation panicked, for example because a derived read itself
+    Panicked = 5,
+}
+
+#[derive(Clone)]
+enum Object {
+    Timeline(Rc<Timeline>),
+    Cell(Cell<i64>),
+    Derived(Derived<i64>),
+}
+
+struct Handles {
+    next: EverafterHandle,
+    objects: BTreeMap<EverafterHandle, Object>,
+}
+
+thread_local! {
+    static HANDLES: RefCell<Handles> = const {
+        RefCell::new(Handles {
+            next: 1,
+            objects: BTreeMap::new(),
+        })
+    };
+}
+
+/**
+ * The host's computation and the data it's called with. The host is responsible for the data
+ * being usable from wherever the derived is read.
+ */
+struct Computation {
+    compute: EverafterCompute,
+    user_data: *mut c_void,
+}
+
+unsafe impl Send for Computation {}
+unsafe impl Sync for Computation {}
+
+impl Computation {
+    // a method, so closures capture the whole `Computation` rather than its pointer
+    fn run(&self) -> i64 {
+        (self.compute)(self.user_data)
+    }
+}
+
+type Result<T> = std::result::Result<T, EverafterStatus>;
+
+/**
+ * Run `f`, reporting its error or a panic as a status.
+ */
+fn guard(f: impl FnOnce() -> Result<()>) -> EverafterStatus {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => EverafterStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => EverafterStatus::Panicked,
+    }
+}
+
+fn insert(object: Object) -> EverafterHandle {
+    HANDLES.with(|handles| {
+        let mut handles = handles.borrow_mut();
+        let handle = handles.next;
+        handles.next += 1;
+        handles.objects.insert(handle, object);
+        handle
+    })
+}
+
+/**
+ * The value `handle` names. It's cloned out of the table, since using it can call back into the
+ * host, which can create and free handles.
+ */
+fn lookup(handle: EverafterHandle) -> Result<Object> {
+    if handle == 0 {
+        return Err(EverafterStatus::NullHandle);
+    }
+
+    HANDLES.with(|handles| {
+        handles
+            .borrow()
+            .objects
+            .get(&handle)
+            .cloned()
+            .ok_or(EverafterStatus::InvalidHandle)
+    })
+}
+
+fn timeline(handle: EverafterHandle) -> Result<Rc<Timeline>> {
+    match lookup(handle)? {
+        Object::Timeline(timeline) => Ok(timeline),
+        _ => Err(EverafterStatus::WrongKind),
+    }
+}
+
+fn cell(handle: EverafterHandle) -> Result<Cell<i64>> {
+    match lookup(handle)? {
+        Object::Cell(cell) => Ok(cell),
+        _ => Err(EverafterStatus::WrongKind),
+    }
+}
+
+fn derived(handle: EverafterHandle) -> Result<Derived<i64>> {
+    match lookup(handle)? {
+        Object::Derived(derived) => Ok(derived),
+        _ => Err(EverafterStatus::WrongKind),
+    }
+}
+
+/**
+ * # Safety
+ *
+ * `out` must be null or valid for writes.
+ */
+unsafe fn write<T>(out: *mut T, value: T) -> Result<()> {
+    if out.is_null() {
+        return Err(EverafterStatus::NullPointer);
+    }
+
+    out.write(value);
+    Ok(())
+}
+
+/**
+ * Create a timeline, writing its handle to `out`.
+ *
+ * # Safety
+ *
+ * `out` must be null or valid for writes.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn everafter_timeline_new(out: *mut EverafterHandle) -> EverafterStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(EverafterStatus::NullPointer);
+        }
+
+        write(out, insert(Object::Timeline(Rc::new(Timeline::new()))))
+    })
+}
+
+/**
+ * Create a cell with `value` in `timeline`, writing its handle to `out`.
+ *
+ * # Safety
+ *
+ * `out` must be null or valid for writes.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn everafter_cell_new(
+    timeline: EverafterHandle,
+    value: i64,
+    out: *mut EverafterHandle,
+) -> EverafterStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(EverafterStatus::NullPointer);
+        }
+
+        let cell = self::timeline(timeline)?.cell(value);
+        write(out, insert(Object::Cell(cell)))
+    })
+}
+
+/**
+ * Read the value of `cell` into `out`. Inside a derived's computation, the derived depends on
+ * the cell from then on.
+ *
+ * # Safety
+ *
+ * `out` must be null or valid for writes.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn everafter_cell_get(
+    cell: EverafterHandle,
+    out: *mut i64,
+) -> EverafterStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(EverafterStatus::NullPointer);
+        }
+
+        let cell = self::cell(cell)?;
+        write(out, cell.get())
+    })
+}
+
+/**
+ * Write `value` into `cell`. Writing the value it already has doesn't advance the revision.
+ */
+#[no_mangle]
+pub extern "C" fn everafter_cell_set(cell: EverafterHandle, value: i64) -> EverafterStatus {
+    guard(|| {
+        self::cell(cell)?.set(value);
+        Ok(())
+    })
+}
+
+/**
+ * Create a derived in `timeline` whose value `compute` computes, writing its handle to `out`.
+ * Like every derived, it's computed when it's first read, and recomputed when it's read after
+ * one of the values it read changed.
+ *
+ * # Safety
+ *
+ * `out` must be null or valid for writes, and `user_data` must stay valid for `compute` for as
+ * long as the derived can be read.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn everafter_derived_new(
+    timeline: EverafterHandle,
+    compute: EverafterCompute,
+    user_data: *mut c_void,
+    out: *mut EverafterHandle,
+) -> EverafterStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(EverafterStatus::NullPointer);
+        }
+
+        let computation = Computation { compute, user_data };
+        let derived = self::timeline(timeline)?.derived(move || computation.run());
+        write(out, insert(Object::Derived(derived)))
+    })
+}
+
+/**
+ * Read the value of `derived` into `out`, computing it if it's stale. Inside another derived's
+ * computation, that derived depends on this one from then on.
+ *
+ * # Safety
+ *
+ * `out` must be null or valid for writes.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn everafter_derived_get(
+    derived: EverafterHandle,
+    out: *mut i64,
+) -> EverafterStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(EverafterStatus::NullPointer);
+        }
+
+        let derived = self::derived(derived)?;
+        write(out, derived.get())
+    })
+}
+
+/**
+ * Free `handle`. Reading a freed cell or derived fails with `InvalidHandle`, also from inside
+ * a computation. A timeline's cells and deriveds keep working after the timeline's handle was
+ * freed.
+ */
+#[no_mangle]
+pub extern "C" fn everafter_free(handle: EverafterHandle) -> EverafterStatus {
+    guard(|| {
+        if handle == 0 {
+            return Err(EverafterStatus::NullHandle);
+        }
+
+        let object = HANDLES.with(|handles| handles.borrow_mut().objects.remove(&handle));
+
+        // dropping the value happens outside of the table, since it can drop other values
+        match object {
+            Some(object) => {
+                drop(object);
+                Ok(())
+            }
+            None => Err(EverafterStatus::InvalidHandle),
+        }
+    })
+}
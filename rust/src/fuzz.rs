@@ -0,0 +1,289 @@
+/*!
+ * Drivers that interpret arbitrary bytes as operations and check the crate's invariants after
+ * every step. The `cargo fuzz` targets in `fuzz/` feed them generated inputs, and
+ * `tests/frames.rs` runs them on fixed ones. Available with the `testing` feature, and in
+ * builds made by `cargo fuzz`.
+ */
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+};
+
+use crate::{
+    reactive::{Cell, Derived},
+    timeline::{track_reads, untrack, ComputeStack, TagId, Timeline},
+};
+
+/**
+ * The message of the panics the drivers cause on purpose, and catch.
+ */
+pub const DELIBERATE_PANIC: &str = "the fuzz driver panicked on purpose";
+
+const CELLS: usize = 4;
+// deeper nesting is read as a read, so a long input can't overflow the thread's stack
+const MAX_DEPTH: usize = 32;
+
+/**
+ * Keep the panic hook from printing, or from aborting like `cargo fuzz`'s does, for the panics
+ * the drivers cause on purpose. Other panics go to the hook that was installed before.
+ */
+pub fn quiet_deliberate_panics() {
+    static QUIET: Once = Once::new();
+
+    QUIET.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let message = match payload.downcast_ref::<String>() {
+                Some(message) => message.as_str(),
+                None => payload.downcast_ref::<&str>().copied().unwrap_or(""),
+            };
+
+            // including a derived that reports it was poisoned by one
+            if !message.contains(DELIBERATE_PANIC) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/**
+ * Interpret `data` as operations on the `ComputeStack`, one per byte: the low three bits pick
+ * the operation, and the rest pick the cell or derived it applies to.
+ *
+ * - push a tracked frame, which runs the operations that follow until a pop
+ * - push an untracked frame, the same way
+ * - pop the frame the last push opened
+ * - read a cell
+ * - read a derived, whose computation pushes frames of its own, or panics once its cell is odd,
+ *   which poisons it
+ * - write a cell
+ * - read a cell in a frame that panics
+ * - read a cell inside `track_reads`
+ *
+ * After every operation, the stack has to hold exactly the frames the operations opened, and
+ * each frame has to have recorded exactly the values read while it was the innermost one. A
+ * panic has to unwind exactly the frame it happened in, and nothing read in that frame is
+ * recorded anywhere.
+ */
+pub fn compute_stack_frames(data: &[u8]) {
+    Driver::new(data).run(false);
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Push,
+    Untrack,
+    Pop,
+    Read(usize),
+    ReadDerived(usize),
+    Write(usize),
+    PanicInFrame(usize),
+    TrackReads(usize),
+}
+
+impl Op {
+    fn decode(byte: u8) -> Op {
+        let index = usize::from(byte >> 3);
+
+        match byte & 7 {
+            0 => Op::Push,
+            1 => Op::Untrack,
+            2 => Op::Pop,
+            3 => Op::Read(index % CELLS),
+            4 => Op::ReadDerived(index % DERIVEDS),
+            5 => Op::Write(index % CELLS),
+            6 => Op::PanicInFrame(index % CELLS),
+            _ => Op::TrackReads(index % CELLS),
+        }
+    }
+}
+
+const DERIVEDS: usize = 3;
+
+/**
+ * A frame the driver expects on the stack: a tracked frame with what it should have recorded so
+ * far, or an untracked one.
+ */
+enum Frame {
+    Tracked(Vec<TagId>),
+    Untracked,
+}
+
+struct Driver<'a> {
+    ops: std::slice::Iter<'a, u8>,
+    // kept alive for the cells and deriveds, which only hold the timeline's state
+    _timeline: Timeline,
+    cells: Vec<Cell<i64>>,
+    values: Vec<i64>,
+    deriveds: Vec<Derived<i64>>,
+    // whether the derived that panics did, which it keeps doing from then on
+    poisoned: bool,
+    frames: Vec<Frame>,
+    // the frames that were open before the driver started
+    base: usize,
+}
+
+impl<'a> Driver<'a> {
+    fn new(data: &'a [u8]) -> Driver<'a> {
+        let timeline = Timeline::new();
+        let cells: Vec<Cell<i64>> = (0..CELLS)
+            .map(|i| timeline.cell(0).named(format!("c{}", i)))
+            .collect();
+
+        // reads a cell, and another one in a frame of its own
+        let first = {
+            let (a, b) = (cells[0].clone(), cells[1].clone());
+            timeline.derived(move || {
+                let (b, dependencies) = ComputeStack::track(|| b.get());
+                assert_eq!(dependencies.len(), 1);
+                a.get() + b
+            })
+        };
+
+        // reads a derived, and a cell it doesn't depend on
+        let second = {
+            let (first, c) = (first.clone(), cells[2].clone());
+            timeline.derived(move || first.get() + untrack(|| c.get()))
+        };
+
+        let panicking = {
+            let d = cells[3].clone();
+            timeline.derived(move || match d.get() {
+                odd if odd % 2 == 1 => panic!("{}", DELIBERATE_PANIC),
+                even => even,
+            })
+        };
+
+        Driver {
+            ops: data.iter(),
+            _timeline: timeline,
+            cells,
+            values: vec![0; CELLS],
+            deriveds: vec![
+                first.named("first"),
+                second.named("second"),
+                panicking.named("panicking"),
+            ],
+            poisoned: false,
+            frames: vec![],
+            base: ComputeStack::depth(),
+        }
+    }
+
+    /**
+     * Run operations until the input ends, or until a pop if the driver is inside a frame it
+     * pushed.
+     */
+    fn run(&mut self, nested: bool) {
+        while let Some(&byte) = self.ops.next() {
+            match Op::decode(byte) {
+                Op::Pop if nested => return,
+                Op::Pop => {}
+                Op::Push if self.frames.len() < MAX_DEPTH => {
+                    self.frames.push(Frame::Tracked(vec![]));
+                    let ((), dependencies) = ComputeStack::track(|| self.run(true));
+                    let read: Vec<TagId> = dependencies.tags().iter().map(TagId::new).collect();
+
+                    match self.frames.pop() {
+                        Some(Frame::Tracked(expected)) => {
+                            assert_eq!(read, expected, "a popped frame lost or gained reads")
+                        }
+                        _ => unreachable!("the driver pushed a tracked frame"),
+                    }
+                }
+                Op::Untrack if self.frames.len() < MAX_DEPTH => {
+                    self.frames.push(Frame::Untracked);
+                    untrack(|| self.run(true));
+                    self.frames.pop();
+                }
+                Op::Push | Op::Untrack => self.read(0),
+                Op::Read(cell) => self.read(cell),
+                Op::ReadDerived(derived) => self.read_derived(derived),
+                Op::Write(cell) => {
+                    self.values[cell] += 1;
+                    self.cells[cell].set(self.values[cell]);
+                }
+                Op::PanicInFrame(cell) => {
+                    let cell = &self.cells[cell];
+                    let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+                        ComputeStack::track(|| {
+                            cell.get();
+                            panic!("{}", DELIBERATE_PANIC);
+                        })
+                    }));
+                    assert!(panicked.is_err());
+                }
+                Op::TrackReads(cell) => {
+                    let cell = &self.cells[cell];
+                    let read = track_reads(|| {
+                        cell.get();
+                    });
+                    let id = cell.tag().unwrap().id();
+                    assert_eq!(
+                        read,
+                        std::slice::from_ref(&id),
+                        "track_reads lost or gained reads"
+                    );
+                    self.record(id);
+                }
+            }
+
+            self.check();
+        }
+    }
+
+    fn read(&mut self, cell: usize) {
+        self.cells[cell].get();
+        self.record(self.cells[cell].tag().unwrap().id());
+    }
+
+    fn read_derived(&mut self, index: usize) {
+        let derived = &self.deriveds[index];
+        let read = panic::catch_unwind(AssertUnwindSafe(|| derived.get()));
+
+        let panics = index == 2 && (self.poisoned || self.values[3] % 2 == 1);
+        assert_eq!(
+            read.is_err(),
+            panics,
+            "{} panicked unexpectedly",
+            derived.label()
+        );
+        self.poisoned |= panics;
+
+        if !panics {
+            self.record(derived.tag().id());
+        }
+    }
+
+    /**
+     * Record a read in the innermost frame, like the stack should have.
+     */
+    fn record(&mut self, id: TagId) {
+        if let Some(Frame::Tracked(read)) = self.frames.last_mut() {
+            if !read.contains(&id) {
+                read.push(id);
+            }
+        }
+    }
+
+    fn check(&self) {
+        let frames = ComputeStack::frame_reads();
+        assert_eq!(
+            frames.len(),
+            self.base + self.frames.len(),
+            "the stack has the wrong number of frames"
+        );
+
+        for (depth, (frame, expected)) in frames[self.base..].iter().zip(&self.frames).enumerate() {
+            match (frame, expected) {
+                (Some(read), Frame::Tracked(expected)) => {
+                    assert_eq!(read, expected, "frame {} recorded the wrong reads", depth)
+                }
+                (None, Frame::Untracked) => {}
+                (frame, _) => panic!("frame {} is tracked: {}", depth, frame.is_some()),
+            }
+        }
+    }
+}
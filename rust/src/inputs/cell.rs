@@ -1,18 +1,61 @@
 use derive_new::new;
 use std::{fmt::Debug, sync::Arc};
 
-use crate::timeline::revision::{AtomicRevision, Revision};
+use crate::{
+    reactive::{effect, label::Label},
+    timeline::{
+        node_key::NodeSlot,
+        revision::{AtomicRevision, Revision},
+        state::TimelineState,
+    },
+};
 
-use super::{Reactive, ReactiveTag};
+use super::{reactive::Dependents, Reactive, ReactiveTag};
 
 #[derive(Debug)]
 pub struct Tag {
     pub(crate) revision: AtomicRevision,
+    // the label of the value the tag belongs to, used to describe invalidations
+    pub(crate) label: Option<Arc<Label>>,
+    // computations that read the tag in eager validation mode
+    pub(crate) dependents: Dependents,
+    pub(crate) slot: NodeSlot,
 }
 
+// tags are only `Send` and `Sync` when their dependents are, like every other handle
+#[allow(clippy::arc_with_non_send_sync)]
 impl Tag {
     pub(crate) fn arc(revision: AtomicRevision) -> Arc<Tag> {
-        Arc::new(Tag { revision })
+        Arc::new(Tag {
+            revision,
+            label: None,
+            dependents: Dependents::default(),
+            slot: NodeSlot::new(),
+        })
+    }
+
+    pub(crate) fn labeled(revision: AtomicRevision, label: Arc<Label>) -> Arc<Tag> {
+        Arc::new(Tag {
+            revision,
+            label: Some(label),
+            dependents: Dependents::default(),
+            slot: NodeSlot::new(),
+        })
+    }
+
+    /**
+     * Record a write at `revision`, and mark the computations that read the tag in eager
+     * validation mode as dirty, unless `timeline` is paused and does it once it resumes. The
+     * write is attributed to the effect running on this thread, if there is one.
+     */
+    pub(crate) fn write(self: &Arc<Self>, revision: Revision, timeline: &TimelineState) {
+        self.revision.update(revision);
+        effect::record_write(self.slot.key());
+        timeline.caused_by(self, revision);
+
+        if !timeline.defer_marking(self) {
+            self.dependents.mark_dirty();
+        }
     }
 }
 
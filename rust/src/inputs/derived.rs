@@ -3,7 +3,7 @@ use std::{fmt::Debug, sync::Arc};
 use derive_new::new;
 use parking_lot::{Mutex, MutexGuard};
 
-use crate::{timeline::EvaluationContext, timeline::Revision};
+use crate::timeline::{node_key::NodeSlot, EvaluationContext, NodeKey, Revision};
 
 use super::{Reactive, ReactiveTag};
 
@@ -29,7 +29,7 @@ impl DerivedTagData {
             .iter()
             .map(|d| d.revision())
             .max()
-            .unwrap_or(Revision::constant())
+            .unwrap_or(Revision::CONSTANT)
     }
 
     pub(crate) fn add_dep(&mut self, tag: ReactiveTag) {
@@ -40,10 +40,11 @@ impl DerivedTagData {
 #[derive(Debug, Clone, Default)]
 pub struct DerivedTag {
     tag: Arc<Mutex<DerivedTagData>>,
+    slot: Arc<NodeSlot>,
 }
 
 impl DerivedTag {
-    fn assert_not_modifying(&self, operation: &'static str) -> MutexGuard<DerivedTagData> {
+    fn assert_not_modifying(&self, operation: &'static str) -> MutexGuard<'_, DerivedTagData> {
         let tag = self.tag.lock();
 
         if tag.modifying {
@@ -53,7 +54,7 @@ impl DerivedTag {
         tag
     }
 
-    fn assert_modifying(&self, operation: &'static str) -> MutexGuard<DerivedTagData> {
+    fn assert_modifying(&self, operation: &'static str) -> MutexGuard<'_, DerivedTagData> {
         let tag = self.tag.lock();
 
         if !tag.modifying {
@@ -81,11 +82,15 @@ impl DerivedTag {
     pub(crate) fn add_dep(&self, tag: ReactiveTag) {
         self.assert_modifying("add a dependency").add_dep(tag);
     }
+
+    pub(crate) fn key(&self) -> NodeKey {
+        self.slot.key()
+    }
 }
 
-impl Into<ReactiveTag> for DerivedTag {
-    fn into(self) -> ReactiveTag {
-        ReactiveTag::Derived(self)
+impl From<DerivedTag> for ReactiveTag {
+    fn from(tag: DerivedTag) -> ReactiveTag {
+        ReactiveTag::Derived(tag)
     }
 }
 
@@ -7,5 +7,5 @@ pub(crate) use cell::{ReactiveCell, Tag};
 pub use derived::DynamicComputation;
 pub(crate) use derived::{DerivedTag, ReactiveDerived};
 pub use iterable::{GetReactiveKey, Key};
-pub use reactive::Reactive;
 pub(crate) use reactive::ReactiveTag;
+pub use reactive::{ComputedTag, Dependent, Reactive};
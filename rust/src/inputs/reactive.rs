@@ -1,13 +1,372 @@
-use std::sync::Arc;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt::Debug,
+    mem,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+};
 
-use crate::timeline::Revision;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::{
+    reactive::{Invalidation, MaybeSync},
+    timeline::{ComputationId, ComputeStack, NodeKey, Revision},
+};
 
 use super::{DerivedTag, Tag};
 
+/**
+ * A tag whose revision is only known once the computation behind it has been brought up to
+ * date. Asking for the revision may re-run the computation.
+ */
+pub trait ComputedTag: Debug + MaybeSync {
+    fn validate(&self) -> Revision;
+
+    /**
+     * The revision reported by the last validation, without validating again.
+     */
+    fn last_revision(&self) -> Revision;
+
+    /**
+     * Whether validating would recompute, judged without recomputing anything.
+     */
+    fn is_dirty(&self) -> bool;
+
+    /**
+     * The label of the computation, for diagnostics.
+     */
+    fn label(&self) -> String;
+
+    /**
+     * Identifies the computation, like `ReactiveTag::key`.
+     */
+    fn key(&self) -> NodeKey;
+
+    /**
+     * Why the computation last recomputed, if it ever recomputed because a dependency changed.
+     */
+    fn last_invalidation(&self) -> Option<Invalidation>;
+
+    /**
+     * Record that `dependent` read this computation, so it is marked dirty when the computation
+     * is. Returns whether the computation's own reverse edges are complete, so that it really
+     * will be marked dirty when anything it read changes.
+     */
+    fn add_dependent(&self, id: ComputationId, dependent: Weak<dyn Dependent>) -> bool;
+
+    /**
+     * Whether the revision is polled from outside of the timeline, so it can advance while the
+     * timeline's revision stays the same.
+     */
+    fn is_polled(&self) -> bool {
+        false
+    }
+
+    /**
+     * Whether validating is cheap, because it won't bring any dependencies of the tag up to
+     * date first.
+     */
+    fn is_settled(&self) -> bool {
+        true
+    }
+
+    /**
+     * The first of the tag's dependencies, starting at `from`, that has to be settled before the
+     * tag can be, along with its index. Returns `None` once validating the tag is cheap, or if a
+     * dependency before the next unsettled one changed, because validating then recomputes.
+     */
+    fn unsettled_dependency(&self, _from: usize) -> Option<(usize, Arc<dyn ComputedTag>)> {
+        None
+    }
+
+    /**
+     * The tags the computation read the last time it ran, without validating it. A computation
+     * that is running on this thread reports nothing.
+     */
+    fn dependencies(&self) -> Vec<ReactiveTag> {
+        vec![]
+    }
+
+    /**
+     * Describes a computation, this one or one it read, whose value is older than a
+     * dependency that changed since it was computed, judged from the revisions they recorded
+     * without validating anything. Computations whose keys are in `visited` were already
+     * checked. See `Timeline::set_validate_on_read`.
+     */
+    fn stale_dependency(&self, _visited: &mut HashSet<NodeKey>) -> Option<String> {
+        None
+    }
+
+    /**
+     * The computations that read this one in eager validation mode, like `Dependents::live`.
+     */
+    fn dependents(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)> {
+        vec![]
+    }
+
+    /**
+     * Push a frame for the tag's computation while its dependencies are settled. Popped with
+     * `exit`.
+     */
+    fn enter(&self) {}
+
+    fn exit(&self) {}
+}
+
+/**
+ * Settle the dependencies of `root` with an explicit stack instead of recursion: the deepest
+ * unsettled dependency is validated first, and every tag is validated once the dependencies it
+ * will look at are settled, so no validation reaches more than one level down. Dependencies are
+ * visited in the order `Dependencies::first_changed` would validate them, and every tag the walk
+ * descends through is entered on the `ComputeStack` like a recursive validation would be.
+ */
+pub(crate) fn settle(root: &dyn ComputedTag) {
+    ComputeStack::unwinding_frames(|| {
+        // every tag being settled, with the index to resume at and whether it was entered
+        let mut stack: Vec<(Arc<dyn ComputedTag>, usize, bool)> = vec![];
+        let (mut root_from, mut root_entered) = (0, false);
+
+        loop {
+            let next = match stack.last() {
+                Some((tag, from, _)) => tag.unsettled_dependency(*from),
+                None => root.unsettled_dependency(root_from),
+            };
+
+            match next {
+                Some((index, dependency)) => {
+                    let (tag, from, entered): (&dyn ComputedTag, _, _) = match stack.last_mut() {
+                        Some((tag, from, entered)) => (&**tag, from, entered),
+                        None => (root, &mut root_from, &mut root_entered),
+                    };
+
+                    *from = index;
+
+                    if !*entered {
+                        tag.enter();
+                        *entered = true;
+                    }
+
+                    stack.push((dependency, 0, false));
+                }
+                None => match stack.pop() {
+                    Some((tag, _, entered)) => {
+                        if entered {
+                            tag.exit();
+                        }
+
+                        tag.validate();
+                    }
+                    None => {
+                        if root_entered {
+                            root.exit();
+                        }
+
+                        return;
+                    }
+                },
+            }
+        }
+    })
+}
+
+/**
+ * A computation that can be told eagerly that something it read was written.
+ */
+pub trait Dependent: MaybeSync {
+    fn mark_dirty(&self);
+
+    /**
+     * The computations that read this one in eager validation mode.
+     */
+    fn dependents(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)>;
+
+    /**
+     * Validate the computation, and recompute it if something it read changed.
+     */
+    fn bring_up_to_date(&self);
+}
+
+/**
+ * Every computation that read one of `roots` in eager validation mode, directly or through other
+ * computations, ordered so that each one comes after everything it read among them.
+ */
+pub(crate) fn downstream(
+    roots: Vec<(ComputationId, Arc<dyn Dependent>)>,
+) -> Vec<Arc<dyn Dependent>> {
+    let mut visited = HashSet::new();
+    let mut finished = vec![];
+
+    // a depth-first walk along the reverse edges finishes every computation after the ones that
+    // read it, so the reverse of that order puts readers last. The walk keeps its own stack, so a
+    // deep graph is limited by the heap rather than by the thread's stack.
+    for (id, root) in roots {
+        if !visited.insert(id) {
+            continue;
+        }
+
+        let mut stack = vec![(root.dependents(), root)];
+
+        while let Some((dependents, _)) = stack.last_mut() {
+            match dependents.pop() {
+                Some((id, dependent)) => {
+                    if visited.insert(id) {
+                        stack.push((dependent.dependents(), dependent));
+                    }
+                }
+                None => {
+                    if let Some((_, computation)) = stack.pop() {
+                        finished.push(computation);
+                    }
+                }
+            }
+        }
+    }
+
+    finished.reverse();
+    finished
+}
+
+/**
+ * The reverse edges of a tag or computation: the computations that read it while the timeline
+ * was in `ValidationMode::Eager`. An edge stays until the dependent is dropped, so a dependent
+ * that stopped reading the value may still be marked dirty when it changes.
+ *
+ * Edges are weak, so they never keep a dependent alive. The edges of dropped dependents are
+ * discarded whenever the edges are walked, and whenever they doubled since they were last
+ * compacted, so a value that many short-lived computations read without ever being written
+ * doesn't grow its edges without bound.
+ */
+#[derive(Default)]
+pub(crate) struct Dependents {
+    dependents: Mutex<IndexMap<ComputationId, Weak<dyn Dependent>>>,
+    compact_at: AtomicUsize,
+}
+
+/**
+ * The bytes one reverse edge takes up.
+ */
+pub(crate) const EDGE_SIZE: usize = mem::size_of::<(ComputationId, Weak<dyn Dependent>)>();
+
+impl Dependents {
+    pub(crate) fn add(&self, id: ComputationId, dependent: Weak<dyn Dependent>) {
+        let mut dependents = self.dependents.lock();
+
+        if dependents.len() >= self.compact_at.load(Ordering::Relaxed) {
+            dependents.retain(|_, dependent| dependent.strong_count() > 0);
+            self.compact_at
+                .store((dependents.len() * 2).max(16), Ordering::Relaxed);
+        }
+
+        dependents.insert(id, dependent);
+    }
+
+    /**
+     * Discard the edges of dropped dependents, and free the room for edges if less than half of
+     * it is used. Returns the number of edges there is no longer room for.
+     */
+    pub(crate) fn compact(&self) -> usize {
+        let mut dependents = self.dependents.lock();
+        let capacity = dependents.capacity();
+
+        dependents.retain(|_, dependent| dependent.strong_count() > 0);
+        self.compact_at
+            .store((dependents.len() * 2).max(16), Ordering::Relaxed);
+
+        if dependents.len() < capacity / 2 {
+            dependents.shrink_to_fit();
+        }
+
+        capacity - dependents.capacity()
+    }
+
+    /**
+     * The number of edges there is room for, live or not.
+     */
+    pub(crate) fn capacity(&self) -> usize {
+        self.dependents.lock().capacity()
+    }
+
+    /**
+     * The dependents that are still alive, forgetting the ones that were dropped.
+     */
+    pub(crate) fn live(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)> {
+        let mut dependents = self.dependents.lock();
+
+        if dependents.is_empty() {
+            return vec![];
+        }
+
+        dependents.retain(|_, dependent| dependent.strong_count() > 0);
+        dependents
+            .iter()
+            .filter_map(|(id, dependent)| Some((*id, dependent.upgrade()?)))
+            .collect()
+    }
+
+    pub(crate) fn mark_dirty(&self) {
+        let dependents: Vec<Arc<dyn Dependent>> = {
+            let mut dependents = self.dependents.lock();
+
+            if dependents.is_empty() {
+                return;
+            }
+
+            dependents.retain(|_, dependent| dependent.strong_count() > 0);
+            dependents.values().filter_map(Weak::upgrade).collect()
+        };
+
+        // a dependent marks its own dependents from inside `mark_dirty`, so only the outermost
+        // call works through the queue, and the ones inside it add to it instead of recursing
+        let outermost = MARKING.with(|queue| {
+            let mut queue = queue.borrow_mut();
+            let outermost = queue.is_none();
+            queue.get_or_insert_with(Vec::new).extend(dependents);
+            outermost
+        });
+
+        if !outermost {
+            return;
+        }
+
+        let _end = EndMarking;
+
+        while let Some(dependent) = MARKING.with(|queue| queue.borrow_mut().as_mut()?.pop()) {
+            dependent.mark_dirty();
+        }
+    }
+}
+
+thread_local! {
+    // the dependents that still have to be marked dirty by the outermost `Dependents::mark_dirty`
+    static MARKING: RefCell<Option<Vec<Arc<dyn Dependent>>>> = RefCell::new(None);
+}
+
+/**
+ * Clears the queue when the outermost `mark_dirty` returns or unwinds.
+ */
+struct EndMarking;
+
+impl Drop for EndMarking {
+    fn drop(&mut self) {
+        MARKING.with(|queue| queue.borrow_mut().take());
+    }
+}
+
+impl Debug for Dependents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Dependents({})", self.dependents.lock().len())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ReactiveTag {
     Tag(Arc<Tag>),
     Derived(DerivedTag),
+    Computed(Arc<dyn ComputedTag>),
 }
 
 impl ReactiveTag {
@@ -15,6 +374,94 @@ impl ReactiveTag {
         match self {
             ReactiveTag::Tag(tag) => tag.revision.get(),
             ReactiveTag::Derived(tag) => tag.revision(),
+            ReactiveTag::Computed(tag) => tag.validate(),
+        }
+    }
+
+    /**
+     * The revision the tag last reported, without bringing a computed tag up to date.
+     */
+    pub(crate) fn last_revision(&self) -> Revision {
+        match self {
+            ReactiveTag::Tag(tag) => tag.revision.get(),
+            ReactiveTag::Derived(tag) => tag.revision(),
+            ReactiveTag::Computed(tag) => tag.last_revision(),
+        }
+    }
+
+    /**
+     * Whether the tag may have advanced past `revision`. Unlike comparing `revision()`, this
+     * never brings a computed tag up to date.
+     */
+    pub(crate) fn changed_since(&self, revision: Revision) -> bool {
+        match self {
+            ReactiveTag::Tag(tag) => tag.revision.get() > revision,
+            ReactiveTag::Derived(tag) => tag.revision() > revision,
+            ReactiveTag::Computed(tag) => tag.is_dirty() || tag.last_revision() > revision,
+        }
+    }
+
+    /**
+     * The computations that read the tag in eager validation mode.
+     */
+    pub(crate) fn dependents(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)> {
+        match self {
+            ReactiveTag::Tag(tag) => tag.dependents.live(),
+            ReactiveTag::Derived(_) => vec![],
+            ReactiveTag::Computed(tag) => tag.dependents(),
+        }
+    }
+
+    /**
+     * Record a reverse edge from the tag to `dependent`. Returns whether writes that advance the
+     * tag are guaranteed to mark `dependent` dirty.
+     */
+    pub(crate) fn add_dependent(&self, id: ComputationId, dependent: Weak<dyn Dependent>) -> bool {
+        match self {
+            ReactiveTag::Tag(tag) => {
+                tag.dependents.add(id, dependent);
+                true
+            }
+            ReactiveTag::Derived(_) => false,
+            ReactiveTag::Computed(tag) => tag.add_dependent(id, dependent),
+        }
+    }
+
+    /**
+     * Whether the tag's revision can advance without the timeline advancing, see
+     * `ComputedTag::is_polled`.
+     */
+    pub(crate) fn is_polled(&self) -> bool {
+        match self {
+            ReactiveTag::Computed(tag) => tag.is_polled(),
+            _ => false,
+        }
+    }
+
+    /**
+     * The label of the value the tag belongs to, for diagnostics.
+     */
+    pub(crate) fn label(&self) -> String {
+        match self {
+            ReactiveTag::Tag(tag) => match &tag.label {
+                Some(label) => label.to_string(),
+                None => String::from("tag"),
+            },
+            ReactiveTag::Derived(_) => String::from("derived"),
+            ReactiveTag::Computed(tag) => tag.label(),
+        }
+    }
+
+    /**
+     * Identifies the value the tag belongs to, so a frame that reads the same value twice only
+     * records it once. Unlike the value's address, the key isn't reused once the value is
+     * dropped.
+     */
+    pub(crate) fn key(&self) -> NodeKey {
+        match self {
+            ReactiveTag::Tag(tag) => tag.slot.key(),
+            ReactiveTag::Derived(tag) => tag.key(),
+            ReactiveTag::Computed(tag) => tag.key(),
         }
     }
 }
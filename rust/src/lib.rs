@@ -1,9 +1,54 @@
+/*!
+ * Without the default `std` feature, the crate is `no_std` and only provides `Revision` and the
+ * `Local` storage that per-thread state is reached through. Everything else is built on `std`'s
+ * locks, maps and thread-locals.
+ */
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(dead_code)]
 
+#[cfg(feature = "bench-helpers")]
+pub mod bench;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "std", any(feature = "testing", fuzzing)))]
+pub mod fuzz;
+#[cfg(feature = "std")]
 #[macro_use]
 pub mod inputs;
+#[cfg(feature = "std")]
 pub mod outputs;
+#[cfg(feature = "std")]
+pub mod reactive;
+#[cfg(feature = "sync")]
+pub mod sync;
 pub mod timeline;
+#[cfg(feature = "trace")]
+pub mod trace;
 
+#[cfg(feature = "std")]
 pub use inputs::{GetReactiveKey, Key, Reactive};
-pub use timeline::{Revision, TypedInputId};
+#[cfg(feature = "std")]
+pub use reactive::{
+    each, zip, CachedMethods, Cell, ChangedId, Collected, CombinedTag, Constant, DeferredScheduler,
+    Derived, DerivedAsync, DerivedStats, Effect, ExternalSource, ExternalTag, ExternalValue, Flush,
+    Forked, Handles, ImmediateScheduler, Invalidation, InvalidationStep, KeyedList, ListChange,
+    ManualScheduler, Mapped, MaybeSend, MaybeSync, Memo, MemoryStats, MultiDerived, ObservedNode,
+    ObserverEvent, Outputs, Priority, ReactiveValue, ReadonlyCell, RecordingObserver, Resolve,
+    RunawayFlush, Scheduler, Snapshot, SubscriptionHandle, Tag, TimelineObserver, TrackedMap,
+    TrackedVec, Validation, WatchHandle, WritableDerived, Writer, Zipped,
+};
+#[cfg(feature = "debug-graph")]
+pub use reactive::{DebugGraph, GraphNode};
+#[cfg(feature = "history")]
+pub use reactive::{
+    Mismatch, ParseStateError, RecordedWrite, ReplayError, RewindError, SerializedState, Skipped,
+    WriteLog,
+};
+#[cfg(feature = "std")]
+pub use timeline::{
+    track_reads, tracked_async, untrack, with_owner, BumpHandle, ComputeStack, Owner, ReadPolicy,
+    TagId, Timeline, TrackedFuture, TypedInputId, UntrackedRead, ValidationMode,
+};
+#[cfg(feature = "revision-timestamps")]
+pub use timeline::{Clock, SystemClock};
+pub use timeline::{Local, Revision, SingleThreaded};
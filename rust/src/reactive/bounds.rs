@@ -0,0 +1,68 @@
+/*!
+ * With the `sync` feature enabled, everything a timeline stores on behalf of its reactive values
+ * (computations, effect callbacks, schedulers and the derived values themselves) has to be
+ * `Send + Sync`, which makes `Cell`, `Derived` and `Effect` handles shareable across threads.
+ * Without the feature these bounds are satisfied by every type.
+ *
+ * The compute stack is per-thread, so concurrent computations track into their own frames. The
+ * timeline's revision and every tag are atomics accessed with `SeqCst`, and values live behind
+ * mutexes. A cell's value is stored before its tag advances, and the tag advances before the
+ * timeline's revision is published, so a reader that observes a new revision also observes the
+ * write behind it. A computation that races with a write may still return the
+ * old value, but it never records a revision newer than the one it started at, so the first
+ * validation after the write recomputes it: readers eventually see every write.
+ */
+
+use std::future::Future;
+
+use crate::timeline::{Revision, TagId, UntrackedRead};
+
+#[cfg(feature = "sync")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "sync")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+pub(crate) trait Computation<T>: Fn() -> T + MaybeSync {}
+impl<T, F: Fn() -> T + MaybeSync> Computation<T> for F {}
+
+pub(crate) trait MemoComputation<A, T>: Fn(&A) -> T + MaybeSync {}
+impl<A, T, F: Fn(&A) -> T + MaybeSync> MemoComputation<A, T> for F {}
+
+pub(crate) trait Callback: Fn() + MaybeSync {}
+impl<F: Fn() + MaybeSync> Callback for F {}
+
+pub(crate) trait Cleanup<T>: Fn(T) + MaybeSync {}
+impl<T, F: Fn(T) + MaybeSync> Cleanup<T> for F {}
+
+pub(crate) trait OnDispose: FnOnce() + MaybeSend {}
+impl<F: FnOnce() + MaybeSend> OnDispose for F {}
+
+pub(crate) trait Setter<T>: Fn(T) + MaybeSync {}
+impl<T, F: Fn(T) + MaybeSync> Setter<T> for F {}
+
+pub(crate) trait ReadHook: Fn(&UntrackedRead) + MaybeSync {}
+impl<F: Fn(&UntrackedRead) + MaybeSync> ReadHook for F {}
+
+pub(crate) trait BumpHook: FnMut(Revision, &[TagId]) + MaybeSend {}
+impl<F: FnMut(Revision, &[TagId]) + MaybeSend> BumpHook for F {}
+
+pub(crate) trait Equality<T>: Fn(&T, &T) -> bool + MaybeSync {}
+impl<T, F: Fn(&T, &T) -> bool + MaybeSync> Equality<T> for F {}
+
+pub(crate) trait ComputationFuture<T>: Future<Output = T> + MaybeSend {}
+impl<T, F: Future<Output = T> + MaybeSend + ?Sized> ComputationFuture<T> for F {}
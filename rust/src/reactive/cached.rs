@@ -0,0 +1,161 @@
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Weak},
+};
+
+use parking_lot::Mutex;
+
+use crate::timeline::state::TimelineState;
+
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    derived::Derived,
+};
+
+/**
+ * The deriveds behind the `#[cached]` methods of one value, created with
+ * `Timeline::cached_methods`. A struct that derives `everafter_derive::Reactive` keeps one in a
+ * field, and each cached method looks up its derived here by name, creating it the first time the
+ * method is called. Clones of the value share its cached methods, like they share its cells.
+ *
+ * A cached method's derived holds a clone of the value it belongs to. That clone only refers to
+ * the cached methods weakly, so the deriveds don't keep themselves alive once the value is
+ * dropped.
+ */
+pub struct CachedMethods {
+    timeline: Arc<TimelineState>,
+    deriveds: Deriveds,
+}
+
+type Methods = Mutex<HashMap<&'static str, Box<dyn AnyDerived>>>;
+
+enum Deriveds {
+    Owned(Arc<Methods>),
+    // the cached methods of a clone that a derived captured
+    Captured(Weak<Methods>),
+}
+
+thread_local! {
+    // set while a cached method's computation is being created, so the clone of the value it
+    // captures doesn't keep the cached methods alive
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+}
+
+struct Capturing;
+
+impl Capturing {
+    fn start() -> Capturing {
+        CAPTURING.with(|capturing| capturing.set(true));
+        Capturing
+    }
+}
+
+impl Drop for Capturing {
+    fn drop(&mut self) {
+        CAPTURING.with(|capturing| capturing.set(false));
+    }
+}
+
+trait AnyDerived: MaybeSync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: MaybeSend + 'static> AnyDerived for Derived<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl CachedMethods {
+    // the deriveds are only `Send` and `Sync` when their values are, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(timeline: Arc<TimelineState>) -> CachedMethods {
+        CachedMethods {
+            timeline,
+            deriveds: Deriveds::Owned(Arc::new(Mutex::new(HashMap::new()))),
+        }
+    }
+
+    /**
+     * The derived for `method`, creating it with the computation returned by `computation` if
+     * this is the first call. Panics if `method` was already cached with a different type.
+     */
+    pub fn derived<T, F>(&self, method: &'static str, computation: impl FnOnce() -> F) -> Derived<T>
+    where
+        T: MaybeSend + 'static,
+        F: Fn() -> T + MaybeSync + 'static,
+    {
+        let create = || {
+            let computation = {
+                let _capturing = Capturing::start();
+                computation()
+            };
+
+            Derived::new(self.timeline.clone(), computation)
+        };
+
+        let methods = match self.methods() {
+            Some(methods) => methods,
+            // the value this clone was captured from is gone, so there is nothing to cache into
+            None => return create(),
+        };
+
+        let mut deriveds = methods.lock();
+        let derived = deriveds.entry(method).or_insert_with(|| Box::new(create()));
+
+        match derived.as_any().downcast_ref::<Derived<T>>() {
+            Some(derived) => derived.clone(),
+            None => panic!("the cached method {} was cached with another type", method),
+        }
+    }
+
+    /**
+     * The number of methods that were cached so far.
+     */
+    pub fn len(&self) -> usize {
+        self.methods().map_or(0, |methods| methods.lock().len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn methods(&self) -> Option<Arc<Methods>> {
+        match &self.deriveds {
+            Deriveds::Owned(methods) => Some(methods.clone()),
+            Deriveds::Captured(methods) => methods.upgrade(),
+        }
+    }
+}
+
+impl Clone for CachedMethods {
+    fn clone(&self) -> Self {
+        let deriveds = match &self.deriveds {
+            Deriveds::Owned(methods) if CAPTURING.with(Cell::get) => {
+                Deriveds::Captured(Arc::downgrade(methods))
+            }
+            Deriveds::Owned(methods) => Deriveds::Owned(methods.clone()),
+            Deriveds::Captured(methods) => Deriveds::Captured(methods.clone()),
+        };
+
+        CachedMethods {
+            timeline: self.timeline.clone(),
+            deriveds,
+        }
+    }
+}
+
+impl Debug for CachedMethods {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut methods: Vec<&str> = match self.methods() {
+            Some(methods) => methods.lock().keys().copied().collect(),
+            None => vec![],
+        };
+        methods.sort_unstable();
+
+        f.debug_tuple("CachedMethods").field(&methods).finish()
+    }
+}
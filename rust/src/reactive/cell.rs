@@ -0,0 +1,1172 @@
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    mem,
+    panic::Location,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
+
+use crate::{
+    inputs::{self, ReactiveTag},
+    timeline::{state::TimelineState, ComputeStack, NodeKey, ReadPolicy, Revision, UntrackedRead},
+};
+
+#[cfg(feature = "history")]
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{OnceLock, Weak},
+};
+
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    effect::Effect,
+    fork::{self, ForkState, Overridden},
+    label::Label,
+    observer::ObservedNode,
+    registry::Entry,
+    snapshot::Snapshot,
+    subscription::SubscriptionHandle,
+    tag::Tag,
+    value::ReactiveValue,
+    watch::{watch, WatchHandle},
+};
+#[cfg(feature = "history")]
+use super::{history::Restore, replay::Replay};
+
+/**
+ * A tracked value. Reading a cell with `get` records its tag in the current `ComputeStack`
+ * frame, and writing it with `set` advances the timeline's revision.
+ *
+ * ```
+ * use everafter::{ComputeStack, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let cell = timeline.cell(1);
+ *
+ * let (value, dependencies) = ComputeStack::track(|| cell.get() + 1);
+ * assert_eq!(value, 2);
+ *
+ * let revision = dependencies.revision();
+ * cell.set(5);
+ *
+ * assert!(dependencies.revision() > revision);
+ * ```
+ */
+pub struct Cell<T> {
+    inner: Arc<CellInner<T>>,
+}
+
+static NEXT_CELL: AtomicU64 = AtomicU64::new(1);
+
+struct CellInner<T> {
+    label: Arc<Label>,
+    value: Mutex<T>,
+    // values that were overwritten while a snapshot could still read them, with the revisions
+    // they were written at, oldest first. `None` stands for a value that `Cell::update` changed
+    // in place, which left no copy to keep
+    history: Mutex<Vec<(Revision, Option<T>)>>,
+    // `None` for constants, which are never written and so have nothing to track
+    tracked: Option<Tracked>,
+    // the values written by forks that haven't been committed or dropped, with the fork's id
+    forked: Mutex<Vec<(u64, T)>>,
+    // set by `Cell::recorded`, where the value is known to be `Clone`
+    #[cfg(feature = "history")]
+    recorder: OnceLock<Recorder<T>>,
+    // set by `Cell::keyed`, where the value is known to be `Display`
+    #[cfg(feature = "history")]
+    key: OnceLock<Key<T>>,
+}
+
+#[cfg(feature = "history")]
+struct Key<T> {
+    key: Arc<str>,
+    serialize: fn(&T) -> String,
+}
+
+#[cfg(feature = "history")]
+type Recorder<T> = fn(&Arc<CellInner<T>>, &T) -> Arc<dyn Restore>;
+
+struct Tracked {
+    tag: Arc<inputs::Tag>,
+    timeline: Arc<TimelineState>,
+}
+
+/**
+ * What a write did to the value it changed.
+ */
+enum Replaced<T> {
+    /**
+     * The write replaced the value, which it returns.
+     */
+    Value(T),
+    /**
+     * The write changed the value in place, so it only has the copy a recorded cell takes for
+     * the timeline's history.
+     */
+    InPlace {
+        #[cfg(feature = "history")]
+        recorded: Option<Arc<dyn Restore>>,
+    },
+}
+
+impl<T> Cell<T> {
+    pub(crate) fn new(timeline: Arc<TimelineState>, value: T) -> Cell<T> {
+        let revision = timeline.now();
+        Cell::at(timeline, value, revision)
+    }
+
+    /**
+     * Create a cell for a value that never changes. A constant doesn't belong to a timeline and
+     * has no tag, so reading it never records a dependency, and a derived that only reads
+     * constants is `Derived::is_const`. Its revision is always `Revision::CONSTANT`.
+     *
+     * Writing a constant panics. Subscribing to one returns a handle whose callback never runs,
+     * and constants don't appear in snapshots or debug graphs.
+     *
+     * ```
+     * use everafter::{Cell, ComputeStack};
+     *
+     * let limit = Cell::constant(10);
+     * let (value, dependencies) = ComputeStack::track(|| limit.get());
+     *
+     * assert_eq!(value, 10);
+     * assert!(dependencies.is_empty());
+     * ```
+     */
+    pub fn constant(value: T) -> Cell<T> {
+        Cell::build(value, None)
+    }
+
+    fn at(timeline: Arc<TimelineState>, value: T, revision: Revision) -> Cell<T> {
+        Cell::build(value, Some((timeline, revision)))
+    }
+
+    // a cell is only `Send` and `Sync` when its value is, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn build(value: T, timeline: Option<(Arc<TimelineState>, Revision)>) -> Cell<T> {
+        let label = Arc::new(Label::new(
+            "cell",
+            NEXT_CELL.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        let tracked = timeline.map(|(timeline, revision)| {
+            let tag = inputs::Tag::labeled(revision.atomic(), label.clone());
+            timeline.register_value(Entry::Cell(Arc::downgrade(&tag)));
+            timeline.observe(|observer| observer.node_created(&ObservedNode::new(&label)));
+            Tracked { tag, timeline }
+        });
+
+        Cell {
+            inner: Arc::new(CellInner {
+                label,
+                value: Mutex::new(value),
+                history: Mutex::new(vec![]),
+                tracked,
+                forked: Mutex::new(vec![]),
+                #[cfg(feature = "history")]
+                recorder: OnceLock::new(),
+                #[cfg(feature = "history")]
+                key: OnceLock::new(),
+            }),
+        }
+    }
+
+    /**
+     * Attach a debug label to the cell. A cell can only be named once.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(0).named("count");
+     *
+     * assert_eq!(count.label(), "count");
+     * ```
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> Cell<T> {
+        self.inner.label.name(label.into());
+        self
+    }
+
+    /**
+     * The cell's debug label, or a name like `cell#7` if it was never named.
+     */
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * The revision at which this cell was last written.
+     */
+    pub fn revision(&self) -> Revision {
+        match &self.inner.tracked {
+            Some(tracked) => tracked.tag.revision.get(),
+            None => Revision::CONSTANT,
+        }
+    }
+
+    /**
+     * The cell's tag, which tracks the cell without reading its value. Constants have no tag.
+     */
+    pub fn tag(&self) -> Option<Tag> {
+        self.inner.tracked.as_ref().map(|tracked| {
+            let tag = ReactiveTag::Tag(tracked.tag.clone());
+            Tag::new(tag, self.inner.label.clone(), tracked.timeline.clone())
+        })
+    }
+
+    /**
+     * Write a new value into the cell and advance the revision, even if the value is equal to the
+     * current one. This is the only way to write a cell whose value isn't `PartialEq`.
+     *
+     * Cells are written through a shared reference, so effects and computations can write cells
+     * while they run. Effects that such a write makes stale run after the effects that are
+     * already running. With `Timeline::set_strict_writes`, writing from a derived panics.
+     *
+     * Panics if the cell is a constant.
+     */
+    pub fn set_always(&self, value: T) {
+        self.write_with(|current| {
+            self.check_strict_writes();
+            Some(Replaced::Value(mem::replace(current, value)))
+        });
+    }
+
+    /**
+     * Change the cell's value in place with `f`, as a single write that always advances the
+     * revision. The cell stays locked from the time `f` runs until the write is recorded, so
+     * concurrent updates never lose each other's changes. `f` runs inside the timeline's write,
+     * so it must not read this cell or write any cell of the timeline.
+     *
+     * The value isn't copied, so `T` doesn't have to be `Clone`, but a snapshot taken before the
+     * update can't read the value it changed: `get_at` returns `None` for it. A recorded cell
+     * still copies the value for `Timeline::rewind_to`. `update_if_changed` keeps the copy, and
+     * skips writes that leave the value equal.
+     *
+     * Reading the current value isn't tracked, so updating a cell from inside a computation
+     * doesn't make the computation depend on it. The write is still a write, and panics in a
+     * derived under `Timeline::set_strict_writes` before `f` runs, like every `set` does.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let items = timeline.cell(vec![1, 2]);
+     *
+     * items.update(|items| items.push(3));
+     * assert_eq!(items.get(), [1, 2, 3]);
+     * ```
+     */
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        self.write_with(|current| {
+            self.check_strict_writes();
+
+            #[cfg(feature = "history")]
+            let recorded = self
+                .inner
+                .recorder
+                .get()
+                .map(|record| record(&self.inner, current));
+
+            f(current);
+
+            Some(Replaced::InPlace {
+                #[cfg(feature = "history")]
+                recorded,
+            })
+        });
+    }
+
+    /**
+     * Write the cell through `change`, which gets the current value and returns what it did to
+     * it, or `None` if it left the cell alone. The cell stays locked from the time `change` runs
+     * until the write is recorded, so no other write can come in between.
+     *
+     * Panics if the cell is a constant.
+     */
+    fn write_with(&self, change: impl FnOnce(&mut T) -> Option<Replaced<T>>) {
+        let inner = &self.inner;
+
+        let tracked = match &inner.tracked {
+            Some(tracked) => tracked,
+            None => panic!("{} is a constant and can't be written", self.label()),
+        };
+
+        tracked.timeline.write_if(
+            || {
+                let mut current = inner.value.lock();
+                let previous = change(&mut current)?;
+                Some((current, previous))
+            },
+            |(current, previous), revision| inner.replace(tracked, current, previous, revision),
+        );
+    }
+
+    /**
+     * Panic if the cell is written while a derived computes under `Timeline::set_strict_writes`.
+     */
+    fn check_strict_writes(&self) {
+        let strict = match &self.inner.tracked {
+            Some(tracked) => tracked.timeline.strict_writes(),
+            None => false,
+        };
+
+        if strict {
+            if let Some(derived) = ComputeStack::computing() {
+                panic!(
+                    "{} was written while {} was computing. With strict writes, a derived can't \
+                     write cells; write from an effect instead.",
+                    self.label(),
+                    derived
+                );
+            }
+        }
+    }
+}
+
+impl<T> CellInner<T> {
+    /**
+     * Record the write of the value in `current`, which replaced `previous`, at `revision`, from
+     * inside the timeline's write. The previous value is kept for the snapshots that can still
+     * read it, and for the timeline's history if the cell is recorded.
+     */
+    fn replace(
+        self: &Arc<Self>,
+        tracked: &Tracked,
+        current: MutexGuard<'_, T>,
+        previous: Replaced<T>,
+        revision: Revision,
+    ) {
+        let written_at = tracked.tag.revision.get();
+
+        let previous = match previous {
+            Replaced::Value(previous) => {
+                #[cfg(feature = "history")]
+                if let Some(record) = self.recorder.get() {
+                    tracked.timeline.record(revision, record(self, &previous));
+                }
+
+                Some(previous)
+            }
+            Replaced::InPlace {
+                #[cfg(feature = "history")]
+                recorded,
+            } => {
+                #[cfg(feature = "history")]
+                if let Some(recorded) = recorded {
+                    tracked.timeline.record(revision, recorded);
+                }
+
+                None
+            }
+        };
+
+        #[cfg(feature = "history")]
+        if let Some(key) = self.key.get() {
+            let serialize = || (key.serialize)(&current);
+            tracked.timeline.record_write(&key.key, serialize, revision);
+        }
+
+        let mut history = self.history.lock();
+        // values are written in order, so anything newer was written before the timeline
+        // was reset
+        history.retain(|(revision, _)| *revision < written_at);
+        history.push((written_at, previous));
+        prune(&tracked.timeline, &mut history, revision);
+
+        tracked.tag.write(revision, &tracked.timeline);
+        drop(history);
+        drop(current);
+
+        tracked.timeline.observe(|observer| {
+            observer.cell_written(&ObservedNode::new(&self.label), written_at, revision)
+        });
+    }
+}
+
+impl<T> Drop for CellInner<T> {
+    fn drop(&mut self) {
+        if let Some(tracked) = &self.tracked {
+            let label = &self.label;
+            tracked
+                .timeline
+                .observe(|observer| observer.node_dropped(&ObservedNode::new(label)));
+        }
+    }
+}
+
+#[cfg(feature = "history")]
+impl<T> Cell<T>
+where
+    T: Clone + MaybeSend + MaybeSync + 'static,
+{
+    /**
+     * Record the values that writes to the cell replace in the timeline's history, so
+     * `Timeline::rewind_to` can restore them. Recording keeps a clone of every replaced value,
+     * until the history reaches its capacity, so cells only take part once they opt in.
+     */
+    pub fn recorded(self) -> Cell<T> {
+        let _ = self.inner.recorder.set(|cell, value| {
+            Arc::new(RecordedValue {
+                cell: Arc::downgrade(cell),
+                value: value.clone(),
+            })
+        });
+        self
+    }
+}
+
+/**
+ * A value that a write to a recorded cell replaced.
+ */
+#[cfg(feature = "history")]
+struct RecordedValue<T> {
+    cell: Weak<CellInner<T>>,
+    value: T,
+}
+
+#[cfg(feature = "history")]
+impl<T: Clone + MaybeSend + MaybeSync> Restore for RecordedValue<T> {
+    fn restore(&self, revision: Revision) {
+        let cell = match self.cell.upgrade() {
+            Some(cell) => cell,
+            None => return,
+        };
+
+        if let Some(tracked) = &cell.tracked {
+            let mut current = cell.value.lock();
+            let previous = mem::replace(&mut *current, self.value.clone());
+            cell.replace(tracked, current, Replaced::Value(previous), revision);
+        }
+    }
+}
+
+#[cfg(feature = "history")]
+impl<T> Cell<T>
+where
+    T: Display + FromStr + MaybeSend + MaybeSync + 'static,
+    T::Err: Display,
+{
+    /**
+     * Give the cell a stable key, which `Timeline::start_recording` logs its writes under, and
+     * which `WriteLog::replay` finds the cell to write by in the timeline the log is replayed
+     * onto. Written values are logged with `Display` and parsed back with `FromStr`.
+     *
+     * A cell can only be keyed once, and a key can only name one live cell of a timeline.
+     * Panics if the cell is a constant.
+     */
+    pub fn keyed(self, key: impl Into<Arc<str>>) -> Cell<T> {
+        let key = key.into();
+
+        let tracked = match &self.inner.tracked {
+            Some(tracked) => tracked,
+            None => panic!("{} is a constant and can't be keyed", self.label()),
+        };
+
+        let keyed = Key {
+            key: key.clone(),
+            serialize: |value: &T| value.to_string(),
+        };
+
+        if self.inner.key.set(keyed).is_err() {
+            panic!(
+                "tried to key {} {:?}, but it was already keyed",
+                self.label(),
+                key
+            );
+        }
+
+        let cell = Arc::downgrade(&self.inner);
+        tracked.timeline.key_cell(key, Arc::new(KeyedCell { cell }));
+        self
+    }
+}
+
+/**
+ * A keyed cell, as its timeline's keys know it.
+ */
+#[cfg(feature = "history")]
+struct KeyedCell<T> {
+    cell: Weak<CellInner<T>>,
+}
+
+#[cfg(feature = "history")]
+impl<T> Replay for KeyedCell<T>
+where
+    T: Display + FromStr + MaybeSend + MaybeSync,
+    T::Err: Display,
+{
+    fn is_alive(&self) -> bool {
+        self.cell.strong_count() > 0
+    }
+
+    fn current(&self) -> Option<String> {
+        let cell = self.cell.upgrade()?;
+        let value = cell.value.lock().to_string();
+        Some(value)
+    }
+
+    fn replay(&self, value: &str) -> Option<Result<Revision, String>> {
+        let cell = Cell {
+            inner: self.cell.upgrade()?,
+        };
+
+        let replayed = value.parse().map(|value| {
+            cell.set_always(value);
+            cell.revision()
+        });
+
+        Some(replayed.map_err(|error: T::Err| error.to_string()))
+    }
+}
+
+/**
+ * Discard the values in `history` that no live snapshot can read. Each value was current from
+ * the revision it was written at until the next value was written, and the newest one until
+ * `until`.
+ */
+fn prune<T>(timeline: &TimelineState, history: &mut Vec<(Revision, T)>, until: Revision) {
+    let ends: Vec<Revision> = history
+        .iter()
+        .skip(1)
+        .map(|(revision, _)| *revision)
+        .chain(Some(until))
+        .collect();
+    let mut ends = ends.into_iter();
+
+    history.retain(|(revision, _)| {
+        let end = ends.next().expect("every value has an end");
+        timeline.is_pinned(*revision, end)
+    });
+}
+
+impl<T> Cell<T> {
+    /**
+     * A handle that reads this cell but can't write it, for code that should only observe the
+     * value. It shares the cell's value and tag, so it sees every write made through the cell.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(1);
+     * let reader = count.readonly();
+     *
+     * count.set(2);
+     * assert_eq!(reader.get(), 2);
+     * ```
+     *
+     * ```compile_fail
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let reader = timeline.cell(1).readonly();
+     *
+     * reader.set(2);
+     * ```
+     */
+    pub fn readonly(&self) -> ReadonlyCell<T> {
+        ReadonlyCell { cell: self.clone() }
+    }
+
+    /**
+     * Split the cell into a read half, which can be cloned and handed out freely, and a write
+     * half, which can't be cloned, so it can be moved into the one place that owns writes.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let (count, writer) = timeline.cell(0).split();
+     *
+     * writer.set(5);
+     * assert_eq!(count.get(), 5);
+     * ```
+     */
+    pub fn split(self) -> (ReadonlyCell<T>, Writer<T>) {
+        (self.readonly(), Writer { cell: self })
+    }
+
+    /**
+     * Record the read in the current `ComputeStack` frame, or apply the read policy if there is
+     * no frame. Constant cells are never recorded or reported.
+     */
+    #[track_caller]
+    fn track(&self) -> Result<(), UntrackedRead> {
+        let tracked = match &self.inner.tracked {
+            Some(tracked) => tracked,
+            None => return Ok(()),
+        };
+
+        // the policy is checked first, so reads under the default policy don't look at the
+        // stack twice
+        if tracked.timeline.read_policy() != ReadPolicy::Allow && ComputeStack::is_empty() {
+            return tracked
+                .timeline
+                .untracked_read(&self.inner.label, Location::caller());
+        }
+
+        let tag = ReactiveTag::Tag(tracked.tag.clone());
+        ComputeStack::consume(&tracked.timeline, &self.inner.label, tag);
+        Ok(())
+    }
+
+    /**
+     * `track`, panicking in debug builds when the read policy denies the read.
+     */
+    #[track_caller]
+    fn track_or_report(&self) {
+        if let Err(read) = self.track() {
+            if cfg!(debug_assertions) {
+                panic!("{}", read);
+            }
+
+            // release builds only report a denied read
+            if let Some(tracked) = &self.inner.tracked {
+                tracked.timeline.warn(&read);
+            }
+        }
+    }
+
+    /**
+     * Call `f` with the current value, without cloning it. Like `get`, the read is recorded in
+     * the current `ComputeStack` frame, or checked against the timeline's `ReadPolicy` outside
+     * of any frame.
+     *
+     * The cell stays locked while `f` runs, so `f` must not write the cell.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let text = timeline.cell("hello".repeat(1000));
+     *
+     * let length = {
+     *     let text = text.clone();
+     *     timeline.derived(move || text.with(|text| text.len()))
+     * };
+     *
+     * assert_eq!(length.get(), 5000);
+     *
+     * text.set(String::from("hi"));
+     * assert_eq!(length.get(), 2);
+     * ```
+     */
+    #[track_caller]
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.track_or_report();
+        self.read(f)
+    }
+
+    /**
+     * Call `f` with the value of the fork that is running on this thread, if it wrote the cell,
+     * or with the cell's own value.
+     */
+    fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        if let Some(fork) = self.fork() {
+            let forked = self.inner.forked.lock();
+
+            if let Some((_, value)) = forked.iter().find(|(id, _)| *id == fork.id()) {
+                return f(value);
+            }
+        }
+
+        f(&self.inner.value.lock())
+    }
+
+    /**
+     * Write `value` in `fork`, returning the key of the cell's tag.
+     */
+    pub(crate) fn fork_write(&self, fork: &ForkState, value: T) -> NodeKey {
+        let tracked = match &self.inner.tracked {
+            Some(tracked) => tracked,
+            None => panic!("{} is a constant and can't be written", self.label()),
+        };
+
+        assert!(
+            fork.belongs_to(&tracked.timeline),
+            "{} belongs to another timeline than the fork",
+            self.label()
+        );
+
+        let mut forked = self.inner.forked.lock();
+        match forked.iter_mut().find(|(id, _)| *id == fork.id()) {
+            Some((_, forked)) => *forked = value,
+            None => forked.push((fork.id(), value)),
+        }
+
+        tracked.tag.slot.key()
+    }
+
+    /**
+     * Borrow the current value without recording a dependency. Inside `Forked::run`, that's the
+     * fork's value if the fork wrote the cell. The cell stays locked until the guard is dropped,
+     * so don't write the cell while holding it.
+     */
+    pub fn peek_ref(&self) -> MappedMutexGuard<'_, T> {
+        if let Some(fork) = self.fork() {
+            let forked = self.inner.forked.lock();
+            let value = MutexGuard::try_map(forked, |forked| {
+                let (_, value) = forked.iter_mut().find(|(id, _)| *id == fork.id())?;
+                Some(value)
+            });
+
+            if let Ok(value) = value {
+                return value;
+            }
+        }
+
+        MutexGuard::map(self.inner.value.lock(), |value| value)
+    }
+
+    /**
+     * The fork that is running on this thread, if it forked the cell's timeline.
+     */
+    fn fork(&self) -> Option<Arc<ForkState>> {
+        fork::current(&self.inner.tracked.as_ref()?.timeline)
+    }
+}
+
+impl<T> Cell<T>
+where
+    T: Clone,
+{
+    /**
+     * Read the current value. Outside of any `ComputeStack` frame the read is untracked, and the
+     * timeline's `ReadPolicy` decides whether that is reported. Under `ReadPolicy::Deny`, an
+     * untracked read panics in debug builds.
+     */
+    #[track_caller]
+    pub fn get(&self) -> T {
+        self.track_or_report();
+        self.read(T::clone)
+    }
+
+    /**
+     * Read the current value like `get`, but fail instead of panicking when the timeline's
+     * `ReadPolicy` denies reads outside of any `ComputeStack` frame.
+     */
+    #[track_caller]
+    pub fn try_get(&self) -> Result<T, UntrackedRead> {
+        self.track()?;
+        Ok(self.read(T::clone))
+    }
+
+    /**
+     * Read the current value without recording a dependency, even inside a computation. Unlike
+     * `untrack`, this doesn't touch the `ComputeStack` at all.
+     */
+    pub fn peek(&self) -> T {
+        self.read(T::clone)
+    }
+
+    /**
+     * Read the value the cell had when `snapshot` was taken, without recording a dependency.
+     * Cells written after the snapshot keep their previous values around until the snapshot is
+     * dropped, so a reader holding a snapshot sees every cell as of the same revision. Returns
+     * `None` if the cell was created after the snapshot, or if `update` changed the value the
+     * snapshot would read in place.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(1);
+     *
+     * let snapshot = timeline.snapshot();
+     * cell.set(2);
+     *
+     * assert_eq!(cell.get_at(&snapshot), Some(1));
+     * assert_eq!(cell.get(), 2);
+     * ```
+     *
+     * A snapshot taken before the timeline renumbered its revisions can't be read consistently.
+     */
+    pub fn get_at(&self, snapshot: &Snapshot) -> Option<T> {
+        let revision = snapshot.revision();
+        let current = self.inner.value.lock();
+
+        if self.revision() <= revision {
+            return Some(current.clone());
+        }
+
+        self.inner
+            .history
+            .lock()
+            .iter()
+            .rev()
+            .find(|(written_at, _)| *written_at <= revision)
+            .and_then(|(_, value)| value.clone())
+    }
+}
+
+impl<T> Cell<T>
+where
+    T: Clone + MaybeSend + 'static,
+{
+    /**
+     * Call `callback` with the new value whenever the cell changes, starting with the next
+     * change. Callbacks are delivered by the timeline's scheduler, like effects, and stop once
+     * the returned handle is dropped.
+     *
+     * ```
+     * use std::sync::{Arc, Mutex};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(1);
+     * let seen = Arc::new(Mutex::new(vec![]));
+     *
+     * let subscription = {
+     *     let seen = seen.clone();
+     *     cell.subscribe(move |value| seen.lock().unwrap().push(*value))
+     * };
+     *
+     * cell.set(2);
+     * drop(subscription);
+     * cell.set(3);
+     *
+     * assert_eq!(*seen.lock().unwrap(), vec![2]);
+     * ```
+     */
+    pub fn subscribe(&self, callback: impl Fn(&T) + MaybeSync + 'static) -> SubscriptionHandle {
+        let timeline = match &self.inner.tracked {
+            Some(tracked) => tracked.timeline.clone(),
+            None => return SubscriptionHandle::inert(),
+        };
+
+        let cell = self.clone();
+        let subscribed = AtomicBool::new(false);
+
+        let effect = Effect::new(timeline, move || {
+            let value = cell.get();
+
+            // the effect runs once to start tracking the cell, which isn't a change
+            if subscribed.swap(true, Ordering::SeqCst) {
+                callback(&value);
+            }
+        });
+
+        effect.into_subscription()
+    }
+}
+
+impl<T> Cell<T>
+where
+    T: Clone + PartialEq + MaybeSend + 'static,
+{
+    /**
+     * Call `callback` with the cell's value right away, and then with each new value once the
+     * writes that changed it settle. Unlike `subscribe`, a write of a value equal to the last
+     * one delivered doesn't call `callback`, and a transaction that writes the cell several
+     * times delivers only the value it committed.
+     *
+     * ```
+     * use std::sync::{Arc, Mutex};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(1);
+     * let seen = Arc::new(Mutex::new(vec![]));
+     *
+     * let watch = {
+     *     let seen = seen.clone();
+     *     cell.watch(move |value| seen.lock().unwrap().push(*value))
+     * };
+     *
+     * timeline.batch(|| {
+     *     cell.set(2);
+     *     cell.set(3);
+     * });
+     * watch.unwatch();
+     * cell.set(4);
+     *
+     * assert_eq!(*seen.lock().unwrap(), vec![1, 3]);
+     * ```
+     */
+    pub fn watch(&self, callback: impl Fn(&T) + MaybeSync + 'static) -> WatchHandle {
+        let timeline = match &self.inner.tracked {
+            Some(tracked) => tracked.timeline.clone(),
+            None => {
+                callback(&self.get());
+                return WatchHandle::inert();
+            }
+        };
+
+        let cell = self.clone();
+        watch(timeline, move || cell.get(), callback)
+    }
+
+    /**
+     * Like `watch`, but each value is sent to `sender`. Values stop being sent once the
+     * receiver is dropped, but the watch stays registered until the handle is dropped.
+     */
+    #[cfg(feature = "channel")]
+    pub fn watch_into(&self, sender: std::sync::mpsc::Sender<T>) -> WatchHandle {
+        self.watch(move |value| {
+            let _ = sender.send(value.clone());
+        })
+    }
+}
+
+impl<T> Cell<T>
+where
+    T: PartialEq,
+{
+    /**
+     * Write a new value into the cell. Writing a value that is equal to the current value does
+     * not advance the revision.
+     */
+    pub fn set(&self, value: T) {
+        self.set_if_changed(|current| mem::replace(current, value));
+    }
+
+    /**
+     * Change the value in place with `change`, which returns the value it replaced, and write
+     * the change unless the value stayed equal. The comparison and the write happen under the
+     * cell's lock, so a concurrent write can't change the value in between.
+     */
+    fn set_if_changed(&self, change: impl FnOnce(&mut T) -> T) {
+        self.write_with(|current| {
+            self.check_strict_writes();
+            let previous = change(current);
+
+            if *current == previous {
+                // keep the value that was there, rather than an equal one
+                *current = previous;
+                None
+            } else {
+                Some(Replaced::Value(previous))
+            }
+        });
+    }
+}
+
+impl<T> Cell<T>
+where
+    T: Clone + PartialEq,
+{
+    /**
+     * Change the cell's value in place with `f`, like `update`, but only write the change if
+     * the value didn't stay equal. Telling needs a copy of the value from before `f` ran, which
+     * is why the value has to be `Clone`, and snapshots keep reading that copy.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let name = timeline.cell(String::from("ada"));
+     * let before = name.revision();
+     *
+     * name.update_if_changed(|name| name.make_ascii_lowercase());
+     * assert_eq!(name.revision(), before);
+     *
+     * name.update_if_changed(|name| name.make_ascii_uppercase());
+     * assert!(name.revision() > before);
+     * ```
+     */
+    pub fn update_if_changed(&self, f: impl FnOnce(&mut T)) {
+        self.set_if_changed(|current| {
+            let previous = current.clone();
+            f(current);
+            previous
+        });
+    }
+}
+
+impl<T: PartialEq> Overridden for Cell<T> {
+    fn commit(&self, fork: u64) {
+        let value = {
+            let mut forked = self.inner.forked.lock();
+            let index = forked.iter().position(|(id, _)| *id == fork);
+            index.map(|index| forked.remove(index).1)
+        };
+
+        if let Some(value) = value {
+            self.set(value);
+        }
+    }
+
+    fn discard(&self, fork: u64) {
+        self.inner.forked.lock().retain(|(id, _)| *id != fork);
+    }
+}
+
+impl<T: Clone> ReactiveValue for Cell<T> {
+    type Value = T;
+
+    #[track_caller]
+    fn get(&self) -> T {
+        Cell::get(self)
+    }
+
+    #[track_caller]
+    fn track(&self) {
+        self.track_or_report();
+    }
+
+    fn revision(&self) -> Revision {
+        Cell::revision(self)
+    }
+}
+
+impl<T> Clone for Cell<T> {
+    fn clone(&self) -> Self {
+        Cell {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Debug for Cell<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cell")
+            .field("label", &self.inner.label)
+            .field("value", &*self.inner.value.lock())
+            .field("revision", &self.revision())
+            .finish()
+    }
+}
+
+/**
+ * A handle that reads a cell but can't write it, returned by `Cell::readonly` and `Cell::split`.
+ * Reads are tracked like reads of the cell itself.
+ */
+pub struct ReadonlyCell<T> {
+    cell: Cell<T>,
+}
+
+impl<T> ReadonlyCell<T> {
+    /**
+     * The cell's debug label.
+     */
+    pub fn label(&self) -> &str {
+        self.cell.label()
+    }
+
+    /**
+     * The revision at which the cell was last written.
+     */
+    pub fn revision(&self) -> Revision {
+        self.cell.revision()
+    }
+}
+
+impl<T: Clone> ReadonlyCell<T> {
+    /**
+     * Read the current value, like `Cell::get`.
+     */
+    #[track_caller]
+    pub fn get(&self) -> T {
+        self.cell.get()
+    }
+
+    /**
+     * Read the current value without recording a dependency, like `Cell::peek`.
+     */
+    pub fn peek(&self) -> T {
+        self.cell.peek()
+    }
+}
+
+impl<T: Clone> ReactiveValue for ReadonlyCell<T> {
+    type Value = T;
+
+    #[track_caller]
+    fn get(&self) -> T {
+        self.cell.get()
+    }
+
+    #[track_caller]
+    fn track(&self) {
+        self.cell.track_or_report();
+    }
+
+    fn revision(&self) -> Revision {
+        self.cell.revision()
+    }
+}
+
+impl<T> Clone for ReadonlyCell<T> {
+    fn clone(&self) -> Self {
+        ReadonlyCell {
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T> Debug for ReadonlyCell<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ReadonlyCell").field(&self.cell).finish()
+    }
+}
+
+/**
+ * The write half of a cell, returned by `Cell::split`. It can't be cloned, so writes to the cell
+ * only happen wherever it was moved to.
+ */
+pub struct Writer<T> {
+    cell: Cell<T>,
+}
+
+impl<T> Writer<T> {
+    /**
+     * The cell's debug label.
+     */
+    pub fn label(&self) -> &str {
+        self.cell.label()
+    }
+
+    /**
+     * Write a new value into the cell and advance the revision, like `Cell::set_always`.
+     */
+    pub fn set_always(&self, value: T) {
+        self.cell.set_always(value);
+    }
+
+    /**
+     * Change the cell's value in place with `f`, like `Cell::update`.
+     */
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        self.cell.update(f);
+    }
+}
+
+impl<T: PartialEq> Writer<T> {
+    /**
+     * Write a new value into the cell, like `Cell::set`.
+     */
+    pub fn set(&self, value: T) {
+        self.cell.set(value);
+    }
+}
+
+impl<T: Clone + PartialEq> Writer<T> {
+    /**
+     * Change the cell's value with `f` unless it stays equal, like `Cell::update_if_changed`.
+     */
+    pub fn update_if_changed(&self, f: impl FnOnce(&mut T)) {
+        self.cell.update_if_changed(f);
+    }
+}
+
+impl<T> Debug for Writer<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Writer").field(&self.cell).finish()
+    }
+}
@@ -0,0 +1,92 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::timeline::Revision;
+
+use super::value::ReactiveValue;
+
+/**
+ * A value that never changes, like configuration that is loaded once. A constant has no tag and
+ * doesn't belong to a timeline, so reading it records nothing in the current `ComputeStack`
+ * frame. Unlike a cell created with `Cell::constant`, it has no label and isn't behind a lock,
+ * and it can be borrowed with `get_ref`.
+ *
+ * A derived that only reads constants has no dependencies. It is computed once, and every read
+ * after that returns the cached value without validating anything.
+ *
+ * ```
+ * use everafter::{ComputeStack, Constant, Revision, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let limit = Constant::new(10);
+ *
+ * let doubled = {
+ *     let limit = limit.clone();
+ *     timeline.derived(move || limit.get() * 2)
+ * };
+ *
+ * let (value, dependencies) = ComputeStack::track(|| doubled.get());
+ * assert_eq!(value, 20);
+ * assert!(dependencies.is_empty());
+ * assert_eq!(doubled.revision(), Revision::CONSTANT);
+ * ```
+ */
+pub struct Constant<T> {
+    value: Arc<T>,
+}
+
+impl<T> Constant<T> {
+    // a constant is only `Send` and `Sync` when its value is, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(value: T) -> Constant<T> {
+        Constant {
+            value: Arc::new(value),
+        }
+    }
+
+    /**
+     * Borrow the value. Like `get`, this isn't tracked.
+     */
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Clone> Constant<T> {
+    pub fn get(&self) -> T {
+        (*self.value).clone()
+    }
+}
+
+impl<T: Clone> ReactiveValue for Constant<T> {
+    type Value = T;
+
+    fn get(&self) -> T {
+        Constant::get(self)
+    }
+
+    fn track(&self) {}
+
+    fn revision(&self) -> Revision {
+        Revision::CONSTANT
+    }
+}
+
+impl<T> From<T> for Constant<T> {
+    fn from(value: T) -> Constant<T> {
+        Constant::new(value)
+    }
+}
+
+impl<T> Clone for Constant<T> {
+    fn clone(&self) -> Self {
+        Constant {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T: Debug> Debug for Constant<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Constant").field(&self.value).finish()
+    }
+}
@@ -0,0 +1,1763 @@
+use std::{
+    any::Any,
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashSet,
+    fmt::Debug,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+    time::Instant,
+};
+
+#[cfg(feature = "history")]
+use std::fmt::Display;
+#[cfg(feature = "revision-timestamps")]
+use std::time::Duration;
+
+use parking_lot::{Mutex, MutexGuard};
+
+use crate::{
+    inputs::{
+        reactive::{settle, Dependents},
+        ComputedTag, Dependent, ReactiveTag,
+    },
+    timeline::{
+        node_key::NodeSlot, owner::Scope, state::TimelineState, ComputationId, ComputeStack,
+        CycleError, Dependencies, NodeKey, Revision,
+    },
+};
+
+#[cfg(feature = "debug-graph")]
+use super::graph::{dependency_keys, GraphNode};
+#[cfg(feature = "history")]
+use super::replay::Output;
+#[cfg(feature = "revision-timestamps")]
+use super::scheduler::reachable_keys;
+use super::{
+    bounds::{Cleanup, Computation, Equality, MaybeSend, MaybeSync},
+    effect::Effect,
+    fork,
+    invalidation::Invalidation,
+    label::Label,
+    observer::ObservedNode,
+    registry::{Entry, Registered},
+    subscription::SubscriptionHandle,
+    tag::Tag,
+    value::ReactiveValue,
+    watch::{watch, WatchHandle},
+};
+
+/**
+ * A memoized computation. The computation runs inside a `ComputeStack` frame the first time the
+ * value is read, and the newest revision of everything it read becomes its watermark. Later reads
+ * return the cached value until one of those dependencies advances past the watermark.
+ *
+ * Reading a derived inside another computation makes the derived a dependency of that
+ * computation, so revisions propagate through chains of deriveds.
+ *
+ * Every recomputation replaces the recorded dependencies, so a computation that branches only
+ * depends on the values it read the last time it ran. Writes to values that only an untaken
+ * branch reads never recompute it, and a run that takes the other branch tracks that branch's
+ * values from then on. `Timeline::branch` builds such a derived from a condition and two branches.
+ *
+ * A derived created with `Timeline::derived_with_eq` compares each new value with the previous
+ * one, and only advances its revision when the value actually changed. Dependents of an
+ * unchanged derived keep their cached values. `Timeline::derived_with_eq_by` does the same with
+ * a custom equality, for values that aren't `PartialEq` or that are equal in a domain-specific
+ * way.
+ *
+ * A computation that can fail returns a `Result`, and the error is cached like any other value:
+ * a `Derived<Result<T, E>>` that produced an `Err` returns the same error until one of the values
+ * it read before failing changes. Returning early with `?` only records the reads that happened
+ * before the early return. `Timeline::try_derived` shares each error behind an `Arc`, so reading
+ * it and passing it on with `try_map` or `?` doesn't clone it.
+ *
+ * A computation that panics poisons its derived. The panic propagates to the reader, and every
+ * later read panics with its message.
+ *
+ * In `ValidationMode::Eager`, every derived records itself as a dependent of the values it read,
+ * and writes mark it dirty right away, so `is_dirty` doesn't have to walk its dependencies.
+ *
+ * A derived that reads itself, directly or through other deriveds, is a cycle. `try_get` reports
+ * the cycle as a `CycleError`, and `get` panics with the chain of computations involved.
+ *
+ * A computation can drop the last handle to a derived that is further up the `ComputeStack`, for
+ * example by clearing a collection that owns it. The derived finishes computing, and once its
+ * last handle is dropped it stops tracking the values it read: the computations that read it
+ * keep the value they got, and the dropped derived never invalidates them again.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let cell = timeline.cell(2);
+ *
+ * let doubled = {
+ *     let cell = cell.clone();
+ *     timeline.derived(move || cell.get() * 2)
+ * };
+ *
+ * assert_eq!(doubled.get(), 4);
+ *
+ * cell.set(10);
+ * assert_eq!(doubled.get(), 20);
+ * ```
+ */
+pub struct Derived<T> {
+    inner: Arc<DerivedInner<T>>,
+}
+
+struct DerivedInner<T> {
+    id: ComputationId,
+    this: Weak<dyn Dependent>,
+    label: Arc<Label>,
+    computation: Box<dyn Computation<T>>,
+    eq: Option<Box<dyn Equality<T>>>,
+    // called with each value the derived computed once it is replaced or the derived is dropped
+    cleanup: Option<Box<dyn Cleanup<T>>>,
+    state: Mutex<DerivedState<T>>,
+    // set by writes to anything the derived read in eager validation mode, and cleared when the
+    // derived is brought up to date
+    dirty: AtomicBool,
+    // set by `Derived::freeze`: the cached value is returned without validating the dependencies
+    frozen: AtomicBool,
+    // the computations that read this one in eager validation mode
+    dependents: Dependents,
+    // the live `Derived` handles. The computations that read the derived keep it alive after the
+    // last one is dropped, but it is retired: it keeps its value and never changes again
+    handles: AtomicUsize,
+    slot: NodeSlot,
+    timeline: Arc<TimelineState>,
+}
+
+struct DerivedState<T> {
+    value: Option<T>,
+    // what the last computation owns, see `Owner`
+    owner: Option<Arc<Scope>>,
+    dependencies: Dependencies,
+    // the newest revision consumed by the last computation
+    revision: Revision,
+    // the revision reported to dependents. It is the same as `revision` unless the derived
+    // compares its values, in which case recomputing an equal value leaves it alone.
+    changed_at: Revision,
+    // the timeline's revision when the dependencies were last checked
+    verified_at: Revision,
+    // whether a dependency is polled from an external source, which can change without the
+    // timeline advancing, so the dependencies are checked again by every outermost read
+    polled: bool,
+    // the `ComputeStack::settling` pass that last validated the derived
+    settled_in: Option<u64>,
+    // the dependency that caused the last recomputation
+    invalidation: Option<Invalidation>,
+    // the eager period in which the last computation recorded complete reverse edges, if any.
+    // `dirty` can only be trusted while the timeline is still in that period.
+    tracked_in: Option<u64>,
+    // the message of the panic that interrupted a computation. A poisoned derived can't be read
+    // again, because it never finished computing a value for its current dependencies.
+    poisoned: Option<String>,
+    // how many times the computation ran, and how many reads returned the cached value
+    runs: u64,
+    hits: u64,
+    // the timeline's revision when the computation last ran
+    recomputed_at: Option<Revision>,
+    // the effect that brings an eager derived up to date whenever the timeline flushes
+    eager: Option<SubscriptionHandle>,
+}
+
+thread_local! {
+    // the deriveds whose `Derived::with` is running on this thread
+    static LENDING: RefCell<Vec<ComputationId>> = const { RefCell::new(Vec::new()) };
+}
+
+// the number of `Derived::with` calls running on every thread, so locking a derived only looks
+// at `LENDING` while there are any
+static LENDS: AtomicUsize = AtomicUsize::new(0);
+
+/**
+ * How often a derived recomputed and how often reading it returned the cached value, from
+ * `Derived::stats`. Only reads of the value count as hits: validating the derived on behalf of
+ * a dependent, like `Derived::revision` does, doesn't.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let count = timeline.cell(1);
+ *
+ * let doubled = {
+ *     let count = count.clone();
+ *     timeline.derived(move || count.get() * 2)
+ * };
+ *
+ * doubled.get();
+ * doubled.get();
+ * count.set(2);
+ * doubled.get();
+ *
+ * let stats = doubled.stats();
+ * assert_eq!((stats.runs(), stats.hits()), (2, 1));
+ * assert_eq!(stats.recomputed_at(), Some(count.revision()));
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivedStats {
+    runs: u64,
+    hits: u64,
+    recomputed_at: Option<Revision>,
+}
+
+impl DerivedStats {
+    /**
+     * How many times the computation ran, including runs that panicked.
+     */
+    pub fn runs(&self) -> u64 {
+        self.runs
+    }
+
+    /**
+     * How many reads returned the cached value without running the computation.
+     */
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /**
+     * The timeline's revision when the computation last ran, or `None` if it never ran. A
+     * computation that ran inside a transaction reports the revision the transaction started
+     * at.
+     */
+    pub fn recomputed_at(&self) -> Option<Revision> {
+        self.recomputed_at
+    }
+}
+
+/**
+ * What `Derived::validate_only` found out about whether reading a derived would do any work.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /**
+     * Reading the derived returns its cached value without running any computation.
+     */
+    Clean,
+
+    /**
+     * Reading the derived may run its computation or the computation of a derived it read.
+     * `dirty_dependencies` counts the values the derived read that may have changed since it
+     * was computed, and is 0 for a derived that was never computed.
+     */
+    Stale { dirty_dependencies: usize },
+}
+
+impl<T> Derived<T>
+where
+    T: MaybeSend + 'static,
+{
+    pub(crate) fn new(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::build(timeline, Box::new(computation), None, None)
+    }
+
+    /**
+     * Like `new`, but `cleanup` is called with every value the derived computes once the value
+     * is replaced by a recomputation, or when the derived is dropped.
+     */
+    pub(crate) fn with_cleanup(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        cleanup: impl Fn(T) + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::build(
+            timeline,
+            Box::new(computation),
+            None,
+            Some(Box::new(cleanup)),
+        )
+    }
+
+    /**
+     * Like `new`, but a recomputation that produces a value `eq` considers equal to the previous
+     * one doesn't advance the derived's revision, so its dependents don't recompute either.
+     */
+    pub(crate) fn with_eq_by(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        eq: impl Fn(&T, &T) -> bool + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::build(timeline, Box::new(computation), Some(Box::new(eq)), None)
+    }
+
+    /**
+     * Like `new`, but the derived is computed right away, and recomputed whenever the timeline
+     * flushes after one of its dependencies changed, instead of when it's next read.
+     */
+    pub(crate) fn eager(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        let derived = Derived::new(timeline.clone(), computation);
+
+        // the derived owns the effect, so the effect only holds onto it weakly, and depends on
+        // what the derived read rather than on the derived itself
+        let inner = Arc::downgrade(&derived.inner);
+        let effect = Effect::new(timeline, move || {
+            if let Some(inner) = inner.upgrade() {
+                let (state, _) = inner.refreshed();
+                let dependencies = state.dependencies.tags().to_vec();
+                drop(state);
+                drop(inner);
+
+                ComputeStack::forward(dependencies);
+            }
+        });
+
+        derived.inner.state.lock().eager = Some(effect.into_subscription());
+        derived
+    }
+
+    fn build(
+        timeline: Arc<TimelineState>,
+        computation: Box<dyn Computation<T>>,
+        eq: Option<Box<dyn Equality<T>>>,
+        cleanup: Option<Box<dyn Cleanup<T>>>,
+    ) -> Derived<T> {
+        let id = ComputationId::next();
+
+        let inner = Arc::new_cyclic(|this: &Weak<DerivedInner<T>>| DerivedInner {
+            id,
+            this: this.clone(),
+            label: Arc::new(Label::new("derived", id.raw())),
+            computation,
+            eq,
+            cleanup,
+            state: Mutex::new(DerivedState {
+                value: None,
+                owner: None,
+                dependencies: Dependencies::default(),
+                revision: Revision::CONSTANT,
+                changed_at: Revision::CONSTANT,
+                verified_at: Revision::UNINITIALIZED,
+                polled: false,
+                settled_in: None,
+                invalidation: None,
+                tracked_in: None,
+                poisoned: None,
+                runs: 0,
+                hits: 0,
+                recomputed_at: None,
+                eager: None,
+            }),
+            dirty: AtomicBool::new(false),
+            frozen: AtomicBool::new(false),
+            dependents: Dependents::default(),
+            handles: AtomicUsize::new(1),
+            slot: NodeSlot::new(),
+            timeline,
+        });
+
+        let registered: Arc<dyn Registered> = inner.clone();
+        inner
+            .timeline
+            .register_value(Entry::Computation(Arc::downgrade(&registered)));
+        inner
+            .timeline
+            .observe(|observer| observer.node_created(&ObservedNode::new(&inner.label)));
+
+        Derived { inner }
+    }
+
+    pub fn id(&self) -> ComputationId {
+        self.inner.id
+    }
+
+    /**
+     * Attach a debug label to the derived, which is used in its `Debug` output and in cycle
+     * errors. A derived can only be named once.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> Derived<T> {
+        self.inner.label.name(label.into());
+        self
+    }
+
+    /**
+     * The derived's debug label, or a name like `derived#3` if it was never named.
+     */
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * The revision that dependents of this computation observe, after bringing it up to date.
+     */
+    pub fn revision(&self) -> Revision {
+        self.inner.validate()
+    }
+
+    /**
+     * Why the derived last recomputed, or `None` if it only ever computed its initial value.
+     * This doesn't bring the derived up to date, so it describes the last read rather than any
+     * writes since.
+     *
+     * If several dependencies changed at once, the one that was read first is reported.
+     */
+    pub fn last_invalidation(&self) -> Option<Invalidation> {
+        self.inner.lock().invalidation.clone()
+    }
+
+    /**
+     * Whether the derived has to run its computation before it can be read: it was never
+     * computed, or one of the values it tracked advanced past the revision it was computed at.
+     * This is judged without recomputing anything or pushing a `ComputeStack` frame. In
+     * `ValidationMode::Eager` it is a single flag; otherwise it walks the derived's dependencies.
+     *
+     * A derived whose inputs changed back and forth, or whose dependencies would recompute equal
+     * values, is dirty even though reading it returns the same value.
+     */
+    pub fn is_dirty(&self) -> bool {
+        self.inner.is_dirty()
+    }
+
+    /**
+     * An alias of `is_dirty`, matching the name used by `DerivedAsync::is_stale` and by
+     * debug-graph nodes.
+     */
+    pub fn is_stale(&self) -> bool {
+        self.is_dirty()
+    }
+
+    /**
+     * Find out whether reading the derived would do any work, without doing it: the revisions
+     * of the values it read are compared against the revision it was computed at, and no
+     * computation runs, not even the computation of a derived it read. A frozen derived is
+     * always `Validation::Clean`.
+     *
+     * A derived it read counts as dirty as soon as something that derived read changed, since
+     * telling whether it would recompute an equal value means running it. So a `Stale` report
+     * may be a false positive: when a derived created with `Timeline::derived_with_eq`
+     * recomputes an equal value, reading runs that derived but not this one. A `Clean` report
+     * is never wrong.
+     *
+     * ```
+     * use everafter::{Timeline, Validation};
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(1);
+     * let doubled = {
+     *     let count = count.clone();
+     *     timeline.derived(move || count.get() * 2)
+     * };
+     *
+     * doubled.get();
+     * assert_eq!(doubled.validate_only(), Validation::Clean);
+     *
+     * count.set(2);
+     * assert_eq!(
+     *     doubled.validate_only(),
+     *     Validation::Stale { dirty_dependencies: 1 }
+     * );
+     * ```
+     */
+    pub fn validate_only(&self) -> Validation {
+        self.inner.validate_only()
+    }
+
+    /**
+     * How long the derived has been stale: the time since the first write after it was computed
+     * to anything it read, directly or through the deriveds it read. `None` if it isn't stale,
+     * was never computed, or was computed before the oldest revision the timeline still has a
+     * time for. Like `is_dirty`, this doesn't recompute anything.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(1);
+     * let doubled = {
+     *     let cell = cell.clone();
+     *     timeline.derived(move || cell.get() * 2)
+     * };
+     *
+     * doubled.get();
+     * assert_eq!(doubled.staleness_age(), None);
+     *
+     * cell.set(2);
+     * assert!(doubled.staleness_age().is_some());
+     * ```
+     */
+    #[cfg(feature = "revision-timestamps")]
+    pub fn staleness_age(&self) -> Option<Duration> {
+        if !self.inner.is_dirty() {
+            return None;
+        }
+
+        let revision = {
+            let state = self.inner.lock();
+            state.value.as_ref()?;
+            state.revision
+        };
+
+        let keys = reachable_keys(vec![self.reactive_tag()]);
+        let times = self.inner.timeline.revision_times();
+        let since = times.first_written(revision, &keys)?;
+
+        Some(times.now().saturating_duration_since(since))
+    }
+
+    /**
+     * Stop the derived from recomputing: until `unfreeze` is called, reads return the cached
+     * value even after its dependencies changed, and dependents see it unchanged. A derived that
+     * was never computed is still computed by the first read.
+     *
+     * The dependencies the derived read are kept while it is frozen, so once it is unfrozen it
+     * validates them against the revision it was computed at like any other derived.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(1);
+     *
+     * let doubled = {
+     *     let cell = cell.clone();
+     *     timeline.derived(move || cell.get() * 2)
+     * };
+     *
+     * assert_eq!(doubled.get(), 2);
+     *
+     * doubled.freeze();
+     * cell.set(5);
+     * assert_eq!(doubled.get(), 2);
+     *
+     * doubled.unfreeze();
+     * assert_eq!(doubled.get(), 10);
+     * ```
+     */
+    pub fn freeze(&self) {
+        self.inner.frozen.store(true, Ordering::SeqCst);
+    }
+
+    /**
+     * Let a frozen derived recompute again. If anything it read changed while it was frozen, it
+     * recomputes once, right away.
+     */
+    pub fn unfreeze(&self) {
+        if !self.inner.frozen.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
+        self.inner.catch_up();
+    }
+
+    /**
+     * Whether `freeze` was called without a matching `unfreeze`.
+     */
+    pub fn is_frozen(&self) -> bool {
+        self.inner.frozen.load(Ordering::SeqCst)
+    }
+
+    /**
+     * The number of distinct values the derived read the last time it was computed. This doesn't
+     * bring the derived up to date.
+     */
+    pub fn dependency_count(&self) -> usize {
+        self.inner.lock().dependencies.len()
+    }
+
+    /**
+     * How many times the derived recomputed and how many reads hit its cache. This doesn't bring
+     * the derived up to date.
+     */
+    pub fn stats(&self) -> DerivedStats {
+        let state = self.inner.lock();
+
+        DerivedStats {
+            runs: state.runs,
+            hits: state.hits,
+            recomputed_at: state.recomputed_at,
+        }
+    }
+
+    /**
+     * Whether the derived tracked nothing the last time it was computed. A constant derived can
+     * never become dirty, and reading it isn't tracked either. A derived that was never computed
+     * isn't constant yet.
+     */
+    pub fn is_const(&self) -> bool {
+        self.inner.lock().is_constant()
+    }
+
+    /**
+     * The derived's tag, which tracks the derived without cloning its value. Its revision is
+     * the one dependents observe.
+     */
+    pub fn tag(&self) -> Tag {
+        Tag::new(
+            self.reactive_tag(),
+            self.inner.label.clone(),
+            self.inner.timeline.clone(),
+        )
+    }
+
+    fn reactive_tag(&self) -> ReactiveTag {
+        ReactiveTag::Computed(self.inner.clone())
+    }
+
+    /**
+     * Call `f` with the value, without cloning it. Like `get`, this brings the derived up to
+     * date and records it as a dependency of the enclosing computation.
+     *
+     * The derived stays locked while `f` runs, so `f` must not read it again, directly or
+     * through another derived. Doing so panics instead of waiting for the lock forever; use
+     * `get` to read a clone of the value instead.
+     */
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        /**
+         * Marks the derived as lent on this thread until `with` returns or unwinds.
+         */
+        struct Lending;
+
+        impl Drop for Lending {
+            fn drop(&mut self) {
+                LENDING.with(|lending| lending.borrow_mut().pop());
+                LENDS.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        if let Some(cycle) = ComputeStack::cycle(self.inner.id) {
+            panic!("{}", cycle);
+        }
+
+        if let Some(value) = self.forked() {
+            return f(&value);
+        }
+
+        let state = self.tracked();
+        LENDING.with(|lending| lending.borrow_mut().push(self.inner.id));
+        LENDS.fetch_add(1, Ordering::SeqCst);
+        let _lending = Lending;
+
+        f(state
+            .value
+            .as_ref()
+            .expect("an up to date derived has a value"))
+    }
+
+    /**
+     * A derived that reads one part of this derived's value, like a field of a struct. It only
+     * advances its revision when the part it selects changed, so its readers don't recompute
+     * after changes to the rest of the value. Each recomputation of this derived is compared
+     * once per selector, which keeps a clone of the part it selected.
+     *
+     * ```
+     * use std::sync::{
+     *     atomic::{AtomicUsize, Ordering},
+     *     Arc,
+     * };
+     * use everafter::Timeline;
+     *
+     * #[derive(Clone)]
+     * struct Layout {
+     *     width: u32,
+     *     height: u32,
+     * }
+     *
+     * let timeline = Timeline::new();
+     * let height = timeline.cell(10);
+     * let layout = {
+     *     let height = height.clone();
+     *     timeline.derived(move || Layout { width: 80, height: height.get() })
+     * };
+     *
+     * let width = layout.select(|layout| &layout.width);
+     * let renders = Arc::new(AtomicUsize::new(0));
+     * let ruler = {
+     *     let renders = renders.clone();
+     *     timeline.derived(move || {
+     *         renders.fetch_add(1, Ordering::SeqCst);
+     *         "-".repeat(width.get() as usize)
+     *     })
+     * };
+     *
+     * ruler.get();
+     * height.set(20);
+     * ruler.get();
+     * assert_eq!(renders.load(Ordering::SeqCst), 1);
+     * ```
+     */
+    pub fn select<U>(&self, project: impl Fn(&T) -> &U + MaybeSync + 'static) -> Derived<U>
+    where
+        U: Clone + PartialEq + MaybeSend + 'static,
+    {
+        let parent = self.clone();
+
+        Derived::with_eq(self.inner.timeline.clone(), move || {
+            parent.with(|value| project(value).clone())
+        })
+    }
+
+    /**
+     * The value computed from the values of the fork that is running on this thread, if the
+     * fork wrote something the derived depends on, recorded in the current `ComputeStack`
+     * frame. The derived's own value is only up to date with the timeline, so a derived that
+     * has to recompute is computed from the fork's values too. Neither changes what the
+     * derived cached.
+     */
+    fn forked(&self) -> Option<Arc<T>> {
+        let fork = fork::current(&self.inner.timeline)?;
+        let key = self.inner.slot.key();
+
+        let value = match fork.computed::<T>(key) {
+            Some(value) => value,
+            None if !self.inner.is_dirty() && !fork.affects(self.reactive_tag()) => return None,
+            None => {
+                let at = fork.current();
+                let (value, dependencies, owner) = ComputeStack::track_computation(
+                    self.inner.id,
+                    &self.inner.label,
+                    &self.inner.timeline,
+                    || (self.inner.computation)(),
+                );
+                ComputeStack::recycle(dependencies);
+                owner.dispose();
+
+                let value = Arc::new(value);
+                fork.store(key, value.clone(), at);
+                value
+            }
+        };
+
+        ComputeStack::consume(&self.inner.timeline, &self.inner.label, self.reactive_tag());
+        Some(value)
+    }
+
+    /**
+     * Bring the derived up to date and record it in the current `ComputeStack` frame.
+     */
+    fn tracked(&self) -> MutexGuard<'_, DerivedState<T>> {
+        let (mut state, ran) = self.inner.refreshed();
+
+        if !ran {
+            state.hits += 1;
+
+            if self.inner.timeline.validates_on_read() {
+                let mut visited = HashSet::new();
+
+                if let Some(stale) = self.inner.find_stale(&state, &mut visited) {
+                    panic!(
+                        "read a stale value of {}: {}. Deriveds only observe a write once the \
+                         timeline's revision advances past it, which a transaction only does \
+                         when it closes.",
+                        self.inner.label, stale
+                    );
+                }
+            }
+        }
+
+        // a derived that read nothing can never change, so reading it doesn't have to be
+        // recorded either
+        if !state.is_constant() {
+            ComputeStack::consume(&self.inner.timeline, &self.inner.label, self.reactive_tag());
+        }
+
+        state
+    }
+
+    /**
+     * Call `callback` whenever the derived's revision advances, starting with the next change.
+     * The derived is computed right away so there is something to compare with, and after that
+     * it is brought up to date whenever one of its dependencies is written, when the timeline's
+     * scheduler flushes. A recomputation that `derived_with_eq` considers equal to the previous
+     * value doesn't advance the revision, so it doesn't call `callback`.
+     *
+     * Like `Cell::subscribe`, the callback stops once the returned handle is dropped.
+     *
+     * ```
+     * use std::sync::{
+     *     atomic::{AtomicUsize, Ordering},
+     *     Arc,
+     * };
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(3);
+     * let changes = Arc::new(AtomicUsize::new(0));
+     *
+     * let is_even = {
+     *     let cell = cell.clone();
+     *     timeline.derived_with_eq(move || cell.get() % 2 == 0)
+     * };
+     *
+     * let subscription = {
+     *     let changes = changes.clone();
+     *     is_even.subscribe(move || {
+     *         changes.fetch_add(1, Ordering::SeqCst);
+     *     })
+     * };
+     *
+     * cell.set(5);
+     * assert_eq!(changes.load(Ordering::SeqCst), 0);
+     *
+     * cell.set(6);
+     * assert_eq!(changes.load(Ordering::SeqCst), 1);
+     * ```
+     */
+    pub fn subscribe(&self, callback: impl Fn() + MaybeSync + 'static) -> SubscriptionHandle {
+        let derived = self.clone();
+        let subscribed = AtomicBool::new(false);
+
+        let effect = Effect::new(self.inner.timeline.clone(), move || {
+            // the callback may read the derived, so its state can't stay locked
+            drop(derived.tracked());
+
+            // the effect runs once to start tracking the derived, which isn't a change
+            if subscribed.swap(true, Ordering::SeqCst) {
+                callback();
+            }
+        });
+
+        effect.into_subscription()
+    }
+}
+
+/**
+ * Hooks for checking a derived's bookkeeping from tests. None of them bring the derived up to
+ * date, so they describe it as of its last computation.
+ */
+#[cfg(feature = "testing")]
+impl<T> Derived<T>
+where
+    T: MaybeSend + 'static,
+{
+    /**
+     * The newest revision the last computation consumed, which the dependencies are validated
+     * against.
+     */
+    pub fn recorded_revision(&self) -> Revision {
+        self.inner.lock().revision
+    }
+
+    /**
+     * The last revision each dependency reported, in the order the last computation read them. A
+     * derived dependency reports the revision it had when it was last validated.
+     */
+    pub fn dependency_revisions(&self) -> Vec<Revision> {
+        let state = self.inner.lock();
+        state
+            .dependencies
+            .tags()
+            .iter()
+            .map(ReactiveTag::last_revision)
+            .collect()
+    }
+
+    /**
+     * Run the computation again and return what it computes, without caching the value or
+     * recording any of its reads. Deriveds it reads are brought up to date as usual.
+     */
+    pub fn force_recompute_for_test(&self) -> T {
+        let (value, dependencies) = ComputeStack::track(|| (self.inner.computation)());
+        ComputeStack::recycle(dependencies);
+        value
+    }
+}
+
+impl<T, E> Derived<Result<T, Arc<E>>>
+where
+    T: MaybeSend + 'static,
+    E: MaybeSync + 'static,
+{
+    /**
+     * Like `new`, for a computation that can fail. Each error is put behind an `Arc` once, when
+     * the computation returns it, so reading it or passing it on doesn't clone it.
+     */
+    pub(crate) fn try_new(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> Result<T, E> + MaybeSync + 'static,
+    ) -> Derived<Result<T, Arc<E>>> {
+        Derived::new(timeline, move || computation().map_err(share))
+    }
+
+    /**
+     * A derived that applies `f` to this one's value when it is `Ok`, like `map`. When this
+     * derived failed, the new one fails with the same error without calling `f`, and without
+     * cloning the value or the error.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let input = timeline.cell("12");
+     *
+     * let parsed = {
+     *     let input = input.clone();
+     *     timeline.try_derived(move || input.get().parse::<u32>())
+     * };
+     * let doubled = parsed.try_map(|n| Ok(n * 2));
+     *
+     * assert_eq!(doubled.get().unwrap(), 24);
+     *
+     * input.set("twelve");
+     * assert!(doubled.get().is_err());
+     * ```
+     */
+    pub fn try_map<U: MaybeSend + 'static>(
+        &self,
+        f: impl Fn(&T) -> Result<U, E> + MaybeSync + 'static,
+    ) -> Derived<Result<U, Arc<E>>> {
+        let this = self.clone();
+        let timeline = self.inner.timeline.clone();
+
+        Derived::new(timeline, move || {
+            this.with(|result| match result {
+                Ok(value) => f(value).map_err(share),
+                Err(error) => Err(error.clone()),
+            })
+        })
+    }
+}
+
+// the error is only `Send` and `Sync` when `E` is, like every other value a derived stores
+#[allow(clippy::arc_with_non_send_sync)]
+fn share<E>(error: E) -> Arc<E> {
+    Arc::new(error)
+}
+
+impl<T> Derived<T>
+where
+    T: PartialEq + MaybeSend + 'static,
+{
+    /**
+     * `with_eq_by` with `PartialEq`.
+     */
+    pub(crate) fn with_eq(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_eq_by(timeline, computation, |old: &T, new: &T| old == new)
+    }
+}
+
+impl<T> Derived<T>
+where
+    T: Clone + PartialEq + MaybeSend + 'static,
+{
+    /**
+     * Call `callback` with the derived's value right away, and then with each new value once the
+     * writes that changed it settle. A recomputation that produces a value equal to the last one
+     * delivered doesn't call `callback`, and a transaction delivers at most one value.
+     *
+     * ```
+     * use std::sync::{Arc, Mutex};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(1);
+     * let parity = {
+     *     let count = count.clone();
+     *     timeline.derived(move || count.get() % 2)
+     * };
+     * let seen = Arc::new(Mutex::new(vec![]));
+     *
+     * let _watch = {
+     *     let seen = seen.clone();
+     *     parity.watch(move |parity| seen.lock().unwrap().push(*parity))
+     * };
+     *
+     * count.set(3);
+     * count.set(4);
+     * assert_eq!(*seen.lock().unwrap(), vec![1, 0]);
+     * ```
+     */
+    pub fn watch(&self, callback: impl Fn(&T) + MaybeSync + 'static) -> WatchHandle {
+        let derived = self.clone();
+        watch(self.inner.timeline.clone(), move || derived.get(), callback)
+    }
+
+    /**
+     * Like `watch`, but each value is sent to `sender`. Values stop being sent once the
+     * receiver is dropped, but the watch stays registered until the handle is dropped.
+     */
+    #[cfg(feature = "channel")]
+    pub fn watch_into(&self, sender: std::sync::mpsc::Sender<T>) -> WatchHandle {
+        self.watch(move |value| {
+            let _ = sender.send(value.clone());
+        })
+    }
+}
+
+#[cfg(feature = "history")]
+impl<T> Derived<T>
+where
+    T: Clone + Display + MaybeSend + MaybeSync + 'static,
+{
+    /**
+     * Give the derived a stable key, which `Timeline::stop_recording` records its value under,
+     * formatted with `Display`. `WriteLog::replay` compares the recorded value with the value of
+     * the derived with the same key in the timeline the log is replayed onto. A key can only
+     * name one live derived of a timeline.
+     */
+    pub fn keyed(self, key: impl Into<Arc<str>>) -> Derived<T> {
+        let derived = Arc::downgrade(&self.inner);
+        let output = Arc::new(KeyedDerived { derived });
+        self.inner.timeline.key_output(key.into(), output);
+        self
+    }
+}
+
+/**
+ * A keyed derived, as its timeline's keys know it.
+ */
+#[cfg(feature = "history")]
+struct KeyedDerived<T> {
+    derived: Weak<DerivedInner<T>>,
+}
+
+#[cfg(feature = "history")]
+impl<T> Output for KeyedDerived<T>
+where
+    T: Clone + Display + MaybeSend + MaybeSync + 'static,
+{
+    fn is_alive(&self) -> bool {
+        self.derived
+            .upgrade()
+            .is_some_and(|inner| !inner.is_retired())
+    }
+
+    fn output(&self) -> Option<String> {
+        let inner = self.derived.upgrade().filter(|inner| !inner.is_retired())?;
+        inner.handles.fetch_add(1, Ordering::SeqCst);
+        let derived = Derived { inner };
+
+        Some(ComputeStack::untrack(|| derived.get()).to_string())
+    }
+}
+
+impl<T> Derived<T>
+where
+    T: Clone + MaybeSend + 'static,
+{
+    pub fn get(&self) -> T {
+        match self.try_get() {
+            Ok(value) => value,
+            Err(cycle) => panic!("{}", cycle),
+        }
+    }
+
+    pub fn try_get(&self) -> Result<T, CycleError> {
+        if let Some(cycle) = ComputeStack::cycle(self.inner.id) {
+            return Err(cycle);
+        }
+
+        if let Some(value) = self.forked() {
+            return Ok(T::clone(&value));
+        }
+
+        Ok(self
+            .tracked()
+            .value
+            .clone()
+            .expect("an up to date derived has a value"))
+    }
+
+    /**
+     * A derived that applies `f` to this one's value. Like any derived, it isn't computed until
+     * it is read, and it only recomputes after this derived changed.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let name = timeline.cell("world");
+     *
+     * let greeting = {
+     *     let name = name.clone();
+     *     timeline.derived(move || format!("hello {}", name.get()))
+     * };
+     * let shouted = greeting.map(|greeting| greeting.to_uppercase());
+     *
+     * assert_eq!(shouted.get(), "HELLO WORLD");
+     * ```
+     */
+    pub fn map<U: MaybeSend + 'static>(
+        self,
+        f: impl Fn(&T) -> U + MaybeSync + 'static,
+    ) -> Derived<U> {
+        let timeline = self.inner.timeline.clone();
+        Derived::new(timeline, move || self.with(&f))
+    }
+}
+
+impl<T> ReactiveValue for Derived<T>
+where
+    T: Clone + MaybeSend + 'static,
+{
+    type Value = T;
+
+    fn get(&self) -> T {
+        Derived::get(self)
+    }
+
+    fn track(&self) {
+        drop(self.tracked());
+    }
+
+    fn revision(&self) -> Revision {
+        Derived::revision(self)
+    }
+}
+
+/**
+ * A derived that combines the values of `a` and `b` with `f`. It isn't computed until it is
+ * read, and it recomputes after either of them changed.
+ *
+ * ```
+ * use everafter::{zip, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let items = timeline.cell(vec![2, 4]);
+ *
+ * let count = {
+ *     let items = items.clone();
+ *     timeline.derived(move || items.get().len())
+ * };
+ * let sum = {
+ *     let items = items.clone();
+ *     timeline.derived(move || items.get().iter().sum::<usize>())
+ * };
+ * let average = zip(&sum, &count, |sum, count| sum / count);
+ *
+ * assert_eq!(average.get(), 3);
+ * ```
+ */
+pub fn zip<A, B, U>(
+    a: &Derived<A>,
+    b: &Derived<B>,
+    f: impl Fn(&A, &B) -> U + MaybeSync + 'static,
+) -> Derived<U>
+where
+    A: Clone + MaybeSend + 'static,
+    B: Clone + MaybeSend + 'static,
+    U: MaybeSend + 'static,
+{
+    let (a, b) = (a.clone(), b.clone());
+    let timeline = a.inner.timeline.clone();
+
+    Derived::new(timeline, move || {
+        // a derived can't be read inside its own `with`
+        if a.inner.id == b.inner.id {
+            let b = b.get();
+            return a.with(|a| f(a, &b));
+        }
+
+        // validating `b` can read `a`, which has to happen before `a` is locked
+        b.revision();
+        a.with(|a| b.with(|b| f(a, b)))
+    })
+}
+
+impl<T> DerivedState<T> {
+    /**
+     * Whether the derived was computed without reading anything tracked, so no write can ever
+     * make it stale.
+     */
+    fn is_constant(&self) -> bool {
+        self.value.is_some() && self.dependencies.is_empty()
+    }
+}
+
+impl<T: MaybeSend> DerivedInner<T> {
+    fn up_to_date(&self) -> MutexGuard<'_, DerivedState<T>> {
+        self.refreshed().0
+    }
+
+    /**
+     * Bring the derived up to date, and report whether that ran the computation.
+     */
+    fn refreshed(&self) -> (MutexGuard<'_, DerivedState<T>>, bool) {
+        // a derived that is being computed holds its own lock, so a re-entrant read must be
+        // reported before trying to take it again
+        if let Some(cycle) = ComputeStack::cycle(self.id) {
+            panic!("{}", cycle);
+        }
+
+        let state = self.lock();
+        self.check_poisoned(&state);
+
+        if self.is_settled_at(&state) {
+            return (state, false);
+        }
+
+        drop(state);
+
+        // the dependencies are settled from the bottom up before this derived validates, so a
+        // deep graph is limited by the heap rather than by the thread's stack
+        ComputeStack::settling(|| {
+            settle(self);
+            self.refresh()
+        })
+    }
+
+    /**
+     * Bring a derived that was just unfrozen up to date. Dependents may have validated against
+     * the frozen value at the current revision, so a changed value is published as a write,
+     * which they observe like any other.
+     */
+    fn catch_up(&self) {
+        let state = self.lock();
+
+        // a derived that was never computed waits for its first read, like any other
+        if state.value.is_none() || state.poisoned.is_some() {
+            return;
+        }
+
+        let before = state.changed_at;
+        drop(state);
+
+        let (state, _) = self.refreshed();
+        let changed = state.changed_at != before;
+        drop(state);
+
+        if changed {
+            self.timeline.write(|revision| {
+                self.lock().changed_at = revision;
+                self.dependents.mark_dirty();
+            });
+        }
+    }
+
+    /**
+     * Whether the derived was already validated at the current revision, so reading it won't
+     * look at its dependencies. A derived that reads an external source is only settled until
+     * the outermost read that validated it is done.
+     */
+    fn is_settled_at(&self, state: &DerivedState<T>) -> bool {
+        if state.value.is_none() {
+            return false;
+        }
+
+        if state.is_constant() || self.frozen.load(Ordering::SeqCst) {
+            return true;
+        }
+
+        state.verified_at == self.timeline.now()
+            && (!state.polled
+                || (state.settled_in.is_some() && state.settled_in == ComputeStack::pass()))
+    }
+
+    /**
+     * The first dependency that changed after the value in `state` was computed, in this derived
+     * or in a derived it read, judged from the revisions they recorded. A frozen derived keeps
+     * its value on purpose, so it is never stale.
+     */
+    fn find_stale(
+        &self,
+        state: &DerivedState<T>,
+        visited: &mut HashSet<NodeKey>,
+    ) -> Option<String> {
+        if state.value.is_none() || self.frozen.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        for tag in state.dependencies.tags() {
+            let revision = tag.last_revision();
+
+            if revision > state.revision {
+                return Some(format!(
+                    "{} was computed at {}, but {} changed at {}",
+                    self.label,
+                    state.revision,
+                    tag.label(),
+                    revision
+                ));
+            }
+
+            if let ReactiveTag::Computed(tag) = tag {
+                if visited.insert(tag.key()) {
+                    if let Some(stale) = tag.stale_dependency(visited) {
+                        return Some(stale);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn check_poisoned(&self, state: &DerivedState<T>) {
+        if let Some(message) = &state.poisoned {
+            panic!(
+                "{} was poisoned by a panic in its computation: {}",
+                self.label, message
+            );
+        }
+    }
+
+    /**
+     * Validate the dependencies, and recompute if one of them changed. Reports whether the
+     * computation ran.
+     */
+    fn refresh(&self) -> (MutexGuard<'_, DerivedState<T>>, bool) {
+        let mut state = self.lock();
+        self.check_poisoned(&state);
+
+        if state.is_constant() {
+            return (state, false);
+        }
+
+        let mut now = self.timeline.now();
+
+        if state.value.is_some() {
+            if self.is_settled_at(&state) {
+                return (state, false);
+            }
+
+            // writes that land from here on must mark the derived dirty again
+            self.dirty.store(false, Ordering::SeqCst);
+
+            // validating the dependencies can recompute other deriveds, which must see this one
+            // on the stack if they read it back
+            let (changed, validated, _) =
+                ComputeStack::track_computation(self.id, &self.label, &self.timeline, || {
+                    state.dependencies.first_changed(state.revision)
+                });
+            ComputeStack::recycle(validated);
+
+            match changed {
+                Some((tag, revision)) => {
+                    state.invalidation = Some(Invalidation::caused_by(&tag, revision));
+
+                    // a polled source that changed advanced the timeline while it was validated
+                    now = self.timeline.now();
+                }
+                None => {
+                    state.verified_at = now;
+                    state.settled_in = ComputeStack::pass();
+                    return (state, false);
+                }
+            }
+        }
+
+        self.dirty.store(false, Ordering::SeqCst);
+
+        // what the last computation owned is disposed once the lock is released, see `release`
+        let disowned = state.owner.take();
+
+        let started = self.timeline.is_observed().then(Instant::now);
+        let computed = panic::catch_unwind(AssertUnwindSafe(|| {
+            ComputeStack::track_computation(self.id, &self.label, &self.timeline, || {
+                (self.computation)()
+            })
+        }));
+
+        state.runs += 1;
+        state.recomputed_at = Some(now);
+
+        let (value, dependencies, owner) = match computed {
+            Ok(computed) => computed,
+            Err(payload) => {
+                state.poisoned = Some(panic_message(&*payload));
+                drop(state);
+                self.release(disowned, None);
+                panic::resume_unwind(payload)
+            }
+        };
+
+        if let Some(started) = started {
+            self.timeline.observe(|observer| {
+                let node = ObservedNode::new(&self.label);
+                observer.node_recomputed(&node, started.elapsed(), dependencies.len())
+            });
+        }
+
+        let mut tracked_in = self.timeline.eager_epoch();
+
+        if tracked_in.is_some() {
+            for tag in dependencies.tags() {
+                if !tag.add_dependent(self.id, self.this.clone()) {
+                    tracked_in = None;
+                }
+            }
+        }
+
+        // a write that lands while the computation is running may or may not have been observed,
+        // so never claim to be newer than the revision the computation started at. If such a
+        // write happened, the next validation sees it and recomputes.
+        let revision = dependencies.revision().min(now);
+
+        let unchanged = match (&self.eq, &state.value) {
+            (Some(eq), Some(old)) => eq(old, &value),
+            _ => false,
+        };
+
+        if !unchanged {
+            state.changed_at = revision;
+        }
+
+        let replaced = state.value.replace(value);
+        state.owner = Some(owner);
+        state.revision = revision;
+        let previous = mem::replace(&mut state.dependencies, dependencies);
+        state.verified_at = now;
+        state.settled_in = ComputeStack::pass();
+        state.polled = state.dependencies.is_polled();
+        state.tracked_in = tracked_in;
+        ComputeStack::recycle(previous);
+        drop(state);
+
+        self.release(disowned, replaced);
+        (self.lock(), true)
+    }
+
+    /**
+     * Dispose what a replaced run owned, children first, and then clean up the value it
+     * computed. This runs once the new value is stored and the derived's lock is released, so
+     * cleanups and the teardown of child effects can read the derived, or anything that reads
+     * it. What they read isn't tracked.
+     */
+    fn release(&self, owner: Option<Arc<Scope>>, value: Option<T>) {
+        ComputeStack::untrack(|| {
+            if let Some(owner) = owner {
+                owner.dispose();
+            }
+
+            if let (Some(cleanup), Some(value)) = (&self.cleanup, value) {
+                cleanup(value);
+            }
+        });
+    }
+
+    fn validate_only(&self) -> Validation {
+        if !self.is_dirty() {
+            return Validation::Clean;
+        }
+
+        let state = self.lock();
+
+        if state.value.is_none() {
+            return Validation::Stale {
+                dirty_dependencies: 0,
+            };
+        }
+
+        let dirty_dependencies = state
+            .dependencies
+            .tags()
+            .iter()
+            .filter(|tag| tag.changed_since(state.revision))
+            .count();
+
+        Validation::Stale { dirty_dependencies }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("a panic without a message")
+    }
+}
+
+impl<T: MaybeSend> ComputedTag for DerivedInner<T> {
+    fn validate(&self) -> Revision {
+        if self.is_retired() {
+            return self.lock().changed_at;
+        }
+
+        self.up_to_date().changed_at
+    }
+
+    fn key(&self) -> NodeKey {
+        self.slot.key()
+    }
+
+    fn last_revision(&self) -> Revision {
+        self.lock().changed_at
+    }
+
+    fn is_dirty(&self) -> bool {
+        let state = self.lock();
+
+        if state.value.is_none() {
+            true
+        } else if self.frozen.load(Ordering::SeqCst) || self.is_retired() {
+            false
+        } else if state.tracked_in.is_some() && state.tracked_in == self.timeline.eager_epoch() {
+            self.dirty.load(Ordering::SeqCst)
+        } else {
+            state.dependencies.changed_since(state.revision)
+        }
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn last_invalidation(&self) -> Option<Invalidation> {
+        self.lock().invalidation.clone()
+    }
+
+    fn dependencies(&self) -> Vec<ReactiveTag> {
+        // a derived that is computing holds its own lock
+        match self.state.try_lock() {
+            Some(state) => state.dependencies.tags().to_vec(),
+            None => vec![],
+        }
+    }
+
+    fn dependents(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)> {
+        self.dependents.live()
+    }
+
+    fn stale_dependency(&self, visited: &mut HashSet<NodeKey>) -> Option<String> {
+        if self.is_retired() {
+            return None;
+        }
+
+        // a derived that is computing is brought up to date once it returns
+        let state = self.state.try_lock()?;
+        self.find_stale(&state, visited)
+    }
+
+    fn add_dependent(&self, id: ComputationId, dependent: Weak<dyn Dependent>) -> bool {
+        self.dependents.add(id, dependent);
+
+        let tracked_in = self.lock().tracked_in;
+        tracked_in.is_some() && tracked_in == self.timeline.eager_epoch()
+    }
+
+    fn is_polled(&self) -> bool {
+        self.lock().polled
+    }
+
+    fn is_settled(&self) -> bool {
+        // a derived that is being computed can't be validated, and reading it reports the cycle
+        if ComputeStack::cycle(self.id).is_some() || self.is_retired() {
+            return true;
+        }
+
+        self.is_settled_at(&self.lock())
+    }
+
+    fn enter(&self) {
+        ComputeStack::enter(self.id, &self.label, &self.timeline);
+    }
+
+    fn exit(&self) {
+        ComputeStack::exit();
+    }
+
+    fn unsettled_dependency(&self, from: usize) -> Option<(usize, Arc<dyn ComputedTag>)> {
+        let state = self.lock();
+
+        if state.value.is_none()
+            || state.poisoned.is_some()
+            || self.is_retired()
+            || self.is_settled_at(&state)
+        {
+            return None;
+        }
+
+        // the same order `first_changed` validates in: stop at the first dependency that
+        // changed, since the ones after it may not be read again
+        for (index, tag) in state.dependencies.tags().iter().enumerate().skip(from) {
+            if let ReactiveTag::Computed(computed) = tag {
+                if !computed.is_settled() {
+                    return Some((index, computed.clone()));
+                }
+            }
+
+            if tag.revision() > state.revision {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+impl<T: MaybeSend> Dependent for DerivedInner<T> {
+    fn mark_dirty(&self) {
+        if self.is_retired() {
+            return;
+        }
+
+        // a derived that is already dirty already marked its dependents
+        if !self.dirty.swap(true, Ordering::SeqCst) {
+            self.dependents.mark_dirty();
+        }
+    }
+
+    fn dependents(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)> {
+        self.dependents.live()
+    }
+
+    fn bring_up_to_date(&self) {
+        if !self.is_retired() {
+            drop(self.refreshed());
+        }
+    }
+}
+
+impl<T: MaybeSend> Registered for DerivedInner<T> {
+    fn label(&self) -> Arc<Label> {
+        self.label.clone()
+    }
+
+    fn last_revision(&self) -> Revision {
+        self.lock().changed_at
+    }
+
+    fn revisions(&self, out: &mut Vec<Revision>) {
+        let state = self.lock();
+        out.extend_from_slice(&[state.revision, state.changed_at, state.verified_at]);
+        out.extend(state.recomputed_at);
+    }
+
+    fn renumber(&self, renumber: &dyn Fn(Revision) -> Revision) {
+        let mut state = self.lock();
+        state.revision = renumber(state.revision);
+        state.changed_at = renumber(state.changed_at);
+        state.verified_at = renumber(state.verified_at);
+        state.recomputed_at = state.recomputed_at.map(renumber);
+    }
+
+    fn reset(&self) {
+        // every dependency is now at the initial revision, so claiming to have consumed nothing
+        // makes the next read recompute. A derived that read nothing stays as it is.
+        let mut state = self.lock();
+
+        if state.is_constant() {
+            return;
+        }
+
+        state.revision = Revision::CONSTANT;
+        state.changed_at = Revision::initial();
+        state.verified_at = Revision::UNINITIALIZED;
+        state.settled_in = None;
+        state.tracked_in = None;
+        // the revision belongs to the timeline's previous life
+        state.recomputed_at = None;
+    }
+
+    fn dependency_capacity(&self) -> usize {
+        self.lock().dependencies.capacity()
+    }
+
+    fn dependent_capacity(&self) -> usize {
+        self.dependents.capacity()
+    }
+
+    fn compact_dependents(&self) -> usize {
+        self.dependents.compact()
+    }
+
+    #[cfg(feature = "debug-graph")]
+    fn node(&self) -> GraphNode {
+        let state = self.lock();
+
+        GraphNode {
+            key: self.slot.key(),
+            kind: self.label.kind(),
+            id: self.label.id(),
+            label: self.label.to_string(),
+            revision: state.changed_at,
+            stale: state.value.is_none() || state.dependencies.changed_since(state.revision),
+            dependencies: dependency_keys(&state.dependencies),
+        }
+    }
+}
+
+impl<T> Clone for Derived<T> {
+    fn clone(&self) -> Self {
+        self.inner.handles.fetch_add(1, Ordering::SeqCst);
+
+        Derived {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Derived<T> {
+    fn drop(&mut self) {
+        if self.inner.handles.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.inner.retire();
+        }
+    }
+}
+
+impl<T> DerivedInner<T> {
+    fn is_retired(&self) -> bool {
+        self.handles.load(Ordering::SeqCst) == 0
+    }
+
+    /**
+     * Lock the derived's state, or panic if a `Derived::with` of this derived is running on
+     * this thread, which already holds the lock.
+     */
+    fn lock(&self) -> MutexGuard<'_, DerivedState<T>> {
+        if LENDS.load(Ordering::SeqCst) > 0
+            && LENDING.with(|lending| lending.borrow().contains(&self.id))
+        {
+            panic!(
+                "{} was read inside its own `Derived::with`, which keeps it locked until the \
+                 callback returns. Read the value with `get` to read the derived again.",
+                self.label
+            );
+        }
+
+        self.state.lock()
+    }
+
+    /**
+     * Let go of what the derived read once its last handle is dropped. A derived that is
+     * computing holds its own lock, and what it records once it returns is never validated.
+     */
+    fn retire(&self) {
+        let dependencies = match self.state.try_lock() {
+            Some(mut state) => mem::take(&mut state.dependencies),
+            None => return,
+        };
+
+        drop(dependencies);
+    }
+}
+
+impl<T> Drop for DerivedInner<T> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut();
+
+        // children are disposed before their parent
+        if let Some(owner) = state.owner.take() {
+            owner.dispose();
+        }
+
+        if let (Some(cleanup), Some(value)) = (&self.cleanup, state.value.take()) {
+            cleanup(value);
+        }
+
+        let label = &self.label;
+        self.timeline
+            .observe(|observer| observer.node_dropped(&ObservedNode::new(label)));
+    }
+}
+
+impl<T> Debug for DerivedInner<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Derived<{}>({})", std::any::type_name::<T>(), self.label)
+    }
+}
+
+impl<T> Debug for Derived<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
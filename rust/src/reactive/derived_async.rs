@@ -0,0 +1,406 @@
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::{self, ReactiveTag},
+    timeline::{
+        state::TimelineState, tracked_async, ComputeStack, Dependencies, Revision, TrackedFuture,
+    },
+};
+
+#[cfg(feature = "debug-graph")]
+use super::graph::{dependency_keys, GraphNode};
+use super::{
+    bounds::{Computation, ComputationFuture, MaybeSend, MaybeSync},
+    label::Label,
+    observer::ObservedNode,
+    registry::{Entry, Registered},
+};
+
+type BoxedFuture<T> = Pin<Box<dyn ComputationFuture<T>>>;
+
+/**
+ * A derived value computed by a future. Everything the future reads is tracked across its await
+ * points, so reads before and after an `.await` land in the same set of dependencies, and a
+ * change to any of them runs the whole future again from the start.
+ *
+ * Nothing is computed until the value is awaited with `resolve`, which drives the future with
+ * the caller's executor. Once a run completes, its value is cached and `get` returns it to
+ * synchronous code, which is invalidated whenever a newer run completes.
+ *
+ * The future can't read its own `DerivedAsync`.
+ *
+ * ```
+ * use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let user = timeline.cell("alice");
+ *
+ * let greeting = {
+ *     let user = user.clone();
+ *     timeline.derived_async(move || {
+ *         let user = user.clone();
+ *         async move { format!("hello, {}", user.get()) }
+ *     })
+ * };
+ *
+ * assert_eq!(greeting.get(), None, "nothing has awaited it yet");
+ *
+ * let mut cx = Context::from_waker(Waker::noop());
+ * assert_eq!(
+ *     pin!(greeting.resolve()).poll(&mut cx),
+ *     Poll::Ready("hello, alice".to_string())
+ * );
+ * assert_eq!(greeting.get().as_deref(), Some("hello, alice"));
+ *
+ * user.set("bob");
+ * assert!(greeting.is_stale());
+ * assert_eq!(
+ *     pin!(greeting.resolve()).poll(&mut cx),
+ *     Poll::Ready("hello, bob".to_string())
+ * );
+ * ```
+ */
+pub struct DerivedAsync<T> {
+    inner: Arc<AsyncInner<T>>,
+}
+
+static NEXT_ASYNC: AtomicU64 = AtomicU64::new(1);
+
+struct AsyncInner<T> {
+    label: Arc<Label>,
+    computation: Box<dyn Computation<BoxedFuture<T>>>,
+    state: Mutex<AsyncState<T>>,
+    // written whenever a run completes, so readers of `get` see the new value
+    tag: Arc<inputs::Tag>,
+    timeline: Arc<TimelineState>,
+}
+
+struct AsyncState<T> {
+    value: Option<T>,
+    // everything the run that produced `value` read
+    dependencies: Dependencies,
+    // the timeline's revision when the run that produced `value` started
+    revision: Revision,
+    running: Option<Run<T>>,
+    runs: u64,
+}
+
+/**
+ * A run of the future that hasn't completed yet.
+ */
+struct Run<T> {
+    future: TrackedFuture<BoxedFuture<T>>,
+    started_at: Revision,
+}
+
+impl<T: MaybeSend + 'static> DerivedAsync<T> {
+    // a derived is only `Send` and `Sync` when its timeline is, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new<F: Future<Output = T> + MaybeSend + 'static>(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> F + MaybeSync + 'static,
+    ) -> DerivedAsync<T> {
+        let label = Arc::new(Label::new(
+            "async",
+            NEXT_ASYNC.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        let inner = Arc::new(AsyncInner {
+            computation: Box::new(move || -> BoxedFuture<T> { Box::pin(computation()) }),
+            state: Mutex::new(AsyncState {
+                value: None,
+                dependencies: Dependencies::default(),
+                revision: Revision::CONSTANT,
+                running: None,
+                runs: 0,
+            }),
+            tag: timeline.tag(Some(label.clone())),
+            label,
+            timeline,
+        });
+
+        let registered: Arc<dyn Registered> = inner.clone();
+        inner
+            .timeline
+            .register_value(Entry::Computation(Arc::downgrade(&registered)));
+        inner
+            .timeline
+            .observe(|observer| observer.node_created(&ObservedNode::new(&inner.label)));
+
+        DerivedAsync { inner }
+    }
+}
+
+impl<T> DerivedAsync<T> {
+    /**
+     * Attach a debug label to the derived. A derived can only be named once.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> DerivedAsync<T> {
+        self.inner.label.name(label.into());
+        self
+    }
+
+    /**
+     * The derived's debug label, or a name like `async#2` if it was never named.
+     */
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * The revision at which the latest run completed.
+     */
+    pub fn revision(&self) -> Revision {
+        self.inner.tag.revision.get()
+    }
+
+    /**
+     * Whether awaiting the derived would run the future, because it never completed or because
+     * something the last completed run read has changed since.
+     */
+    pub fn is_stale(&self) -> bool {
+        let state = self.inner.state.lock();
+        state.value.is_none() || state.dependencies.changed_since(state.revision)
+    }
+
+    /**
+     * The number of times the future ran to completion.
+     */
+    pub fn runs(&self) -> u64 {
+        self.inner.state.lock().runs
+    }
+}
+
+impl<T: Clone> DerivedAsync<T> {
+    /**
+     * The value of the latest completed run, or `None` if no run has completed yet, recording a
+     * dependency in the current `ComputeStack` frame. Doesn't run the future, even if the value
+     * is stale.
+     */
+    pub fn get(&self) -> Option<T> {
+        self.inner.consume();
+        self.inner.state.lock().value.clone()
+    }
+
+    /**
+     * A future that resolves to the derived's value once it is up to date. If the last completed
+     * run is stale, or a run that is still in progress read something that has changed since it
+     * started, the future starts over.
+     *
+     * Once the value resolves, the current `ComputeStack` frame depends on the derived and on
+     * everything its value read, so a computation that awaits it is invalidated as soon as the
+     * value is stale, before anything awaited the new value.
+     */
+    pub fn resolve(&self) -> Resolve<T> {
+        Resolve {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> AsyncInner<T> {
+    fn consume(&self) {
+        ComputeStack::consume(
+            &self.timeline,
+            &self.label,
+            ReactiveTag::Tag(self.tag.clone()),
+        );
+    }
+}
+
+/**
+ * The future returned by `DerivedAsync::resolve`.
+ */
+pub struct Resolve<T> {
+    inner: Arc<AsyncInner<T>>,
+}
+
+impl<T: Clone> Future for Resolve<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let inner = &self.inner;
+        // the guard is dropped before the tag is written, since the write may run effects that
+        // read the derived
+        let polled = inner.poll_value(&mut inner.state.lock(), cx);
+
+        let (value, completed) = match polled {
+            Poll::Ready(ready) => ready,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        if completed {
+            let (tag, timeline) = (&inner.tag, &inner.timeline);
+            timeline.write(|revision| tag.write(revision, timeline));
+        }
+
+        inner.consume();
+
+        // a stale value only advances the tag once it is awaited, so whoever awaits it also
+        // depends on what the value was computed from
+        let dependencies = inner.state.lock().dependencies.tags().to_vec();
+        for tag in dependencies {
+            ComputeStack::consume(&inner.timeline, &inner.label, tag);
+        }
+
+        Poll::Ready(value)
+    }
+}
+
+impl<T: Clone> AsyncInner<T> {
+    /**
+     * Poll the derived's value, starting a new run if there is no up to date value. Also returns
+     * whether a run completed.
+     */
+    fn poll_value(&self, state: &mut AsyncState<T>, cx: &mut Context<'_>) -> Poll<(T, bool)> {
+        loop {
+            let run = match &mut state.running {
+                // the run already read something that changed, so its value would be stale
+                Some(run)
+                    if run
+                        .future
+                        .dependencies()
+                        .first_changed(run.started_at)
+                        .is_some() =>
+                {
+                    state.running = None;
+                    continue;
+                }
+                Some(run) => run,
+                None => {
+                    if let Some(value) = &state.value {
+                        if state.dependencies.first_changed(state.revision).is_none() {
+                            return Poll::Ready((value.clone(), false));
+                        }
+                    }
+
+                    state.running.insert(Run {
+                        started_at: self.timeline.now(),
+                        future: tracked_async(|| (self.computation)()),
+                    })
+                }
+            };
+
+            let (value, dependencies) = match Pin::new(&mut run.future).poll(cx) {
+                Poll::Ready(ready) => ready,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            let started_at = run.started_at;
+            state.running = None;
+
+            // something the run read changed while it was being polled
+            if dependencies.first_changed(started_at).is_some() {
+                continue;
+            }
+
+            state.value = Some(value.clone());
+            state.dependencies = dependencies;
+            state.revision = started_at;
+            state.runs += 1;
+
+            return Poll::Ready((value, true));
+        }
+    }
+}
+
+impl<T> Drop for AsyncInner<T> {
+    fn drop(&mut self) {
+        self.timeline
+            .observe(|observer| observer.node_dropped(&ObservedNode::new(&self.label)));
+    }
+}
+
+impl<T: MaybeSend> Registered for AsyncInner<T> {
+    fn label(&self) -> Arc<Label> {
+        self.label.clone()
+    }
+
+    fn last_revision(&self) -> Revision {
+        self.tag.revision.get()
+    }
+
+    fn revisions(&self, out: &mut Vec<Revision>) {
+        let state = self.state.lock();
+        out.push(state.revision);
+        out.extend(state.running.as_ref().map(|run| run.started_at));
+    }
+
+    fn renumber(&self, renumber: &dyn Fn(Revision) -> Revision) {
+        let mut state = self.state.lock();
+        state.revision = renumber(state.revision);
+
+        if let Some(run) = &mut state.running {
+            run.started_at = renumber(run.started_at);
+        }
+    }
+
+    fn reset(&self) {
+        // like a derived, claiming to have consumed nothing makes the next resolve run the future
+        // again, unless it read nothing
+        let mut state = self.state.lock();
+        state.revision = Revision::CONSTANT;
+        state.running = None;
+    }
+
+    fn dependency_capacity(&self) -> usize {
+        self.state.lock().dependencies.capacity()
+    }
+
+    #[cfg(feature = "debug-graph")]
+    fn node(&self) -> GraphNode {
+        let state = self.state.lock();
+
+        GraphNode {
+            key: self.tag.slot.key(),
+            kind: self.label.kind(),
+            id: self.label.id(),
+            label: self.label.to_string(),
+            revision: self.tag.revision.get(),
+            stale: state.value.is_none() || state.dependencies.changed_since(state.revision),
+            dependencies: dependency_keys(&state.dependencies),
+        }
+    }
+}
+
+impl<T> Clone for DerivedAsync<T> {
+    fn clone(&self) -> Self {
+        DerivedAsync {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Debug> Debug for DerivedAsync<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.inner.state.lock();
+
+        f.debug_struct("DerivedAsync")
+            .field("label", &self.inner.label)
+            .field("value", &state.value)
+            .field("running", &state.running.is_some())
+            .field("revision", &self.inner.tag.revision.get())
+            .finish()
+    }
+}
+
+impl<T> Debug for Resolve<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolve")
+            .field("label", &self.inner.label)
+            .finish()
+    }
+}
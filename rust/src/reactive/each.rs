@@ -0,0 +1,206 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+};
+
+use parking_lot::Mutex;
+
+use crate::timeline::ComputeStack;
+
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    derived::Derived,
+    vec::TrackedVec,
+};
+
+/**
+ * How one element of a `KeyedList` changed since the list was last computed. Indices in
+ * `Removed` and the `from` of `Moved` refer to the previous list, and every other index refers to
+ * the new one.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListChange {
+    /**
+     * The element kept its index and its output.
+     */
+    Retained(usize),
+
+    /**
+     * The element is new, or its value changed, so its output was computed by `map`.
+     */
+    Inserted(usize),
+
+    /**
+     * The element is gone, or its value changed and its old output was replaced.
+     */
+    Removed(usize),
+
+    /**
+     * The element kept its output but moved, including when it was only shifted by an
+     * insertion or a removal in front of it.
+     */
+    Moved(usize, usize),
+}
+
+/**
+ * The value of a derived created with `each`: the output for every element, in the order of the
+ * elements, and the changes since the previous value.
+ */
+#[derive(Debug, Clone)]
+pub struct KeyedList<K, U> {
+    entries: Vec<(K, U)>,
+    changes: Vec<ListChange>,
+}
+
+impl<K, U> KeyedList<K, U> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&U> {
+        self.entries.get(index).map(|(_, output)| output)
+    }
+
+    /**
+     * The keys and outputs, in the order of the elements.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &U)> {
+        self.entries.iter().map(|(key, output)| (key, output))
+    }
+
+    /**
+     * What changed since the list was last computed: every removal first, then one change for
+     * each element in order. The first computation inserts every element.
+     */
+    pub fn changes(&self) -> &[ListChange] {
+        &self.changes
+    }
+}
+
+struct Mapped<K, T, U> {
+    key: K,
+    value: T,
+    output: U,
+}
+
+/**
+ * A derived list with `map` applied to every element of `vec`, for rendering lists. Elements are
+ * identified by `key`, and an element whose key was already in the list with an equal value
+ * reuses its previous output, even if it moved. `map` is only called for new keys and for keys
+ * whose value changed.
+ *
+ * `map` runs untracked, because a reused output wouldn't notice a change in anything else it
+ * read. It should only depend on the element it is given. Keys must be unique, and computing a
+ * list with a duplicate key panics.
+ *
+ * ```
+ * use everafter::{each, ListChange, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let names = timeline.vec(vec!["alice", "bob"]);
+ *
+ * let items = each(&names, |name| *name, |name| format!("<li>{}</li>", name));
+ * assert_eq!(items.get().get(0).unwrap(), "<li>alice</li>");
+ *
+ * names.insert(0, "carol");
+ *
+ * let list = items.get();
+ * assert_eq!(
+ *     list.changes(),
+ *     &[
+ *         ListChange::Inserted(0),
+ *         ListChange::Moved(0, 1),
+ *         ListChange::Moved(1, 2),
+ *     ]
+ * );
+ * ```
+ */
+pub fn each<T, K, U>(
+    vec: &TrackedVec<T>,
+    key: impl Fn(&T) -> K + MaybeSync + 'static,
+    map: impl Fn(&T) -> U + MaybeSync + 'static,
+) -> Derived<KeyedList<K, U>>
+where
+    T: Clone + PartialEq + MaybeSend + 'static,
+    K: Hash + Eq + Clone + Debug + MaybeSend + 'static,
+    U: Clone + MaybeSend + 'static,
+{
+    let vec = vec.clone();
+    let timeline = vec.timeline().clone();
+    let previous: Mutex<Vec<Mapped<K, T, U>>> = Mutex::new(vec![]);
+
+    Derived::new(timeline, move || {
+        let values: Vec<T> = vec.iter().collect();
+        let mut previous = previous.lock();
+
+        let old: HashMap<&K, usize> = previous
+            .iter()
+            .enumerate()
+            .map(|(index, mapped)| (&mapped.key, index))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut kept = vec![false; previous.len()];
+        let mut changes = vec![];
+        let mut mapped = Vec::with_capacity(values.len());
+
+        for (index, value) in values.into_iter().enumerate() {
+            let key = key(&value);
+
+            if !seen.insert(key.clone()) {
+                panic!(
+                    "{} has more than one element with the key {:?}",
+                    vec.label(),
+                    key
+                );
+            }
+
+            let reused = old
+                .get(&key)
+                .copied()
+                .filter(|&from| previous[from].value == value);
+
+            let output = match reused {
+                Some(from) => {
+                    kept[from] = true;
+
+                    changes.push(if from == index {
+                        ListChange::Retained(index)
+                    } else {
+                        ListChange::Moved(from, index)
+                    });
+
+                    previous[from].output.clone()
+                }
+                None => {
+                    changes.push(ListChange::Inserted(index));
+                    ComputeStack::untrack(|| map(&value))
+                }
+            };
+
+            mapped.push(Mapped { key, value, output });
+        }
+
+        let removed = kept
+            .iter()
+            .enumerate()
+            .filter(|(_, kept)| !**kept)
+            .map(|(from, _)| ListChange::Removed(from));
+        changes.splice(0..0, removed);
+
+        let entries = mapped
+            .iter()
+            .map(|mapped| (mapped.key.clone(), mapped.output.clone()))
+            .collect();
+
+        drop(old);
+        *previous = mapped;
+
+        KeyedList { entries, changes }
+    })
+}
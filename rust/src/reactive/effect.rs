@@ -0,0 +1,472 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::Debug,
+    mem,
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+
+use atomig::Atomic;
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::ReactiveTag,
+    timeline::{
+        node_key::NodeSlot, owner::Scope, state::TimelineState, ComputationId, ComputeStack,
+        Dependencies, NodeKey, Revision,
+    },
+};
+
+#[cfg(feature = "debug-graph")]
+use super::graph::{dependency_keys, GraphNode};
+use super::{
+    bounds::{Callback, MaybeSync},
+    label::Label,
+    observer::ObservedNode,
+    registry::{Entry, Registered},
+    scheduler::{Priority, Reaction},
+    subscription::SubscriptionHandle,
+};
+
+/**
+ * A side effect that re-runs whenever a value it read changes. The effect runs once when it is
+ * created, and afterwards whenever the timeline's scheduler flushes after a relevant write, or
+ * when it is polled.
+ *
+ * Writes performed by an effect don't re-enter the flush that is running it. They are picked up
+ * by the same flush once the current pass over the effects is done.
+ *
+ * The handle owns the effect, and dropping it disposes the effect. An effect that should keep
+ * running without a handle can be detached. An effect created while a derived or another effect
+ * runs belongs to that run instead, see `Owner`: it keeps running after its handle is dropped,
+ * until the computation runs again or is dropped.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let counter = timeline.cell(0);
+ *
+ * let effect = {
+ *     let counter = counter.clone();
+ *     timeline.effect(move || println!("counter: {}", counter.get()))
+ * };
+ *
+ * counter.set(1);
+ * assert!(!effect.poll(), "the default scheduler already ran the effect");
+ *
+ * drop(effect);
+ * counter.set(2);
+ * ```
+ */
+pub struct Effect {
+    inner: Arc<EffectInner>,
+    subscription: SubscriptionHandle,
+}
+
+struct EffectInner {
+    id: ComputationId,
+    label: Arc<Label>,
+    callback: Box<dyn Callback>,
+    // the number of flushes the dependencies have to be left alone for before a flush runs the
+    // effect, or 0 for an effect that runs on every flush
+    delay_flushes: u32,
+    priority: Atomic<Priority>,
+    state: Mutex<EffectState>,
+    slot: NodeSlot,
+    timeline: Arc<TimelineState>,
+}
+
+struct EffectState {
+    dependencies: Dependencies,
+    // what the last run owns, see `Owner`
+    owner: Option<Arc<Scope>>,
+    revision: Revision,
+    disposed: bool,
+    // for a debounced effect, the newest revision of the dependencies a flush has seen, and the
+    // number of flushes since then
+    seen: Revision,
+    quiet: u32,
+    // the last flush that was counted, so later passes of the same flush aren't counted again
+    counted_in: u64,
+    // the keys of the tags the last run wrote, which `Flush::run_ordered` orders effects by
+    written: Vec<NodeKey>,
+}
+
+thread_local! {
+    // the keys of the tags written by the effect that is running on this thread, if any
+    static WRITES: RefCell<Option<Vec<NodeKey>>> = const { RefCell::new(None) };
+}
+
+/**
+ * Record that the tag identified by `key` was written, on behalf of the effect that is running on
+ * this thread.
+ */
+pub(crate) fn record_write(key: NodeKey) {
+    WRITES.with(|writes| {
+        if let Some(writes) = &mut *writes.borrow_mut() {
+            writes.push(key);
+        }
+    });
+}
+
+/**
+ * Collects the writes of one run of an effect, and puts back the collection of the effect it
+ * interrupted when the run returns or unwinds.
+ */
+struct CollectWrites {
+    outer: Option<Vec<NodeKey>>,
+}
+
+impl CollectWrites {
+    fn start() -> CollectWrites {
+        CollectWrites {
+            outer: WRITES.with(|writes| writes.replace(Some(vec![]))),
+        }
+    }
+
+    fn finish(mut self) -> Vec<NodeKey> {
+        let outer = self.outer.take();
+        let mut written = WRITES
+            .with(|writes| writes.replace(outer))
+            .unwrap_or_default();
+        mem::forget(self);
+
+        written.sort_unstable();
+        written.dedup();
+        written
+    }
+}
+
+impl Drop for CollectWrites {
+    fn drop(&mut self) {
+        let outer = self.outer.take();
+        WRITES.with(|writes| writes.replace(outer));
+    }
+}
+
+impl Effect {
+    pub(crate) fn new(
+        timeline: Arc<TimelineState>,
+        callback: impl Fn() + MaybeSync + 'static,
+    ) -> Effect {
+        Effect::debounced(timeline, 0, callback)
+    }
+
+    /**
+     * Like `new`, but a flush only runs the effect once its dependencies haven't changed for
+     * `delay_flushes` flushes.
+     */
+    // effect callbacks aren't required to be `Send`, but the handle shares the timeline's `Arc`
+    // machinery with cells and deriveds.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn debounced(
+        timeline: Arc<TimelineState>,
+        delay_flushes: u32,
+        callback: impl Fn() + MaybeSync + 'static,
+    ) -> Effect {
+        let id = ComputationId::next();
+
+        let inner = Arc::new(EffectInner {
+            id,
+            label: Arc::new(Label::new("effect", id.raw())),
+            callback: Box::new(callback),
+            delay_flushes,
+            priority: Atomic::new(Priority::default()),
+            state: Mutex::new(EffectState {
+                dependencies: Dependencies::default(),
+                owner: None,
+                revision: Revision::CONSTANT,
+                disposed: false,
+                seen: Revision::CONSTANT,
+                quiet: 0,
+                counted_in: 0,
+                written: vec![],
+            }),
+            slot: NodeSlot::new(),
+            timeline,
+        });
+
+        inner.run();
+
+        let subscription = SubscriptionHandle::register(&inner.timeline, inner.clone());
+
+        // an effect created inside a computation belongs to it, and the handle no longer
+        // disposes the effect when it's dropped
+        let subscription = match ComputeStack::scope() {
+            Some(owner) => {
+                let handle = subscription.shared();
+                owner.own_subscription(subscription);
+                handle
+            }
+            None => subscription,
+        };
+
+        let registered: Arc<dyn Registered> = inner.clone();
+        inner
+            .timeline
+            .register_value(Entry::Computation(Arc::downgrade(&registered)));
+        inner
+            .timeline
+            .observe(|observer| observer.node_created(&ObservedNode::new(&inner.label)));
+
+        Effect {
+            inner,
+            subscription,
+        }
+    }
+
+    pub fn id(&self) -> ComputationId {
+        self.inner.id
+    }
+
+    /**
+     * Attach a debug label to the effect, which errors like `RunawayFlush` name it by. An effect
+     * can only be named once.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> Effect {
+        self.inner.label.name(label.into());
+        self
+    }
+
+    /**
+     * Flush the effect in the `priority` lane instead of `Priority::Render`. Like every effect,
+     * it already ran once when it was created.
+     */
+    pub fn with_priority(self, priority: Priority) -> Effect {
+        self.inner.priority.store(priority, Ordering::Relaxed);
+        self
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.inner.priority.load(Ordering::Relaxed)
+    }
+
+    /**
+     * The effect's debug label, or a name like `effect#4` if it was never named.
+     */
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * Unregister the effect. It will not run again, even if its dependencies change.
+     */
+    pub fn dispose(&self) {
+        self.subscription.unsubscribe();
+    }
+
+    pub fn is_disposed(&self) -> bool {
+        self.inner.state.lock().disposed
+    }
+
+    /**
+     * Run the effect now if anything it read changed since it last ran, without waiting for the
+     * scheduler. Returns whether the effect ran.
+     */
+    pub fn poll(&self) -> bool {
+        self.inner.run_if_stale()
+    }
+
+    /**
+     * Give up the handle without disposing the effect. A detached effect keeps running until
+     * the `Timeline` that created it is dropped.
+     */
+    pub fn detach(self) {
+        self.subscription.detach();
+    }
+
+    /**
+     * Give up the effect's handle in exchange for the subscription that keeps it registered.
+     */
+    pub fn into_subscription(self) -> SubscriptionHandle {
+        self.subscription
+    }
+}
+
+impl EffectInner {
+    fn run(&self) {
+        let now = self.timeline.now();
+
+        // what the last run owned is disposed before it runs again
+        let disowned = self.state.lock().owner.take();
+        if let Some(owner) = disowned {
+            owner.dispose();
+        }
+
+        let started = self.timeline.is_observed().then(Instant::now);
+        let writes = CollectWrites::start();
+        let ((), dependencies, owner) =
+            ComputeStack::track_computation(self.id, &self.label, &self.timeline, || {
+                (self.callback)()
+            });
+        let written = writes.finish();
+
+        if let Some(started) = started {
+            self.timeline.observe(|observer| {
+                let node = ObservedNode::new(&self.label);
+                observer.node_recomputed(&node, started.elapsed(), dependencies.len())
+            });
+        }
+
+        // like a derived, an effect that raced with a write runs again once it is flushed
+        let mut state = self.state.lock();
+        state.revision = dependencies.revision().min(now);
+        let previous = mem::replace(&mut state.dependencies, dependencies);
+        state.owner = Some(owner);
+        state.written = written;
+        drop(state);
+
+        ComputeStack::recycle(previous);
+    }
+
+    fn flushed(&self) {
+        self.timeline
+            .observe(|observer| observer.effect_flushed(&ObservedNode::new(&self.label)));
+    }
+
+    fn is_stale(&self) -> bool {
+        let state = self.state.lock();
+        !state.disposed && state.dependencies.revision() > state.revision
+    }
+}
+
+impl Registered for EffectInner {
+    fn label(&self) -> Arc<Label> {
+        self.label.clone()
+    }
+
+    fn last_revision(&self) -> Revision {
+        self.state.lock().revision
+    }
+
+    fn revisions(&self, out: &mut Vec<Revision>) {
+        out.push(self.state.lock().revision);
+    }
+
+    fn renumber(&self, renumber: &dyn Fn(Revision) -> Revision) {
+        let mut state = self.state.lock();
+        state.revision = renumber(state.revision);
+    }
+
+    fn reset(&self) {
+        self.state.lock().revision = Revision::CONSTANT;
+    }
+
+    fn dependency_capacity(&self) -> usize {
+        self.state.lock().dependencies.capacity()
+    }
+
+    #[cfg(feature = "debug-graph")]
+    fn node(&self) -> GraphNode {
+        let state = self.state.lock();
+
+        GraphNode {
+            key: self.slot.key(),
+            kind: self.label.kind(),
+            id: self.label.id(),
+            label: self.label.to_string(),
+            revision: state.revision,
+            stale: !state.disposed && state.dependencies.changed_since(state.revision),
+            dependencies: dependency_keys(&state.dependencies),
+        }
+    }
+}
+
+impl Reaction for EffectInner {
+    fn id(&self) -> ComputationId {
+        self.id
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn is_stale(&self) -> bool {
+        EffectInner::is_stale(self)
+    }
+
+    fn dependencies(&self) -> Vec<ReactiveTag> {
+        self.state.lock().dependencies.tags().to_vec()
+    }
+
+    fn written(&self) -> Vec<NodeKey> {
+        self.state.lock().written.clone()
+    }
+
+    fn run_if_stale(&self) -> bool {
+        let stale = self.is_stale();
+
+        if stale {
+            self.run();
+        }
+
+        stale
+    }
+
+    fn flush(&self, flush: u64) -> bool {
+        if self.delay_flushes == 0 || !self.is_stale() {
+            if self.run_if_stale() {
+                self.flushed();
+            }
+
+            return false;
+        }
+
+        let mut state = self.state.lock();
+        let revision = state.dependencies.revision();
+
+        if revision != state.seen {
+            // something changed since the last flush looked, so the wait starts over
+            state.seen = revision;
+            state.quiet = 0;
+        } else if state.counted_in != flush {
+            state.quiet += 1;
+        }
+
+        state.counted_in = flush;
+
+        if state.quiet < self.delay_flushes {
+            return true;
+        }
+
+        drop(state);
+        self.run();
+        self.flushed();
+        false
+    }
+
+    fn priority(&self) -> Priority {
+        self.priority.load(Ordering::Relaxed)
+    }
+
+    fn dispose(&self) {
+        // children are disposed before their parent
+        let owner = self.state.lock().owner.take();
+        if let Some(owner) = owner {
+            owner.dispose();
+        }
+
+        self.state.lock().disposed = true;
+    }
+}
+
+impl Drop for EffectInner {
+    fn drop(&mut self) {
+        self.timeline
+            .observe(|observer| observer.node_dropped(&ObservedNode::new(&self.label)));
+    }
+}
+
+impl Debug for EffectInner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Effect({})", self.label)
+    }
+}
+
+impl Debug for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
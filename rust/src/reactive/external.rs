@@ -0,0 +1,151 @@
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    inputs::{self, ReactiveTag},
+    timeline::{state::TimelineState, ComputeStack, Revision},
+};
+
+use super::{label::Label, tag::Tag};
+
+/**
+ * A dependency on something outside of the reactive system, like a file on disk or the messages
+ * arriving on a socket, created with `Timeline::external_tag`. An external tag has no value.
+ * Computations call `track` to depend on it, and whatever watches the outside world calls `bump`
+ * when it changed, which invalidates everything that tracked the tag.
+ *
+ * Bumping is a write like any other: it advances the timeline's revision, joins the open
+ * transaction if there is one, and lets the scheduler run the effects it made stale. With the
+ * `sync` feature, clones of the tag can be moved to the thread that does the I/O.
+ *
+ * ```
+ * use std::sync::{Arc, Mutex};
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let config = Arc::new(Mutex::new("debug = false".to_string()));
+ * let changed = timeline.external_tag().named("config file");
+ *
+ * let debug = {
+ *     let (config, changed) = (config.clone(), changed.clone());
+ *     timeline.derived(move || {
+ *         changed.track();
+ *         config.lock().unwrap().contains("debug = true")
+ *     })
+ * };
+ *
+ * assert!(!debug.get());
+ *
+ * *config.lock().unwrap() = "debug = true".to_string();
+ * assert!(!debug.get(), "nothing told the derived about the change");
+ *
+ * changed.bump();
+ * assert!(debug.get());
+ * ```
+ */
+pub struct ExternalTag {
+    inner: Arc<ExternalInner>,
+}
+
+static NEXT_EXTERNAL: AtomicU64 = AtomicU64::new(1);
+
+struct ExternalInner {
+    label: Arc<Label>,
+    tag: Arc<inputs::Tag>,
+    timeline: Arc<TimelineState>,
+}
+
+impl ExternalTag {
+    // a tag is only `Send` and `Sync` when its timeline is, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(timeline: Arc<TimelineState>) -> ExternalTag {
+        let label = Arc::new(Label::new(
+            "external",
+            NEXT_EXTERNAL.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        ExternalTag {
+            inner: Arc::new(ExternalInner {
+                tag: timeline.tag(Some(label.clone())),
+                label,
+                timeline,
+            }),
+        }
+    }
+
+    /**
+     * Attach a debug label to the tag. A tag can only be named once.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> ExternalTag {
+        self.inner.label.name(label.into());
+        self
+    }
+
+    /**
+     * The tag's debug label, or a name like `external#2` if it was never named.
+     */
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * The revision at which the tag was last bumped.
+     */
+    pub fn revision(&self) -> Revision {
+        self.inner.tag.revision.get()
+    }
+
+    /**
+     * Record a dependency on the tag in the current `ComputeStack` frame.
+     */
+    pub fn track(&self) {
+        ComputeStack::consume(&self.inner.timeline, &self.inner.label, self.reactive_tag());
+    }
+
+    /**
+     * The tag as a `Tag`, so it can be combined with the tags of other values.
+     */
+    pub fn tag(&self) -> Tag {
+        Tag::new(
+            self.reactive_tag(),
+            self.inner.label.clone(),
+            self.inner.timeline.clone(),
+        )
+    }
+
+    fn reactive_tag(&self) -> ReactiveTag {
+        ReactiveTag::Tag(self.inner.tag.clone())
+    }
+
+    /**
+     * Advance the tag to a new revision of its timeline, so every computation that tracked it
+     * recomputes the next time it is read.
+     */
+    pub fn bump(&self) {
+        let (tag, timeline) = (&self.inner.tag, &self.inner.timeline);
+        timeline.write(|revision| tag.write(revision, timeline));
+    }
+}
+
+impl Clone for ExternalTag {
+    fn clone(&self) -> Self {
+        ExternalTag {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl Debug for ExternalTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalTag")
+            .field("label", &self.inner.label)
+            .field("revision", &self.revision())
+            .finish()
+    }
+}
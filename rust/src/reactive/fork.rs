@@ -0,0 +1,266 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Debug,
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::ReactiveTag,
+    timeline::{state::TimelineState, NodeKey, Revision, Transaction},
+};
+
+use super::{cell::Cell, scheduler::reachable_keys};
+
+/**
+ * A speculative view of a timeline, created with `Timeline::fork`. Cells written with
+ * `Forked::set` keep their new values in the fork instead of in the timeline, and code that
+ * runs inside `Forked::run` reads them through the fork: cells the fork wrote have the fork's
+ * value, everything else falls through to the timeline, and deriveds that depend on a cell the
+ * fork wrote are computed from the fork's values without touching the values they cached. The
+ * fork keeps what it computed until the fork or the timeline is written again.
+ *
+ * `commit` writes every value the fork holds to the timeline at once, as a single revision.
+ * Dropping the fork discards them.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let password = timeline.cell(String::new());
+ * let valid = {
+ *     let password = password.clone();
+ *     timeline.derived(move || password.get().len() >= 8)
+ * };
+ *
+ * let preview = timeline.fork();
+ * preview.set(&password, String::from("hunter22"));
+ *
+ * assert!(preview.run(|| valid.get()));
+ * assert!(!valid.get(), "the timeline doesn't see the fork's writes");
+ *
+ * preview.commit();
+ * assert!(valid.get());
+ * ```
+ *
+ * Inside `run`, writes to the timeline panic, since they would escape the fork.
+ * `Cell::peek_ref` borrows the fork's value of a cell the fork wrote, while `Cell::get_at` reads
+ * the timeline's values at the snapshot. A fork stays on the thread that created it.
+ */
+pub struct Forked {
+    state: Arc<ForkState>,
+}
+
+static NEXT_FORK: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) struct ForkState {
+    id: u64,
+    timeline: Arc<TimelineState>,
+    // the cells the fork wrote, by the key of their tag, in the order they were first written
+    written: Mutex<IndexMap<NodeKey, Box<dyn Overridden>>>,
+    // the values of deriveds computed from the fork's values, discarded by every write to the
+    // fork. Each one is an `Arc<T>`, so reading it doesn't hold the lock, and is kept with the
+    // timeline's revision it was computed at.
+    computed: Mutex<HashMap<NodeKey, (Revision, ComputedValue)>>,
+}
+
+type ComputedValue = Box<dyn Any>;
+
+/**
+ * A cell that a fork wrote.
+ */
+pub(crate) trait Overridden {
+    /**
+     * Write the fork's value to the cell, inside the transaction of `Forked::commit`.
+     */
+    fn commit(&self, fork: u64);
+
+    /**
+     * Forget the fork's value.
+     */
+    fn discard(&self, fork: u64);
+}
+
+thread_local! {
+    // the fork whose `run` is executing on this thread
+    static CURRENT: RefCell<Option<Arc<ForkState>>> = const { RefCell::new(None) };
+}
+
+/**
+ * The fork of `timeline` that code on this thread is running in, if any.
+ */
+pub(crate) fn current(timeline: &TimelineState) -> Option<Arc<ForkState>> {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .filter(|fork| std::ptr::eq(&*fork.timeline, timeline))
+            .cloned()
+    })
+}
+
+/**
+ * Whether code on this thread is running in a fork of any timeline.
+ */
+pub(crate) fn is_forked() -> bool {
+    CURRENT.with(|current| current.borrow().is_some())
+}
+
+impl Forked {
+    // a fork is read through on the thread it was created on, so it's never `Send`
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(timeline: Arc<TimelineState>) -> Forked {
+        Forked {
+            state: Arc::new(ForkState {
+                id: NEXT_FORK.fetch_add(1, Ordering::Relaxed),
+                timeline,
+                written: Mutex::new(IndexMap::new()),
+                computed: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /**
+     * Write `value` to `cell` in the fork. The timeline keeps the cell's value until the fork is
+     * committed.
+     *
+     * Panics if the cell is a constant or belongs to another timeline.
+     */
+    pub fn set<T: PartialEq + 'static>(&self, cell: &Cell<T>, value: T) {
+        let key = cell.fork_write(&self.state, value);
+
+        self.state
+            .written
+            .lock()
+            .entry(key)
+            .or_insert_with(|| Box::new(cell.clone()));
+        self.state.computed.lock().clear();
+    }
+
+    /**
+     * Run `f` with reads going through the fork.
+     */
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<Arc<ForkState>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let outer = self.0.take();
+                CURRENT.with(|current| *current.borrow_mut() = outer);
+            }
+        }
+
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(self.state.clone()))));
+        f()
+    }
+
+    /**
+     * Whether the fork hasn't written anything.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.state.written.lock().is_empty()
+    }
+
+    /**
+     * Write the fork's values to the timeline in a single transaction, so they land at one
+     * revision. Cells whose fork value equals their current value aren't written.
+     */
+    pub fn commit(self) {
+        let written = mem::take(&mut *self.state.written.lock());
+
+        let transaction = Transaction::begin(&self.state.timeline);
+        for cell in written.values() {
+            cell.commit(self.state.id);
+        }
+        drop(transaction);
+    }
+}
+
+impl ForkState {
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn belongs_to(&self, timeline: &Arc<TimelineState>) -> bool {
+        Arc::ptr_eq(&self.timeline, timeline)
+    }
+
+    /**
+     * Whether `tag`, or anything it was computed from, was written by the fork.
+     */
+    pub(crate) fn affects(&self, tag: ReactiveTag) -> bool {
+        let written = self.written.lock();
+
+        if written.is_empty() {
+            return false;
+        }
+
+        reachable_keys(vec![tag])
+            .iter()
+            .any(|key| written.contains_key(key))
+    }
+
+    /**
+     * The value the fork computed for the derived `key`, unless the timeline was written since.
+     * Values computed while a transaction has written something are never reused, since the
+     * transaction's later writes don't move the revision either.
+     */
+    pub(crate) fn computed<T: 'static>(&self, key: NodeKey) -> Option<Arc<T>> {
+        let now = self.current()?;
+        let mut computed = self.computed.lock();
+
+        match computed.get(&key) {
+            Some((at, value)) if *at == now => value.downcast_ref::<Arc<T>>().cloned(),
+            Some(_) => {
+                computed.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /**
+     * Keep the value the fork computed for the derived `key`, in a computation that started at
+     * the timeline's revision `at`.
+     */
+    pub(crate) fn store<T: 'static>(&self, key: NodeKey, value: Arc<T>, at: Option<Revision>) {
+        if let Some(at) = at {
+            self.computed.lock().insert(key, (at, Box::new(value)));
+        }
+    }
+
+    /**
+     * The timeline's revision, or `None` while an open transaction has written something.
+     */
+    pub(crate) fn current(&self) -> Option<Revision> {
+        match self.timeline.pending() {
+            Some(_) => None,
+            None => Some(self.timeline.now()),
+        }
+    }
+}
+
+impl Drop for Forked {
+    fn drop(&mut self) {
+        for cell in self.state.written.lock().values() {
+            cell.discard(self.state.id);
+        }
+    }
+}
+
+impl Debug for Forked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Forked")
+            .field("id", &self.state.id)
+            .field("written", &self.state.written.lock().len())
+            .finish()
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt::Display;
+
+use crate::{
+    inputs::ReactiveTag,
+    timeline::{Dependencies, NodeKey, Revision},
+};
+
+use super::registry::Entry;
+
+/**
+ * The keys of the dependencies that can appear in a graph. Legacy derived inputs aren't
+ * registered with the timeline, so they are left out.
+ */
+pub(crate) fn dependency_keys(dependencies: &Dependencies) -> Vec<NodeKey> {
+    dependencies
+        .tags()
+        .iter()
+        .filter(|tag| !matches!(tag, ReactiveTag::Derived(_)))
+        .map(ReactiveTag::key)
+        .collect()
+}
+
+/**
+ * A node in a `DebugGraph`: a cell, derived or effect, with the dependencies it recorded the
+ * last time it ran.
+ */
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub(crate) key: NodeKey,
+    pub(crate) kind: &'static str,
+    pub(crate) id: u64,
+    pub(crate) label: String,
+    pub(crate) revision: Revision,
+    pub(crate) stale: bool,
+    pub(crate) dependencies: Vec<NodeKey>,
+}
+
+impl GraphNode {
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /**
+     * The id of the cell, derived or effect, which is unique among values of the same kind.
+     */
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /**
+     * Whether the node would recompute the next time it is read or flushed.
+     */
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /**
+     * The label shown in the DOT output: the debug label, the kind and id if the label doesn't
+     * already consist of them, and the revision.
+     */
+    fn description(&self) -> String {
+        let fallback = format!("{}#{}", self.kind, self.id);
+
+        if self.label == fallback {
+            format!("{}\n{}", self.label, self.revision)
+        } else {
+            format!("{} ({})\n{}", self.label, fallback, self.revision)
+        }
+    }
+}
+
+/**
+ * A snapshot of the dependency edges between the live values of a timeline, as recorded by
+ * their last computations. Its `Display` implementation produces a graphviz digraph with an
+ * edge from every dependency to the computation that read it.
+ */
+#[derive(Debug, Clone)]
+pub struct DebugGraph {
+    nodes: Vec<GraphNode>,
+}
+
+impl DebugGraph {
+    pub(crate) fn new(entries: &[Entry]) -> DebugGraph {
+        DebugGraph {
+            nodes: entries.iter().filter_map(Entry::node).collect(),
+        }
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    /**
+     * Every `(dependency, dependent)` pair between nodes in the graph.
+     */
+    pub fn edges(&self) -> Vec<(&GraphNode, &GraphNode)> {
+        self.nodes
+            .iter()
+            .flat_map(|dependent| {
+                dependent.dependencies.iter().filter_map(move |key| {
+                    let dependency = self.nodes.iter().find(|node| node.key == *key)?;
+                    Some((dependency, dependent))
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for DebugGraph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "digraph {{")?;
+
+        for node in &self.nodes {
+            let description = node.description();
+
+            if node.stale {
+                writeln!(
+                    f,
+                    "  {:?} [label={:?}, style=dashed];",
+                    node.label, description
+                )?;
+            } else {
+                writeln!(f, "  {:?} [label={:?}];", node.label, description)?;
+            }
+        }
+
+        for (dependency, dependent) in self.edges() {
+            writeln!(f, "  {:?} -> {:?};", dependency.label, dependent.label)?;
+        }
+
+        write!(f, "}}")
+    }
+}
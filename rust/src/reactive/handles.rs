@@ -0,0 +1,60 @@
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    value::ReactiveValue,
+};
+
+/**
+ * The reactive values that `Timeline::derived_with` clones into a computation: a reference to a
+ * `Cell`, `Derived`, `ReadonlyCell` or any other `ReactiveValue`, or a tuple of up to six of
+ * them. Cloning a handle only clones the pointer the handle holds, so the values themselves are
+ * shared with the handles they were cloned from.
+ */
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be captured by `Timeline::derived_with`",
+    label = "expected a reference to a reactive value, or a tuple of up to six of them",
+    note = "pass references to the handles, like `(&first, &last)`, and they are cloned for the \
+            computation"
+)]
+pub trait Handles {
+    /**
+     * The cloned handles, which the computation gets a reference to.
+     */
+    type Owned: MaybeSend + MaybeSync + 'static;
+
+    fn to_owned(&self) -> Self::Owned;
+}
+
+impl<H> Handles for &H
+where
+    H: ReactiveValue + Clone + MaybeSend + MaybeSync + 'static,
+{
+    type Owned = H;
+
+    fn to_owned(&self) -> H {
+        (*self).clone()
+    }
+}
+
+macro_rules! tuple_handles {
+    ($($handle:ident),+) => {
+        impl<$($handle),+> Handles for ($(&$handle,)+)
+        where
+            $($handle: ReactiveValue + Clone + MaybeSend + MaybeSync + 'static),+
+        {
+            type Owned = ($($handle,)+);
+
+            #[allow(non_snake_case)]
+            fn to_owned(&self) -> Self::Owned {
+                let ($($handle,)+) = self;
+                ($((*$handle).clone(),)+)
+            }
+        }
+    };
+}
+
+tuple_handles!(A);
+tuple_handles!(A, B);
+tuple_handles!(A, B, C);
+tuple_handles!(A, B, C, D);
+tuple_handles!(A, B, C, D, E);
+tuple_handles!(A, B, C, D, E, F);
@@ -0,0 +1,165 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+
+use crate::timeline::Revision;
+
+use super::bounds::{MaybeSend, MaybeSync};
+
+/**
+ * A value that a write replaced, which can be written back into its cell.
+ */
+pub(crate) trait Restore: MaybeSend + MaybeSync {
+    /**
+     * Write the value back into its cell at `revision`, which records the value it replaces in
+     * turn. Does nothing if the cell was dropped.
+     */
+    fn restore(&self, revision: Revision);
+}
+
+struct Recorded {
+    // the revision of the write that replaced the value
+    revision: Revision,
+    value: Arc<dyn Restore>,
+}
+
+/**
+ * The values that writes to recorded cells replaced, oldest first, for `Timeline::rewind_to`.
+ * Only the last `capacity` of them are kept.
+ */
+pub(crate) struct History {
+    capacity: usize,
+    recorded: VecDeque<Recorded>,
+    // every write after this revision is still recorded, so the cells can be rewound to it
+    complete_since: Revision,
+}
+
+impl History {
+    pub(crate) fn new() -> History {
+        History {
+            capacity: 0,
+            recorded: VecDeque::new(),
+            complete_since: Revision::initial(),
+        }
+    }
+
+    /**
+     * Keep the last `capacity` replaced values, starting with the writes after `now`. A
+     * capacity of 0 stops recording and forgets every recorded value.
+     */
+    pub(crate) fn set_capacity(&mut self, capacity: usize, now: Revision) {
+        if self.capacity == 0 {
+            self.complete_since = now;
+        }
+
+        self.capacity = capacity;
+        self.truncate();
+    }
+
+    pub(crate) fn record(&mut self, revision: Revision, value: Arc<dyn Restore>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.recorded.push_back(Recorded { revision, value });
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.recorded.len() > self.capacity {
+            if let Some(forgotten) = self.recorded.pop_front() {
+                self.complete_since = self.complete_since.max(forgotten.revision);
+            }
+        }
+    }
+
+    /**
+     * The values to restore, newest first, to return every recorded cell to its value at
+     * `revision`.
+     */
+    pub(crate) fn since(&self, revision: Revision) -> Result<Vec<Arc<dyn Restore>>, RewindError> {
+        if self.capacity == 0 || revision < self.complete_since {
+            return Err(RewindError {
+                revision,
+                oldest: (self.capacity > 0).then_some(self.complete_since),
+            });
+        }
+
+        Ok(self
+            .recorded
+            .iter()
+            .rev()
+            .take_while(|recorded| recorded.revision > revision)
+            .map(|recorded| recorded.value.clone())
+            .collect())
+    }
+
+    pub(crate) fn revisions(&self, out: &mut Vec<Revision>) {
+        out.push(self.complete_since);
+        out.extend(self.recorded.iter().map(|recorded| recorded.revision));
+    }
+
+    pub(crate) fn renumber(&mut self, renumber: &dyn Fn(Revision) -> Revision) {
+        self.complete_since = renumber(self.complete_since);
+
+        for recorded in &mut self.recorded {
+            recorded.revision = renumber(recorded.revision);
+        }
+    }
+
+    /**
+     * Forget every recorded value, once the timeline is back at its initial revision.
+     */
+    pub(crate) fn reset(&mut self) {
+        self.recorded.clear();
+        self.complete_since = Revision::initial();
+    }
+}
+
+/**
+ * A `Timeline::rewind_to` that couldn't restore the cells, because the history isn't recorded
+ * or no longer reaches back to the revision.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RewindError {
+    revision: Revision,
+    oldest: Option<Revision>,
+}
+
+impl RewindError {
+    /**
+     * The revision the timeline was asked to rewind to.
+     */
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /**
+     * The oldest revision the history can still rewind to, or `None` if it isn't recorded.
+     */
+    pub fn oldest(&self) -> Option<Revision> {
+        self.oldest
+    }
+}
+
+impl Display for RewindError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.oldest {
+            Some(oldest) => write!(
+                f,
+                "can't rewind to {}, the history only reaches back to {}",
+                self.revision, oldest
+            ),
+            None => write!(
+                f,
+                "can't rewind to {}, the timeline doesn't record its history",
+                self.revision
+            ),
+        }
+    }
+}
+
+impl Error for RewindError {}
@@ -0,0 +1,161 @@
+use std::{
+    fmt::{Debug, Display},
+    iter,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{inputs::ReactiveTag, timeline::Revision};
+
+/**
+ * Why a derived last recomputed: the chain of dependencies whose revisions advanced, starting
+ * with the value that was written and ending with the derived's own dependency.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let count = timeline.cell(1).named("count");
+ *
+ * let doubled = {
+ *     let count = count.clone();
+ *     timeline.derived(move || count.get() * 2).named("doubled")
+ * };
+ *
+ * let label = {
+ *     let doubled = doubled.clone();
+ *     timeline.derived(move || format!("{} items", doubled.get()))
+ * };
+ *
+ * label.get();
+ * count.set(2);
+ * label.get();
+ *
+ * let invalidation = label.last_invalidation().unwrap();
+ * assert_eq!(invalidation.cause().label(), "count");
+ * assert_eq!(invalidation.to_string(), format!("count ({0}) → doubled ({0})", count.revision()));
+ * ```
+ */
+#[derive(Clone)]
+pub struct Invalidation {
+    // the newest step. Every step leads back to the one before it, so a derived shares the
+    // chain of the dependency that invalidated it instead of copying it.
+    last: Arc<Link>,
+}
+
+struct Link {
+    step: InvalidationStep,
+    previous: Option<Arc<Link>>,
+    // the whole chain up to this step, flattened the first time someone asks for it
+    chain: OnceLock<Vec<InvalidationStep>>,
+}
+
+/**
+ * One dependency in an `Invalidation`, with the revision it advanced to.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidationStep {
+    label: String,
+    revision: Revision,
+}
+
+impl InvalidationStep {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+}
+
+impl Invalidation {
+    /**
+     * Describe an invalidation caused by `tag` advancing to `revision`. If the tag belongs to a
+     * computation, the computation's own invalidation leads up to it.
+     */
+    pub(crate) fn caused_by(tag: &ReactiveTag, revision: Revision) -> Invalidation {
+        let label = tag.label();
+        let previous = match tag {
+            ReactiveTag::Computed(tag) => tag
+                .last_invalidation()
+                .map(|invalidation| invalidation.last),
+            _ => None,
+        };
+
+        Invalidation {
+            last: Arc::new(Link {
+                step: InvalidationStep { label, revision },
+                previous,
+                chain: OnceLock::new(),
+            }),
+        }
+    }
+
+    pub fn chain(&self) -> &[InvalidationStep] {
+        self.last.chain.get_or_init(|| {
+            let mut chain: Vec<InvalidationStep> = self.steps().cloned().collect();
+            chain.reverse();
+            chain
+        })
+    }
+
+    /**
+     * The first value in the chain, which is usually the value that was written.
+     */
+    pub fn cause(&self) -> &InvalidationStep {
+        self.steps()
+            .last()
+            .expect("an invalidation has at least one step")
+    }
+
+    /**
+     * The steps from the newest one back to the cause.
+     */
+    fn steps(&self) -> impl Iterator<Item = &InvalidationStep> {
+        iter::successors(Some(&*self.last), |link| link.previous.as_deref()).map(|link| &link.step)
+    }
+}
+
+impl Display for Invalidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, step) in self.chain().iter().enumerate() {
+            if i > 0 {
+                write!(f, " → ")?;
+            }
+
+            write!(f, "{} ({})", step.label, step.revision)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for Invalidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Invalidation")
+            .field("chain", &self.chain())
+            .finish()
+    }
+}
+
+impl PartialEq for Invalidation {
+    fn eq(&self, other: &Invalidation) -> bool {
+        self.steps().eq(other.steps())
+    }
+}
+
+impl Eq for Invalidation {}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        // unlink the steps one at a time, so dropping a long chain doesn't recurse through it
+        let mut previous = self.previous.take();
+
+        while let Some(link) = previous {
+            match Arc::try_unwrap(link) {
+                Ok(mut link) => previous = link.previous.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{borrow::Cow, fmt::Debug, sync::OnceLock};
+
+/**
+ * The debug label of a cell, derived or effect. Values that were never named fall back to their
+ * kind and id, like `cell#7`.
+ */
+pub(crate) struct Label {
+    kind: &'static str,
+    id: u64,
+    name: OnceLock<Cow<'static, str>>,
+    fallback: OnceLock<String>,
+}
+
+impl Label {
+    pub(crate) fn new(kind: &'static str, id: u64) -> Label {
+        Label {
+            kind,
+            id,
+            name: OnceLock::new(),
+            fallback: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn name(&self, name: Cow<'static, str>) {
+        if let Err(name) = self.name.set(name) {
+            panic!(
+                "tried to name {} {:?}, but it was already named",
+                self, name
+            );
+        }
+    }
+
+    pub(crate) fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn get(&self) -> &str {
+        match self.name.get() {
+            Some(name) => name,
+            None => self
+                .fallback
+                .get_or_init(|| format!("{}#{}", self.kind, self.id)),
+        }
+    }
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.get())
+    }
+}
+
+impl Debug for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::{ReactiveTag, Tag},
+    timeline::{state::TimelineState, ComputeStack, Revision},
+};
+
+use super::label::Label;
+
+/**
+ * A map with a tag per key and a structural tag for its set of keys. Reading a key only depends
+ * on that key, whether or not it is present, so a computation that reads a missing key is
+ * invalidated when the key is inserted. Inserting a new key or removing one also changes the
+ * structure, which computations that look at the whole map depend on.
+ *
+ * A key's tag outlives its entry, so removing a key and inserting it again invalidates readers
+ * of that key both times.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let settings = timeline.map(vec![(String::from("theme"), "dark")]);
+ *
+ * let font = {
+ *     let settings = settings.clone();
+ *     timeline.derived(move || settings.get("font").unwrap_or("serif"))
+ * };
+ *
+ * assert_eq!(font.get(), "serif");
+ *
+ * settings.insert(String::from("font"), "mono");
+ * assert_eq!(font.get(), "mono");
+ * ```
+ */
+pub struct TrackedMap<K, V> {
+    inner: Arc<MapInner<K, V>>,
+}
+
+static NEXT_MAP: AtomicU64 = AtomicU64::new(1);
+
+struct MapInner<K, V> {
+    label: Arc<Label>,
+    state: Mutex<MapState<K, V>>,
+    structure: Arc<Tag>,
+    timeline: Arc<TimelineState>,
+}
+
+struct MapState<K, V> {
+    entries: IndexMap<K, V>,
+    // every key that was ever present or read, including keys that are currently absent
+    tags: HashMap<K, Arc<Tag>>,
+}
+
+impl<K, V> MapState<K, V>
+where
+    K: Hash + Eq,
+{
+    fn tag<Q>(&mut self, key: &Q, timeline: &TimelineState, label: &Arc<Label>) -> Arc<Tag>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        if let Some(tag) = self.tags.get(key) {
+            return tag.clone();
+        }
+
+        let tag = timeline.tag(Some(label.clone()));
+        self.tags.insert(key.to_owned(), tag.clone());
+        tag
+    }
+}
+
+impl<K, V> TrackedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub(crate) fn new(
+        timeline: Arc<TimelineState>,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> TrackedMap<K, V> {
+        let label = Arc::new(Label::new("map", NEXT_MAP.fetch_add(1, Ordering::Relaxed)));
+        let entries: IndexMap<K, V> = entries.into_iter().collect();
+        let tags = entries
+            .keys()
+            .map(|key| (key.clone(), timeline.tag(Some(label.clone()))))
+            .collect();
+
+        TrackedMap {
+            inner: Arc::new(MapInner {
+                structure: timeline.tag(Some(label.clone())),
+                label,
+                state: Mutex::new(MapState { entries, tags }),
+                timeline,
+            }),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * The revision at which keys were last added or removed.
+     */
+    pub fn revision(&self) -> Revision {
+        self.inner.structure.revision.get()
+    }
+
+    pub fn len(&self) -> usize {
+        self.consume_structure();
+        self.inner.state.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * The keys in insertion order, depending only on the map's structure.
+     */
+    pub fn keys(&self) -> std::vec::IntoIter<K> {
+        self.consume_structure();
+
+        let keys: Vec<K> = self.inner.state.lock().entries.keys().cloned().collect();
+        keys.into_iter()
+    }
+
+    /**
+     * Whether `key` is present, depending only on that key.
+     */
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let mut state = self.inner.state.lock();
+        self.consume_key(&mut state, key);
+        state.entries.contains_key(key)
+    }
+
+    /**
+     * Insert a value, invalidating computations that read `key`. Inserting a key that wasn't
+     * present also invalidates computations that depend on the map's structure.
+     */
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let (tag, previous) = {
+            let mut state = self.inner.state.lock();
+            let tag = state.tag(&key, &self.inner.timeline, &self.inner.label);
+            (tag, state.entries.insert(key, value))
+        };
+
+        self.write(tag, previous.is_none());
+        previous
+    }
+
+    /**
+     * Remove `key`, invalidating computations that read it and computations that depend on the
+     * map's structure. Removing a missing key does nothing.
+     */
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let (tag, value) = {
+            let mut state = self.inner.state.lock();
+            let value = state.entries.shift_remove(key)?;
+            (state.tags[key].clone(), value)
+        };
+
+        self.write(tag, true);
+        Some(value)
+    }
+
+    fn consume(&self, tag: ReactiveTag) {
+        ComputeStack::consume(&self.inner.timeline, &self.inner.label, tag);
+    }
+
+    fn consume_structure(&self) {
+        self.consume(ReactiveTag::Tag(self.inner.structure.clone()));
+    }
+
+    fn consume_key<Q>(&self, state: &mut MapState<K, V>, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let tag = state.tag(key, &self.inner.timeline, &self.inner.label);
+        self.consume(ReactiveTag::Tag(tag));
+    }
+
+    fn write(&self, tag: Arc<Tag>, structural: bool) {
+        let (structure, timeline) = (&self.inner.structure, &self.inner.timeline);
+
+        timeline.write(|revision| {
+            tag.write(revision, timeline);
+
+            if structural {
+                structure.write(revision, timeline);
+            }
+        });
+    }
+}
+
+impl<K, V> TrackedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    /**
+     * Read the value of `key`, depending only on that key. If the key is missing, the read is
+     * invalidated once it is inserted.
+     */
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let mut state = self.inner.state.lock();
+        self.consume_key(&mut state, key);
+        state.entries.get(key).cloned()
+    }
+
+    /**
+     * The entries in insertion order. Iterating depends on the map's structure and on every key,
+     * so any insert, update or removal invalidates it.
+     */
+    pub fn iter(&self) -> std::vec::IntoIter<(K, V)> {
+        self.consume_structure();
+
+        let state = self.inner.state.lock();
+        let entries: Vec<(K, V)> = state
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                self.consume(ReactiveTag::Tag(state.tags[key].clone()));
+                (key.clone(), value.clone())
+            })
+            .collect();
+
+        entries.into_iter()
+    }
+}
+
+impl<K, V> Clone for TrackedMap<K, V> {
+    fn clone(&self) -> Self {
+        TrackedMap {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> Debug for TrackedMap<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrackedMap")
+            .field("label", &self.inner.label)
+            .field("entries", &self.inner.state.lock().entries)
+            .field("revision", &self.inner.structure.revision.get())
+            .finish()
+    }
+}
@@ -0,0 +1,223 @@
+use std::{fmt::Debug, hash::Hash, sync::Arc};
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::timeline::state::TimelineState;
+
+use super::{
+    bounds::{MaybeSend, MaybeSync, MemoComputation},
+    derived::Derived,
+};
+
+/**
+ * A function of one argument whose results are cached per argument, created with
+ * `Timeline::memo`. Each argument gets a derived of its own the first time it is passed to `get`,
+ * which records the dependencies of that call only. A write that invalidates one argument's
+ * result recomputes that argument the next time it is read, and leaves the others alone.
+ *
+ * Clones of a memo share its cache. A memo keeps every argument it was called with unless it was
+ * given a capacity with `with_capacity`.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let factor = timeline.cell(2);
+ *
+ * let scaled = {
+ *     let factor = factor.clone();
+ *     timeline.memo(move |value: &u32| value * factor.get())
+ * };
+ *
+ * assert_eq!(scaled.get(&10), 20);
+ * assert_eq!(scaled.get(&15), 30);
+ * assert_eq!(scaled.len(), 2);
+ *
+ * factor.set(3);
+ * assert_eq!(scaled.get(&10), 30);
+ * ```
+ */
+pub struct Memo<A, T> {
+    inner: Arc<MemoInner<A, T>>,
+}
+
+struct MemoInner<A, T> {
+    timeline: Arc<TimelineState>,
+    computation: Arc<dyn MemoComputation<A, T>>,
+    entries: Mutex<Entries<A, T>>,
+}
+
+struct Entries<A, T> {
+    // ordered from the least to the most recently used argument
+    deriveds: IndexMap<A, Derived<T>>,
+    capacity: Option<usize>,
+}
+
+impl<A: Hash + Eq, T> Entries<A, T> {
+    /**
+     * Remove the least recently used entries until the memo is within its capacity.
+     */
+    fn evict(&mut self) -> Vec<Derived<T>> {
+        let capacity = match self.capacity {
+            Some(capacity) => capacity,
+            None => return vec![],
+        };
+
+        let mut evicted = vec![];
+
+        while self.deriveds.len() > capacity {
+            evicted.extend(
+                self.deriveds
+                    .shift_remove_index(0)
+                    .map(|(_, derived)| derived),
+            );
+        }
+
+        evicted
+    }
+}
+
+impl<A, T> Memo<A, T>
+where
+    A: Hash + Eq + Clone + MaybeSync + 'static,
+    T: MaybeSend + 'static,
+{
+    // the entries are only `Send` and `Sync` when the arguments and values are, like every
+    // other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn(&A) -> T + MaybeSync + 'static,
+    ) -> Memo<A, T> {
+        Memo {
+            inner: Arc::new(MemoInner {
+                timeline,
+                computation: Arc::new(computation),
+                entries: Mutex::new(Entries {
+                    deriveds: IndexMap::new(),
+                    capacity: None,
+                }),
+            }),
+        }
+    }
+
+    /**
+     * Keep at most `capacity` arguments, evicting the one that was used least recently to make
+     * room for a new one. Reading an argument, or asking for its derived, counts as using it.
+     *
+     * An evicted argument's derived, and with it the dependencies it recorded, is dropped unless
+     * something else still holds it. Passing the argument again computes it from scratch.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let squares = timeline.memo(|n: &u32| n * n).with_capacity(2);
+     *
+     * squares.get(&1);
+     * squares.get(&2);
+     * squares.get(&1);
+     * squares.get(&3);
+     *
+     * assert!(squares.contains(&1));
+     * assert!(!squares.contains(&2), "2 was used least recently");
+     * ```
+     */
+    pub fn with_capacity(self, capacity: usize) -> Memo<A, T> {
+        assert!(capacity > 0, "a memo needs room for at least one argument");
+
+        let evicted = {
+            let mut entries = self.inner.entries.lock();
+            entries.capacity = Some(capacity);
+            entries.evict()
+        };
+
+        drop(evicted);
+        self
+    }
+
+    /**
+     * The derived that caches the result for `arg`, creating it if `arg` wasn't passed to this
+     * memo before.
+     */
+    pub fn derived(&self, arg: &A) -> Derived<T> {
+        let (derived, evicted) = {
+            let mut entries = self.inner.entries.lock();
+
+            // a hit moves to the back, the most recently used end
+            if let Some((arg, derived)) = entries.deriveds.shift_remove_entry(arg) {
+                entries.deriveds.insert(arg, derived.clone());
+                return derived;
+            }
+
+            let derived = {
+                let computation = self.inner.computation.clone();
+                let arg = arg.clone();
+                Derived::new(self.inner.timeline.clone(), move || computation(&arg))
+            };
+
+            entries.deriveds.insert(arg.clone(), derived.clone());
+            (derived, entries.evict())
+        };
+
+        // dropping an evicted derived drops the computation and everything it read, which
+        // mustn't happen while the entries are locked
+        drop(evicted);
+        derived
+    }
+
+    /**
+     * Whether the result for `arg` is cached. Unlike `get`, this doesn't count as using `arg`.
+     */
+    pub fn contains(&self, arg: &A) -> bool {
+        self.inner.entries.lock().deriveds.contains_key(arg)
+    }
+
+    /**
+     * The number of arguments whose results are cached.
+     */
+    pub fn len(&self) -> usize {
+        self.inner.entries.lock().deriveds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<A, T> Memo<A, T>
+where
+    A: Hash + Eq + Clone + MaybeSync + 'static,
+    T: Clone + MaybeSend + 'static,
+{
+    /**
+     * The result for `arg`, computing it if it wasn't cached yet or if one of the values it read
+     * changed since it was computed. Like reading a derived, this records the result as a
+     * dependency of the enclosing computation.
+     */
+    pub fn get(&self, arg: &A) -> T {
+        // the entries aren't locked while the result is computed, so the computation can read
+        // other arguments of the same memo
+        self.derived(arg).get()
+    }
+}
+
+impl<A, T> Clone for Memo<A, T> {
+    fn clone(&self) -> Self {
+        Memo {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<A, T> Debug for Memo<A, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self.inner.entries.lock();
+
+        f.debug_struct("Memo")
+            .field("len", &entries.deriveds.len())
+            .field("capacity", &entries.capacity)
+            .finish()
+    }
+}
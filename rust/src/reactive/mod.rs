@@ -0,0 +1,73 @@
+pub(crate) mod bounds;
+pub(crate) mod cached;
+pub(crate) mod cell;
+pub(crate) mod constant;
+pub(crate) mod derived;
+pub(crate) mod derived_async;
+pub(crate) mod each;
+pub(crate) mod effect;
+pub(crate) mod external;
+pub(crate) mod fork;
+#[cfg(feature = "debug-graph")]
+pub(crate) mod graph;
+pub(crate) mod handles;
+#[cfg(feature = "history")]
+pub(crate) mod history;
+pub(crate) mod invalidation;
+pub(crate) mod label;
+pub(crate) mod map;
+pub(crate) mod memo;
+pub(crate) mod multi;
+pub(crate) mod observer;
+#[cfg(feature = "history")]
+pub(crate) mod persisted;
+pub(crate) mod registry;
+#[cfg(feature = "history")]
+pub(crate) mod replay;
+pub(crate) mod scheduler;
+pub(crate) mod snapshot;
+pub(crate) mod source;
+pub(crate) mod subscription;
+pub(crate) mod tag;
+pub(crate) mod value;
+pub(crate) mod vec;
+pub(crate) mod watch;
+pub(crate) mod writable;
+
+pub use bounds::{MaybeSend, MaybeSync};
+pub use cached::CachedMethods;
+pub use cell::{Cell, ReadonlyCell, Writer};
+pub use constant::Constant;
+pub use derived::{zip, Derived, DerivedStats, Validation};
+pub use derived_async::{DerivedAsync, Resolve};
+pub use each::{each, KeyedList, ListChange};
+pub use effect::Effect;
+pub use external::ExternalTag;
+pub use fork::Forked;
+#[cfg(feature = "debug-graph")]
+pub use graph::{DebugGraph, GraphNode};
+pub use handles::Handles;
+#[cfg(feature = "history")]
+pub use history::RewindError;
+pub use invalidation::{Invalidation, InvalidationStep};
+pub use map::TrackedMap;
+pub use memo::Memo;
+pub use multi::{MultiDerived, Outputs};
+pub use observer::{ObservedNode, ObserverEvent, RecordingObserver, TimelineObserver};
+#[cfg(feature = "history")]
+pub use persisted::{ParseStateError, SerializedState, Skipped};
+pub use registry::{Collected, MemoryStats};
+#[cfg(feature = "history")]
+pub use replay::{Mismatch, RecordedWrite, ReplayError, WriteLog};
+pub use scheduler::{
+    DeferredScheduler, Flush, ImmediateScheduler, ManualScheduler, Priority, RunawayFlush,
+    Scheduler,
+};
+pub use snapshot::{ChangedId, Snapshot};
+pub use source::{ExternalSource, ExternalValue};
+pub use subscription::SubscriptionHandle;
+pub use tag::{CombinedTag, Tag};
+pub use value::{Mapped, ReactiveValue, Zipped};
+pub use vec::{TrackedVec, VecIter};
+pub use watch::WatchHandle;
+pub use writable::WritableDerived;
@@ -0,0 +1,168 @@
+use std::{
+    any::{type_name, Any},
+    borrow::Cow,
+    fmt::Debug,
+    sync::Arc,
+};
+
+use indexmap::IndexMap;
+
+use crate::timeline::state::TimelineState;
+
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    derived::Derived,
+};
+
+/**
+ * A computation that produces several named outputs at once, created with
+ * `Timeline::multi_derived`. The computation runs once per change to what it read, and writes
+ * each output with `Outputs::set`. Readers read one output through the derived returned by
+ * `output`, which only advances its revision when that output changed, so a reader of one
+ * output doesn't recompute after a run that only changed the others.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let source = timeline.cell(String::from("let x = 1"));
+ *
+ * let parsed = {
+ *     let source = source.clone();
+ *     timeline.multi_derived(move |outputs| {
+ *         let source = source.get();
+ *         let tokens: Vec<String> = source.split_whitespace().map(String::from).collect();
+ *
+ *         outputs.set("errors", tokens.iter().filter(|token| *token == "?").count());
+ *         outputs.set("tokens", tokens);
+ *     })
+ * };
+ *
+ * let tokens = parsed.output::<Vec<String>>("tokens");
+ * let errors = parsed.output::<usize>("errors");
+ *
+ * assert_eq!(tokens.get().len(), 4);
+ * assert_eq!(errors.get(), 0);
+ * ```
+ *
+ * Reading an output that the last run didn't set, or with another type than it was set with,
+ * panics.
+ */
+pub struct MultiDerived {
+    timeline: Arc<TimelineState>,
+    outputs: Derived<Outputs>,
+}
+
+/**
+ * The outputs of one run of a `MultiDerived`'s computation.
+ */
+#[derive(Default)]
+pub struct Outputs {
+    values: IndexMap<Cow<'static, str>, Box<dyn Output>>,
+}
+
+trait Output: Any + MaybeSend + MaybeSync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + MaybeSend + MaybeSync> Output for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Outputs {
+    /**
+     * Set the output called `name`, replacing the value an earlier call set it to.
+     */
+    pub fn set<T>(&mut self, name: impl Into<Cow<'static, str>>, value: T)
+    where
+        T: MaybeSend + MaybeSync + 'static,
+    {
+        self.values.insert(name.into(), Box::new(value));
+    }
+
+    fn get<T: 'static>(&self, label: &str, name: &str) -> &T {
+        let value = match self.values.get(name) {
+            Some(value) => value,
+            None => panic!("{} didn't set an output called {}", label, name),
+        };
+
+        match (**value).as_any().downcast_ref() {
+            Some(value) => value,
+            None => panic!(
+                "the output {} of {} isn't a {}",
+                name,
+                label,
+                type_name::<T>()
+            ),
+        }
+    }
+}
+
+impl MultiDerived {
+    pub(crate) fn new(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn(&mut Outputs) + MaybeSync + 'static,
+    ) -> MultiDerived {
+        MultiDerived {
+            timeline: timeline.clone(),
+            outputs: Derived::new(timeline, move || {
+                let mut outputs = Outputs::default();
+                computation(&mut outputs);
+                outputs
+            }),
+        }
+    }
+
+    /**
+     * Give the computation a debug label. Outputs are labeled after it, so name it before
+     * calling `output`.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> MultiDerived {
+        MultiDerived {
+            timeline: self.timeline,
+            outputs: self.outputs.named(label),
+        }
+    }
+
+    /**
+     * The computation's debug label.
+     */
+    pub fn label(&self) -> &str {
+        self.outputs.label()
+    }
+
+    /**
+     * A derived that reads the output called `name`. Reading it runs the computation if it is
+     * stale, and it only advances its revision when the output is different from the last
+     * value it read.
+     */
+    pub fn output<T>(&self, name: impl Into<Cow<'static, str>>) -> Derived<T>
+    where
+        T: Clone + PartialEq + MaybeSend + MaybeSync + 'static,
+    {
+        let name = name.into();
+        let label = format!("{}.{}", self.label(), name);
+        let parent = self.outputs.clone();
+
+        Derived::with_eq(self.timeline.clone(), move || {
+            parent.with(|outputs| outputs.get::<T>(parent.label(), &name).clone())
+        })
+        .named(label)
+    }
+}
+
+impl Debug for MultiDerived {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiDerived")
+            .field("label", &self.label())
+            .finish()
+    }
+}
+
+impl Debug for Outputs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values.keys()).finish()
+    }
+}
@@ -0,0 +1,202 @@
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+
+use crate::timeline::{Revision, UntrackedRead};
+
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    label::Label,
+};
+
+/**
+ * Lifecycle callbacks for tooling like devtools panels and tracing, installed with
+ * `Timeline::set_observer`. Every method does nothing by default, so an observer only implements
+ * the events it cares about.
+ *
+ * Observers are called while the timeline is in the middle of the work they report, so they
+ * must not read or write the timeline's values.
+ */
+pub trait TimelineObserver: MaybeSend + MaybeSync {
+    /**
+     * A cell, derived or effect was created. Nodes are reported before they can be named, so the
+     * label is the fallback one, like `cell#7`.
+     */
+    fn node_created(&self, _node: &ObservedNode) {}
+
+    /**
+     * A derived recomputed, or an effect ran, taking `duration` and reading `consumed`
+     * dependencies.
+     */
+    fn node_recomputed(&self, _node: &ObservedNode, _duration: Duration, _consumed: usize) {}
+
+    /**
+     * A cell last written at `from` was written at `to`.
+     */
+    fn cell_written(&self, _cell: &ObservedNode, _from: Revision, _to: Revision) {}
+
+    /**
+     * A write scheduled a flush of the timeline's effects.
+     */
+    fn flush_scheduled(&self) {}
+
+    /**
+     * An effect ran during a flush.
+     */
+    fn effect_flushed(&self, _effect: &ObservedNode) {}
+
+    /**
+     * A cell, derived or effect was dropped.
+     */
+    fn node_dropped(&self, _node: &ObservedNode) {}
+
+    /**
+     * A cell was read outside of any `ComputeStack` frame under `ReadPolicy::Warn`, or under
+     * `ReadPolicy::Deny` in a release build.
+     */
+    fn untracked_read(&self, _read: &UntrackedRead) {}
+}
+
+/**
+ * The node an observer event is about.
+ */
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ObservedNode {
+    kind: &'static str,
+    id: u64,
+    label: String,
+}
+
+impl ObservedNode {
+    pub(crate) fn new(label: &Label) -> ObservedNode {
+        ObservedNode {
+            kind: label.kind(),
+            id: label.id(),
+            label: label.to_string(),
+        }
+    }
+
+    /**
+     * What the node is, like `cell`, `derived` or `effect`.
+     */
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /**
+     * The node's id, which is unique among the nodes of its kind.
+     */
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /**
+     * The node's debug label when the event happened.
+     */
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/**
+ * An event recorded by a `RecordingObserver`.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ObserverEvent {
+    Created(ObservedNode),
+    Recomputed {
+        node: ObservedNode,
+        duration: Duration,
+        consumed: usize,
+    },
+    Written {
+        cell: ObservedNode,
+        from: Revision,
+        to: Revision,
+    },
+    FlushScheduled,
+    EffectFlushed(ObservedNode),
+    Dropped(ObservedNode),
+}
+
+/**
+ * An observer that keeps every event in order, for tests. Clones share the same events, so a
+ * test can install one clone and read the events from another.
+ *
+ * ```
+ * use everafter::{ObserverEvent, RecordingObserver, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let recording = RecordingObserver::new();
+ * timeline.set_observer(Box::new(recording.clone()));
+ *
+ * let count = timeline.cell(0);
+ * count.set(1);
+ *
+ * let events = recording.take();
+ * assert!(matches!(&events[0], ObserverEvent::Created(node) if node.kind() == "cell"));
+ * assert!(matches!(&events[1], ObserverEvent::Written { .. }));
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RecordingObserver {
+    events: Arc<Mutex<Vec<ObserverEvent>>>,
+}
+
+impl RecordingObserver {
+    pub fn new() -> RecordingObserver {
+        RecordingObserver::default()
+    }
+
+    /**
+     * The events recorded so far.
+     */
+    pub fn events(&self) -> Vec<ObserverEvent> {
+        self.events.lock().clone()
+    }
+
+    /**
+     * The events recorded so far, which are forgotten.
+     */
+    pub fn take(&self) -> Vec<ObserverEvent> {
+        std::mem::take(&mut *self.events.lock())
+    }
+
+    fn push(&self, event: ObserverEvent) {
+        self.events.lock().push(event);
+    }
+}
+
+impl TimelineObserver for RecordingObserver {
+    fn node_created(&self, node: &ObservedNode) {
+        self.push(ObserverEvent::Created(node.clone()));
+    }
+
+    fn node_recomputed(&self, node: &ObservedNode, duration: Duration, consumed: usize) {
+        self.push(ObserverEvent::Recomputed {
+            node: node.clone(),
+            duration,
+            consumed,
+        });
+    }
+
+    fn cell_written(&self, cell: &ObservedNode, from: Revision, to: Revision) {
+        self.push(ObserverEvent::Written {
+            cell: cell.clone(),
+            from,
+            to,
+        });
+    }
+
+    fn flush_scheduled(&self) {
+        self.push(ObserverEvent::FlushScheduled);
+    }
+
+    fn effect_flushed(&self, effect: &ObservedNode) {
+        self.push(ObserverEvent::EffectFlushed(effect.clone()));
+    }
+
+    fn node_dropped(&self, node: &ObservedNode) {
+        self.push(ObserverEvent::Dropped(node.clone()));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use crate::timeline::{state::TimelineState, Transaction};
+
+/**
+ * The values of a timeline's keyed cells, by key, as `Timeline::serialize_state` found them.
+ * Values are formatted with `Display`, and are parsed back with `FromStr` when the state is
+ * restored.
+ *
+ * The state formats as one line per cell, its key and its value separated by a tab, with
+ * backslashes, tabs and line breaks escaped, and parses back from that, so it can be saved as text.
+ *
+ * ```
+ * use everafter::SerializedState;
+ *
+ * let mut state = SerializedState::new();
+ * state.insert("greeting", "hello\tworld");
+ * state.insert("count", "3");
+ *
+ * let saved = state.to_string();
+ * assert_eq!(saved, "count\t3\ngreeting\thello\\tworld\n");
+ * assert_eq!(saved.parse::<SerializedState>().unwrap(), state);
+ * ```
+ */
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SerializedState {
+    values: BTreeMap<String, String>,
+}
+
+impl SerializedState {
+    pub fn new() -> SerializedState {
+        SerializedState::default()
+    }
+
+    /**
+     * The saved value of the cell keyed `key`.
+     */
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /**
+     * Save `value` for the cell keyed `key`, replacing the value saved for it before.
+     */
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(key.into(), value.into());
+    }
+
+    /**
+     * The keys and saved values, sorted by key.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /**
+     * The current value of every live keyed cell of `timeline`.
+     */
+    pub(crate) fn capture(timeline: &TimelineState) -> SerializedState {
+        let mut state = SerializedState::new();
+
+        for (key, cell) in timeline.keyed_cells() {
+            if let Some(value) = cell.current() {
+                state.insert(&*key, value);
+            }
+        }
+
+        state
+    }
+
+    /**
+     * Write the saved values into the keyed cells of `timeline`, in a single transaction, and
+     * return the ones that were skipped. With `if_changed`, a value that formats the same as
+     * the cell's current value isn't written.
+     */
+    pub(crate) fn restore(&self, timeline: &TimelineState, if_changed: bool) -> Vec<Skipped> {
+        let mut skipped = vec![];
+        let transaction = Transaction::begin(timeline);
+
+        for (key, value) in &self.values {
+            let cell = match timeline.keyed_cell(key) {
+                Some(cell) => cell,
+                None => {
+                    skipped.push(Skipped::Missing { key: key.clone() });
+                    continue;
+                }
+            };
+
+            if if_changed && cell.current().as_ref() == Some(value) {
+                continue;
+            }
+
+            match cell.replay(value) {
+                Some(Ok(_)) => {}
+                Some(Err(error)) => skipped.push(Skipped::Unparsable {
+                    key: key.clone(),
+                    value: value.clone(),
+                    error,
+                }),
+                None => skipped.push(Skipped::Missing { key: key.clone() }),
+            }
+        }
+
+        drop(transaction);
+        skipped
+    }
+}
+
+impl Display for SerializedState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in &self.values {
+            writeln!(f, "{}\t{}", escape(key), escape(value))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SerializedState {
+    type Err = ParseStateError;
+
+    fn from_str(saved: &str) -> Result<SerializedState, ParseStateError> {
+        let mut state = SerializedState::new();
+
+        for (index, line) in saved.lines().enumerate() {
+            let error = || ParseStateError { line: index + 1 };
+            let (key, value) = line.split_once('\t').ok_or_else(error)?;
+            let key = unescape(key).ok_or_else(error)?;
+            let value = unescape(value).ok_or_else(error)?;
+            state.insert(key, value);
+        }
+
+        Ok(state)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+/**
+ * Undo `escape`, or return `None` for a text that `escape` couldn't have produced.
+ */
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => unescaped.push(match chars.next()? {
+                '\\' => '\\',
+                't' => '\t',
+                'n' => '\n',
+                'r' => '\r',
+                _ => return None,
+            }),
+            '\t' => return None,
+            character => unescaped.push(character),
+        }
+    }
+
+    Some(unescaped)
+}
+
+/**
+ * A saved `SerializedState` that doesn't parse, with the line that doesn't, counting from 1.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseStateError {
+    line: usize,
+}
+
+impl ParseStateError {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Display for ParseStateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {} of the saved state isn't an escaped key and value separated by a tab",
+            self.line
+        )
+    }
+}
+
+impl Error for ParseStateError {}
+
+/**
+ * A saved value that restoring a `SerializedState` didn't write.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Skipped {
+    /**
+     * The timeline has no live cell with the key.
+     */
+    Missing { key: String },
+    /**
+     * The value didn't parse back into the cell's value, with the error it failed with.
+     */
+    Unparsable {
+        key: String,
+        value: String,
+        error: String,
+    },
+}
+
+impl Display for Skipped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Skipped::Missing { key } => write!(f, "the timeline has no cell keyed {:?}", key),
+            Skipped::Unparsable { key, value, error } => write!(
+                f,
+                "the value {:?} saved for {:?} doesn't parse: {}",
+                value, key, error
+            ),
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    mem,
+    sync::{Arc, Weak},
+};
+
+use crate::{
+    inputs::{reactive::EDGE_SIZE, ReactiveTag, Tag},
+    timeline::Revision,
+};
+
+#[cfg(feature = "debug-graph")]
+use super::graph::GraphNode;
+use super::{bounds::MaybeSync, label::Label};
+
+/**
+ * Weak handles to every cell, tag, derived and effect created from a timeline, for the
+ * timeline's diagnostics and for renumbering its revisions. Registering a value doesn't keep it
+ * alive.
+ *
+ * Entries for dropped values are discarded whenever the registry is read, and whenever it has
+ * doubled in size since it was last compacted, so a timeline that creates many short-lived values
+ * doesn't grow the registry without bound. Writes also sweep a few entries at a time, which
+ * compacts the reverse edges of the values they visit, and `Timeline::collect` sweeps all of them
+ * at once.
+ */
+#[derive(Default)]
+pub(crate) struct Registry {
+    entries: Vec<Entry>,
+    compact_at: usize,
+    // where the next `sweep` starts, and how many dropped values the sweeps since the last
+    // compaction came across
+    cursor: usize,
+    swept: usize,
+}
+
+impl Registry {
+    pub(crate) fn register(&mut self, entry: Entry) {
+        if self.entries.len() >= self.compact_at {
+            self.compact();
+            self.compact_at = (self.entries.len() * 2).max(16);
+        }
+
+        self.entries.push(entry);
+    }
+
+    /**
+     * The entries for values that are still alive, in the order they were created.
+     */
+    pub(crate) fn entries(&mut self) -> &[Entry] {
+        self.compact();
+        &self.entries
+    }
+
+    fn compact(&mut self) {
+        self.entries.retain(Entry::is_alive);
+        self.cursor = 0;
+        self.swept = 0;
+    }
+
+    /**
+     * Discard the entries for dropped values and the reverse edges to dropped computations,
+     * and free the room they took up.
+     */
+    pub(crate) fn collect(&mut self) -> Collected {
+        let (entries, capacity) = (self.entries.len(), self.entries.capacity());
+        self.compact();
+        self.compact_at = (self.entries.len() * 2).max(16);
+
+        let edges: usize = self.entries.iter().map(Entry::compact_dependents).sum();
+
+        if self.entries.len() < capacity / 2 {
+            self.entries.shrink_to_fit();
+        }
+
+        Collected {
+            nodes: entries - self.entries.len(),
+            bytes: (capacity - self.entries.capacity()) * mem::size_of::<Entry>()
+                + edges * EDGE_SIZE,
+        }
+    }
+
+    /**
+     * Compact the reverse edges of the next `budget` entries, going round the registry, and
+     * discard the entries for dropped values once a round came across any.
+     */
+    pub(crate) fn sweep(&mut self, budget: usize) {
+        let end = (self.cursor + budget).min(self.entries.len());
+
+        for entry in &self.entries[self.cursor..end] {
+            if entry.is_alive() {
+                entry.compact_dependents();
+            } else {
+                self.swept += 1;
+            }
+        }
+
+        self.cursor = end;
+
+        if self.cursor == self.entries.len() {
+            if self.swept > 0 {
+                self.compact();
+            }
+
+            self.cursor = 0;
+            self.swept = 0;
+        }
+    }
+
+    pub(crate) fn memory_stats(&mut self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+
+        for entry in self.entries() {
+            match entry {
+                Entry::Cell(tag) | Entry::Tag(tag) => {
+                    if let Some(tag) = tag.upgrade() {
+                        stats.nodes += 1;
+                        stats.bytes +=
+                            mem::size_of::<Tag>() + tag.dependents.capacity() * EDGE_SIZE;
+                    }
+                }
+                Entry::Computation(computation) => {
+                    if let Some(computation) = computation.upgrade() {
+                        let slots = computation.dependency_capacity();
+
+                        stats.nodes += 1;
+                        stats.tag_slots += slots;
+                        stats.bytes += mem::size_of_val(&*computation)
+                            + slots * mem::size_of::<ReactiveTag>()
+                            + computation.dependent_capacity() * EDGE_SIZE;
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+/**
+ * An estimate of the memory a timeline's live values take up, returned by
+ * `Timeline::memory_stats`. Collecting it visits every live value once without allocating or
+ * validating anything, so it is cheap enough to collect every frame.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let cells: Vec<_> = (0..3).map(|i| timeline.cell(i)).collect();
+ *
+ * let sum = {
+ *     let cells = cells.clone();
+ *     timeline.derived(move || cells.iter().map(|cell| cell.get()).sum::<i32>())
+ * };
+ * sum.get();
+ *
+ * let stats = timeline.memory_stats();
+ * assert_eq!(stats.nodes(), 4);
+ * assert!(stats.tag_slots() >= sum.dependency_count());
+ * ```
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    nodes: usize,
+    tag_slots: usize,
+    bytes: usize,
+}
+
+impl MemoryStats {
+    /**
+     * The number of live cells, tags and computations.
+     */
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /**
+     * The number of dependencies the computations have room for, used or not.
+     */
+    pub fn tag_slots(&self) -> usize {
+        self.tag_slots
+    }
+
+    /**
+     * The bytes taken up by the values' own bookkeeping, their dependency lists and the reverse
+     * edges that eager validation records. Memory that cached values own on the heap isn't
+     * counted.
+     */
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/**
+ * What `Timeline::collect` discarded from the bookkeeping of values that were dropped.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Collected {
+    nodes: usize,
+    bytes: usize,
+}
+
+impl Collected {
+    /**
+     * The number of dropped cells, tags and computations the timeline forgot.
+     */
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /**
+     * The bytes freed by forgetting them and the reverse edges to them.
+     */
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+pub(crate) enum Entry {
+    // a cell is represented by its tag, which carries the cell's label
+    Cell(Weak<Tag>),
+    // a tag that doesn't belong to a cell, like the tags of vecs and maps. Tags are only
+    // registered so their revisions can be renumbered.
+    Tag(Weak<Tag>),
+    Computation(Weak<dyn Registered>),
+}
+
+impl Entry {
+    fn is_alive(&self) -> bool {
+        match self {
+            Entry::Cell(tag) | Entry::Tag(tag) => tag.strong_count() > 0,
+            Entry::Computation(computation) => computation.strong_count() > 0,
+        }
+    }
+
+    /**
+     * The value's label and current revision, or `None` if it was dropped.
+     */
+    pub(crate) fn revision(&self) -> Option<(Arc<Label>, Revision)> {
+        match self {
+            Entry::Cell(tag) => {
+                let tag = tag.upgrade()?;
+                Some((tag.label.clone()?, tag.revision.get()))
+            }
+            Entry::Tag(_) => None,
+            Entry::Computation(computation) => {
+                let computation = computation.upgrade()?;
+                Some((computation.label(), computation.last_revision()))
+            }
+        }
+    }
+
+    #[cfg(feature = "debug-graph")]
+    pub(crate) fn node(&self) -> Option<GraphNode> {
+        match self {
+            Entry::Cell(tag) => {
+                let tag = tag.upgrade()?;
+
+                Some(GraphNode {
+                    key: tag.slot.key(),
+                    kind: "cell",
+                    id: tag.label.as_ref()?.id(),
+                    label: tag.label.as_ref()?.to_string(),
+                    revision: tag.revision.get(),
+                    stale: false,
+                    dependencies: vec![],
+                })
+            }
+            Entry::Tag(_) => None,
+            Entry::Computation(computation) => Some(computation.upgrade()?.node()),
+        }
+    }
+
+    /**
+     * Push every revision the value stores onto `out`.
+     */
+    pub(crate) fn revisions(&self, out: &mut Vec<Revision>) {
+        match self {
+            Entry::Cell(tag) | Entry::Tag(tag) => {
+                if let Some(tag) = tag.upgrade() {
+                    out.push(tag.revision.get());
+                }
+            }
+            Entry::Computation(computation) => {
+                if let Some(computation) = computation.upgrade() {
+                    computation.revisions(out);
+                }
+            }
+        }
+    }
+
+    /**
+     * Replace every revision the value stores with `renumber(revision)`.
+     */
+    pub(crate) fn renumber(&self, renumber: &dyn Fn(Revision) -> Revision) {
+        match self {
+            Entry::Cell(tag) | Entry::Tag(tag) => {
+                if let Some(tag) = tag.upgrade() {
+                    tag.revision.update(renumber(tag.revision.get()));
+                }
+            }
+            Entry::Computation(computation) => {
+                if let Some(computation) = computation.upgrade() {
+                    computation.renumber(renumber);
+                }
+            }
+        }
+    }
+
+    /**
+     * Discard the reverse edges to dropped computations, returning the number of edges there is
+     * no longer room for.
+     */
+    fn compact_dependents(&self) -> usize {
+        match self {
+            Entry::Cell(tag) | Entry::Tag(tag) => {
+                tag.upgrade().map_or(0, |tag| tag.dependents.compact())
+            }
+            Entry::Computation(computation) => computation
+                .upgrade()
+                .map_or(0, |computation| computation.compact_dependents()),
+        }
+    }
+
+    /**
+     * Move the value back to the initial revision after its timeline was reset.
+     */
+    pub(crate) fn reset(&self) {
+        match self {
+            Entry::Cell(tag) | Entry::Tag(tag) => {
+                if let Some(tag) = tag.upgrade() {
+                    if !tag.revision.get().is_constant() {
+                        tag.revision.update(Revision::initial());
+                    }
+                }
+            }
+            Entry::Computation(computation) => {
+                if let Some(computation) = computation.upgrade() {
+                    computation.reset();
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Implemented by computations so the registry can describe them.
+ */
+pub(crate) trait Registered: MaybeSync {
+    fn label(&self) -> Arc<Label>;
+
+    /**
+     * The revision the computation last reported, without bringing it up to date.
+     */
+    fn last_revision(&self) -> Revision;
+
+    /**
+     * Push every revision the computation stores onto `out`.
+     */
+    fn revisions(&self, out: &mut Vec<Revision>);
+
+    /**
+     * Replace every revision the computation stores with `renumber(revision)`.
+     */
+    fn renumber(&self, renumber: &dyn Fn(Revision) -> Revision);
+
+    /**
+     * Forget the revisions the computation stores after its timeline was reset, so it is brought
+     * up to date the next time it is used.
+     */
+    fn reset(&self);
+
+    /**
+     * The number of dependencies the computation has room for, used or not.
+     */
+    fn dependency_capacity(&self) -> usize;
+
+    /**
+     * The number of reverse edges to the computations that read this one there is room for.
+     */
+    fn dependent_capacity(&self) -> usize {
+        0
+    }
+
+    /**
+     * Discard the reverse edges to dropped computations, like `Dependents::compact`.
+     */
+    fn compact_dependents(&self) -> usize {
+        0
+    }
+
+    #[cfg(feature = "debug-graph")]
+    fn node(&self) -> GraphNode;
+}
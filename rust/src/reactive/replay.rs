@@ -0,0 +1,453 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+
+use crate::timeline::{Revision, Timeline};
+
+use super::bounds::{MaybeSend, MaybeSync};
+
+/**
+ * A cell created with `Cell::keyed`, which a replay writes by parsing the values that were
+ * recorded for its key.
+ */
+pub(crate) trait Replay: MaybeSend + MaybeSync {
+    fn is_alive(&self) -> bool;
+
+    /**
+     * The cell's value in the timeline, formatted with `Display`, or `None` if the cell was
+     * dropped.
+     */
+    fn current(&self) -> Option<String>;
+
+    /**
+     * Parse `value` and write it into the cell, returning the revision the cell was written at,
+     * or why the value didn't parse. Returns `None` if the cell was dropped.
+     */
+    fn replay(&self, value: &str) -> Option<Result<Revision, String>>;
+}
+
+/**
+ * A derived created with `Derived::keyed`, whose value a replay compares with the recorded one.
+ */
+pub(crate) trait Output: MaybeSend + MaybeSync {
+    fn is_alive(&self) -> bool;
+
+    /**
+     * The derived's current value, formatted with `Display`, without tracking the read. Returns
+     * `None` if the derived was dropped.
+     */
+    fn output(&self) -> Option<String>;
+}
+
+/**
+ * The timeline's keyed cells and deriveds, and the writes to keyed cells since
+ * `Timeline::start_recording`.
+ */
+#[derive(Default)]
+pub(crate) struct Keys {
+    cells: HashMap<Arc<str>, Arc<dyn Replay>>,
+    outputs: HashMap<Arc<str>, Arc<dyn Output>>,
+    // `None` while the timeline isn't recording
+    recording: Option<Vec<RecordedWrite>>,
+}
+
+impl Keys {
+    pub(crate) fn key_cell(&mut self, key: Arc<str>, cell: Arc<dyn Replay>) {
+        self.cells.retain(|_, cell| cell.is_alive());
+        assert!(
+            !self.cells.contains_key(&key),
+            "{:?} already keys a live cell",
+            key
+        );
+        self.cells.insert(key, cell);
+    }
+
+    pub(crate) fn key_output(&mut self, key: Arc<str>, output: Arc<dyn Output>) {
+        self.outputs.retain(|_, output| output.is_alive());
+        assert!(
+            !self.outputs.contains_key(&key),
+            "{:?} already keys a live derived",
+            key
+        );
+        self.outputs.insert(key, output);
+    }
+
+    pub(crate) fn cell(&self, key: &str) -> Option<Arc<dyn Replay>> {
+        self.cells.get(key).cloned()
+    }
+
+    /**
+     * The live keyed cells, sorted by key.
+     */
+    pub(crate) fn cells(&self) -> Vec<(Arc<str>, Arc<dyn Replay>)> {
+        let mut cells: Vec<_> = self
+            .cells
+            .iter()
+            .filter(|(_, cell)| cell.is_alive())
+            .map(|(key, cell)| (key.clone(), cell.clone()))
+            .collect();
+
+        cells.sort_by(|(a, _), (b, _)| a.cmp(b));
+        cells
+    }
+
+    pub(crate) fn output(&self, key: &str) -> Option<Arc<dyn Output>> {
+        self.outputs.get(key).cloned()
+    }
+
+    /**
+     * The live keyed deriveds, sorted by key.
+     */
+    pub(crate) fn outputs(&self) -> Vec<(Arc<str>, Arc<dyn Output>)> {
+        let mut outputs: Vec<_> = self
+            .outputs
+            .iter()
+            .filter(|(_, output)| output.is_alive())
+            .map(|(key, output)| (key.clone(), output.clone()))
+            .collect();
+
+        outputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        outputs
+    }
+
+    pub(crate) fn start_recording(&mut self) {
+        self.recording = Some(vec![]);
+    }
+
+    pub(crate) fn stop_recording(&mut self) -> Vec<RecordedWrite> {
+        self.recording.take().unwrap_or_default()
+    }
+
+    /**
+     * Log the write of a keyed cell at `revision`, serializing its new value with `value` only
+     * if the timeline is recording.
+     */
+    pub(crate) fn record(
+        &mut self,
+        key: &Arc<str>,
+        value: impl FnOnce() -> String,
+        revision: Revision,
+    ) {
+        if let Some(writes) = &mut self.recording {
+            writes.push(RecordedWrite {
+                key: key.clone(),
+                value: value(),
+                revision,
+            });
+        }
+    }
+
+    pub(crate) fn revisions(&self, out: &mut Vec<Revision>) {
+        if let Some(writes) = &self.recording {
+            out.extend(writes.iter().map(|write| write.revision));
+        }
+    }
+
+    pub(crate) fn renumber(&mut self, renumber: &dyn Fn(Revision) -> Revision) {
+        if let Some(writes) = &mut self.recording {
+            for write in writes {
+                write.revision = renumber(write.revision);
+            }
+        }
+    }
+
+    /**
+     * Forget the writes logged so far, once the timeline is back at its initial revision. A
+     * recording timeline keeps recording.
+     */
+    pub(crate) fn reset(&mut self) {
+        if let Some(writes) = &mut self.recording {
+            writes.clear();
+        }
+    }
+}
+
+/**
+ * A write to a keyed cell, as `Timeline::stop_recording` logged it.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RecordedWrite {
+    key: Arc<str>,
+    value: String,
+    revision: Revision,
+}
+
+impl RecordedWrite {
+    /**
+     * The key of the cell that was written.
+     */
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /**
+     * The value that was written, formatted with `Display`.
+     */
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /**
+     * The revision the cell was written at. Writes in the same transaction share it.
+     */
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+}
+
+/**
+ * The writes to keyed cells between `Timeline::start_recording` and `Timeline::stop_recording`,
+ * oldest first, and the value every keyed derived had when the recording stopped. Replaying the
+ * log onto a freshly built graph should leave its keyed deriveds with the same values.
+ */
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct WriteLog {
+    writes: Vec<RecordedWrite>,
+    // sorted by key
+    outputs: Vec<(Arc<str>, String)>,
+}
+
+impl WriteLog {
+    pub(crate) fn new(writes: Vec<RecordedWrite>, outputs: Vec<(Arc<str>, String)>) -> WriteLog {
+        WriteLog { writes, outputs }
+    }
+
+    /**
+     * The logged writes, oldest first.
+     */
+    pub fn writes(&self) -> &[RecordedWrite] {
+        &self.writes
+    }
+
+    /**
+     * The keys of the keyed deriveds the log recorded, with their values formatted with
+     * `Display`, sorted by key.
+     */
+    pub fn outputs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.outputs
+            .iter()
+            .map(|(key, value)| (&**key, value.as_str()))
+    }
+
+    /**
+     * Apply the logged writes in order to the cells of `timeline` with the same keys, parsing
+     * each value with `FromStr`. Writes that were logged in the same transaction are applied in
+     * one transaction, and every write advances the revision, even if it writes an equal value.
+     *
+     * The replay fails as soon as a write's cell isn't keyed in `timeline`, its value doesn't
+     * parse, or the writes' revisions advance in a different relative order than they did when
+     * they were recorded, which happens when the replay itself runs inside a transaction. The
+     * writes before it stay applied. Once every write is applied, every keyed derived the log
+     * recorded is read, and the replay fails with each one whose value differs, including the
+     * ones `timeline` doesn't have.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let build = |timeline: &Timeline| {
+     *     let count = timeline.cell(0).keyed("count");
+     *     let doubled = {
+     *         let count = count.clone();
+     *         timeline.derived(move || count.get() * 2).keyed("doubled")
+     *     };
+     *     (count, doubled)
+     * };
+     *
+     * let recorded = Timeline::new();
+     * let (count, _doubled) = build(&recorded);
+     *
+     * recorded.start_recording();
+     * count.set(1);
+     * count.set(2);
+     * let log = recorded.stop_recording();
+     * assert_eq!(log.writes().len(), 2);
+     *
+     * let replayed = Timeline::new();
+     * let (_, doubled) = build(&replayed);
+     *
+     * log.replay(&replayed).unwrap();
+     * assert_eq!(doubled.get(), 4);
+     * ```
+     */
+    pub fn replay(&self, timeline: &Timeline) -> Result<(), ReplayError> {
+        let state = timeline.state();
+        // the recorded and the replayed revision of the last write
+        let mut previous: Option<(Revision, Revision)> = None;
+        let mut index = 0;
+
+        for group in self.writes.chunk_by(|a, b| a.revision == b.revision) {
+            timeline.transaction(|_| {
+                for write in group {
+                    let replayed = state
+                        .keyed_cell(&write.key)
+                        .and_then(|cell| cell.replay(&write.value));
+
+                    let revision = match replayed {
+                        Some(Ok(revision)) => revision,
+                        Some(Err(error)) => {
+                            return Err(ReplayError::Unparsable {
+                                key: write.key.to_string(),
+                                value: write.value.clone(),
+                                error,
+                            })
+                        }
+                        None => {
+                            return Err(ReplayError::MissingCell {
+                                key: write.key.to_string(),
+                            })
+                        }
+                    };
+
+                    if let Some((recorded, replayed)) = previous {
+                        if recorded.cmp(&write.revision) != replayed.cmp(&revision) {
+                            return Err(ReplayError::OutOfOrder {
+                                key: write.key.to_string(),
+                                index,
+                            });
+                        }
+                    }
+
+                    previous = Some((write.revision, revision));
+                    index += 1;
+                }
+
+                Ok(())
+            })?;
+        }
+
+        let mismatches: Vec<Mismatch> = self
+            .outputs
+            .iter()
+            .filter_map(|(key, recorded)| {
+                let replayed = state.keyed_output(key).and_then(|output| output.output());
+
+                (replayed.as_ref() != Some(recorded)).then(|| Mismatch {
+                    key: key.to_string(),
+                    recorded: recorded.clone(),
+                    replayed,
+                })
+            })
+            .collect();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(ReplayError::Mismatched(mismatches))
+        }
+    }
+}
+
+/**
+ * A `WriteLog::replay` that couldn't apply the log, or whose keyed deriveds ended up with
+ * different values.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReplayError {
+    /**
+     * The timeline has no live cell with the key of a logged write.
+     */
+    MissingCell { key: String },
+    /**
+     * A logged value didn't parse back into its cell's value, with the error it failed with.
+     */
+    Unparsable {
+        key: String,
+        value: String,
+        error: String,
+    },
+    /**
+     * The write at `index` in the log advanced the revision in a different order, relative to
+     * the write before it, than when it was recorded.
+     */
+    OutOfOrder { key: String, index: usize },
+    /**
+     * The keyed deriveds whose values differ once every write was applied.
+     */
+    Mismatched(Vec<Mismatch>),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::MissingCell { key } => {
+                write!(f, "the timeline has no cell keyed {:?} to replay onto", key)
+            }
+            ReplayError::Unparsable { key, value, error } => write!(
+                f,
+                "the value {:?} recorded for {:?} doesn't parse: {}",
+                value, key, error
+            ),
+            ReplayError::OutOfOrder { key, index } => write!(
+                f,
+                "write {} of the log, to {:?}, advanced the revision in a different order than \
+                 when it was recorded",
+                index, key
+            ),
+            ReplayError::Mismatched(mismatches) => {
+                write!(f, "the replayed deriveds differ from the recorded ones:")?;
+
+                for mismatch in mismatches {
+                    write!(f, "\n  {}", mismatch)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/**
+ * A keyed derived whose replayed value differs from the recorded one.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Mismatch {
+    key: String,
+    recorded: String,
+    replayed: Option<String>,
+}
+
+impl Mismatch {
+    /**
+     * The key of the derived.
+     */
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /**
+     * The value the derived had when the recording stopped.
+     */
+    pub fn recorded(&self) -> &str {
+        &self.recorded
+    }
+
+    /**
+     * The value the derived had after the replay, or `None` if the replayed timeline has no
+     * derived with the key.
+     */
+    pub fn replayed(&self) -> Option<&str> {
+        self.replayed.as_deref()
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.replayed {
+            Some(replayed) => write!(
+                f,
+                "{}: recorded {:?}, replayed {:?}",
+                self.key, self.recorded, replayed
+            ),
+            None => write!(
+                f,
+                "{}: recorded {:?}, but the replay has no derived with the key",
+                self.key, self.recorded
+            ),
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    error::Error,
+    fmt::{Debug, Display, Formatter},
+    sync::{Arc, Weak},
+};
+
+use atomig::Atom;
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::ReactiveTag,
+    timeline::{state::TimelineState, ComputationId, NodeKey},
+};
+
+use super::bounds::MaybeSync;
+
+/**
+ * Something that is registered with a timeline and re-runs when its dependencies change.
+ */
+pub(crate) trait Reaction: Debug + MaybeSync {
+    fn id(&self) -> ComputationId;
+
+    fn label(&self) -> String;
+
+    /**
+     * Whether any of the values the reaction read have changed since it last ran.
+     */
+    fn is_stale(&self) -> bool;
+
+    /**
+     * The tags the reaction read the last time it ran.
+     */
+    fn dependencies(&self) -> Vec<ReactiveTag> {
+        vec![]
+    }
+
+    /**
+     * The keys of the tags the reaction wrote the last time it ran.
+     */
+    fn written(&self) -> Vec<NodeKey> {
+        vec![]
+    }
+
+    /**
+     * Run the reaction again if any of the values it read have changed since it last ran, and
+     * report whether it ran.
+     */
+    fn run_if_stale(&self) -> bool;
+
+    /**
+     * Run the reaction as part of the flush numbered `flush`, which may be a later pass of the
+     * same flush. Returns whether the reaction is stale but waiting for a later flush, so the
+     * timeline schedules one even if nothing is written in the meantime.
+     */
+    fn flush(&self, _flush: u64) -> bool {
+        self.run_if_stale();
+        false
+    }
+
+    /**
+     * The lane the reaction is flushed in.
+     */
+    fn priority(&self) -> Priority {
+        Priority::default()
+    }
+
+    /**
+     * Stop the reaction from running again.
+     */
+    fn dispose(&self);
+}
+
+/**
+ * The lane an effect is flushed in, set with `Effect::with_priority`. A flush runs the stale
+ * effects of each lane in registration order, `Sync` first and `Idle` last. When an effect
+ * invalidates an effect in a higher lane, the flush goes back to run the higher one before it
+ * continues with the lower lane, up to the number of times `Timeline::set_max_preemptions`
+ * allows per flush. After that the lower lanes finish first, so a pair of effects that keep
+ * invalidating each other can't starve them.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Atom)]
+#[repr(u8)]
+pub enum Priority {
+    /**
+     * Effects that everything else relies on, like layout.
+     */
+    Sync,
+
+    /**
+     * The lane of effects that weren't given a priority.
+     */
+    #[default]
+    Render,
+
+    /**
+     * Effects that can wait for everything else, like logging and analytics.
+     */
+    Idle,
+}
+
+/**
+ * Decides when the effects of a timeline are flushed. The timeline calls `schedule` after
+ * every write that could have invalidated an effect, and the scheduler is responsible for
+ * eventually calling `Flush::run`.
+ */
+pub trait Scheduler: MaybeSync {
+    fn schedule(&self, flush: Flush);
+}
+
+/**
+ * The default scheduler, which flushes synchronously as part of the write.
+ */
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ImmediateScheduler;
+
+impl Scheduler for ImmediateScheduler {
+    fn schedule(&self, flush: Flush) {
+        flush.run();
+    }
+}
+
+/**
+ * A scheduler that collects flushes until `flush` is called, for callers that want to decide
+ * when effects run, like once per frame.
+ *
+ * Flushing runs every effect whose dependencies changed since it last ran, in the order the
+ * effects were created. An effect runs at most once per flush no matter how many of its
+ * dependencies changed, unless another effect writes to one of its dependencies during the flush.
+ *
+ * ```
+ * use everafter::{ManualScheduler, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let scheduler = ManualScheduler::default();
+ * timeline.set_scheduler(scheduler.clone());
+ *
+ * let cell = timeline.cell(1);
+ * let effect = {
+ *     let cell = cell.clone();
+ *     timeline.effect(move || println!("{}", cell.get()))
+ * };
+ *
+ * cell.set(2);
+ * assert!(scheduler.is_pending());
+ *
+ * scheduler.flush();
+ * assert!(!scheduler.is_pending());
+ * ```
+ */
+#[derive(Debug, Default, Clone)]
+pub struct ManualScheduler {
+    pending: Arc<Mutex<Vec<Flush>>>,
+}
+
+impl ManualScheduler {
+    /**
+     * Whether a write happened since the last flush.
+     */
+    pub fn is_pending(&self) -> bool {
+        !self.pending.lock().is_empty()
+    }
+
+    /**
+     * Run the stale effects of every timeline that was written since the last flush.
+     */
+    pub fn flush(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock());
+
+        for flush in pending {
+            flush.run();
+        }
+    }
+}
+
+impl Scheduler for ManualScheduler {
+    fn schedule(&self, flush: Flush) {
+        let mut pending = self.pending.lock();
+
+        // a timeline is flushed once no matter how many writes happened
+        if !pending
+            .iter()
+            .any(|other| other.timeline.ptr_eq(&flush.timeline))
+        {
+            pending.push(flush);
+        }
+    }
+}
+
+/**
+ * A scheduler for rendering, which coalesces every write until `flush` is called, like once per
+ * animation frame. Unlike a `ManualScheduler`, it flushes with `Flush::run_ordered`: an effect
+ * that writes a value another effect reads runs first, so each of them runs once per flush, and a
+ * flush whose effects keep invalidating each other stops after a number of passes instead of
+ * looping forever.
+ *
+ * ```
+ * use std::sync::{Arc, Mutex};
+ * use everafter::{DeferredScheduler, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let scheduler = DeferredScheduler::default();
+ * timeline.set_scheduler(scheduler.clone());
+ *
+ * let width = timeline.cell(100);
+ * let content = timeline.cell(0);
+ * let log = Arc::new(Mutex::new(vec![]));
+ *
+ * // created first, so a flush in creation order would run it before the layout
+ * let child = {
+ *     let (width, content, log) = (width.clone(), content.clone(), log.clone());
+ *     timeline.effect(move || log.lock().unwrap().push(("child", width.get(), content.get())))
+ * };
+ * let layout = {
+ *     let (width, content, log) = (width.clone(), content.clone(), log.clone());
+ *     timeline.effect(move || {
+ *         log.lock().unwrap().push(("layout", width.get(), 0));
+ *         content.set(width.get() - 20);
+ *     })
+ * };
+ *
+ * scheduler.flush().unwrap();
+ * log.lock().unwrap().clear();
+ *
+ * width.set(300);
+ * assert!(scheduler.is_pending());
+ * scheduler.flush().unwrap();
+ * assert_eq!(*log.lock().unwrap(), [("layout", 300, 0), ("child", 300, 280)]);
+ * assert!(!scheduler.is_pending());
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct DeferredScheduler {
+    pending: Arc<Mutex<Vec<Flush>>>,
+    max_passes: usize,
+}
+
+impl DeferredScheduler {
+    /**
+     * A scheduler whose flushes give up once their effects were still invalidating each other
+     * after `max_passes` passes. The default is 100.
+     */
+    pub fn with_max_passes(max_passes: usize) -> DeferredScheduler {
+        assert!(max_passes > 0, "a flush needs at least one pass");
+
+        DeferredScheduler {
+            pending: Arc::default(),
+            max_passes,
+        }
+    }
+
+    /**
+     * Whether a write happened since the last flush.
+     */
+    pub fn is_pending(&self) -> bool {
+        !self.pending.lock().is_empty()
+    }
+
+    /**
+     * Run the stale effects of every timeline that was written since the last flush, in
+     * dependency order. Every timeline is flushed even if one of them runs away, and the first
+     * `RunawayFlush` is returned.
+     */
+    pub fn flush(&self) -> Result<(), RunawayFlush> {
+        let pending = std::mem::take(&mut *self.pending.lock());
+        let mut result = Ok(());
+
+        for flush in pending {
+            let flushed = flush.run_ordered(self.max_passes);
+            result = result.and(flushed);
+        }
+
+        result
+    }
+}
+
+impl Default for DeferredScheduler {
+    fn default() -> DeferredScheduler {
+        DeferredScheduler::with_max_passes(100)
+    }
+}
+
+impl Scheduler for DeferredScheduler {
+    fn schedule(&self, flush: Flush) {
+        let mut pending = self.pending.lock();
+
+        if !pending
+            .iter()
+            .any(|other| other.timeline.ptr_eq(&flush.timeline))
+        {
+            pending.push(flush);
+        }
+    }
+}
+
+/**
+ * A flush that still had stale effects after its last allowed pass, because effects kept
+ * writing values that invalidated effects which had already run. The effects that are stale are
+ * left for the next flush.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RunawayFlush {
+    effect: String,
+    passes: usize,
+}
+
+impl RunawayFlush {
+    pub(crate) fn new(effect: String, passes: usize) -> RunawayFlush {
+        RunawayFlush { effect, passes }
+    }
+
+    /**
+     * The label of the first effect that was still stale.
+     */
+    pub fn effect(&self) -> &str {
+        &self.effect
+    }
+
+    /**
+     * The number of passes the flush made before it gave up.
+     */
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+}
+
+impl Display for RunawayFlush {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was still stale after {} passes of the flush",
+            self.effect, self.passes
+        )
+    }
+}
+
+impl Error for RunawayFlush {}
+
+/**
+ * Sort `reactions`, which are in registration order, so that a reaction that wrote a value runs
+ * before the reactions that read it, directly or through deriveds. Reactions that don't depend on
+ * each other stay in registration order, and so do reactions that depend on each other in a
+ * cycle.
+ */
+pub(crate) fn in_dependency_order(reactions: Vec<Arc<dyn Reaction>>) -> Vec<Arc<dyn Reaction>> {
+    let mut writers: HashMap<NodeKey, Vec<usize>> = HashMap::new();
+
+    for (index, reaction) in reactions.iter().enumerate() {
+        for key in reaction.written() {
+            writers.entry(key).or_default().push(index);
+        }
+    }
+
+    if writers.is_empty() {
+        return reactions;
+    }
+
+    // an edge from every writer to the reactions that read what it wrote
+    let mut readers: Vec<Vec<usize>> = vec![vec![]; reactions.len()];
+    let mut blocked_by = vec![0; reactions.len()];
+
+    for (index, reaction) in reactions.iter().enumerate() {
+        let mut writes_read = BTreeSet::new();
+
+        for key in reachable_keys(reaction.dependencies()) {
+            if let Some(writers) = writers.get(&key) {
+                writes_read.extend(writers.iter().copied().filter(|writer| *writer != index));
+            }
+        }
+
+        blocked_by[index] = writes_read.len();
+
+        for writer in writes_read {
+            readers[writer].push(index);
+        }
+    }
+
+    let mut ready: BTreeSet<usize> = (0..reactions.len())
+        .filter(|index| blocked_by[*index] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(reactions.len());
+    let mut placed = vec![false; reactions.len()];
+
+    loop {
+        // a cycle leaves every remaining reaction blocked, so the earliest one goes next
+        let next = match ready.iter().next() {
+            Some(next) => *next,
+            None => match placed.iter().position(|placed| !placed) {
+                Some(next) => next,
+                None => break,
+            },
+        };
+
+        ready.remove(&next);
+        placed[next] = true;
+        order.push(next);
+
+        for reader in &readers[next] {
+            blocked_by[*reader] = blocked_by[*reader].saturating_sub(1);
+
+            if blocked_by[*reader] == 0 && !placed[*reader] {
+                ready.insert(*reader);
+            }
+        }
+    }
+
+    let mut reactions: Vec<_> = reactions.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|index| reactions[index].take())
+        .collect()
+}
+
+/**
+ * The keys of `tags` and of everything the computations among them read, without validating
+ * any of them.
+ */
+pub(crate) fn reachable_keys(tags: Vec<ReactiveTag>) -> HashSet<NodeKey> {
+    let mut keys = HashSet::new();
+    let mut stack = tags;
+
+    while let Some(tag) = stack.pop() {
+        if !keys.insert(tag.key()) {
+            continue;
+        }
+
+        if let ReactiveTag::Computed(computed) = &tag {
+            stack.extend(computed.dependencies());
+        }
+    }
+
+    keys
+}
+
+/**
+ * A pending flush of a timeline's effects. Running it after the timeline was dropped does
+ * nothing.
+ */
+#[derive(Debug, Clone)]
+pub struct Flush {
+    timeline: Weak<TimelineState>,
+}
+
+impl Flush {
+    pub(crate) fn new(timeline: Weak<TimelineState>) -> Flush {
+        Flush { timeline }
+    }
+
+    pub fn run(self) {
+        if let Some(timeline) = self.timeline.upgrade() {
+            timeline.flush();
+        }
+    }
+
+    /**
+     * Run the timeline's stale effects like `run`, but in dependency order: an effect that wrote
+     * a value the last time it ran goes before the effects that read that value, directly or
+     * through deriveds. An effect that is invalidated during the flush runs again in a later
+     * pass of the same flush, and once `max_passes` passes didn't leave every effect up to
+     * date, the flush stops and names the first effect that is still stale.
+     */
+    pub fn run_ordered(self, max_passes: usize) -> Result<(), RunawayFlush> {
+        match self.timeline.upgrade() {
+            Some(timeline) => timeline.flush_ordered(max_passes),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    sync::{Arc, Weak},
+};
+
+use crate::timeline::{state::TimelineState, Revision};
+
+use super::{label::Label, registry::Entry};
+
+/**
+ * The revisions of a timeline's live cells and deriveds at one point in time. Snapshots only
+ * hold onto labels, so they don't keep the values themselves alive.
+ *
+ * A snapshot also pins its revision: while it or one of its clones is alive, cells keep the
+ * values that `Cell::get_at` needs to read them as they were when the snapshot was taken.
+ *
+ * Deriveds are recorded at the revision they last reported, so a derived that nobody read since
+ * its inputs changed hasn't changed yet as far as a snapshot is concerned.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let first = timeline.cell(1).named("first");
+ * let last = timeline.cell(2).named("last");
+ *
+ * let before = timeline.snapshot();
+ * last.set(20);
+ * let after = timeline.snapshot();
+ *
+ * let changed = before.diff(&after);
+ * assert_eq!(changed.len(), 1);
+ * assert_eq!(changed[0].label(), "last");
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pin: Arc<SnapshotPin>,
+    values: Vec<(ChangedId, Revision)>,
+}
+
+/**
+ * Unpins a snapshot's revision once the snapshot and all of its clones are dropped.
+ */
+#[derive(Debug)]
+pub(crate) struct SnapshotPin {
+    timeline: Weak<TimelineState>,
+    revision: Revision,
+}
+
+impl SnapshotPin {
+    // the pin is only `Send` and `Sync` when the timeline is, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(timeline: Weak<TimelineState>, revision: Revision) -> Arc<SnapshotPin> {
+        Arc::new(SnapshotPin { timeline, revision })
+    }
+}
+
+impl Drop for SnapshotPin {
+    fn drop(&mut self) {
+        if let Some(timeline) = self.timeline.upgrade() {
+            timeline.unpin(self.revision);
+        }
+    }
+}
+
+/**
+ * A cell or derived reported by `Snapshot::diff`. Two ids are equal if they belong to the same
+ * value, whatever its label is.
+ */
+#[derive(Debug, Clone)]
+pub struct ChangedId {
+    label: Arc<Label>,
+}
+
+impl ChangedId {
+    /**
+     * `"cell"` or `"derived"`.
+     */
+    pub fn kind(&self) -> &'static str {
+        self.label.kind()
+    }
+
+    pub fn label(&self) -> &str {
+        self.label.get()
+    }
+}
+
+impl ChangedId {
+    fn key(&self) -> (&'static str, u64) {
+        (self.label.kind(), self.label.id())
+    }
+}
+
+impl PartialEq for ChangedId {
+    fn eq(&self, other: &ChangedId) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ChangedId {}
+
+impl Hash for ChangedId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl Display for ChangedId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn new(pin: Arc<SnapshotPin>, entries: &[Entry]) -> Snapshot {
+        let values = entries
+            .iter()
+            .filter_map(Entry::revision)
+            .filter(|(label, _)| label.kind() != "effect")
+            .map(|(label, revision)| (ChangedId { label }, revision))
+            .collect();
+
+        Snapshot { pin, values }
+    }
+
+    /**
+     * The timeline's revision when the snapshot was taken.
+     */
+    pub fn revision(&self) -> Revision {
+        self.pin.revision
+    }
+
+    /**
+     * The number of cells and deriveds that were alive when the snapshot was taken.
+     */
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /**
+     * The values that advanced between this snapshot and `other`, in the order they were
+     * created. Values that only exist in one of the snapshots, because they were created or
+     * dropped in between, aren't reported.
+     */
+    pub fn diff(&self, other: &Snapshot) -> Vec<ChangedId> {
+        let before: HashMap<(&str, u64), Revision> = self
+            .values
+            .iter()
+            .map(|(id, revision)| (id.key(), *revision))
+            .collect();
+
+        other
+            .values
+            .iter()
+            .filter(|(id, revision)| match before.get(&id.key()) {
+                Some(previous) => previous != revision,
+                None => false,
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::{self, ComputedTag, Dependent, ReactiveTag},
+    timeline::{
+        node_key::NodeSlot, state::TimelineState, ComputationId, ComputeStack, NodeKey, Revision,
+    },
+};
+
+use super::{bounds::MaybeSync, invalidation::Invalidation, label::Label, tag::Tag};
+
+/**
+ * Something outside of the reactive system that keeps its own revision, like a file watcher or
+ * the latest message from a socket. The source advances `current_revision` whenever new data
+ * arrives, usually with `Revision::increment`, and the timeline polls it to find out whether
+ * anything that read the source is stale.
+ *
+ * The revisions of a source are only compared with each other, so they don't have to come from
+ * any timeline. `Revision::initial()` is a fine place to start.
+ */
+pub trait ExternalSource: MaybeSync + 'static {
+    type Value;
+
+    fn current_revision(&self) -> Revision;
+
+    fn read(&self) -> Self::Value;
+}
+
+/**
+ * A source registered with `Timeline::register_external`. Reading it records a dependency like
+ * reading a cell does, but nothing is written to the timeline when the source changes. Instead,
+ * deriveds that read it ask the source for its revision every time they are validated, and the
+ * first validation that sees a new revision advances the timeline, so everything that read the
+ * source recomputes lazily.
+ *
+ * Effects aren't scheduled by a source that changed, since nothing tells the timeline about it.
+ * They notice the change the next time they run.
+ *
+ * ```
+ * use std::sync::Mutex;
+ * use everafter::{ExternalSource, Revision, Timeline};
+ *
+ * struct Inbox {
+ *     messages: Mutex<(Revision, Vec<String>)>,
+ * }
+ *
+ * impl ExternalSource for Inbox {
+ *     type Value = Vec<String>;
+ *
+ *     fn current_revision(&self) -> Revision {
+ *         self.messages.lock().unwrap().0
+ *     }
+ *
+ *     fn read(&self) -> Vec<String> {
+ *         self.messages.lock().unwrap().1.clone()
+ *     }
+ * }
+ *
+ * let timeline = Timeline::new();
+ * let inbox = timeline.register_external(Inbox {
+ *     messages: Mutex::new((Revision::initial(), vec![])),
+ * });
+ *
+ * let unread = {
+ *     let inbox = inbox.clone();
+ *     timeline.derived(move || inbox.get().len())
+ * };
+ * assert_eq!(unread.get(), 0);
+ *
+ * {
+ *     let mut messages = inbox.source().messages.lock().unwrap();
+ *     messages.1.push("hello".to_string());
+ *     messages.0 = messages.0.increment();
+ * }
+ *
+ * assert_eq!(unread.get(), 1);
+ * ```
+ */
+pub struct ExternalValue<S: ExternalSource> {
+    inner: Arc<SourceInner<S>>,
+}
+
+static NEXT_SOURCE: AtomicU64 = AtomicU64::new(1);
+
+struct SourceInner<S> {
+    source: S,
+    label: Arc<Label>,
+    // the timeline's revision at which the source was last seen to change. The tag is
+    // registered, so it is renumbered and reset along with the rest of the timeline.
+    tag: Arc<inputs::Tag>,
+    // the source's own revision, as of the last time it was polled
+    seen: Mutex<Revision>,
+    slot: NodeSlot,
+    timeline: Arc<TimelineState>,
+}
+
+impl<S: ExternalSource> ExternalValue<S> {
+    // a source is only `Send` and `Sync` when its timeline is, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(timeline: Arc<TimelineState>, source: S) -> ExternalValue<S> {
+        let label = Arc::new(Label::new(
+            "source",
+            NEXT_SOURCE.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        ExternalValue {
+            inner: Arc::new(SourceInner {
+                seen: Mutex::new(source.current_revision()),
+                source,
+                tag: timeline.tag(Some(label.clone())),
+                label,
+                slot: NodeSlot::new(),
+                timeline,
+            }),
+        }
+    }
+
+    /**
+     * Attach a debug label to the source. A source can only be named once.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> ExternalValue<S> {
+        self.inner.label.name(label.into());
+        self
+    }
+
+    /**
+     * The source's debug label, or a name like `source#2` if it was never named.
+     */
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    /**
+     * Read the source, recording a dependency in the current `ComputeStack` frame.
+     */
+    pub fn get(&self) -> S::Value {
+        ComputeStack::consume(&self.inner.timeline, &self.inner.label, self.reactive_tag());
+        self.inner.source.read()
+    }
+
+    /**
+     * The registered source, for the code that feeds it new data.
+     */
+    pub fn source(&self) -> &S {
+        &self.inner.source
+    }
+
+    /**
+     * The timeline's revision at which the source was last seen to change. Polls the source
+     * first, so a change that nothing has noticed yet advances the timeline.
+     */
+    pub fn revision(&self) -> Revision {
+        self.inner.validate()
+    }
+
+    /**
+     * The source as a `Tag`, so it can be combined with the tags of other values.
+     */
+    pub fn tag(&self) -> Tag {
+        Tag::new(
+            self.reactive_tag(),
+            self.inner.label.clone(),
+            self.inner.timeline.clone(),
+        )
+    }
+
+    fn reactive_tag(&self) -> ReactiveTag {
+        ReactiveTag::Computed(self.inner.clone())
+    }
+}
+
+impl<S: ExternalSource> ComputedTag for SourceInner<S> {
+    fn key(&self) -> NodeKey {
+        self.slot.key()
+    }
+
+    fn validate(&self) -> Revision {
+        let mut seen = self.seen.lock();
+        let current = self.source.current_revision();
+
+        if current != *seen {
+            *seen = current;
+
+            // everything that read the old data was computed at an older revision, so the change
+            // gets a revision of its own
+            self.tag.write(self.timeline.bump(), &self.timeline);
+        }
+
+        self.tag.revision.get()
+    }
+
+    fn last_revision(&self) -> Revision {
+        self.tag.revision.get()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.source.current_revision() != *self.seen.lock()
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn last_invalidation(&self) -> Option<Invalidation> {
+        None
+    }
+
+    fn add_dependent(&self, _: ComputationId, _: Weak<dyn Dependent>) -> bool {
+        // the source changes without a write, so nothing can mark its dependents dirty
+        false
+    }
+
+    fn is_polled(&self) -> bool {
+        true
+    }
+}
+
+impl<S> Debug for SourceInner<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalSource")
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+impl<S: ExternalSource> Clone for ExternalValue<S> {
+    fn clone(&self) -> Self {
+        ExternalValue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: ExternalSource> Debug for ExternalValue<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalValue")
+            .field("label", &self.inner.label)
+            .field("revision", &self.inner.last_revision())
+            .finish()
+    }
+}
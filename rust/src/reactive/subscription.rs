@@ -0,0 +1,142 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Weak},
+};
+
+use parking_lot::Mutex;
+
+use crate::timeline::{state::TimelineState, ComputationId};
+
+use super::scheduler::Reaction;
+
+/**
+ * Keeps a reaction registered with its timeline. The timeline itself only holds the reaction
+ * weakly, so dropping the handle disposes the reaction and removes it from the timeline.
+ *
+ * `Cell::subscribe` returns a bare handle, and every `Effect` owns one.
+ */
+pub struct SubscriptionHandle {
+    reaction: Arc<dyn Reaction>,
+    // `None` once the reaction was disposed or detached, shared with the handles `shared` made
+    timeline: Arc<Mutex<Option<Weak<TimelineState>>>>,
+    // false for a handle made with `shared`, which doesn't dispose the reaction when it's dropped
+    disposes: bool,
+}
+
+impl SubscriptionHandle {
+    // the handles `shared` makes share the state, which is only `Send` with the `sync` feature,
+    // like the timeline itself
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn register(
+        timeline: &Arc<TimelineState>,
+        reaction: Arc<dyn Reaction>,
+    ) -> SubscriptionHandle {
+        timeline.register(reaction.id(), Arc::downgrade(&reaction));
+
+        SubscriptionHandle {
+            reaction,
+            timeline: Arc::new(Mutex::new(Some(Arc::downgrade(timeline)))),
+            disposes: true,
+        }
+    }
+
+    /**
+     * Another handle to the same reaction, which can unsubscribe or detach it like this one,
+     * but doesn't dispose it when it's dropped.
+     */
+    pub(crate) fn shared(&self) -> SubscriptionHandle {
+        SubscriptionHandle {
+            reaction: self.reaction.clone(),
+            timeline: self.timeline.clone(),
+            disposes: false,
+        }
+    }
+
+    /**
+     * A handle for a subscription that can never fire, like a subscription to a constant.
+     */
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn inert() -> SubscriptionHandle {
+        SubscriptionHandle {
+            reaction: Arc::new(Inert {
+                id: ComputationId::next(),
+            }),
+            timeline: Arc::new(Mutex::new(None)),
+            disposes: true,
+        }
+    }
+
+    pub fn id(&self) -> ComputationId {
+        self.reaction.id()
+    }
+
+    /**
+     * Dispose the reaction and remove it from the timeline. It will not run again, even if its
+     * dependencies change.
+     */
+    pub fn unsubscribe(&self) {
+        let timeline = match self.timeline.lock().take() {
+            Some(timeline) => timeline,
+            None => return,
+        };
+
+        self.reaction.dispose();
+
+        if let Some(timeline) = timeline.upgrade() {
+            timeline.unregister(self.reaction.id());
+        }
+    }
+
+    /**
+     * Give up the handle without disposing the reaction. A detached reaction keeps running until
+     * the `Timeline` that created it is dropped.
+     */
+    pub fn detach(self) {
+        let timeline = self.timeline.lock().take();
+
+        if let Some(timeline) = timeline.and_then(|timeline| timeline.upgrade()) {
+            timeline.detach(self.reaction.clone());
+        }
+    }
+}
+
+impl Drop for SubscriptionHandle {
+    fn drop(&mut self) {
+        if self.disposes {
+            self.unsubscribe();
+        }
+    }
+}
+
+impl Debug for SubscriptionHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SubscriptionHandle")
+            .field(&self.reaction)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct Inert {
+    id: ComputationId,
+}
+
+impl Reaction for Inert {
+    fn id(&self) -> ComputationId {
+        self.id
+    }
+
+    fn label(&self) -> String {
+        String::from("subscription")
+    }
+
+    fn is_stale(&self) -> bool {
+        false
+    }
+
+    fn run_if_stale(&self) -> bool {
+        false
+    }
+
+    fn dispose(&self) {}
+}
@@ -0,0 +1,350 @@
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+};
+
+use crate::{
+    inputs::{self, ComputedTag, Dependent, ReactiveTag},
+    timeline::{
+        node_key::NodeSlot, state::TimelineState, ComputationId, ComputeStack, NodeKey, Revision,
+        TagId,
+    },
+};
+
+use super::{invalidation::Invalidation, label::Label};
+
+/**
+ * The revision of a cell, a derived or an external tag, without its value. `Cell::tag`,
+ * `Derived::tag` and `ExternalTag::tag` hand out the tag for a value, so a reactive value that
+ * is assembled by hand can depend on it with `track` and compare revisions with `revision`.
+ *
+ * ```
+ * use everafter::{ComputeStack, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let cell = timeline.cell(1).named("count");
+ * let tag = cell.tag().expect("the cell isn't a constant");
+ *
+ * let (_, dependencies) = ComputeStack::track(|| tag.track());
+ * assert_eq!(dependencies.len(), 1);
+ *
+ * cell.set(2);
+ * assert_eq!(tag.revision(), cell.revision());
+ * assert_eq!(tag.label(), "count");
+ * ```
+ */
+#[derive(Clone)]
+pub struct Tag {
+    tag: ReactiveTag,
+    label: Arc<Label>,
+    // `None` for a tag that can never change, which doesn't belong to any timeline
+    timeline: Option<Arc<TimelineState>>,
+}
+
+static NEXT_TAG: AtomicU64 = AtomicU64::new(1);
+
+impl Tag {
+    pub(crate) fn new(tag: ReactiveTag, label: Arc<Label>, timeline: Arc<TimelineState>) -> Tag {
+        Tag {
+            tag,
+            label,
+            timeline: Some(timeline),
+        }
+    }
+
+    /**
+     * A tag that never changes. Its revision is `Revision::CONSTANT`, and tracking it records
+     * nothing, like reading a constant.
+     */
+    pub fn constant() -> Tag {
+        Tag {
+            tag: ReactiveTag::Tag(inputs::Tag::arc(Revision::CONSTANT.atomic())),
+            label: Arc::new(Label::new(
+                "constant",
+                NEXT_TAG.fetch_add(1, Ordering::Relaxed),
+            )),
+            timeline: None,
+        }
+    }
+
+    /**
+     * A single tag whose revision is the newer revision of `self` and `other`, like
+     * `Tag::combine(&[self.clone(), other.clone()]).tag()`.
+     */
+    pub fn max(&self, other: &Tag) -> Tag {
+        Tag::combine(&[self.clone(), other.clone()]).tag()
+    }
+
+    /**
+     * A tag whose revision is the newest revision of `tags`. Its members aren't copied, so the
+     * combined revision advances whenever one of them does.
+     */
+    pub fn combine(tags: &[Tag]) -> CombinedTag {
+        CombinedTag {
+            tags: tags.to_vec(),
+        }
+    }
+
+    /**
+     * The label of the value the tag belongs to.
+     */
+    pub fn label(&self) -> &str {
+        self.label.get()
+    }
+
+    pub(crate) fn reactive_tag(&self) -> &ReactiveTag {
+        &self.tag
+    }
+
+    pub(crate) fn timeline(&self) -> Option<&Arc<TimelineState>> {
+        self.timeline.as_ref()
+    }
+
+    /**
+     * The identity of the value the tag belongs to, as `track_reads` reports it.
+     */
+    pub fn id(&self) -> TagId {
+        TagId::new(&self.tag)
+    }
+
+    /**
+     * The revision at which the value last changed. The tag of a derived brings the derived up
+     * to date first, like `Derived::revision`.
+     */
+    pub fn revision(&self) -> Revision {
+        self.tag.revision()
+    }
+
+    /**
+     * Record a dependency on the tag in the current `ComputeStack` frame, as if its value was
+     * read.
+     */
+    pub fn track(&self) {
+        if let Some(timeline) = &self.timeline {
+            ComputeStack::consume(timeline, &self.label, self.tag.clone());
+        }
+    }
+}
+
+impl Debug for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tag").field("label", &self.label).finish()
+    }
+}
+
+/**
+ * Several tags that are tracked together, created with `Tag::combine`. A computation that tracks
+ * a combined tag depends on every member, so bumping any of them invalidates it.
+ *
+ * ```
+ * use everafter::{Tag, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let width = timeline.cell(10);
+ * let height = timeline.cell(20);
+ *
+ * let size = Tag::combine(&[width.tag().unwrap(), height.tag().unwrap()]);
+ *
+ * height.set(30);
+ * assert_eq!(size.revision(), height.revision());
+ *
+ * width.set(15);
+ * assert_eq!(size.revision(), width.revision());
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct CombinedTag {
+    tags: Vec<Tag>,
+}
+
+impl CombinedTag {
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /**
+     * The newest revision of the members, or `Revision::CONSTANT` if there are none.
+     */
+    pub fn revision(&self) -> Revision {
+        self.tags
+            .iter()
+            .map(Tag::revision)
+            .max()
+            .unwrap_or(Revision::CONSTANT)
+    }
+
+    /**
+     * Record a dependency on every member in the current `ComputeStack` frame.
+     */
+    pub fn track(&self) {
+        for tag in &self.tags {
+            tag.track();
+        }
+    }
+
+    /**
+     * The combination as a single `Tag`, which can be combined again. A computation that tracks
+     * it records one dependency instead of one per member, and validating it brings every
+     * member up to date. Without members it is a constant tag.
+     *
+     * ```
+     * use everafter::{Revision, Tag, Timeline};
+     *
+     * let timeline = Timeline::new();
+     * let (x, y, z) = (timeline.cell(1), timeline.cell(2), timeline.cell(3));
+     *
+     * let xy = Tag::combine(&[x.tag().unwrap(), y.tag().unwrap()]).tag();
+     * let xyz = xy.max(&z.tag().unwrap());
+     *
+     * x.set(10);
+     * assert_eq!(xyz.revision(), x.revision());
+     *
+     * assert_eq!(Tag::combine(&[]).tag().revision(), Revision::CONSTANT);
+     * ```
+     */
+    // a combined tag is only `Send` and `Sync` when its members are, like every other handle
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn tag(&self) -> Tag {
+        let mut timelines = self.tags.iter().filter_map(|tag| tag.timeline.as_ref());
+
+        let timeline = match timelines.next() {
+            Some(timeline) => timeline.clone(),
+            None => return Tag::constant(),
+        };
+
+        assert!(
+            timelines.all(|other| Arc::ptr_eq(other, &timeline)),
+            "the members of a combined tag belong to different timelines, so their revisions \
+             can't be compared"
+        );
+
+        let label = Arc::new(Label::new(
+            "combined",
+            NEXT_TAG.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        let combined = Combined {
+            tags: self.tags.iter().map(|tag| tag.tag.clone()).collect(),
+            label: label.clone(),
+            slot: NodeSlot::new(),
+        };
+
+        Tag::new(ReactiveTag::Computed(Arc::new(combined)), label, timeline)
+    }
+}
+
+/**
+ * The computed tag behind `CombinedTag::tag`. Everything it is asked is answered by its members,
+ * so it only stores the members themselves.
+ */
+struct Combined {
+    tags: Vec<ReactiveTag>,
+    label: Arc<Label>,
+    slot: NodeSlot,
+}
+
+impl Combined {
+    fn computed(&self) -> impl Iterator<Item = &Arc<dyn ComputedTag>> {
+        self.tags.iter().filter_map(|tag| match tag {
+            ReactiveTag::Computed(tag) => Some(tag),
+            _ => None,
+        })
+    }
+}
+
+impl ComputedTag for Combined {
+    fn key(&self) -> NodeKey {
+        self.slot.key()
+    }
+
+    fn validate(&self) -> Revision {
+        self.tags
+            .iter()
+            .map(ReactiveTag::revision)
+            .max()
+            .unwrap_or(Revision::CONSTANT)
+    }
+
+    fn last_revision(&self) -> Revision {
+        self.tags
+            .iter()
+            .map(ReactiveTag::last_revision)
+            .max()
+            .unwrap_or(Revision::CONSTANT)
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.computed().any(|tag| tag.is_dirty())
+    }
+
+    fn label(&self) -> String {
+        self.label.to_string()
+    }
+
+    fn last_invalidation(&self) -> Option<Invalidation> {
+        None
+    }
+
+    fn dependencies(&self) -> Vec<ReactiveTag> {
+        self.tags.clone()
+    }
+
+    fn dependents(&self) -> Vec<(ComputationId, Arc<dyn Dependent>)> {
+        self.tags.iter().flat_map(ReactiveTag::dependents).collect()
+    }
+
+    fn stale_dependency(&self, visited: &mut HashSet<NodeKey>) -> Option<String> {
+        for tag in self.computed() {
+            if visited.insert(tag.key()) {
+                if let Some(stale) = tag.stale_dependency(visited) {
+                    return Some(stale);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn add_dependent(&self, id: ComputationId, dependent: Weak<dyn Dependent>) -> bool {
+        let mut complete = true;
+
+        // every member needs the edge, even after one of them turned out to be incomplete
+        for tag in &self.tags {
+            complete &= tag.add_dependent(id, dependent.clone());
+        }
+
+        complete
+    }
+
+    fn is_polled(&self) -> bool {
+        self.tags.iter().any(ReactiveTag::is_polled)
+    }
+
+    fn is_settled(&self) -> bool {
+        self.computed().all(|tag| tag.is_settled())
+    }
+
+    fn unsettled_dependency(&self, from: usize) -> Option<(usize, Arc<dyn ComputedTag>)> {
+        self.tags
+            .iter()
+            .enumerate()
+            .skip(from)
+            .find_map(|(index, tag)| match tag {
+                ReactiveTag::Computed(tag) if !tag.is_settled() => Some((index, tag.clone())),
+                _ => None,
+            })
+    }
+}
+
+impl Debug for Combined {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CombinedTag")
+            .field("label", &self.label)
+            .field("tags", &self.tags.len())
+            .finish()
+    }
+}
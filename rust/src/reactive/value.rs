@@ -0,0 +1,207 @@
+use std::fmt::Debug;
+
+use parking_lot::Mutex;
+
+use crate::timeline::Revision;
+
+use super::fork;
+
+/**
+ * A value that can be read like a cell: `Cell`, `Derived`, `Constant`, and the `Mapped` and
+ * `Zipped` values built from them. Code that is generic over `ReactiveValue` can read any of
+ * them, and `map` and `zip` build small pipelines without creating a `Derived` for every step.
+ *
+ * ```
+ * use everafter::{ReactiveValue, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let celsius = timeline.cell(100.0);
+ *
+ * let fahrenheit = celsius.clone().map(|c: &f64| c * 9.0 / 5.0 + 32.0);
+ * assert_eq!(fahrenheit.get(), 212.0);
+ *
+ * let label = fahrenheit.zip(timeline.cell("°F"), |degrees: &f64, unit: &&str| {
+ *     format!("{}{}", degrees, unit)
+ * });
+ *
+ * celsius.set(0.0);
+ * assert_eq!(label.get(), "32°F");
+ * ```
+ */
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't a reactive value",
+    label = "not a cell, derived or other `ReactiveValue`",
+    note = "only reads of reactive values are tracked; put the value in a cell, or move it into \
+            the computation"
+)]
+pub trait ReactiveValue {
+    type Value;
+
+    /**
+     * Read the value, recording a dependency in the current `ComputeStack` frame.
+     */
+    fn get(&self) -> Self::Value;
+
+    /**
+     * Record a dependency in the current `ComputeStack` frame, like `get`, without reading the
+     * value.
+     */
+    fn track(&self);
+
+    /**
+     * The revision at which the value last changed, without recording a dependency. A value
+     * that can never change is at `Revision::CONSTANT`.
+     */
+    fn revision(&self) -> Revision;
+
+    /**
+     * A value that applies `f` to this one. It only keeps this value and `f`, and the result of
+     * the last call to `f` together with the revision it was computed at, so it has no
+     * dependencies of its own: reading it compares that revision with this value's, and
+     * records this value in the current `ComputeStack` frame.
+     *
+     * `Derived::map` shadows this method on deriveds. Call `ReactiveValue::map(derived, f)` to
+     * map a derived without creating another one.
+     */
+    fn map<U, F>(self, f: F) -> Mapped<Self, F, U>
+    where
+        Self: Sized,
+        F: Fn(&Self::Value) -> U,
+    {
+        Mapped {
+            source: self,
+            f,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /**
+     * A value that combines this one with `other` using `f`. Like `map`, it only compares the
+     * revisions of the two values it was built from.
+     */
+    fn zip<B, U, F>(self, other: B, f: F) -> Zipped<Self, B, F, U>
+    where
+        Self: Sized,
+        B: ReactiveValue,
+        F: Fn(&Self::Value, &B::Value) -> U,
+    {
+        Zipped {
+            sources: (self, other),
+            f,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+/**
+ * The value returned by `ReactiveValue::map`.
+ */
+pub struct Mapped<S, F, U> {
+    source: S,
+    f: F,
+    // the last result, with the revision of `source` it was computed from
+    cached: Mutex<Option<(Revision, U)>>,
+}
+
+impl<S, F, U> ReactiveValue for Mapped<S, F, U>
+where
+    S: ReactiveValue,
+    F: Fn(&S::Value) -> U,
+    U: Clone,
+{
+    type Value = U;
+
+    fn get(&self) -> U {
+        // a fork's values don't change the source's revision, so they aren't cached
+        if fork::is_forked() {
+            return (self.f)(&self.source.get());
+        }
+
+        let revision = self.source.revision();
+
+        if let Some((cached_at, value)) = &*self.cached.lock() {
+            if *cached_at == revision {
+                self.source.track();
+                return value.clone();
+            }
+        }
+
+        let value = (self.f)(&self.source.get());
+        *self.cached.lock() = Some((revision, value.clone()));
+        value
+    }
+
+    fn track(&self) {
+        self.source.track();
+    }
+
+    fn revision(&self) -> Revision {
+        self.source.revision()
+    }
+}
+
+/**
+ * The value returned by `ReactiveValue::zip`.
+ */
+pub struct Zipped<A, B, F, U> {
+    sources: (A, B),
+    f: F,
+    // the last result, with the revisions of both sources it was computed from
+    cached: Mutex<Option<((Revision, Revision), U)>>,
+}
+
+impl<A, B, F, U> ReactiveValue for Zipped<A, B, F, U>
+where
+    A: ReactiveValue,
+    B: ReactiveValue,
+    F: Fn(&A::Value, &B::Value) -> U,
+    U: Clone,
+{
+    type Value = U;
+
+    fn get(&self) -> U {
+        let (a, b) = &self.sources;
+
+        if fork::is_forked() {
+            return (self.f)(&a.get(), &b.get());
+        }
+
+        let revisions = (a.revision(), b.revision());
+
+        if let Some((cached_at, value)) = &*self.cached.lock() {
+            if *cached_at == revisions {
+                self.track();
+                return value.clone();
+            }
+        }
+
+        let value = (self.f)(&a.get(), &b.get());
+        *self.cached.lock() = Some((revisions, value.clone()));
+        value
+    }
+
+    fn track(&self) {
+        self.sources.0.track();
+        self.sources.1.track();
+    }
+
+    fn revision(&self) -> Revision {
+        self.sources.0.revision().max(self.sources.1.revision())
+    }
+}
+
+impl<S: Debug, F, U> Debug for Mapped<S, F, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mapped")
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl<A: Debug, B: Debug, F, U> Debug for Zipped<A, B, F, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Zipped")
+            .field("sources", &self.sources)
+            .finish()
+    }
+}
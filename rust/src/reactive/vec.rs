@@ -0,0 +1,301 @@
+use std::{
+    fmt::Debug,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::{ReactiveTag, Tag},
+    timeline::{state::TimelineState, ComputeStack, Revision},
+};
+
+use super::label::Label;
+
+/**
+ * A vector with a tag per index and a structural tag for its length. Reading an index only
+ * depends on that index, so a computation that reads `vec.get(0)` isn't invalidated by writes to
+ * other indices. Inserting or removing an element changes every index after it, and changes the
+ * length.
+ *
+ * Reading an index past the end depends on the length, since the read starts returning a value
+ * once the vector grows far enough. Iterating depends on the length and on every element the
+ * iterator visited.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let list = timeline.vec(vec![1, 2, 3]);
+ *
+ * let first = {
+ *     let list = list.clone();
+ *     timeline.derived(move || list.get(0))
+ * };
+ *
+ * assert_eq!(first.get(), Some(1));
+ *
+ * let revision = first.revision();
+ * list.set(2, 30);
+ * assert_eq!(first.revision(), revision, "index 2 isn't a dependency of `first`");
+ * ```
+ */
+pub struct TrackedVec<T> {
+    inner: Arc<VecInner<T>>,
+}
+
+static NEXT_VEC: AtomicU64 = AtomicU64::new(1);
+
+struct VecInner<T> {
+    label: Arc<Label>,
+    state: Mutex<VecState<T>>,
+    structure: Arc<Tag>,
+    timeline: Arc<TimelineState>,
+}
+
+struct VecState<T> {
+    values: Vec<T>,
+    // one tag per index, so `tags.len() == values.len()`
+    tags: Vec<Arc<Tag>>,
+}
+
+impl<T> TrackedVec<T> {
+    pub(crate) fn new(timeline: Arc<TimelineState>, values: Vec<T>) -> TrackedVec<T> {
+        let label = Arc::new(Label::new("vec", NEXT_VEC.fetch_add(1, Ordering::Relaxed)));
+        let tags = values
+            .iter()
+            .map(|_| timeline.tag(Some(label.clone())))
+            .collect();
+
+        TrackedVec {
+            inner: Arc::new(VecInner {
+                structure: timeline.tag(Some(label.clone())),
+                label,
+                state: Mutex::new(VecState { values, tags }),
+                timeline,
+            }),
+        }
+    }
+
+    fn new_tag(&self) -> Arc<Tag> {
+        self.inner.timeline.tag(Some(self.inner.label.clone()))
+    }
+
+    pub fn label(&self) -> &str {
+        self.inner.label.get()
+    }
+
+    pub(crate) fn timeline(&self) -> &Arc<TimelineState> {
+        &self.inner.timeline
+    }
+
+    /**
+     * The revision at which elements were last added or removed.
+     */
+    pub fn revision(&self) -> Revision {
+        self.inner.structure.revision.get()
+    }
+
+    pub fn len(&self) -> usize {
+        self.consume_structure();
+        self.inner.state.lock().values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&self, value: T) {
+        let index = {
+            let mut state = self.inner.state.lock();
+            state.values.push(value);
+            state.tags.push(self.new_tag());
+            state.values.len() - 1
+        };
+
+        self.changed(index..index + 1);
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let (value, tag) = {
+            let mut state = self.inner.state.lock();
+            (state.values.pop()?, state.tags.pop())
+        };
+
+        self.write(tag.into_iter().collect(), true);
+        Some(value)
+    }
+
+    /**
+     * Insert `value` at `index`, shifting every element after it. Panics if `index > len`.
+     */
+    pub fn insert(&self, index: usize, value: T) {
+        let len = {
+            let mut state = self.inner.state.lock();
+            state.values.insert(index, value);
+            state.tags.push(self.new_tag());
+            state.values.len()
+        };
+
+        self.changed(index..len);
+    }
+
+    /**
+     * Remove and return the element at `index`, shifting every element after it. Panics if
+     * `index` is out of bounds.
+     */
+    pub fn remove(&self, index: usize) -> T {
+        let (value, tags) = {
+            let mut state = self.inner.state.lock();
+            let value = state.values.remove(index);
+
+            // the last index no longer exists, but computations that read it still need to be
+            // invalidated, so its tag is bumped along with the shifted ones
+            let tags = state.tags[index..].to_vec();
+            state.tags.pop();
+            (value, tags)
+        };
+
+        self.write(tags, true);
+        value
+    }
+
+    fn consume(&self, tag: ReactiveTag) {
+        ComputeStack::consume(&self.inner.timeline, &self.inner.label, tag);
+    }
+
+    fn consume_structure(&self) {
+        self.consume(ReactiveTag::Tag(self.inner.structure.clone()));
+    }
+
+    /**
+     * Consume the tag of `index` if it exists, and the structural tag otherwise.
+     */
+    fn consume_index(&self, state: &VecState<T>, index: usize) {
+        match state.tags.get(index) {
+            Some(tag) => self.consume(ReactiveTag::Tag(tag.clone())),
+            None => self.consume_structure(),
+        }
+    }
+
+    fn changed(&self, indices: Range<usize>) {
+        let tags = self.inner.state.lock().tags[indices].to_vec();
+        self.write(tags, true);
+    }
+
+    fn write(&self, tags: Vec<Arc<Tag>>, structural: bool) {
+        let (structure, timeline) = (&self.inner.structure, &self.inner.timeline);
+
+        timeline.write(|revision| {
+            for tag in &tags {
+                tag.write(revision, timeline);
+            }
+
+            if structural {
+                structure.write(revision, timeline);
+            }
+        });
+    }
+}
+
+impl<T> TrackedVec<T>
+where
+    T: Clone,
+{
+    /**
+     * Read the element at `index`, depending only on that index.
+     */
+    pub fn get(&self, index: usize) -> Option<T> {
+        let state = self.inner.state.lock();
+        self.consume_index(&state, index);
+        state.values.get(index).cloned()
+    }
+
+    /**
+     * Iterate over clones of the elements. Creating the iterator depends on the length, and each
+     * element it yields depends on that element's index, so an iterator that stops early doesn't
+     * depend on the rest of the vector.
+     */
+    pub fn iter(&self) -> VecIter<T> {
+        self.consume_structure();
+
+        VecIter {
+            vec: self.clone(),
+            index: 0,
+        }
+    }
+}
+
+/**
+ * The iterator returned by `TrackedVec::iter`.
+ */
+pub struct VecIter<T> {
+    vec: TrackedVec<T>,
+    index: usize,
+}
+
+impl<T> Iterator for VecIter<T>
+where
+    T: Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let state = self.vec.inner.state.lock();
+        let value = state.values.get(self.index)?.clone();
+        self.vec
+            .consume(ReactiveTag::Tag(state.tags[self.index].clone()));
+
+        self.index += 1;
+        Some(value)
+    }
+}
+
+impl<T> TrackedVec<T>
+where
+    T: PartialEq,
+{
+    /**
+     * Replace the element at `index`, invalidating only computations that read that index.
+     * Writing an equal value does nothing. Panics if `index` is out of bounds.
+     */
+    pub fn set(&self, index: usize, value: T) {
+        let tag = {
+            let mut state = self.inner.state.lock();
+
+            if state.values[index] == value {
+                return;
+            }
+
+            state.values[index] = value;
+            state.tags[index].clone()
+        };
+
+        self.write(vec![tag], false);
+    }
+}
+
+impl<T> Clone for TrackedVec<T> {
+    fn clone(&self) -> Self {
+        TrackedVec {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Debug for TrackedVec<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrackedVec")
+            .field("label", &self.inner.label)
+            .field("values", &self.inner.state.lock().values)
+            .field("revision", &self.revision())
+            .finish()
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::timeline::{state::TimelineState, untrack, ComputationId};
+
+use super::{
+    bounds::{MaybeSend, MaybeSync},
+    effect::Effect,
+    subscription::SubscriptionHandle,
+};
+
+/**
+ * The handle returned by `Cell::watch` and `Derived::watch`. Values are delivered until it is
+ * dropped or `unwatch` is called.
+ */
+#[derive(Debug)]
+#[must_use = "values stop being delivered when the handle is dropped"]
+pub struct WatchHandle {
+    subscription: SubscriptionHandle,
+}
+
+impl WatchHandle {
+    pub fn id(&self) -> ComputationId {
+        self.subscription.id()
+    }
+
+    /**
+     * Stop delivering values, even if the watched value changes again.
+     */
+    pub fn unwatch(&self) {
+        self.subscription.unsubscribe();
+    }
+
+    /**
+     * A handle for a value that can never change, like a constant cell, whose only value was
+     * already delivered.
+     */
+    pub(crate) fn inert() -> WatchHandle {
+        WatchHandle {
+            subscription: SubscriptionHandle::inert(),
+        }
+    }
+}
+
+/**
+ * An effect that passes the value `read` returns to `deliver`, right away and then every time
+ * it is different from the last value delivered. The effect runs when the timeline's scheduler
+ * flushes, so the writes of a transaction deliver a single value once it commits.
+ */
+pub(crate) fn watch<T>(
+    timeline: Arc<TimelineState>,
+    read: impl Fn() -> T + MaybeSync + 'static,
+    deliver: impl Fn(&T) + MaybeSync + 'static,
+) -> WatchHandle
+where
+    T: Clone + PartialEq + MaybeSend + 'static,
+{
+    let delivered: Mutex<Option<T>> = Mutex::new(None);
+
+    let effect = Effect::new(timeline, move || {
+        let value = read();
+
+        let mut delivered = delivered.lock();
+        if delivered.as_ref() == Some(&value) {
+            return;
+        }
+        *delivered = Some(value.clone());
+        drop(delivered);
+
+        // what the callback reads isn't something the watch depends on
+        untrack(|| deliver(&value));
+    });
+
+    WatchHandle {
+        subscription: effect.into_subscription(),
+    }
+}
@@ -0,0 +1,144 @@
+use std::{borrow::Cow, fmt::Debug, sync::Arc};
+
+use crate::timeline::{state::TimelineState, ComputeStack, Revision};
+
+use super::{
+    bounds::{MaybeSend, MaybeSync, Setter},
+    derived::Derived,
+    value::ReactiveValue,
+};
+
+/**
+ * A derived that can also be written, created with `Timeline::derived_with_setter`. Reading it
+ * computes its value from what the computation reads, like any derived. Writing it calls the
+ * setter, which writes the new state upstream, usually to the cells the computation reads, so
+ * the next read reflects the write.
+ *
+ * ```
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let celsius = timeline.cell(100.0);
+ *
+ * let fahrenheit = {
+ *     let (read, write) = (celsius.clone(), celsius.clone());
+ *     timeline.derived_with_setter(
+ *         move || read.get() * 9.0 / 5.0 + 32.0,
+ *         move |fahrenheit| write.set((fahrenheit - 32.0) * 5.0 / 9.0),
+ *     )
+ * };
+ *
+ * assert_eq!(fahrenheit.get(), 212.0);
+ *
+ * fahrenheit.set(32.0);
+ * assert_eq!(celsius.get(), 0.0);
+ * assert_eq!(fahrenheit.get(), 32.0);
+ * ```
+ *
+ * The derived cuts off propagation like `Timeline::derived_with_eq`, so a write that leaves the
+ * computed value equal doesn't invalidate the computations that read it.
+ */
+pub struct WritableDerived<T> {
+    derived: Derived<T>,
+    setter: Arc<dyn Setter<T>>,
+}
+
+impl<T> WritableDerived<T>
+where
+    T: PartialEq + MaybeSend + 'static,
+{
+    pub(crate) fn new(
+        timeline: Arc<TimelineState>,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        setter: impl Fn(T) + MaybeSync + 'static,
+    ) -> WritableDerived<T> {
+        WritableDerived {
+            derived: Derived::with_eq(timeline, computation),
+            setter: Arc::new(setter),
+        }
+    }
+}
+
+impl<T: MaybeSend + 'static> WritableDerived<T> {
+    /**
+     * Give the derived a debug label.
+     */
+    pub fn named(self, label: impl Into<Cow<'static, str>>) -> WritableDerived<T> {
+        WritableDerived {
+            derived: self.derived.named(label),
+            setter: self.setter,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        self.derived.label()
+    }
+
+    /**
+     * The revision at which the derived's value last changed. See `Derived::revision`.
+     */
+    pub fn revision(&self) -> Revision {
+        self.derived.revision()
+    }
+
+    /**
+     * Write `value` through the setter. The setter runs inside an untracked frame, so a
+     * computation or effect that writes the derived doesn't depend on anything the setter reads.
+     * Its writes are ordinary writes, which advance the revision and invalidate their readers.
+     */
+    pub fn set(&self, value: T) {
+        ComputeStack::untrack(|| (self.setter)(value));
+    }
+
+    /**
+     * The read side, a plain derived that shares this one's value and revision.
+     */
+    pub fn as_derived(&self) -> &Derived<T> {
+        &self.derived
+    }
+}
+
+impl<T> WritableDerived<T>
+where
+    T: Clone + MaybeSend + 'static,
+{
+    pub fn get(&self) -> T {
+        self.derived.get()
+    }
+}
+
+impl<T> ReactiveValue for WritableDerived<T>
+where
+    T: Clone + MaybeSend + 'static,
+{
+    type Value = T;
+
+    fn get(&self) -> T {
+        self.derived.get()
+    }
+
+    fn track(&self) {
+        ReactiveValue::track(&self.derived);
+    }
+
+    fn revision(&self) -> Revision {
+        self.derived.revision()
+    }
+}
+
+impl<T> Clone for WritableDerived<T> {
+    fn clone(&self) -> Self {
+        WritableDerived {
+            derived: self.derived.clone(),
+            setter: self.setter.clone(),
+        }
+    }
+}
+
+impl<T> Debug for WritableDerived<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WritableDerived")
+            .field(&self.derived)
+            .finish()
+    }
+}
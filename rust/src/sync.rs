@@ -0,0 +1,240 @@
+/*!
+ * Sharing a timeline's reactive values across threads. This module only exists with the `sync`
+ * feature, which requires everything stored in cells, deriveds and effects to be `Send + Sync`.
+ *
+ * `Timeline` itself also owns the legacy inputs, which aren't thread-safe, so it stays on the
+ * thread that created it. `Timeline::shared` hands out a `SharedTimeline`, which can create and
+ * batch reactive values from any thread. Every thread has its own compute stack, so computations
+ * running concurrently on different threads track into their own frames, while writes from any
+ * thread advance the same atomic revision.
+ *
+ * ```
+ * use std::thread;
+ * use everafter::Timeline;
+ *
+ * let timeline = Timeline::new();
+ * let shared = timeline.shared();
+ * let count = shared.cell(0);
+ *
+ * let doubled = thread::spawn({
+ *     let count = count.clone();
+ *     move || {
+ *         count.set(5);
+ *         shared.derived(move || count.get() * 2)
+ *     }
+ * })
+ * .join()
+ * .unwrap();
+ *
+ * assert_eq!(doubled.get(), 10);
+ * ```
+ */
+
+use std::{future::Future, hash::Hash, sync::Arc};
+
+pub use crate::reactive::{
+    CachedMethods, Cell, Derived, DerivedAsync, Effect, ExternalSource, ExternalTag, ExternalValue,
+    Forked, Memo, SubscriptionHandle, TrackedMap, TrackedVec,
+};
+
+#[cfg(feature = "history")]
+use crate::reactive::RewindError;
+use crate::{
+    reactive::{
+        CombinedTag, Constant, MaybeSend, MaybeSync, MemoryStats, ReadonlyCell, Snapshot, Tag,
+        Writer,
+    },
+    timeline::{state::TimelineState, Pause, Revision, Timeline, Transaction},
+};
+
+/**
+ * A thread-safe handle to a timeline's reactive state, created with `Timeline::shared`. Its
+ * methods behave like the `Timeline` methods of the same name.
+ */
+#[derive(Debug, Clone)]
+pub struct SharedTimeline {
+    state: Arc<TimelineState>,
+}
+
+impl Timeline {
+    /**
+     * A handle to this timeline's reactive state that can be sent to other threads.
+     */
+    pub fn shared(&self) -> SharedTimeline {
+        SharedTimeline {
+            state: self.state().clone(),
+        }
+    }
+}
+
+impl SharedTimeline {
+    pub fn now(&self) -> Revision {
+        self.state.now()
+    }
+
+    pub fn bumps_since(&self, revision: Revision) -> u64 {
+        self.now().distance(&revision)
+    }
+
+    pub fn cell<T>(&self, value: T) -> Cell<T> {
+        Cell::new(self.state.clone(), value)
+    }
+
+    pub fn constant<T>(&self, value: T) -> Cell<T> {
+        Cell::constant(value)
+    }
+
+    pub fn external_tag(&self) -> ExternalTag {
+        ExternalTag::new(self.state.clone())
+    }
+
+    pub fn register_external<S: ExternalSource>(&self, source: S) -> ExternalValue<S> {
+        ExternalValue::new(self.state.clone(), source)
+    }
+
+    pub fn vec<T>(&self, values: Vec<T>) -> TrackedVec<T> {
+        TrackedVec::new(self.state.clone(), values)
+    }
+
+    pub fn map<K: Hash + Eq + Clone, V>(
+        &self,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> TrackedMap<K, V> {
+        TrackedMap::new(self.state.clone(), entries)
+    }
+
+    pub fn derived<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::new(self.state.clone(), computation)
+    }
+
+    pub fn try_derived<T: MaybeSend + 'static, E: MaybeSync + 'static>(
+        &self,
+        computation: impl Fn() -> Result<T, E> + MaybeSync + 'static,
+    ) -> Derived<Result<T, Arc<E>>> {
+        Derived::try_new(self.state.clone(), computation)
+    }
+
+    pub fn derived_with_eq<T: PartialEq + MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_eq(self.state.clone(), computation)
+    }
+
+    pub fn derived_with_eq_by<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        eq: impl Fn(&T, &T) -> bool + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_eq_by(self.state.clone(), computation, eq)
+    }
+
+    pub fn derived_with_cleanup<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        cleanup: impl Fn(T) + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_cleanup(self.state.clone(), computation, cleanup)
+    }
+
+    pub fn derived_async<T: MaybeSend + 'static, F: Future<Output = T> + MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> F + MaybeSync + 'static,
+    ) -> DerivedAsync<T> {
+        DerivedAsync::new(self.state.clone(), computation)
+    }
+
+    pub fn memo<A, T>(&self, computation: impl Fn(&A) -> T + MaybeSync + 'static) -> Memo<A, T>
+    where
+        A: Hash + Eq + Clone + MaybeSync + 'static,
+        T: MaybeSend + 'static,
+    {
+        Memo::new(self.state.clone(), computation)
+    }
+
+    pub fn cached_methods(&self) -> CachedMethods {
+        CachedMethods::new(self.state.clone())
+    }
+
+    pub fn effect(&self, callback: impl Fn() + MaybeSync + 'static) -> Effect {
+        Effect::new(self.state.clone(), callback)
+    }
+
+    pub fn effect_debounced(
+        &self,
+        delay_flushes: u32,
+        callback: impl Fn() + MaybeSync + 'static,
+    ) -> Effect {
+        Effect::debounced(self.state.clone(), delay_flushes, callback)
+    }
+
+    pub fn reset(&self) {
+        self.state.reset();
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.state.memory_stats()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.state.snapshot()
+    }
+
+    pub fn fork(&self) -> Forked {
+        Forked::new(self.state.clone())
+    }
+
+    pub fn flush_dirty(&self, roots: &[Tag]) {
+        self.state.flush_dirty(roots);
+    }
+
+    #[cfg(feature = "history")]
+    pub fn record_history(&self, capacity: usize) {
+        self.state.record_history(capacity);
+    }
+
+    #[cfg(feature = "history")]
+    pub fn rewind_to(&self, revision: Revision) -> Result<(), RewindError> {
+        self.state.rewind_to(revision)
+    }
+
+    pub fn transaction<R>(&self, f: impl FnOnce(&Transaction<'_>) -> R) -> R {
+        let transaction = Transaction::begin(&self.state);
+        f(&transaction)
+    }
+
+    pub fn batch(&self, f: impl FnOnce()) {
+        self.transaction(|_| f())
+    }
+
+    pub fn pause(&self) -> Pause<'_> {
+        Pause::begin(&self.state)
+    }
+}
+
+// everything in this module has to stay shareable, so check it where a regression would be
+// introduced rather than in a downstream crate
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<SharedTimeline>();
+    assert_send_sync::<Cell<String>>();
+    assert_send_sync::<ReadonlyCell<String>>();
+    assert_send_sync::<Writer<String>>();
+    assert_send_sync::<Derived<String>>();
+    assert_send_sync::<DerivedAsync<String>>();
+    assert_send_sync::<Effect>();
+    assert_send_sync::<ExternalTag>();
+    assert_send_sync::<SubscriptionHandle>();
+    assert_send_sync::<Snapshot>();
+    assert_send_sync::<CachedMethods>();
+    assert_send_sync::<Memo<String, String>>();
+    assert_send_sync::<Constant<String>>();
+    assert_send_sync::<Tag>();
+    assert_send_sync::<CombinedTag>();
+    assert_send_sync::<TrackedVec<String>>();
+    assert_send_sync::<TrackedMap<String, String>>();
+};
@@ -0,0 +1,117 @@
+/*!
+ * Listeners for the raw stream of revision bumps, registered with `Timeline::on_bump`. Unlike
+ * effects and subscriptions, which run when something they read changed, a listener hears about
+ * every revision a write publishes, along with the tags that were written at it.
+ */
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Weak,
+};
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::{inputs::ReactiveTag, reactive::bounds::BumpHook};
+
+use super::{revision::Revision, state::TimelineState, TagId};
+
+type Listener = Arc<Mutex<Box<dyn BumpHook>>>;
+
+#[derive(Default)]
+pub(crate) struct BumpListeners {
+    // whether any listener is registered, checked before recording the tags behind a write
+    listening: AtomicBool,
+    next: AtomicU64,
+    // each listener is behind its own lock, so listeners can be registered and dropped while
+    // others run
+    listeners: Mutex<IndexMap<u64, Listener>>,
+    // the tags written at revisions that aren't published yet, with the revision of each
+    causes: Mutex<Vec<(Revision, TagId)>>,
+}
+
+impl BumpListeners {
+    // listeners are only `Send` with the `sync` feature, like every other callback
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn add(&self, listener: Box<dyn BumpHook>) -> u64 {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut listeners = self.listeners.lock();
+        listeners.insert(id, Arc::new(Mutex::new(listener)));
+        self.listening.store(true, Ordering::SeqCst);
+        id
+    }
+
+    pub(crate) fn remove(&self, id: u64) {
+        let mut listeners = self.listeners.lock();
+        listeners.shift_remove(&id);
+
+        if listeners.is_empty() {
+            self.listening.store(false, Ordering::SeqCst);
+            self.causes.lock().clear();
+        }
+    }
+
+    /**
+     * Record that `tag` was written at `revision`, which `now` hasn't reached yet, so the
+     * listeners are told about it once the revision is published.
+     */
+    pub(crate) fn caused_by(&self, tag: ReactiveTag, revision: Revision, now: Revision) {
+        if self.listening.load(Ordering::SeqCst) && revision > now {
+            self.causes.lock().push((revision, TagId::new(&tag)));
+        }
+    }
+
+    /**
+     * Tell every listener that `revision` was published, with the tags written at it.
+     */
+    pub(crate) fn bumped(&self, revision: Revision) {
+        if !self.listening.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let mut causes = vec![];
+        self.causes.lock().retain(|(at, tag)| {
+            if *at <= revision {
+                if *at == revision && !causes.contains(tag) {
+                    causes.push(tag.clone());
+                }
+                false
+            } else {
+                true
+            }
+        });
+
+        let listeners: Vec<_> = self.listeners.lock().values().cloned().collect();
+
+        for listener in listeners {
+            (listener.lock())(revision, &causes);
+        }
+    }
+}
+
+/**
+ * The handle returned by `Timeline::on_bump`. Dropping it unregisters the listener.
+ */
+#[derive(Debug)]
+#[must_use = "the listener is unregistered when the handle is dropped"]
+pub struct BumpHandle {
+    timeline: Weak<TimelineState>,
+    id: u64,
+}
+
+impl BumpHandle {
+    pub(crate) fn new(timeline: &Arc<TimelineState>, id: u64) -> BumpHandle {
+        BumpHandle {
+            timeline: Arc::downgrade(timeline),
+            id,
+        }
+    }
+}
+
+impl Drop for BumpHandle {
+    fn drop(&mut self) {
+        if let Some(timeline) = self.timeline.upgrade() {
+            timeline.bump_listeners().remove(self.id);
+        }
+    }
+}
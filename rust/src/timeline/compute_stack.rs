@@ -0,0 +1,1071 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        HashMap, HashSet,
+    },
+    error::Error,
+    fmt::{Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    inputs::ReactiveTag,
+    reactive::{label::Label, SubscriptionHandle},
+};
+
+use super::{local::Local, owner::Scope, state::TimelineState, NodeKey, Revision};
+
+/**
+ * The stable identity of a computation, assigned when the computation is created.
+ */
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ComputationId {
+    id: u64,
+}
+
+static NEXT_COMPUTATION: AtomicU64 = AtomicU64::new(1);
+
+impl ComputationId {
+    pub(crate) fn next() -> ComputationId {
+        ComputationId {
+            id: NEXT_COMPUTATION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn raw(self) -> u64 {
+        self.id
+    }
+}
+
+impl Display for ComputationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.id)
+    }
+}
+
+/**
+ * A computation tried to read itself, either directly or through a chain of other computations.
+ * The chain starts and ends with the computation that was read re-entrantly.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CycleError {
+    chain: Vec<ComputationId>,
+    labels: Vec<String>,
+}
+
+impl CycleError {
+    pub fn chain(&self) -> &[ComputationId] {
+        &self.chain
+    }
+
+    /**
+     * The debug labels of the computations in `chain`.
+     */
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cycle detected: ")?;
+
+        for (i, label) in self.labels.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+
+            write!(f, "{}", label)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for CycleError {}
+
+/**
+ * The tags that were consumed while a frame was on the compute stack.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Dependencies {
+    tags: Vec<ReactiveTag>,
+}
+
+impl Dependencies {
+    pub(crate) fn add(&mut self, tag: ReactiveTag) {
+        self.tags.push(tag);
+    }
+
+    pub(crate) fn extend(&mut self, other: Dependencies) {
+        self.tags.extend(other.tags);
+    }
+
+    pub(crate) fn tags(&self) -> &[ReactiveTag] {
+        &self.tags
+    }
+
+    /**
+     * The number of tags the dependencies have room for without allocating.
+     */
+    pub(crate) fn capacity(&self) -> usize {
+        self.tags.capacity()
+    }
+
+    /**
+     * Free the storage of a list that uses less than half of it, keeping room for as many tags
+     * as most frames consume. Computations whose reads vary a lot would otherwise keep the
+     * storage of their largest run for as long as they live.
+     */
+    fn shrink(&mut self) {
+        let capacity = self.tags.capacity();
+
+        if capacity > LINEAR_DEDUP && self.tags.len() < capacity / 2 {
+            self.tags.shrink_to(self.tags.len().max(LINEAR_DEDUP));
+        }
+    }
+
+    /**
+     * The newest revision of any of the consumed tags, or `Revision::CONSTANT` if nothing was
+     * consumed.
+     */
+    pub fn revision(&self) -> Revision {
+        self.tags
+            .iter()
+            .map(|tag| tag.revision())
+            .max()
+            .unwrap_or(Revision::CONSTANT)
+    }
+
+    /**
+     * The first consumed tag whose revision advanced past `revision`, along with its new
+     * revision. Tags are brought up to date in the order they were consumed, and the tags after
+     * the first changed one aren't looked at.
+     */
+    pub(crate) fn first_changed(&self, revision: Revision) -> Option<(ReactiveTag, Revision)> {
+        self.tags.iter().find_map(|tag| {
+            let current = tag.revision();
+
+            if current > revision {
+                Some((tag.clone(), current))
+            } else {
+                None
+            }
+        })
+    }
+
+    /**
+     * Whether any of the consumed tags may have advanced past `revision`, without bringing
+     * computed tags up to date.
+     */
+    pub(crate) fn changed_since(&self, revision: Revision) -> bool {
+        self.tags.iter().any(|tag| tag.changed_since(revision))
+    }
+
+    /**
+     * Whether any of the consumed tags is polled from outside of the timeline.
+     */
+    pub(crate) fn is_polled(&self) -> bool {
+        self.tags.iter().any(ReactiveTag::is_polled)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct Owner {
+    id: ComputationId,
+    label: Arc<Label>,
+    // identifies the timeline the computation belongs to
+    timeline: usize,
+    // what the run owns, or `None` for the frame a computation validates its dependencies in
+    scope: Option<Arc<Scope>>,
+}
+
+fn timeline_key(timeline: &TimelineState) -> usize {
+    timeline as *const TimelineState as usize
+}
+
+#[derive(Debug)]
+enum Frame {
+    Tracked {
+        owner: Option<Owner>,
+        dependencies: Dependencies,
+        // the keys of `dependencies`, only filled in once the frame consumed `LINEAR_DEDUP` tags
+        seen: HashSet<NodeKey>,
+        cache: FrameCache,
+    },
+    Untracked,
+}
+
+/**
+ * The results of `ComputeStack::cache_in_frame` in one frame, with the tags each of them read.
+ */
+#[derive(Default)]
+struct FrameCache {
+    // by the types of the key and the result, and the hash of the key
+    entries: HashMap<(TypeId, u64), Vec<Cached>>,
+}
+
+struct Cached {
+    key: Box<dyn Any>,
+    value: Box<dyn Any>,
+    tags: Vec<ReactiveTag>,
+}
+
+impl FrameCache {
+    fn slot<K: Hash + 'static, R: 'static>(key: &K) -> (TypeId, u64) {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (TypeId::of::<(K, R)>(), hasher.finish())
+    }
+
+    fn get<K: Hash + Eq + 'static, R: Clone + 'static>(
+        &self,
+        key: &K,
+    ) -> Option<(R, Vec<ReactiveTag>)> {
+        let cached = self
+            .entries
+            .get(&FrameCache::slot::<K, R>(key))?
+            .iter()
+            .find(|cached| cached.key.downcast_ref::<K>() == Some(key))?;
+
+        let value = cached.value.downcast_ref::<R>()?.clone();
+        Some((value, cached.tags.clone()))
+    }
+
+    fn insert<K: Hash + Eq + 'static, R: 'static>(
+        &mut self,
+        key: K,
+        value: R,
+        tags: Vec<ReactiveTag>,
+    ) {
+        self.entries
+            .entry(FrameCache::slot::<K, R>(&key))
+            .or_default()
+            .push(Cached {
+                key: Box::new(key),
+                value: Box::new(value),
+                tags,
+            });
+    }
+}
+
+impl Debug for FrameCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameCache")
+            .field(
+                "entries",
+                &self.entries.values().map(Vec::len).sum::<usize>(),
+            )
+            .finish()
+    }
+}
+
+/**
+ * Most frames consume a handful of tags, and scanning them is cheaper than hashing. Frames that
+ * consume more than this many tags look for duplicates in a hash set instead.
+ */
+const LINEAR_DEDUP: usize = 8;
+
+/**
+ * The number of frames' worth of storage a thread keeps for reuse, unless `ComputeStack::reserve`
+ * asked for more.
+ */
+const POOL_SIZE: usize = 64;
+
+/**
+ * Storage that grew larger than this while a frame was on the stack is freed rather than kept
+ * for reuse, so one large computation doesn't hold onto its memory forever.
+ */
+const POOLED_CAPACITY: usize = 256;
+
+/**
+ * The per-thread stack of tracking frames. Every read of a reactive value is recorded in the
+ * innermost frame, if there is one. Reads that happen when the stack is empty, or when the
+ * innermost frame is untracked, are not recorded anywhere.
+ *
+ * Reading the same value twice in one frame only records it once. The storage of popped frames,
+ * and of dependencies that computations replaced, is kept on the stack and reused by the next
+ * frame that is pushed, so a computation that runs again doesn't allocate to track its reads.
+ */
+#[derive(Debug)]
+pub struct ComputeStack {
+    frames: Vec<Frame>,
+    free_tags: Vec<Vec<ReactiveTag>>,
+    free_sets: Vec<HashSet<NodeKey>>,
+    pool_size: usize,
+    // the outermost read on this thread that is settling deriveds, see `ComputeStack::settling`
+    pass: Option<u64>,
+    // how many frames on the stack belong to each computation, so looking for a cycle doesn't
+    // have to scan a deep stack
+    owners: HashMap<ComputationId, usize>,
+    // the owners entered with `with_owner`, with the number of frames that were on the stack
+    // when each was entered
+    entered: Vec<(usize, Arc<Scope>)>,
+}
+
+static NEXT_PASS: AtomicU64 = AtomicU64::new(1);
+
+impl Default for ComputeStack {
+    fn default() -> ComputeStack {
+        ComputeStack {
+            frames: vec![],
+            free_tags: vec![],
+            free_sets: vec![],
+            pool_size: POOL_SIZE,
+            pass: None,
+            owners: HashMap::new(),
+            entered: vec![],
+        }
+    }
+}
+
+/**
+ * Run `compute` without recording any of its reads in the enclosing frame.
+ */
+pub fn untrack<R>(compute: impl FnOnce() -> R) -> R {
+    ComputeStack::untrack(compute)
+}
+
+/**
+ * Run `compute` and return the identities of everything it read, in the order it first read
+ * them, without creating a derived. The reads are recorded by the enclosing frame too, as if
+ * `compute` ran without a frame of its own, so this can be used inside a tracked region.
+ *
+ * ```
+ * use everafter::{track_reads, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let name = timeline.cell("alice").named("name");
+ * let age = timeline.cell(30).named("age");
+ *
+ * let reads = track_reads(|| {
+ *     name.get();
+ * });
+ *
+ * assert_eq!(reads, vec![name.tag().unwrap().id()]);
+ * assert_eq!(reads[0].label(), "name");
+ * ```
+ */
+pub fn track_reads(compute: impl FnOnce()) -> Vec<TagId> {
+    let ((), dependencies) = ComputeStack::track(compute);
+    let ids = dependencies.tags.iter().map(TagId::new).collect();
+
+    for tag in dependencies.tags {
+        ComputeStack::record(tag, None);
+    }
+
+    ids
+}
+
+/**
+ * The identity of a value that was read, as returned by `track_reads`. Two ids are equal if they
+ * belong to the same value. The id of a dropped value never equals the id of a value created
+ * after it.
+ */
+#[derive(Clone)]
+pub struct TagId {
+    key: NodeKey,
+    label: String,
+}
+
+impl TagId {
+    pub(crate) fn new(tag: &ReactiveTag) -> TagId {
+        TagId {
+            key: tag.key(),
+            label: tag.label(),
+        }
+    }
+
+    /**
+     * The label of the value, for assertion messages.
+     */
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl PartialEq for TagId {
+    fn eq(&self, other: &TagId) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for TagId {}
+
+impl Hash for TagId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl Debug for TagId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TagId({})", self.label)
+    }
+}
+
+/**
+ * Pops the innermost frame if the code that runs inside it panics, together with the frames the
+ * code pushed and didn't pop, so a panic that is caught further up doesn't leave a frame behind
+ * to record reads that belong to someone else. When the code returns, the frame it ran in has
+ * to be the innermost one again, or the reads recorded since were attributed to the wrong
+ * computation, which panics.
+ */
+struct PopOnUnwind {
+    // the number of frames below the one the code runs in
+    depth: usize,
+}
+
+impl PopOnUnwind {
+    fn run<R>(compute: impl FnOnce() -> R) -> R {
+        let depth = ComputeStack::depth() - 1;
+        let guard = PopOnUnwind { depth };
+        let result = compute();
+        mem::forget(guard);
+
+        let open = ComputeStack::depth();
+        assert!(
+            open == depth + 1,
+            "a computation that ran in frame {} returned with {} frames open",
+            depth + 1,
+            open
+        );
+
+        result
+    }
+}
+
+impl Drop for PopOnUnwind {
+    fn drop(&mut self) {
+        drop(TruncateOnUnwind { depth: self.depth });
+    }
+}
+
+/**
+ * Leaves the owner entered with `ComputeStack::with_scope`, also when the code that entered it
+ * panics.
+ */
+struct LeaveScope;
+
+impl Drop for LeaveScope {
+    fn drop(&mut self) {
+        let scope = ComputeStack::with(|stack| stack.entered.pop());
+        drop(scope);
+    }
+}
+
+/**
+ * Pops the frames above `depth` if the code that pushed them panics.
+ */
+struct TruncateOnUnwind {
+    depth: usize,
+}
+
+impl Drop for TruncateOnUnwind {
+    fn drop(&mut self) {
+        // the frames hold onto computations, which mustn't be dropped while the stack is borrowed
+        let frames = ComputeStack::with(|stack| {
+            let mut frames = vec![];
+
+            while stack.frames.len() > self.depth {
+                frames.extend(stack.pop_raw());
+            }
+
+            frames
+        });
+        drop(frames);
+    }
+}
+
+/**
+ * Ends the settling pass when the outermost read that started it returns or unwinds.
+ */
+struct EndPass;
+
+impl Drop for EndPass {
+    fn drop(&mut self) {
+        ComputeStack::with(|stack| stack.pass = None);
+    }
+}
+
+thread_local! {
+    static STACK: RefCell<ComputeStack> = RefCell::new(ComputeStack::default());
+}
+
+impl ComputeStack {
+    /**
+     * Run `compute` inside a fresh frame and return its result together with everything it read.
+     */
+    pub fn track<R>(compute: impl FnOnce() -> R) -> (R, Dependencies) {
+        ComputeStack::push(None);
+        let result = PopOnUnwind::run(compute);
+        let dependencies = ComputeStack::pop();
+        (result, dependencies)
+    }
+
+    /**
+     * Run `compute` inside a frame that belongs to the computation `id`, and return its result
+     * together with everything it read and what the run owns.
+     */
+    pub(crate) fn track_computation<R>(
+        id: ComputationId,
+        label: &Arc<Label>,
+        timeline: &TimelineState,
+        compute: impl FnOnce() -> R,
+    ) -> (R, Dependencies, Arc<Scope>) {
+        ComputeStack::check_depth(label, timeline);
+        let scope = Scope::new(id);
+        ComputeStack::push(Some(Owner {
+            id,
+            label: label.clone(),
+            timeline: timeline_key(timeline),
+            scope: Some(scope.clone()),
+        }));
+        let result = PopOnUnwind::run(compute);
+        let dependencies = ComputeStack::pop();
+        (result, dependencies, scope)
+    }
+
+    /**
+     * Give `subscription` to the derived or effect that is running on this thread. The
+     * subscription is dropped, which disposes it, right before that computation runs again, or
+     * when the computation itself is dropped. Effects created inside a computation already
+     * belong to it, see `Owner`, so this is for subscriptions that were created outside of it.
+     *
+     * Panics if no derived or effect is running on this thread.
+     */
+    pub fn own(subscription: SubscriptionHandle) {
+        match ComputeStack::scope() {
+            Some(scope) => scope.own_subscription(subscription),
+            None => panic!("ComputeStack::own was called outside of a derived or an effect"),
+        }
+    }
+
+    /**
+     * What the innermost run on this thread owns: the run of the innermost derived or effect,
+     * or the owner entered with `with_owner` inside of it.
+     */
+    pub(crate) fn scope() -> Option<Arc<Scope>> {
+        ComputeStack::with(|stack| {
+            let running = stack
+                .frames
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(depth, frame)| match frame {
+                    Frame::Tracked {
+                        owner:
+                            Some(Owner {
+                                scope: Some(scope), ..
+                            }),
+                        ..
+                    } => Some((depth, scope)),
+                    _ => None,
+                });
+
+            match (running, stack.entered.last()) {
+                // an owner entered with `with_owner` is above the frames that were on the stack
+                (Some((depth, _)), Some((entered_at, scope))) if *entered_at > depth => {
+                    Some(scope.clone())
+                }
+                (None, Some((_, scope))) => Some(scope.clone()),
+                (Some((_, scope)), _) => Some(scope.clone()),
+                (None, None) => None,
+            }
+        })
+    }
+
+    /**
+     * Run `f` with `scope` as the innermost run's, without pushing a frame.
+     */
+    pub(crate) fn with_scope<R>(scope: Arc<Scope>, f: impl FnOnce() -> R) -> R {
+        ComputeStack::with(|stack| stack.entered.push((stack.frames.len(), scope)));
+        let _leave = LeaveScope;
+        f()
+    }
+
+    /**
+     * Run `settle` as a settling pass, unless the thread is already in one. Deriveds that read
+     * an external source validate again on every read, except when they were already validated
+     * by the same outermost read, so a pass keeps a deep graph over a source from being walked
+     * once per level.
+     */
+    pub(crate) fn settling<R>(settle: impl FnOnce() -> R) -> R {
+        let outermost = ComputeStack::with(|stack| {
+            if stack.pass.is_some() {
+                return false;
+            }
+
+            stack.pass = Some(NEXT_PASS.fetch_add(1, Ordering::Relaxed));
+            true
+        });
+
+        if !outermost {
+            return settle();
+        }
+
+        let _end = EndPass;
+        settle()
+    }
+
+    /**
+     * Push a frame for the computation `id` while the dependencies it will validate are
+     * settled, like the frame it validates them in, so a dependency that reads it back reports
+     * the cycle. Popped with `exit`.
+     */
+    pub(crate) fn enter(id: ComputationId, label: &Arc<Label>, timeline: &TimelineState) {
+        ComputeStack::check_depth(label, timeline);
+        ComputeStack::push(Some(Owner {
+            id,
+            label: label.clone(),
+            timeline: timeline_key(timeline),
+            scope: None,
+        }));
+    }
+
+    pub(crate) fn exit() {
+        let dependencies = ComputeStack::pop();
+        ComputeStack::recycle(dependencies);
+    }
+
+    /**
+     * Run `settle`, and if it panics, pop every frame it pushed with `enter` and didn't pop.
+     */
+    pub(crate) fn unwinding_frames<R>(settle: impl FnOnce() -> R) -> R {
+        let guard = TruncateOnUnwind {
+            depth: ComputeStack::with(|stack| stack.frames.len()),
+        };
+        let result = settle();
+        mem::forget(guard);
+        result
+    }
+
+    /**
+     * The settling pass the thread is in, if any.
+     */
+    pub(crate) fn pass() -> Option<u64> {
+        ComputeStack::with(|stack| stack.pass)
+    }
+
+    /**
+     * If `id` is already being computed on this thread, describe the cycle that reading it again
+     * would create.
+     */
+    pub(crate) fn cycle(id: ComputationId) -> Option<CycleError> {
+        ComputeStack::with(|stack| {
+            if !stack.owners.contains_key(&id) {
+                return None;
+            }
+
+            let owners = stack.frames.iter().filter_map(|frame| match frame {
+                Frame::Tracked { owner, .. } => owner.as_ref(),
+                Frame::Untracked => None,
+            });
+
+            let mut cycle: Vec<&Owner> = owners.skip_while(|owner| owner.id != id).collect();
+
+            if let Some(&first) = cycle.first() {
+                cycle.push(first);
+
+                Some(CycleError {
+                    chain: cycle.iter().map(|owner| owner.id).collect(),
+                    labels: cycle.iter().map(|owner| owner.label.to_string()).collect(),
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /**
+     * Run `compute` inside an untracked frame, so none of its reads are recorded by the enclosing
+     * frame. Frames pushed inside `compute` still track their own reads.
+     */
+    pub fn untrack<R>(compute: impl FnOnce() -> R) -> R {
+        ComputeStack::with(|stack| stack.push_raw(Frame::Untracked));
+        let result = PopOnUnwind::run(compute);
+
+        match ComputeStack::with(|stack| stack.pop_raw()) {
+            Some(Frame::Untracked) => result,
+            _ => panic!("popped an untracked frame, but the innermost frame was tracked"),
+        }
+    }
+
+    /**
+     * The number of frames open on this thread: one for every derived or effect that is
+     * computing or validating its dependencies, every `ComputeStack::track`, and every `untrack`,
+     * whose frame counts even though it records nothing.
+     */
+    pub fn depth() -> usize {
+        ComputeStack::with(|stack| stack.frames.len())
+    }
+
+    /**
+     * Panic if pushing a frame for the computation `label` would take the stack past the
+     * deepest that `timeline` allows.
+     */
+    fn check_depth(label: &Label, timeline: &TimelineState) {
+        if let Some(max) = timeline.max_compute_depth() {
+            let depth = ComputeStack::depth();
+
+            if depth >= max {
+                panic!(
+                    "{} can't start computing, because the compute stack is already {} frames \
+                     deep, the most that `Timeline::set_max_compute_depth` allows",
+                    label, depth
+                );
+            }
+        }
+    }
+
+    /**
+     * Compute `compute` once per `key` in the innermost frame, and return a clone of the first
+     * result to every later call with an equal key until the frame pops. A computation that
+     * needs the same expensive sub-result in several places can share it without creating a
+     * derived, and since the cache never outlives one run of the computation, it never has to
+     * be invalidated.
+     *
+     * The reads `compute` made are recorded in the frame on every call, including the ones that
+     * return the cached result, so a cached call depends on the same values as the call that
+     * computed it. Without a tracked frame, like inside `untrack`, this just calls `compute`.
+     *
+     * ```
+     * use std::sync::{
+     *     atomic::{AtomicUsize, Ordering},
+     *     Arc,
+     * };
+     * use everafter::{ComputeStack, Timeline};
+     *
+     * let timeline = Timeline::new();
+     * let source = timeline.cell(String::from("a,b,c"));
+     * let parses = Arc::new(AtomicUsize::new(0));
+     *
+     * let summary = {
+     *     let (source, parses) = (source.clone(), parses.clone());
+     *     timeline.derived(move || {
+     *         let parse = || {
+     *             parses.fetch_add(1, Ordering::SeqCst);
+     *             source.get().split(',').map(String::from).collect::<Vec<_>>()
+     *         };
+     *
+     *         let first = ComputeStack::cache_in_frame("fields", parse);
+     *         let count = ComputeStack::cache_in_frame("fields", parse).len();
+     *         format!("{} of {}", first[0], count)
+     *     })
+     * };
+     *
+     * assert_eq!(summary.get(), "a of 3");
+     * assert_eq!(parses.load(Ordering::SeqCst), 1);
+     * ```
+     */
+    pub fn cache_in_frame<K, R>(key: K, compute: impl FnOnce() -> R) -> R
+    where
+        K: Hash + Eq + 'static,
+        R: Clone + 'static,
+    {
+        let cached = ComputeStack::with(|stack| match stack.frames.last() {
+            Some(Frame::Tracked { cache, .. }) => Some(cache.get::<K, R>(&key)),
+            _ => None,
+        });
+
+        match cached {
+            None => compute(),
+            Some(Some((value, tags))) => {
+                ComputeStack::forward(tags);
+                value
+            }
+            Some(None) => {
+                let (value, dependencies) = ComputeStack::track(compute);
+                let tags = dependencies.tags().to_vec();
+                ComputeStack::forward(dependencies.tags);
+
+                let stored = value.clone();
+                ComputeStack::with(|stack| {
+                    if let Some(Frame::Tracked { cache, .. }) = stack.frames.last_mut() {
+                        cache.insert(key, stored, tags);
+                    }
+                });
+
+                value
+            }
+        }
+    }
+
+    /**
+     * Returns true if reads on this thread are currently being recorded.
+     */
+    pub fn is_tracking() -> bool {
+        ComputeStack::with(|stack| matches!(stack.frames.last(), Some(Frame::Tracked { .. })))
+    }
+
+    /**
+     * The label of the derived whose computation is running on this thread, if the innermost
+     * computation is a derived. Untracked frames and frames that don't belong to a computation
+     * are skipped, so a read inside `untrack` is still inside the derived that called it.
+     */
+    pub(crate) fn computing() -> Option<Arc<Label>> {
+        ComputeStack::with(|stack| {
+            let owner = stack.frames.iter().rev().find_map(|frame| match frame {
+                Frame::Tracked {
+                    owner: Some(owner), ..
+                } => Some(owner),
+                _ => None,
+            })?;
+
+            if owner.label.kind() == "derived" {
+                Some(owner.label.clone())
+            } else {
+                None
+            }
+        })
+    }
+
+    /**
+     * Returns true if no frame, tracked or untracked, is open on this thread.
+     */
+    pub(crate) fn is_empty() -> bool {
+        ComputeStack::with(|stack| stack.frames.is_empty())
+    }
+
+    /**
+     * Set aside storage on the current thread for `frames` frames, so the first computations that
+     * run on it don't allocate to track their reads either. The stack keeps at least this many
+     * frames' worth of storage for reuse from then on.
+     */
+    pub fn reserve(frames: usize) {
+        ComputeStack::with(|stack| {
+            stack.pool_size = stack.pool_size.max(frames);
+            stack.frames.reserve(frames);
+
+            while stack.free_tags.len() < frames {
+                stack.free_tags.push(Vec::with_capacity(LINEAR_DEDUP));
+            }
+        })
+    }
+
+    /**
+     * Hand the storage of dependencies that are no longer needed back to the stack, to be reused
+     * by the next frame that is pushed on this thread.
+     */
+    pub(crate) fn recycle(dependencies: Dependencies) {
+        let mut tags = dependencies.tags;
+
+        if tags.capacity() == 0 || tags.capacity() > POOLED_CAPACITY {
+            return;
+        }
+
+        // dropping the tags can drop the computations they belong to, which mustn't happen while
+        // the stack is borrowed
+        tags.clear();
+
+        ComputeStack::with(|stack| {
+            if stack.free_tags.len() < stack.pool_size {
+                stack.free_tags.push(tags);
+            }
+        })
+    }
+
+    fn with<R>(f: impl FnOnce(&mut ComputeStack) -> R) -> R {
+        Local::with(&STACK, f)
+    }
+
+    fn push_raw(&mut self, frame: Frame) {
+        if let Frame::Tracked {
+            owner: Some(owner), ..
+        } = &frame
+        {
+            *self.owners.entry(owner.id).or_insert(0) += 1;
+        }
+
+        self.frames.push(frame);
+    }
+
+    fn pop_raw(&mut self) -> Option<Frame> {
+        let frame = self.frames.pop();
+
+        if let Some(Frame::Tracked {
+            owner: Some(owner), ..
+        }) = &frame
+        {
+            if let Entry::Occupied(mut count) = self.owners.entry(owner.id) {
+                *count.get_mut() -= 1;
+
+                if *count.get() == 0 {
+                    count.remove();
+                }
+            }
+        }
+
+        frame
+    }
+
+    fn push(owner: Option<Owner>) {
+        ComputeStack::with(|stack| {
+            let tags = stack.free_tags.pop().unwrap_or_default();
+
+            stack.push_raw(Frame::Tracked {
+                owner,
+                dependencies: Dependencies { tags },
+                seen: HashSet::new(),
+                cache: FrameCache::default(),
+            })
+        });
+    }
+
+    pub(crate) fn pop() -> Dependencies {
+        ComputeStack::pop_frame().0
+    }
+
+    fn pop_frame() -> (Dependencies, Option<Owner>) {
+        let (dependencies, owner, cache) = ComputeStack::with(|stack| match stack.pop_raw() {
+            Some(Frame::Tracked {
+                owner,
+                mut dependencies,
+                mut seen,
+                cache,
+            }) => {
+                dependencies.shrink();
+
+                if seen.capacity() > 0
+                    && seen.capacity() <= POOLED_CAPACITY
+                    && stack.free_sets.len() < stack.pool_size
+                {
+                    seen.clear();
+                    stack.free_sets.push(seen);
+                }
+
+                (dependencies, owner, cache)
+            }
+            Some(Frame::Untracked) => {
+                panic!("popped a tracked frame, but the innermost frame was untracked")
+            }
+            None => panic!("popped a frame without pushing one"),
+        });
+
+        // the cached values can hold onto computations, like recycled tags
+        drop(cache);
+        (dependencies, owner)
+    }
+
+    /**
+     * Record `tag` in the innermost frame. `timeline` and `label` describe the value the tag
+     * belongs to: debug builds panic if a computation reads a value that was created from
+     * another timeline, since the two timelines' revisions can't be compared.
+     */
+    pub(crate) fn consume(timeline: &TimelineState, label: &Label, tag: ReactiveTag) {
+        ComputeStack::record(tag, Some((timeline, label)));
+    }
+
+    /**
+     * Record tags that were read in another frame in the innermost one.
+     */
+    pub(crate) fn forward(tags: Vec<ReactiveTag>) {
+        for tag in tags {
+            ComputeStack::record(tag, None);
+        }
+    }
+
+    /**
+     * Record `tag` in the innermost frame, checking that `value` belongs to the frame's
+     * timeline. Reads forwarded from an inner frame don't know which value they belong to, so
+     * they aren't checked.
+     */
+    fn record(tag: ReactiveTag, value: Option<(&TimelineState, &Label)>) {
+        let duplicate = ComputeStack::with(|stack| {
+            let ComputeStack {
+                frames, free_sets, ..
+            } = stack;
+
+            let (owner, dependencies, seen) = match frames.last_mut() {
+                Some(Frame::Tracked {
+                    owner,
+                    dependencies,
+                    seen,
+                    ..
+                }) => (owner, dependencies, seen),
+                _ => return None,
+            };
+
+            if cfg!(debug_assertions) {
+                if let (Some(owner), Some((timeline, label))) = (owner, value) {
+                    assert!(
+                        owner.timeline == timeline_key(timeline),
+                        "{} read {}, which belongs to another timeline. A computation can only \
+                         read values created from its own timeline.",
+                        owner.label,
+                        label
+                    );
+                }
+            }
+
+            let key = tag.key();
+
+            let duplicate = if dependencies.len() < LINEAR_DEDUP {
+                dependencies
+                    .tags
+                    .iter()
+                    .any(|consumed| consumed.key() == key)
+            } else {
+                if seen.is_empty() {
+                    if let Some(set) = free_sets.pop() {
+                        *seen = set;
+                    }
+
+                    seen.extend(dependencies.tags.iter().map(ReactiveTag::key));
+                }
+
+                !seen.insert(key)
+            };
+
+            if duplicate {
+                Some(tag)
+            } else {
+                dependencies.add(tag);
+                None
+            }
+        });
+
+        // like recycled tags, a duplicate is dropped once the stack is no longer borrowed
+        drop(duplicate);
+    }
+}
+
+/**
+ * What the fuzz driver in `everafter::fuzz` and the tests built on it check the stack against.
+ */
+#[cfg(any(feature = "testing", fuzzing))]
+impl ComputeStack {
+    /**
+     * What each frame on this thread recorded so far, outermost first, or `None` for an
+     * untracked frame.
+     */
+    pub fn frame_reads() -> Vec<Option<Vec<TagId>>> {
+        ComputeStack::with(|stack| {
+            stack
+                .frames
+                .iter()
+                .map(|frame| match frame {
+                    Frame::Tracked { dependencies, .. } => {
+                        Some(dependencies.tags.iter().map(TagId::new).collect())
+                    }
+                    Frame::Untracked => None,
+                })
+                .collect()
+        })
+    }
+}
@@ -26,8 +26,7 @@ impl<'a> EvaluationContext<'a> {
     }
 
     pub(crate) fn pop(&mut self) -> DerivedTag {
-        let result = self.stack.pop().expect("popped a tag without pushing one");
-        result
+        self.stack.pop().expect("popped a tag without pushing one")
     }
 
     pub(crate) fn consume(&self, tag: ReactiveTag) {
@@ -30,6 +30,7 @@ impl InputId {
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum IdKind {
     CellId,
@@ -122,7 +123,7 @@ where
         self.id
     }
 
-    pub fn value<'a>(self, ctx: &mut EvaluationContext) -> T {
+    pub fn value(self, ctx: &mut EvaluationContext) -> T {
         ctx.value(self)
     }
 }
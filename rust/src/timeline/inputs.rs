@@ -150,10 +150,7 @@ impl<T: Debug + Clone + 'static> TypedInputs<T> {
         value: T,
         revision: Revision,
     ) {
-        let cell = self
-            .cells
-            .get_mut(id.into())
-            .expect("typed cell didn't exist");
+        let cell = self.cells.get_mut(id).expect("typed cell didn't exist");
         cell.update(value, revision);
     }
 }
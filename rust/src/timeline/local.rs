@@ -0,0 +1,70 @@
+/*!
+ * Per-thread state, like the `ComputeStack`, is reached through `Local` instead of through
+ * `thread_local!` directly, so that a build without `std` can keep it in a `SingleThreaded`
+ * static instead.
+ */
+
+use core::cell::RefCell;
+
+/**
+ * A place that keeps one `T` for each thread of execution.
+ */
+pub trait Local<T: 'static> {
+    /**
+     * Call `f` with the value that belongs to the current thread. `f` must not reach the same
+     * value again, which panics.
+     */
+    fn with<R>(&'static self, f: impl FnOnce(&mut T) -> R) -> R;
+}
+
+#[cfg(feature = "std")]
+impl<T: 'static> Local<T> for std::thread::LocalKey<RefCell<T>> {
+    fn with<R>(&'static self, f: impl FnOnce(&mut T) -> R) -> R {
+        std::thread::LocalKey::with(self, |value| f(&mut value.borrow_mut()))
+    }
+}
+
+/**
+ * A `Local` for targets with a single thread of execution, where a `static` can hold the only
+ * value there is. The value is created by `init` the first time it is used.
+ *
+ * ```
+ * use everafter::{Local, SingleThreaded};
+ *
+ * // SAFETY: the example only runs on one thread
+ * static COUNT: SingleThreaded<u32> = unsafe { SingleThreaded::new(|| 10) };
+ *
+ * COUNT.with(|count| *count += 1);
+ * assert_eq!(COUNT.with(|count| *count), 11);
+ * ```
+ */
+pub struct SingleThreaded<T> {
+    value: RefCell<Option<T>>,
+    init: fn() -> T,
+}
+
+impl<T> SingleThreaded<T> {
+    /**
+     * # Safety
+     *
+     * The value is shared by every thread that reaches it, without any synchronization, so it
+     * must only ever be used from one thread of execution. On a target with interrupts, that
+     * includes never using it from an interrupt handler.
+     */
+    pub const unsafe fn new(init: fn() -> T) -> SingleThreaded<T> {
+        SingleThreaded {
+            value: RefCell::new(None),
+            init,
+        }
+    }
+}
+
+// SAFETY: `SingleThreaded::new` requires the value to only be used from one thread
+unsafe impl<T> Sync for SingleThreaded<T> {}
+
+impl<T: 'static> Local<T> for SingleThreaded<T> {
+    fn with<R>(&'static self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut value = self.value.borrow_mut();
+        f(value.get_or_insert_with(self.init))
+    }
+}
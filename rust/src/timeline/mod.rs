@@ -1,13 +1,62 @@
+#[cfg(feature = "std")]
+pub(crate) mod bumps;
+#[cfg(feature = "std")]
+pub(crate) mod compute_stack;
+#[cfg(feature = "std")]
 pub(crate) mod dyn_id;
+#[cfg(feature = "std")]
 pub(crate) mod evaluation_context;
+#[cfg(feature = "std")]
 pub(crate) mod id;
+#[cfg(feature = "std")]
 pub(crate) mod inputs;
+pub(crate) mod local;
+#[cfg(feature = "std")]
+pub(crate) mod node_key;
+#[cfg(feature = "std")]
+pub(crate) mod owner;
+#[cfg(feature = "std")]
 pub(crate) mod partition;
+#[cfg(feature = "std")]
+pub(crate) mod read_policy;
 pub(crate) mod revision;
+#[cfg(feature = "std")]
+pub(crate) mod state;
+#[cfg(feature = "std")]
+#[allow(clippy::module_inception)]
 pub(crate) mod timeline;
+#[cfg(feature = "revision-timestamps")]
+pub(crate) mod timestamps;
+#[cfg(feature = "std")]
+pub(crate) mod tracked_future;
+#[cfg(feature = "std")]
+pub(crate) mod validation;
 
+#[cfg(feature = "std")]
+pub use bumps::BumpHandle;
+#[cfg(feature = "std")]
+pub use compute_stack::{
+    track_reads, untrack, ComputationId, ComputeStack, CycleError, Dependencies, TagId,
+};
+#[cfg(feature = "std")]
 pub use dyn_id::DynId;
+#[cfg(feature = "std")]
 pub use evaluation_context::EvaluationContext;
+#[cfg(feature = "std")]
 pub use id::{CellId, DerivedId, IdKindFor, TypedInputId, TypedInputIdWithKind};
+pub use local::{Local, SingleThreaded};
+#[cfg(feature = "std")]
+pub(crate) use node_key::NodeKey;
+#[cfg(feature = "std")]
+pub use owner::{with_owner, Owner};
+#[cfg(feature = "std")]
+pub use read_policy::{ReadPolicy, UntrackedRead};
 pub use revision::Revision;
-pub use timeline::{RenderTransaction, Timeline};
+#[cfg(feature = "std")]
+pub use timeline::{Pause, RenderTransaction, Timeline, Transaction};
+#[cfg(feature = "revision-timestamps")]
+pub use timestamps::{Clock, SystemClock};
+#[cfg(feature = "std")]
+pub use tracked_future::{tracked_async, TrackedFuture};
+#[cfg(feature = "std")]
+pub use validation::ValidationMode;
@@ -0,0 +1,83 @@
+/*!
+ * The identities that frames, forks, schedulers and debug graphs tell the graph's nodes apart by. A
+ * node's address is reused by whatever is allocated in its place once it is dropped, so a key that
+ * outlives its node, like a `TagId` returned by `track_reads` or a derived's value cached in a
+ * fork, could mistake the new node for the old one. Keys are a slot index and a generation instead:
+ * a dropped node frees its slot for reuse, and the next node in that slot gets the next generation,
+ * so its key never equals the key of a node before it.
+ */
+
+use std::convert::TryFrom;
+
+use parking_lot::{const_mutex, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeKey {
+    slot: u32,
+    generation: u32,
+}
+
+struct Slots {
+    // the generation of the node in each slot, or of the next one if the slot is free
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
+static SLOTS: Mutex<Slots> = const_mutex(Slots {
+    generations: Vec::new(),
+    free: Vec::new(),
+});
+
+/**
+ * A node's claim on its slot, which frees the slot once the node is dropped.
+ */
+#[derive(Debug)]
+pub(crate) struct NodeSlot {
+    key: NodeKey,
+}
+
+impl NodeSlot {
+    pub(crate) fn new() -> NodeSlot {
+        let mut slots = SLOTS.lock();
+
+        let slot = match slots.free.pop() {
+            Some(slot) => slot,
+            None => {
+                let slot = u32::try_from(slots.generations.len())
+                    .expect("more nodes are alive than there are slots");
+                slots.generations.push(0);
+                slot
+            }
+        };
+
+        NodeSlot {
+            key: NodeKey {
+                slot,
+                generation: slots.generations[slot as usize],
+            },
+        }
+    }
+
+    pub(crate) fn key(&self) -> NodeKey {
+        self.key
+    }
+}
+
+impl Default for NodeSlot {
+    fn default() -> NodeSlot {
+        NodeSlot::new()
+    }
+}
+
+impl Drop for NodeSlot {
+    fn drop(&mut self) {
+        let mut slots = SLOTS.lock();
+        let generation = &mut slots.generations[self.key.slot as usize];
+
+        // a slot that ran out of generations is never reused, rather than repeating a key
+        if let Some(next) = generation.checked_add(1) {
+            *generation = next;
+            slots.free.push(self.key.slot);
+        }
+    }
+}
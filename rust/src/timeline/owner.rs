@@ -0,0 +1,197 @@
+use std::{
+    fmt::Debug,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+
+use crate::reactive::{bounds::OnDispose, MaybeSend, SubscriptionHandle};
+
+use super::{ComputationId, ComputeStack};
+
+/**
+ * One run of a derived or an effect, which owns the effects created and the cleanups
+ * registered while it ran. Everything a run owns is disposed when the computation runs again,
+ * or when it is dropped or disposed. An effect that owns effects itself disposes those first,
+ * so disposal goes depth-first, children before their parents, and the newest child first.
+ *
+ * An owner is a handle that can outlive its run. Code that runs later on the run's behalf, like
+ * an async task the run spawned, can get the owner with `Owner::current` and re-enter it with
+ * `with_owner`. Once the run was disposed, what the task creates under it is disposed right away.
+ *
+ * ```
+ * use everafter::{with_owner, Owner, Timeline};
+ *
+ * Timeline::with_default(|timeline| {
+ *     let page = timeline.cell(1);
+ *
+ *     let resumed = {
+ *         let page = page.clone();
+ *         timeline.derived(move || {
+ *             page.get();
+ *             Owner::current().unwrap()
+ *         })
+ *     };
+ *
+ *     let owner = resumed.get();
+ *     let later = with_owner(&owner, || Timeline::with_default(|timeline| timeline.effect(|| {})));
+ *     assert!(!later.is_disposed());
+ *
+ *     page.set(2);
+ *     resumed.get();
+ *     assert!(owner.is_disposed());
+ *     assert!(later.is_disposed());
+ * });
+ * ```
+ */
+#[derive(Clone)]
+pub struct Owner {
+    scope: Arc<Scope>,
+}
+
+/**
+ * What one run owns, shared by the frame the run computes in and every `Owner` handle to it.
+ */
+pub(crate) struct Scope {
+    id: ComputationId,
+    owned: Mutex<Vec<Owned>>,
+    disposed: AtomicBool,
+}
+
+enum Owned {
+    Subscription(SubscriptionHandle),
+    Cleanup(Box<dyn OnDispose>),
+}
+
+impl Owned {
+    fn dispose(self) {
+        match self {
+            Owned::Subscription(subscription) => drop(subscription),
+            Owned::Cleanup(cleanup) => cleanup(),
+        }
+    }
+}
+
+impl Scope {
+    // what a run owns is only `Send` with the `sync` feature, like the effects it owns
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(id: ComputationId) -> Arc<Scope> {
+        Arc::new(Scope {
+            id,
+            owned: Mutex::new(vec![]),
+            disposed: AtomicBool::new(false),
+        })
+    }
+
+    fn own(&self, owned: Owned) {
+        {
+            let mut list = self.owned.lock();
+
+            if !self.disposed.load(Ordering::SeqCst) {
+                list.push(owned);
+                return;
+            }
+        }
+
+        owned.dispose();
+    }
+
+    pub(crate) fn own_subscription(&self, subscription: SubscriptionHandle) {
+        self.own(Owned::Subscription(subscription));
+    }
+
+    /**
+     * Dispose everything the run owns, newest first, and everything it will be given from now
+     * on as soon as it's given.
+     */
+    pub(crate) fn dispose(&self) {
+        let owned = {
+            let mut list = self.owned.lock();
+            self.disposed.store(true, Ordering::SeqCst);
+            mem::take(&mut *list)
+        };
+
+        for owned in owned.into_iter().rev() {
+            owned.dispose();
+        }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        self.dispose();
+    }
+}
+
+impl Owner {
+    pub(crate) fn new(scope: Arc<Scope>) -> Owner {
+        Owner { scope }
+    }
+
+    /**
+     * The run of the derived or effect that is running on this thread, or the owner entered
+     * with `with_owner` inside of it. `None` outside of any computation.
+     */
+    pub fn current() -> Option<Owner> {
+        ComputeStack::scope().map(Owner::new)
+    }
+
+    /**
+     * The derived or effect this is a run of.
+     */
+    pub fn id(&self) -> ComputationId {
+        self.scope.id
+    }
+
+    /**
+     * Call `cleanup` when the run is disposed, right away if it already was.
+     */
+    pub fn on_cleanup(&self, cleanup: impl FnOnce() + MaybeSend + 'static) {
+        self.scope.own(Owned::Cleanup(Box::new(cleanup)));
+    }
+
+    /**
+     * Dispose `subscription` with the run. See `ComputeStack::own`.
+     */
+    pub fn own(&self, subscription: SubscriptionHandle) {
+        self.scope.own_subscription(subscription);
+    }
+
+    /**
+     * Whether the computation ran again or was dropped since this run.
+     */
+    pub fn is_disposed(&self) -> bool {
+        self.scope.disposed.load(Ordering::SeqCst)
+    }
+}
+
+impl Debug for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scope")
+            .field("id", &self.id)
+            .field("owned", &self.owned.lock().len())
+            .finish()
+    }
+}
+
+impl Debug for Owner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Owner")
+            .field("id", &self.scope.id)
+            .field("disposed", &self.is_disposed())
+            .finish()
+    }
+}
+
+/**
+ * Run `f` with `owner` as the current owner, so the effects `f` creates and the cleanups it
+ * registers belong to `owner`'s run. What `f` reads is tracked like it would have been without
+ * entering the owner.
+ */
+pub fn with_owner<R>(owner: &Owner, f: impl FnOnce() -> R) -> R {
+    ComputeStack::with_scope(owner.scope.clone(), f)
+}
@@ -0,0 +1,77 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    panic::Location,
+};
+
+use atomig::Atom;
+
+/**
+ * What a timeline does when one of its cells is read while the `ComputeStack` is empty. Such a
+ * read is never recorded, so a function that was meant to be reactive but is called outside of
+ * a derived or an effect silently stops updating. Reads inside `untrack`, and reads of constant
+ * cells, are deliberate and are always allowed.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Atom)]
+#[repr(u8)]
+pub enum ReadPolicy {
+    /**
+     * Untracked reads simply return the value.
+     */
+    #[default]
+    Allow,
+
+    /**
+     * Untracked reads return the value, and are reported to the callback installed with
+     * `Timeline::on_untracked_read` and to the timeline's `TimelineObserver`. With neither
+     * installed, nothing is reported, so install one of them along with the policy.
+     */
+    Warn,
+
+    /**
+     * `Cell::try_get` fails with an `UntrackedRead`, and `Cell::get` panics in debug builds. In
+     * release builds `Cell::get` reports the read like `ReadPolicy::Warn` instead.
+     */
+    Deny,
+}
+
+/**
+ * A cell was read while the `ComputeStack` was empty.
+ */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UntrackedRead {
+    label: String,
+    location: &'static Location<'static>,
+}
+
+impl UntrackedRead {
+    pub(crate) fn new(label: String, location: &'static Location<'static>) -> UntrackedRead {
+        UntrackedRead { label, location }
+    }
+
+    /**
+     * The label of the cell that was read.
+     */
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /**
+     * Where the cell was read.
+     */
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+impl Display for UntrackedRead {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was read outside of any tracking frame at {}",
+            self.label, self.location
+        )
+    }
+}
+
+impl Error for UntrackedRead {}
@@ -1,56 +1,150 @@
-use atomig::{Atom, Atomic};
-use std::cmp::Ordering;
-use std::sync::atomic;
+#[cfg(feature = "std")]
+use atomig::Atom;
+use core::cmp::Ordering;
+use core::fmt::Display;
+use core::sync::atomic::{self, AtomicU64};
 
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Atom)]
+/**
+ * A point on a timeline. Every write advances the timeline to a revision that is strictly greater
+ * than all of the revisions before it, so comparing a stored revision with `Timeline::now()`
+ * answers "has anything changed since?".
+ */
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "std", derive(Atom))]
 pub struct Revision {
     // 0 is the special value const, which has an additional invariant: once the `timestamp` is 0,
-    // it must never increase
+    // it must never increase. `u64::MAX` is reserved for `UNINITIALIZED`.
     timestamp: u64,
 }
 
 impl Revision {
+    /**
+     * The revision of values that can never change. It is older than every other revision, so
+     * nothing that only depends on constants is ever stale.
+     */
+    pub const CONSTANT: Revision = Revision { timestamp: 0 };
+
+    /**
+     * The revision of something that hasn't been computed yet. It is newer than every other
+     * revision, so anything that depends on it is stale.
+     */
+    pub const UNINITIALIZED: Revision = Revision {
+        timestamp: u64::MAX,
+    };
+
     pub(crate) fn timestamp(n: u64) -> Revision {
         assert_ne!(
             n, 0,
-            "a timestamp must not be `0`. Use `Revision::constant()`"
+            "a timestamp must not be `0`. Use `Revision::CONSTANT`"
+        );
+        assert_ne!(
+            n,
+            u64::MAX,
+            "a timestamp must not be `u64::MAX`. Use `Revision::UNINITIALIZED`"
         );
         Revision { timestamp: n }
     }
 
-    pub(crate) fn start() -> Revision {
+    /**
+     * The revision a new timeline starts at, before anything was written.
+     */
+    pub fn initial() -> Revision {
         Revision { timestamp: 1 }
     }
 
-    pub(crate) fn constant() -> Revision {
-        Revision { timestamp: 0 }
+    pub fn is_constant(self) -> bool {
+        self == Revision::CONSTANT
     }
 
-    pub(crate) fn increment(self) -> Revision {
-        Revision {
-            timestamp: self.timestamp + 1,
+    /**
+     * The number of times the timeline advanced between the two revisions, in either order.
+     * Revisions of one timeline are numbered consecutively, so this counts the writes outside of
+     * a transaction and the transactions that wrote something. Renumbering or resetting the
+     * timeline makes the distance to older revisions meaningless.
+     */
+    pub fn distance(&self, other: &Revision) -> u64 {
+        self.timestamp.abs_diff(other.timestamp)
+    }
+
+    /**
+     * Whether this is the last revision before `UNINITIALIZED`, which can't be incremented.
+     */
+    pub(crate) fn is_last(self) -> bool {
+        self.timestamp == u64::MAX - 1
+    }
+
+    /**
+     * The next revision, which an `ExternalSource` can use to count its own changes. Panics
+     * instead of wrapping around if there are no revisions left, since a wrapped revision would
+     * look older than everything before it. Timelines renumber their revisions before they get
+     * there.
+     */
+    pub fn increment(self) -> Revision {
+        assert!(
+            !self.is_constant(),
+            "a constant revision can't be incremented"
+        );
+
+        match self.timestamp.checked_add(1) {
+            Some(timestamp) if timestamp != u64::MAX => Revision { timestamp },
+            _ => panic!(
+                "revision overflow: incremented {} past the last available revision",
+                self
+            ),
         }
     }
 
     pub(crate) fn atomic(self) -> AtomicRevision {
         AtomicRevision {
-            revision: Atomic::new(self),
+            timestamp: AtomicU64::new(self.timestamp),
+        }
+    }
+}
+
+// every access is `SeqCst`, so all threads agree on the order in which revisions advanced
+impl Display for Revision {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Revision::CONSTANT => write!(f, "constant"),
+            Revision::UNINITIALIZED => write!(f, "uninitialized"),
+            Revision { timestamp } => write!(f, "r{}", timestamp),
         }
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct AtomicRevision {
-    revision: Atomic<Revision>,
+    timestamp: AtomicU64,
 }
 
 impl AtomicRevision {
     pub(crate) fn update(&self, revision: Revision) {
-        self.revision.swap(revision, atomic::Ordering::SeqCst);
+        self.timestamp
+            .swap(revision.timestamp, atomic::Ordering::SeqCst);
     }
 
     pub(crate) fn get(&self) -> Revision {
-        self.revision.load(atomic::Ordering::SeqCst)
+        Revision {
+            timestamp: self.timestamp.load(atomic::Ordering::SeqCst),
+        }
+    }
+
+    pub(crate) fn increment(&self) -> Revision {
+        let mut current = self.get();
+
+        loop {
+            let next = current.increment();
+
+            match self.timestamp.compare_exchange_weak(
+                current.timestamp,
+                next.timestamp,
+                atomic::Ordering::SeqCst,
+                atomic::Ordering::SeqCst,
+            ) {
+                Ok(_) => return next,
+                Err(actual) => current = Revision { timestamp: actual },
+            }
+        }
     }
 }
 
@@ -62,10 +156,7 @@ impl PartialOrd for AtomicRevision {
 
 impl Ord for AtomicRevision {
     fn cmp(&self, other: &Self) -> Ordering {
-        let (left, right) = (
-            self.revision.load(atomic::Ordering::SeqCst),
-            other.revision.load(atomic::Ordering::SeqCst),
-        );
+        let (left, right) = (self.get(), other.get());
 
         left.cmp(&right)
     }
@@ -73,10 +164,7 @@ impl Ord for AtomicRevision {
 
 impl PartialEq for AtomicRevision {
     fn eq(&self, other: &Self) -> bool {
-        let (left, right) = (
-            self.revision.load(atomic::Ordering::SeqCst),
-            other.revision.load(atomic::Ordering::SeqCst),
-        );
+        let (left, right) = (self.get(), other.get());
 
         left.eq(&right)
     }
@@ -0,0 +1,1113 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    mem,
+    panic::Location,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+};
+
+use atomig::Atomic;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::{
+    inputs::{reactive::downstream, ReactiveTag, Tag},
+    reactive::{
+        fork,
+        scheduler::{in_dependency_order, Flush, Reaction, RunawayFlush},
+        ImmediateScheduler, Scheduler,
+    },
+};
+
+#[cfg(feature = "revision-timestamps")]
+use super::timestamps::{Clock, RevisionTimes};
+#[cfg(feature = "debug-graph")]
+use crate::reactive::graph::DebugGraph;
+use crate::reactive::{
+    bounds::ReadHook,
+    label::Label,
+    observer::TimelineObserver,
+    registry::{Collected, Entry, MemoryStats, Registry},
+    snapshot::{Snapshot, SnapshotPin},
+};
+#[cfg(feature = "history")]
+use crate::reactive::{
+    history::{History, Restore, RewindError},
+    replay::{Keys, Output, Replay, WriteLog},
+};
+
+use super::{
+    bumps::BumpListeners,
+    read_policy::{ReadPolicy, UntrackedRead},
+    revision::{AtomicRevision, Revision},
+    ComputationId, NodeKey, ValidationMode,
+};
+
+/**
+ * The number of times a flush goes back to a higher lane, unless
+ * `Timeline::set_max_preemptions` says otherwise.
+ */
+const DEFAULT_MAX_PREEMPTIONS: usize = 16;
+
+/**
+ * Every this many published revisions, a write sweeps `SWEEP_BUDGET` registry entries, so a
+ * timeline whose values are dropped forgets them even if `Timeline::collect` is never called.
+ */
+const SWEEP_EVERY: u64 = 64;
+const SWEEP_BUDGET: usize = 256;
+
+/**
+ * The part of a `Timeline` that is shared with every reactive value created from it. Reactive
+ * values hold onto the state so that writes can advance the timeline's revision without a
+ * reference to the `Timeline` itself.
+ */
+pub(crate) struct TimelineState {
+    this: Weak<TimelineState>,
+    revision: AtomicRevision,
+    transaction: Mutex<TransactionState>,
+    scheduler: Mutex<Arc<dyn Scheduler>>,
+    // reactions are owned by their handles, and unregister themselves when they are dropped.
+    // They are kept in registration order, which is the order they are flushed in.
+    reactions: Mutex<IndexMap<ComputationId, Weak<dyn Reaction>>>,
+    // reactions whose handles were detached, which live until the `Timeline` is dropped
+    detached: Mutex<Vec<Arc<dyn Reaction>>>,
+    flush: Mutex<FlushState>,
+    // every cell, derived and effect created from the timeline
+    registry: Mutex<Registry>,
+    // the revisions of live snapshots and of open transactions that wrote something, with the
+    // number of pins at each of them
+    pins: Mutex<BTreeMap<Revision, usize>>,
+    // whether computations record reverse edges, see `ValidationMode::Eager`. It is 0 in lazy
+    // mode, and otherwise counts the times the timeline became eager, so edges recorded before
+    // a lazy period can be told apart from the current ones.
+    eager: AtomicU64,
+    epochs: AtomicU64,
+    read_policy: Atomic<ReadPolicy>,
+    // whether writing a cell while a derived is computing panics
+    strict_writes: AtomicBool,
+    // how many frames deep the compute stack may be when one of the timeline's computations
+    // pushes another, or `usize::MAX` for no limit
+    max_compute_depth: AtomicUsize,
+    // how many times a flush goes back to a higher lane before it finishes the lower ones
+    max_preemptions: AtomicUsize,
+    // whether reading a derived's cached value checks that nothing it read moved past it
+    validate_on_read: AtomicBool,
+    // where `ReadPolicy::Warn` reports untracked reads, besides the observer
+    on_untracked_read: Mutex<Option<Arc<dyn ReadHook>>>,
+    // whether a `Pause` is open, checked before locking `pause`
+    paused: AtomicBool,
+    pause: Mutex<PauseState>,
+    // whether an observer is installed, checked before building any event for it
+    observed: AtomicBool,
+    observer: Mutex<Option<Arc<dyn TimelineObserver>>>,
+    bump_listeners: BumpListeners,
+    // the number of revisions published, which paces the sweeps of the registry
+    published: AtomicU64,
+    // the values that writes to recorded cells replaced, see `Timeline::rewind_to`
+    #[cfg(feature = "history")]
+    history: Mutex<History>,
+    // the cells and deriveds with stable keys, and the writes to them while recording, see
+    // `Timeline::start_recording`
+    #[cfg(feature = "history")]
+    keys: Mutex<Keys>,
+    // when each recent revision was published, see `Timeline::revision_time`
+    #[cfg(feature = "revision-timestamps")]
+    revision_times: RevisionTimes,
+}
+
+#[derive(Default)]
+struct PauseState {
+    depth: usize,
+    // the tags written while paused, whose dependents are marked dirty once on resume
+    written: IndexMap<NodeKey, Arc<Tag>>,
+    // whether a write while paused would have scheduled a flush
+    scheduled: bool,
+}
+
+#[derive(Debug, Default)]
+struct TransactionState {
+    depth: usize,
+    // the revision that writes inside the open transaction are recorded at. It is only allocated
+    // once something is written, and becomes the current revision when the outermost transaction
+    // closes.
+    pending: Option<Revision>,
+}
+
+#[derive(Debug, Default)]
+struct FlushState {
+    running: bool,
+    // set when a write happens while a flush is running, so the running flush makes another pass
+    // instead of recursing into a new one.
+    again: bool,
+    // set while an ordered flush runs, which makes its own further passes for the reactions
+    // that its writes invalidated, so those writes don't schedule another flush
+    ordered: bool,
+    // the number of flushes that have started, which numbers them for debounced effects
+    count: u64,
+}
+
+impl TimelineState {
+    pub(crate) fn new() -> Arc<TimelineState> {
+        Arc::new_cyclic(|this| TimelineState {
+            this: this.clone(),
+            revision: Revision::initial().atomic(),
+            transaction: Mutex::new(TransactionState::default()),
+            scheduler: Mutex::new(Arc::new(ImmediateScheduler)),
+            reactions: Mutex::new(IndexMap::new()),
+            detached: Mutex::new(vec![]),
+            flush: Mutex::new(FlushState::default()),
+            registry: Mutex::new(Registry::default()),
+            pins: Mutex::new(BTreeMap::new()),
+            eager: AtomicU64::new(0),
+            epochs: AtomicU64::new(0),
+            read_policy: Atomic::new(ReadPolicy::Allow),
+            strict_writes: AtomicBool::new(false),
+            max_compute_depth: AtomicUsize::new(usize::MAX),
+            max_preemptions: AtomicUsize::new(DEFAULT_MAX_PREEMPTIONS),
+            validate_on_read: AtomicBool::new(false),
+            on_untracked_read: Mutex::new(None),
+            paused: AtomicBool::new(false),
+            pause: Mutex::new(PauseState::default()),
+            observed: AtomicBool::new(false),
+            observer: Mutex::new(None),
+            bump_listeners: BumpListeners::default(),
+            published: AtomicU64::new(0),
+            #[cfg(feature = "history")]
+            history: Mutex::new(History::new()),
+            #[cfg(feature = "history")]
+            keys: Mutex::new(Keys::default()),
+            #[cfg(feature = "revision-timestamps")]
+            revision_times: RevisionTimes::new(Revision::initial()),
+        })
+    }
+
+    pub(crate) fn now(&self) -> Revision {
+        self.revision.get()
+    }
+
+    /**
+     * Allocate the revision for a write. Inside a transaction every write shares the same
+     * pending revision, and the current revision doesn't advance until the transaction closes.
+     */
+    pub(crate) fn bump(&self) -> Revision {
+        let mut transaction = self.transaction.lock();
+
+        if transaction.depth == 0 {
+            let revision = self.next_revision();
+            self.revision.update(revision);
+            drop(transaction);
+
+            self.published(revision);
+            revision
+        } else {
+            self.pending_revision(&mut transaction)
+        }
+    }
+
+    /**
+     * The revision that writes in the open transaction are recorded at, allocating it for the
+     * first write. Until the transaction commits, a snapshot can still be taken at the current
+     * revision, so the current revision stays pinned and cells keep the values the transaction
+     * overwrites.
+     */
+    fn pending_revision(&self, transaction: &mut TransactionState) -> Revision {
+        if let Some(pending) = transaction.pending {
+            return pending;
+        }
+
+        let pending = self.next_revision();
+        self.pin(self.now());
+        transaction.pending = Some(pending);
+        pending
+    }
+
+    /**
+     * The revision after the current one. If the timeline ran out of revisions, every live
+     * revision is renumbered first. Must be called with the transaction lock held, so that no
+     * write can happen while the revisions are renumbered.
+     */
+    fn next_revision(&self) -> Revision {
+        if self.now().is_last() {
+            self.renumber();
+        }
+
+        self.now().increment()
+    }
+
+    /**
+     * Renumber every revision stored by the timeline's live values, starting over from
+     * `Revision::initial()`. Revisions keep their order, and revisions that were equal stay
+     * equal, so every comparison between them gives the same answer as before.
+     *
+     * Renumbering assumes that no computation is running on another thread, since a
+     * computation that started before the renumbering would store a revision from the old
+     * numbering. Revisions that were handed out before, like the ones in a `Snapshot` or an
+     * `Invalidation`, keep the old numbering.
+     */
+    fn renumber(&self) {
+        let mut registry = self.registry.lock();
+        let entries = registry.entries();
+
+        let mut revisions = vec![self.now()];
+
+        for entry in entries {
+            entry.revisions(&mut revisions);
+        }
+
+        #[cfg(feature = "history")]
+        {
+            self.history.lock().revisions(&mut revisions);
+            self.keys.lock().revisions(&mut revisions);
+        }
+
+        #[cfg(feature = "revision-timestamps")]
+        self.revision_times.revisions(&mut revisions);
+
+        revisions
+            .retain(|revision| !revision.is_constant() && *revision != Revision::UNINITIALIZED);
+        revisions.sort();
+        revisions.dedup();
+
+        let renumber = |revision: Revision| {
+            if revision.is_constant() || revision == Revision::UNINITIALIZED {
+                return revision;
+            }
+
+            match revisions.binary_search(&revision) {
+                Ok(index) | Err(index) => Revision::timestamp(index as u64 + 1),
+            }
+        };
+
+        for entry in entries {
+            entry.renumber(&renumber);
+        }
+
+        #[cfg(feature = "history")]
+        {
+            self.history.lock().renumber(&renumber);
+            self.keys.lock().renumber(&renumber);
+        }
+
+        #[cfg(feature = "revision-timestamps")]
+        self.revision_times.renumber(&renumber);
+
+        self.revision.update(renumber(self.now()));
+    }
+
+    /**
+     * Return to the initial revision, as if the timeline had just been created. Live cells and
+     * tags move back to the initial revision, deriveds recompute the next time they are read,
+     * and every effect and subscription is disposed.
+     */
+    pub(crate) fn reset(&self) {
+        let reactions: Vec<Arc<dyn Reaction>> = {
+            let transaction = self.transaction.lock();
+            assert!(
+                transaction.depth == 0,
+                "a timeline can't be reset inside a transaction"
+            );
+
+            for entry in self.registry.lock().entries() {
+                entry.reset();
+            }
+
+            self.revision.update(Revision::initial());
+
+            #[cfg(feature = "history")]
+            {
+                self.history.lock().reset();
+                self.keys.lock().reset();
+            }
+
+            #[cfg(feature = "revision-timestamps")]
+            self.revision_times.reset(Revision::initial());
+
+            let registered = std::mem::take(&mut *self.reactions.lock());
+            let detached = std::mem::take(&mut *self.detached.lock());
+
+            registered
+                .into_iter()
+                .filter_map(|(_, reaction)| reaction.upgrade())
+                .chain(detached)
+                .collect()
+        };
+
+        // disposing takes each reaction's own lock, so it happens outside of the timeline's
+        for reaction in &reactions {
+            reaction.dispose();
+        }
+    }
+
+    /**
+     * A tag at the current revision, registered so its revision is renumbered along with the
+     * rest of the timeline.
+     */
+    pub(crate) fn tag(&self, label: Option<Arc<Label>>) -> Arc<Tag> {
+        let revision = self.now().atomic();
+        let tag = match label {
+            Some(label) => Tag::labeled(revision, label),
+            None => Tag::arc(revision),
+        };
+
+        self.register_value(Entry::Tag(Arc::downgrade(&tag)));
+        tag
+    }
+
+    /**
+     * Move the current revision forward to `timestamp`, so tests can exercise what happens
+     * when the timeline runs out of revisions.
+     */
+    #[cfg(any(feature = "testing", fuzzing))]
+    pub(crate) fn fast_forward(&self, timestamp: u64) {
+        let _transaction = self.transaction.lock();
+        let revision = Revision::timestamp(timestamp);
+
+        assert!(
+            revision >= self.now(),
+            "can't fast forward from {} back to {}",
+            self.now(),
+            revision
+        );
+
+        self.revision.update(revision);
+    }
+
+    /**
+     * Perform a write: allocate its revision, record it with `update`, and then let the
+     * scheduler know that effects may be stale. Inside a transaction the scheduler is only told
+     * once the transaction commits.
+     */
+    pub(crate) fn write(&self, update: impl FnOnce(Revision)) {
+        self.write_if(|| Some(()), |(), revision| update(revision));
+    }
+
+    /**
+     * Perform a write like `write` if `prepare` returns what `update` should record, and do
+     * nothing if it returns `None`. `prepare` runs under the same lock as `update`, before the
+     * write's revision is allocated, so what it decided still holds when the write is recorded.
+     */
+    pub(crate) fn write_if<W>(
+        &self,
+        prepare: impl FnOnce() -> Option<W>,
+        update: impl FnOnce(W, Revision),
+    ) {
+        if fork::current(self).is_some() {
+            panic!(
+                "the timeline was written inside `Forked::run`. Write cells in a fork with \
+                 `Forked::set`."
+            );
+        }
+
+        let committed = {
+            let mut transaction = self.transaction.lock();
+
+            let write = match prepare() {
+                Some(write) => write,
+                None => return,
+            };
+
+            if transaction.depth == 0 {
+                // record the write before publishing its revision, so that a reader that
+                // observes the new revision also observes the write
+                let revision = self.next_revision();
+                update(write, revision);
+                self.revision.update(revision);
+                Some(revision)
+            } else {
+                update(write, self.pending_revision(&mut transaction));
+                None
+            }
+        };
+
+        if let Some(revision) = committed {
+            self.published(revision);
+            self.schedule();
+        }
+    }
+
+    pub(crate) fn pending(&self) -> Option<Revision> {
+        self.transaction.lock().pending
+    }
+
+    pub(crate) fn begin_transaction(&self) {
+        self.transaction.lock().depth += 1;
+    }
+
+    pub(crate) fn end_transaction(&self) {
+        let committed = {
+            let mut transaction = self.transaction.lock();
+            transaction.depth -= 1;
+
+            match transaction.pending.take() {
+                Some(pending) if transaction.depth == 0 => {
+                    self.unpin(self.now());
+                    self.revision.update(pending);
+                    Some(pending)
+                }
+                pending => {
+                    transaction.pending = pending;
+                    None
+                }
+            }
+        };
+
+        // the transaction's writes are reported together, as the single revision they landed at
+        if let Some(revision) = committed {
+            self.published(revision);
+            self.schedule();
+        }
+    }
+
+    /**
+     * Tell the listeners registered with `Timeline::on_bump` that `revision` became the current
+     * revision, record when it did, and sweep part of the registry every `SWEEP_EVERY`
+     * revisions.
+     */
+    fn published(&self, revision: Revision) {
+        #[cfg(feature = "revision-timestamps")]
+        self.revision_times.stamp(revision);
+
+        if self.published.fetch_add(1, Ordering::Relaxed) % SWEEP_EVERY == SWEEP_EVERY - 1 {
+            self.registry.lock().sweep(SWEEP_BUDGET);
+        }
+
+        self.bump_listeners.bumped(revision);
+    }
+
+    pub(crate) fn validation_mode(&self) -> ValidationMode {
+        match self.eager.load(Ordering::SeqCst) {
+            0 => ValidationMode::Lazy,
+            _ => ValidationMode::Eager,
+        }
+    }
+
+    pub(crate) fn begin_pause(&self) {
+        self.pause.lock().depth += 1;
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /**
+     * Close a pause. Closing the outermost one marks the dependents of every tag written while
+     * paused dirty, once per tag, and then schedules a single flush if anything was written.
+     */
+    pub(crate) fn end_pause(&self) {
+        let scheduled = loop {
+            let written = {
+                let mut pause = self.pause.lock();
+
+                if pause.depth > 1 {
+                    pause.depth -= 1;
+                    return;
+                }
+
+                if pause.written.is_empty() {
+                    pause.depth = 0;
+                    self.paused.store(false, Ordering::SeqCst);
+                    break mem::take(&mut pause.scheduled);
+                }
+
+                mem::take(&mut pause.written)
+            };
+
+            // still paused, so writes made by the marking are queued for the next round
+            for tag in written.values() {
+                tag.dependents.mark_dirty();
+            }
+        };
+
+        if scheduled {
+            self.schedule();
+        }
+    }
+
+    /**
+     * Queue `tag` to have its dependents marked dirty when the timeline resumes, if it is
+     * paused. Returns whether the tag was queued.
+     */
+    pub(crate) fn defer_marking(&self, tag: &Arc<Tag>) -> bool {
+        if !self.paused.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let mut pause = self.pause.lock();
+
+        if pause.depth == 0 {
+            return false;
+        }
+
+        pause
+            .written
+            .entry(tag.slot.key())
+            .or_insert_with(|| tag.clone());
+        true
+    }
+
+    pub(crate) fn set_validation_mode(&self, mode: ValidationMode) {
+        let _transaction = self.transaction.lock();
+        let current = self.eager.load(Ordering::SeqCst);
+
+        match mode {
+            ValidationMode::Lazy => self.eager.store(0, Ordering::SeqCst),
+            ValidationMode::Eager if current == 0 => {
+                let epoch = self.epochs.fetch_add(1, Ordering::SeqCst) + 1;
+                self.eager.store(epoch, Ordering::SeqCst);
+            }
+            ValidationMode::Eager => {}
+        }
+    }
+
+    pub(crate) fn read_policy(&self) -> ReadPolicy {
+        self.read_policy.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_read_policy(&self, policy: ReadPolicy) {
+        self.read_policy.store(policy, Ordering::SeqCst);
+    }
+
+    pub(crate) fn strict_writes(&self) -> bool {
+        self.strict_writes.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_strict_writes(&self, strict: bool) {
+        self.strict_writes.store(strict, Ordering::SeqCst);
+    }
+
+    pub(crate) fn max_compute_depth(&self) -> Option<usize> {
+        match self.max_compute_depth.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            depth => Some(depth),
+        }
+    }
+
+    pub(crate) fn set_max_compute_depth(&self, depth: Option<usize>) {
+        self.max_compute_depth
+            .store(depth.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    pub(crate) fn max_preemptions(&self) -> usize {
+        self.max_preemptions.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_max_preemptions(&self, preemptions: usize) {
+        self.max_preemptions.store(preemptions, Ordering::Relaxed);
+    }
+
+    pub(crate) fn validates_on_read(&self) -> bool {
+        self.validate_on_read.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_validate_on_read(&self, validate: bool) {
+        self.validate_on_read.store(validate, Ordering::SeqCst);
+    }
+
+    pub(crate) fn on_untracked_read(&self, hook: Arc<dyn ReadHook>) {
+        *self.on_untracked_read.lock() = Some(hook);
+    }
+
+    pub(crate) fn set_observer(&self, observer: Option<Arc<dyn TimelineObserver>>) {
+        let mut current = self.observer.lock();
+        self.observed.store(observer.is_some(), Ordering::SeqCst);
+        *current = observer;
+    }
+
+    pub(crate) fn bump_listeners(&self) -> &BumpListeners {
+        &self.bump_listeners
+    }
+
+    /**
+     * Record that `tag` was written at `revision`, for the listeners registered with
+     * `Timeline::on_bump` and the times of recent revisions.
+     */
+    pub(crate) fn caused_by(&self, tag: &Arc<Tag>, revision: Revision) {
+        let tag = ReactiveTag::Tag(tag.clone());
+
+        #[cfg(feature = "revision-timestamps")]
+        self.revision_times.written(tag.key(), revision, self.now());
+
+        self.bump_listeners.caused_by(tag, revision, self.now());
+    }
+
+    #[cfg(feature = "revision-timestamps")]
+    pub(crate) fn revision_times(&self) -> &RevisionTimes {
+        &self.revision_times
+    }
+
+    #[cfg(feature = "revision-timestamps")]
+    pub(crate) fn set_clock(&self, clock: Box<dyn Clock>) {
+        self.revision_times.set_clock(clock);
+    }
+
+    /**
+     * Whether an observer is installed. Work that is only done for the observer, like timing a
+     * computation, checks this first.
+     */
+    pub(crate) fn is_observed(&self) -> bool {
+        self.observed.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Report an event to the observer installed with `Timeline::set_observer`. Without one, this
+     * is a single branch, and `event` never runs, so it can build the event's data.
+     */
+    #[inline]
+    pub(crate) fn observe(&self, event: impl FnOnce(&dyn TimelineObserver)) {
+        if !self.is_observed() {
+            return;
+        }
+
+        // like the read hook, the observer is free to replace itself
+        let observer = self.observer.lock().clone();
+
+        if let Some(observer) = observer {
+            event(&*observer);
+        }
+    }
+
+    /**
+     * Apply the read policy to a read of `label` that happened while the `ComputeStack` was
+     * empty. Fails if the policy is `ReadPolicy::Deny`.
+     */
+    pub(crate) fn untracked_read(
+        &self,
+        label: &Label,
+        location: &'static Location<'static>,
+    ) -> Result<(), UntrackedRead> {
+        let read = UntrackedRead::new(label.to_string(), location);
+
+        match self.read_policy() {
+            ReadPolicy::Allow => Ok(()),
+            ReadPolicy::Warn => {
+                self.warn(&read);
+                Ok(())
+            }
+            ReadPolicy::Deny => Err(read),
+        }
+    }
+
+    /**
+     * Report an untracked read to the hook installed with `Timeline::on_untracked_read` and to
+     * the timeline's observer. Without either, the read isn't reported anywhere.
+     */
+    pub(crate) fn warn(&self, read: &UntrackedRead) {
+        // the hook is free to read cells or replace itself, so don't hold the lock while it runs
+        let hook = self.on_untracked_read.lock().clone();
+
+        if let Some(hook) = hook {
+            hook(read);
+        }
+
+        self.observe(|observer| observer.untracked_read(read));
+    }
+
+    /**
+     * Which eager period the timeline is in, or `None` if it is lazy. Reverse edges are only
+     * complete if they were all recorded in the current period. While the timeline is paused,
+     * writes don't mark anything dirty, so no reverse edges are complete and it reports `None`.
+     */
+    pub(crate) fn eager_epoch(&self) -> Option<u64> {
+        if self.paused.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        match self.eager.load(Ordering::SeqCst) {
+            0 => None,
+            epoch => Some(epoch),
+        }
+    }
+
+    pub(crate) fn set_scheduler(&self, scheduler: Arc<dyn Scheduler>) {
+        *self.scheduler.lock() = scheduler;
+    }
+
+    pub(crate) fn register(&self, id: ComputationId, reaction: Weak<dyn Reaction>) {
+        self.reactions.lock().insert(id, reaction);
+    }
+
+    pub(crate) fn unregister(&self, id: ComputationId) {
+        self.reactions.lock().shift_remove(&id);
+    }
+
+    pub(crate) fn reaction_count(&self) -> usize {
+        self.reactions.lock().len()
+    }
+
+    /**
+     * Keep a reaction alive without a handle.
+     */
+    pub(crate) fn detach(&self, reaction: Arc<dyn Reaction>) {
+        self.detached.lock().push(reaction);
+    }
+
+    /**
+     * Drop the detached reactions. Detached reactions hold onto the state, so they would
+     * otherwise keep it alive forever.
+     */
+    pub(crate) fn release(&self) {
+        let detached = std::mem::take(&mut *self.detached.lock());
+        drop(detached);
+    }
+
+    /**
+     * Record a value in the registry. Entries are weak, so registering doesn't keep the value
+     * alive.
+     */
+    pub(crate) fn register_value(&self, entry: Entry) {
+        self.registry.lock().register(entry);
+    }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        // pinning under the transaction lock means that every write either happened before the
+        // snapshot, or sees the pin and keeps the value it overwrites
+        let revision = {
+            let _transaction = self.transaction.lock();
+            let revision = self.now();
+            self.pin(revision);
+            revision
+        };
+
+        let pin = SnapshotPin::new(self.this.clone(), revision);
+        let mut registry = self.registry.lock();
+        Snapshot::new(pin, registry.entries())
+    }
+
+    fn pin(&self, revision: Revision) {
+        *self.pins.lock().entry(revision).or_insert(0) += 1;
+    }
+
+    pub(crate) fn unpin(&self, revision: Revision) {
+        let mut pins = self.pins.lock();
+
+        if let Some(count) = pins.get_mut(&revision) {
+            *count -= 1;
+
+            if *count == 0 {
+                pins.remove(&revision);
+            }
+        }
+    }
+
+    /**
+     * Whether a revision in `from..to` is pinned, so a snapshot could read a value that was
+     * current during those revisions.
+     */
+    pub(crate) fn is_pinned(&self, from: Revision, to: Revision) -> bool {
+        from < to && self.pins.lock().range(from..to).next().is_some()
+    }
+
+    pub(crate) fn memory_stats(&self) -> MemoryStats {
+        self.registry.lock().memory_stats()
+    }
+
+    pub(crate) fn collect(&self) -> Collected {
+        self.registry.lock().collect()
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn record_history(&self, capacity: usize) {
+        let _transaction = self.transaction.lock();
+        self.history.lock().set_capacity(capacity, self.now());
+    }
+
+    /**
+     * Remember `value`, which a write at `revision` replaced, if the history is recorded.
+     */
+    #[cfg(feature = "history")]
+    pub(crate) fn record(&self, revision: Revision, value: Arc<dyn Restore>) {
+        self.history.lock().record(revision, value);
+    }
+
+    /**
+     * Restore every recorded cell to its value at `revision`, with a single write.
+     */
+    #[cfg(feature = "history")]
+    pub(crate) fn rewind_to(&self, revision: Revision) -> Result<(), RewindError> {
+        let restored = self.history.lock().since(revision)?;
+
+        if !restored.is_empty() {
+            // restoring a value records the value it replaces, so the history lock isn't held
+            self.write(|revision| {
+                for value in restored {
+                    value.restore(revision);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn key_cell(&self, key: Arc<str>, cell: Arc<dyn Replay>) {
+        self.keys.lock().key_cell(key, cell);
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn key_output(&self, key: Arc<str>, output: Arc<dyn Output>) {
+        self.keys.lock().key_output(key, output);
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn keyed_cell(&self, key: &str) -> Option<Arc<dyn Replay>> {
+        self.keys.lock().cell(key)
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn keyed_cells(&self) -> Vec<(Arc<str>, Arc<dyn Replay>)> {
+        self.keys.lock().cells()
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn keyed_output(&self, key: &str) -> Option<Arc<dyn Output>> {
+        self.keys.lock().output(key)
+    }
+
+    /**
+     * Log the write of the keyed cell `key` at `revision`, if the timeline is recording.
+     * `value` serializes the written value, and is only called while recording.
+     */
+    #[cfg(feature = "history")]
+    pub(crate) fn record_write(
+        &self,
+        key: &Arc<str>,
+        value: impl FnOnce() -> String,
+        revision: Revision,
+    ) {
+        self.keys.lock().record(key, value, revision);
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn start_recording(&self) {
+        let _transaction = self.transaction.lock();
+        self.keys.lock().start_recording();
+    }
+
+    /**
+     * Stop recording, and read every keyed derived for the log. The deriveds are read without
+     * holding any of the timeline's locks, since reading one may recompute it.
+     */
+    #[cfg(feature = "history")]
+    pub(crate) fn stop_recording(&self) -> WriteLog {
+        let (writes, outputs) = {
+            let _transaction = self.transaction.lock();
+            let mut keys = self.keys.lock();
+            (keys.stop_recording(), keys.outputs())
+        };
+
+        let outputs = outputs
+            .into_iter()
+            .filter_map(|(key, output)| Some((key, output.output()?)))
+            .collect();
+
+        WriteLog::new(writes, outputs)
+    }
+
+    /**
+     * Bring every derived that read one of `roots`, directly or through other deriveds, up to
+     * date, each after the deriveds it read among them.
+     */
+    pub(crate) fn flush_dirty(&self, roots: &[crate::reactive::Tag]) {
+        assert!(
+            self.eager.load(Ordering::SeqCst) != 0,
+            "`Timeline::flush_dirty` follows the edges from values to the deriveds that read them, \
+             which are only recorded in `ValidationMode::Eager`"
+        );
+
+        let mut dependents = vec![];
+
+        for root in roots {
+            if let Some(timeline) = root.timeline() {
+                assert!(
+                    std::ptr::eq(&**timeline, self),
+                    "{} belongs to another timeline",
+                    root.label()
+                );
+
+                dependents.extend(root.reactive_tag().dependents());
+            }
+        }
+
+        for derived in downstream(dependents) {
+            derived.bring_up_to_date();
+        }
+    }
+
+    #[cfg(feature = "debug-graph")]
+    pub(crate) fn debug_graph(&self) -> DebugGraph {
+        DebugGraph::new(self.registry.lock().entries())
+    }
+
+    fn schedule(&self) {
+        if self.reactions.lock().is_empty() || self.flush.lock().ordered {
+            return;
+        }
+
+        if self.paused.load(Ordering::SeqCst) {
+            let mut pause = self.pause.lock();
+
+            if pause.depth > 0 {
+                pause.scheduled = true;
+                return;
+            }
+        }
+
+        // the scheduler may flush synchronously, and effects are free to replace the scheduler,
+        // so don't hold the lock while it runs.
+        let scheduler = self.scheduler.lock().clone();
+        self.observe(|observer| observer.flush_scheduled());
+        scheduler.schedule(Flush::new(self.this.clone()));
+    }
+
+    /**
+     * Run every stale reaction, in registration order. Writes made by the reactions are handled
+     * by further passes of this flush rather than by nested flushes.
+     */
+    pub(crate) fn flush(&self) {
+        // without a limit on the passes, the flush can't run away
+        let _ = self.flush_passes(false, usize::MAX);
+    }
+
+    /**
+     * Run every stale reaction like `flush`, but in dependency order, and give up once the
+     * reactions are still stale after `max_passes` passes.
+     */
+    pub(crate) fn flush_ordered(&self, max_passes: usize) -> Result<(), RunawayFlush> {
+        self.flush_passes(true, max_passes)
+    }
+
+    fn flush_passes(&self, ordered: bool, max_passes: usize) -> Result<(), RunawayFlush> {
+        let count = {
+            let mut flush = self.flush.lock();
+
+            if flush.running {
+                flush.again = true;
+                return Ok(());
+            }
+
+            flush.running = true;
+            flush.ordered = ordered;
+            flush.count += 1;
+            flush.count
+        };
+
+        // ends the flush if a reaction panics, so the later writes still flush. The flush ends
+        // itself on every other way out, under the lock that a new flush could start under.
+        struct Unwinding<'a>(&'a Mutex<FlushState>);
+
+        impl Drop for Unwinding<'_> {
+            fn drop(&mut self) {
+                let mut flush = self.0.lock();
+                flush.running = false;
+                flush.again = false;
+                flush.ordered = false;
+            }
+        }
+
+        let unwinding = Unwinding(&self.flush);
+
+        let mut passes = 0;
+        let mut preemptions = 0;
+        let max_preemptions = self.max_preemptions();
+
+        let waiting = loop {
+            let mut reactions: Vec<Arc<dyn Reaction>> = {
+                let mut registered = self.reactions.lock();
+
+                // reactions that were dropped without unregistering, like detached effects that
+                // were released, are compacted away here
+                registered.retain(|_, reaction| reaction.strong_count() > 0);
+                registered.values().filter_map(Weak::upgrade).collect()
+            };
+
+            if ordered {
+                reactions = in_dependency_order(reactions);
+            }
+
+            // lane by lane, keeping the order within each lane
+            reactions.sort_by_key(|reaction| reaction.priority());
+
+            let mut waiting = false;
+            let mut settled = Vec::with_capacity(reactions.len());
+            passes += 1;
+
+            for (index, reaction) in reactions.iter().enumerate() {
+                let waited = reaction.flush(count);
+                waiting |= waited;
+                settled.push(!waited);
+
+                // an effect that invalidated one in a higher lane, which comes before it, makes
+                // way for that one. Effects that are waiting for a later flush are left alone.
+                while preemptions < max_preemptions {
+                    let priority = reaction.priority();
+                    let preempting = reactions[..index]
+                        .iter()
+                        .enumerate()
+                        .take_while(|(_, higher)| higher.priority() < priority)
+                        .find(|(higher, reaction)| settled[*higher] && reaction.is_stale());
+
+                    let (higher, preempting) = match preempting {
+                        Some(preempting) => preempting,
+                        None => break,
+                    };
+
+                    preemptions += 1;
+                    let waited = preempting.flush(count);
+                    waiting |= waited;
+                    settled[higher] = !waited;
+                }
+            }
+
+            // the writes of an ordered flush don't schedule another flush, so it looks for the
+            // reactions they invalidated itself
+            let stale = match ordered {
+                true => reactions
+                    .iter()
+                    .zip(settled)
+                    .find(|(reaction, settled)| *settled && reaction.is_stale())
+                    .map(|(reaction, _)| reaction.clone()),
+                false => None,
+            };
+
+            let mut flush = self.flush.lock();
+
+            if !flush.again && stale.is_none() {
+                flush.running = false;
+                flush.ordered = false;
+                mem::forget(unwinding);
+                break waiting;
+            }
+
+            flush.again = false;
+
+            if let (Some(stale), true) = (&stale, passes == max_passes) {
+                flush.running = false;
+                flush.ordered = false;
+                drop(flush);
+                mem::forget(unwinding);
+
+                // the reactions that are still stale are left for another flush
+                self.schedule();
+                return Err(RunawayFlush::new(stale.label(), passes));
+            }
+        };
+
+        // a debounced effect runs once its dependencies were left alone for enough flushes, so
+        // flushes have to keep coming while it waits
+        if waiting {
+            self.schedule();
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for TimelineState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimelineState")
+            .field("revision", &self.now())
+            .field("transaction", &*self.transaction.lock())
+            .field("reactions", &self.reactions.lock().len())
+            .finish()
+    }
+}
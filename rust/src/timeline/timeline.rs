@@ -1,21 +1,41 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, future::Future, hash::Hash, sync::Arc};
 
 use derive_new::new;
 
 use crate::{
-    inputs::{DerivedTag, DynamicComputation, ReactiveCell, ReactiveDerived, Tag},
+    inputs::{DerivedTag, DynamicComputation, ReactiveCell, ReactiveDerived},
     outputs::PrimitiveOutput,
+    reactive::{
+        CachedMethods, Cell, Collected, Derived, DerivedAsync, Effect, ExternalSource, ExternalTag,
+        ExternalValue, Forked, Handles, MaybeSend, MaybeSync, Memo, MemoryStats, MultiDerived,
+        Outputs, Scheduler, Snapshot, Tag, TimelineObserver, TrackedMap, TrackedVec,
+        WritableDerived,
+    },
 };
 
+#[cfg(feature = "debug-graph")]
+use crate::reactive::DebugGraph;
+#[cfg(feature = "history")]
+use crate::reactive::{RewindError, SerializedState, Skipped, WriteLog};
+#[cfg(feature = "revision-timestamps")]
+use std::time::Instant;
+
+#[cfg(feature = "revision-timestamps")]
+use super::timestamps::Clock;
+
 use super::{
-    inputs::Inputs, CellId, DerivedId, EvaluationContext, Revision, TypedInputId,
-    TypedInputIdWithKind,
+    bumps::BumpHandle,
+    compute_stack::{ComputeStack, TagId},
+    inputs::Inputs,
+    state::TimelineState,
+    CellId, DerivedId, EvaluationContext, ReadPolicy, Revision, TypedInputId, TypedInputIdWithKind,
+    UntrackedRead, ValidationMode,
 };
 
 #[derive(Debug, new)]
 pub struct Timeline {
-    #[new(value = "Revision::start()")]
-    revision: Revision,
+    #[new(value = "TimelineState::new()")]
+    state: Arc<TimelineState>,
     #[new(default)]
     inputs: Inputs,
 }
@@ -26,7 +46,1174 @@ impl Default for Timeline {
     }
 }
 
+impl Drop for Timeline {
+    fn drop(&mut self) {
+        self.state.release();
+    }
+}
+
+thread_local! {
+    static DEFAULT: Timeline = Timeline::new();
+}
+
 impl Timeline {
+    pub(crate) fn state(&self) -> &Arc<TimelineState> {
+        &self.state
+    }
+
+    /**
+     * Run `f` with the current thread's default timeline, which is created the first time it is
+     * used and lives until the thread exits. A program with a single reactive world can create
+     * all of its values from the default timeline instead of passing one around.
+     *
+     * Timelines are independent of each other: writes on one never invalidate computations on
+     * another, so values that must be isolated, like the state of separate documents, belong on
+     * timelines of their own. Debug builds panic if a computation reads a value that was created
+     * from a different timeline than its own.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let count = Timeline::with_default(|timeline| timeline.cell(1));
+     * let doubled = Timeline::with_default(|timeline| {
+     *     let count = count.clone();
+     *     timeline.derived(move || count.get() * 2)
+     * });
+     *
+     * count.set(2);
+     * assert_eq!(doubled.get(), 4);
+     * ```
+     */
+    pub fn with_default<R>(f: impl FnOnce(&Timeline) -> R) -> R {
+        DEFAULT.with(f)
+    }
+
+    /**
+     * The timeline's current revision. It advances with every write outside of a transaction,
+     * and once when a transaction that wrote something commits.
+     */
+    pub fn now(&self) -> Revision {
+        self.state.now()
+    }
+
+    /**
+     * How many times the timeline advanced since `revision`, like `now().distance(&revision)`.
+     */
+    pub fn bumps_since(&self, revision: Revision) -> u64 {
+        self.now().distance(&revision)
+    }
+
+    /**
+     * Return the timeline to its initial revision and dispose every effect and subscription, so
+     * that tests sharing a timeline each start from the same state. Cells, vecs and maps that
+     * are still held move back to the initial revision and keep their values, and deriveds
+     * recompute the next time they are read.
+     *
+     * Snapshots taken before the reset no longer read consistent values. Panics inside a
+     * transaction.
+     */
+    pub fn reset(&self) {
+        self.state.reset();
+    }
+
+    /**
+     * Move the timeline's revision forward to `timestamp`, for tests that exercise a timeline
+     * that is about to run out of revisions. Moving backwards panics. Available with the
+     * `testing` feature.
+     */
+    #[cfg(any(feature = "testing", fuzzing))]
+    pub fn fast_forward(&self, timestamp: u64) {
+        self.state.fast_forward(timestamp);
+    }
+
+    /**
+     * Create a new cell whose writes advance this timeline's revision.
+     */
+    pub fn cell<T>(&self, value: T) -> Cell<T> {
+        Cell::new(self.state.clone(), value)
+    }
+
+    /**
+     * Create a cell for a value that never changes, like `Cell::constant`. Reading it records
+     * nothing, so a derived that only reads constants never recomputes. Writing the cell panics.
+     */
+    pub fn constant<T>(&self, value: T) -> Cell<T> {
+        Cell::constant(value)
+    }
+
+    /**
+     * Create a tag for a dependency on something outside of the timeline, which computations
+     * depend on with `ExternalTag::track` and which is invalidated with `ExternalTag::bump`.
+     */
+    pub fn external_tag(&self) -> ExternalTag {
+        ExternalTag::new(self.state.clone())
+    }
+
+    /**
+     * Bring a source that keeps its own revision into the timeline. Reads through the returned
+     * handle are tracked like reads of a cell, and deriveds that read it poll the source's
+     * revision whenever they are validated, instead of waiting for a write.
+     */
+    pub fn register_external<S: ExternalSource>(&self, source: S) -> ExternalValue<S> {
+        ExternalValue::new(self.state.clone(), source)
+    }
+
+    /**
+     * Create a vector that tracks reads of each index separately from reads of its length.
+     */
+    pub fn vec<T>(&self, values: Vec<T>) -> TrackedVec<T> {
+        TrackedVec::new(self.state.clone(), values)
+    }
+
+    /**
+     * Create a map that tracks reads of each key, present or not, separately from reads of its
+     * set of keys.
+     */
+    pub fn map<K: Hash + Eq + Clone, V>(
+        &self,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> TrackedMap<K, V> {
+        TrackedMap::new(self.state.clone(), entries)
+    }
+
+    /**
+     * Create a lazily evaluated computation over reactive values created from this timeline.
+     */
+    pub fn derived<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::new(self.state.clone(), computation)
+    }
+
+    /**
+     * Like `derived`, but the handles the computation reads are cloned for it. `handles` is a
+     * reference to a cell, derived or other `ReactiveValue`, or a tuple of up to six of them,
+     * and the computation gets a reference to the clones.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let first = timeline.cell("Yehuda");
+     * let last = timeline.cell("Katz");
+     * let greeting = timeline.cell("hello").readonly();
+     *
+     * let full = timeline.derived_with((&first, &last), |(first, last)| {
+     *     format!("{} {}", first.get(), last.get())
+     * });
+     * let line = timeline.derived_with((&greeting, &full), |(greeting, full)| {
+     *     format!("{}, {}", greeting.get(), full.get())
+     * });
+     *
+     * last.set("Doe");
+     * assert_eq!(line.get(), "hello, Yehuda Doe");
+     * ```
+     *
+     * Values that aren't reactive can't be captured, since reading them wouldn't be tracked:
+     *
+     * ```compile_fail
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(1);
+     * let step = 2;
+     *
+     * let next = timeline.derived_with((&count, &step), |(count, step)| count.get() + step);
+     * ```
+     */
+    pub fn derived_with<H: Handles, T: MaybeSend + 'static>(
+        &self,
+        handles: H,
+        computation: impl Fn(&H::Owned) -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        let handles = handles.to_owned();
+        Derived::new(self.state.clone(), move || computation(&handles))
+    }
+
+    /**
+     * Create a derived that is computed right away and recomputed whenever the timeline's
+     * scheduler flushes after one of its dependencies changed, like an effect, instead of the
+     * next time it is read. Reading it only has to check that it is still up to date, so an
+     * eager derived suits a value that is read much more often than it changes. Like a lazy
+     * derived, every recomputation tracks its dependencies anew.
+     *
+     * ```
+     * use std::sync::{
+     *     atomic::{AtomicUsize, Ordering},
+     *     Arc,
+     * };
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let prices = timeline.vec(vec![3, 4]);
+     * let runs = Arc::new(AtomicUsize::new(0));
+     *
+     * let total = {
+     *     let (prices, runs) = (prices.clone(), runs.clone());
+     *     timeline.eager_derived(move || {
+     *         runs.fetch_add(1, Ordering::SeqCst);
+     *         prices.iter().sum::<i32>()
+     *     })
+     * };
+     * assert_eq!(runs.load(Ordering::SeqCst), 1);
+     *
+     * prices.push(5);
+     * assert_eq!(runs.load(Ordering::SeqCst), 2, "recomputed by the flush, before any read");
+     *
+     * assert_eq!(total.get(), 12);
+     * assert_eq!(runs.load(Ordering::SeqCst), 2);
+     * ```
+     *
+     * The derived stays eager until every handle to it is dropped.
+     */
+    pub fn eager_derived<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::eager(self.state.clone(), computation)
+    }
+
+    /**
+     * Create a derived for a computation that can fail. Errors are cached like values, so a
+     * failed computation doesn't run again until one of the values it read changes, and each
+     * error is put behind an `Arc` so that reading it doesn't clone it.
+     *
+     * Other computations that return a `Result<_, Arc<E>>` can pass the error on with `?`.
+     *
+     * ```
+     * use std::sync::Arc;
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let input = timeline.cell("4");
+     *
+     * let parsed = {
+     *     let input = input.clone();
+     *     timeline.try_derived(move || input.get().parse::<u32>())
+     * };
+     *
+     * let squared = {
+     *     let parsed = parsed.clone();
+     *     timeline.derived(move || -> Result<u32, Arc<_>> {
+     *         let n = parsed.get()?;
+     *         Ok(n * n)
+     *     })
+     * };
+     *
+     * assert_eq!(squared.get().unwrap(), 16);
+     *
+     * input.set("four");
+     * let error = squared.get().unwrap_err();
+     * assert!(Arc::ptr_eq(&error, &parsed.get().unwrap_err()));
+     * ```
+     */
+    pub fn try_derived<T: MaybeSend + 'static, E: MaybeSync + 'static>(
+        &self,
+        computation: impl Fn() -> Result<T, E> + MaybeSync + 'static,
+    ) -> Derived<Result<T, Arc<E>>> {
+        Derived::try_new(self.state.clone(), computation)
+    }
+
+    /**
+     * Create a derived that computes `then_branch` while `condition` is true and `else_branch`
+     * otherwise. Like every derived, it only depends on what it read the last time it ran: the
+     * condition and the branch it took. Writes to what only the other branch reads don't
+     * recompute it, and once the condition flips, it tracks the other branch instead.
+     *
+     * ```
+     * use std::sync::{
+     *     atomic::{AtomicUsize, Ordering},
+     *     Arc,
+     * };
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let (signed_in, name, guest) = (timeline.cell(true), timeline.cell("ada"), timeline.cell("guest"));
+     * let runs = Arc::new(AtomicUsize::new(0));
+     *
+     * let greeting = {
+     *     let (signed_in, runs) = (signed_in.clone(), runs.clone());
+     *     let (name, guest) = (name.clone(), guest.clone());
+     *     timeline.branch(
+     *         move || {
+     *             runs.fetch_add(1, Ordering::SeqCst);
+     *             signed_in.get()
+     *         },
+     *         move || format!("hello, {}", name.get()),
+     *         move || format!("hello, {}", guest.get()),
+     *     )
+     * };
+     *
+     * assert_eq!(greeting.get(), "hello, ada");
+     * guest.set("visitor");
+     * assert_eq!(greeting.get(), "hello, ada");
+     * assert_eq!(runs.load(Ordering::SeqCst), 1);
+     *
+     * signed_in.set(false);
+     * assert_eq!(greeting.get(), "hello, visitor");
+     * ```
+     */
+    pub fn branch<T: MaybeSend + 'static>(
+        &self,
+        condition: impl Fn() -> bool + MaybeSync + 'static,
+        then_branch: impl Fn() -> T + MaybeSync + 'static,
+        else_branch: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::new(self.state.clone(), move || {
+            if condition() {
+                then_branch()
+            } else {
+                else_branch()
+            }
+        })
+    }
+
+    /**
+     * Create a derived that cuts off propagation: when it recomputes a value equal to its
+     * previous one, computations that read it are not invalidated.
+     */
+    pub fn derived_with_eq<T: PartialEq + MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_eq(self.state.clone(), computation)
+    }
+
+    /**
+     * Create a derived that can be written. `computation` computes its value, and `setter` is
+     * called with each value it's set to, and writes it back to what `computation` reads. The
+     * derived cuts off propagation like `derived_with_eq`, so a write that leaves the computed
+     * value equal doesn't invalidate its readers.
+     */
+    pub fn derived_with_setter<T: PartialEq + MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        setter: impl Fn(T) + MaybeSync + 'static,
+    ) -> WritableDerived<T> {
+        WritableDerived::new(self.state.clone(), computation, setter)
+    }
+
+    /**
+     * Create a computation that produces several named outputs in one run, which readers read
+     * one at a time with `MultiDerived::output`.
+     */
+    pub fn multi_derived(
+        &self,
+        computation: impl Fn(&mut Outputs) + MaybeSync + 'static,
+    ) -> MultiDerived {
+        MultiDerived::new(self.state.clone(), computation)
+    }
+
+    /**
+     * Create a derived whose values own resources. `cleanup` is called with the previous value
+     * after each recomputation, once the new value is stored, and with the last value when the
+     * derived is dropped. Values are cleaned up in the order they were computed, and every
+     * computed value is cleaned up exactly once. The derived isn't locked while `cleanup` runs,
+     * so it can read the derived.
+     *
+     * Effects the computation creates belong to the derived, see `Owner`. They are disposed
+     * when the derived recomputes, once the new value is stored and before the previous value is
+     * cleaned up, and when the derived is dropped.
+     *
+     * ```
+     * use std::sync::{Arc, Mutex};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let path = timeline.cell("a.txt");
+     * let closed = Arc::new(Mutex::new(vec![]));
+     *
+     * let watcher = {
+     *     let (path, closed) = (path.clone(), closed.clone());
+     *     timeline.derived_with_cleanup(
+     *         move || format!("watching {}", path.get()),
+     *         move |watcher| closed.lock().unwrap().push(watcher),
+     *     )
+     * };
+     *
+     * assert_eq!(watcher.get(), "watching a.txt");
+     * path.set("b.txt");
+     * assert_eq!(watcher.get(), "watching b.txt");
+     * drop(watcher);
+     *
+     * assert_eq!(*closed.lock().unwrap(), vec!["watching a.txt", "watching b.txt"]);
+     * ```
+     */
+    pub fn derived_with_cleanup<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        cleanup: impl Fn(T) + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_cleanup(self.state.clone(), computation, cleanup)
+    }
+
+    /**
+     * Create a derived computed by a future. Reads on both sides of every `.await` are tracked,
+     * and a change to any of them runs the whole future again the next time it is awaited.
+     */
+    pub fn derived_async<T: MaybeSend + 'static, F: Future<Output = T> + MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> F + MaybeSync + 'static,
+    ) -> DerivedAsync<T> {
+        DerivedAsync::new(self.state.clone(), computation)
+    }
+
+    /**
+     * Create a function of one argument that caches its result for every argument it is called
+     * with, and recomputes the result for an argument once a value it read changes.
+     */
+    pub fn memo<A, T>(&self, computation: impl Fn(&A) -> T + MaybeSync + 'static) -> Memo<A, T>
+    where
+        A: Hash + Eq + Clone + MaybeSync + 'static,
+        T: MaybeSend + 'static,
+    {
+        Memo::new(self.state.clone(), computation)
+    }
+
+    /**
+     * Create the storage for the `#[cached]` methods of a value that derives
+     * `everafter_derive::Reactive`.
+     */
+    pub fn cached_methods(&self) -> CachedMethods {
+        CachedMethods::new(self.state.clone())
+    }
+
+    /**
+     * Create a derived that cuts off propagation like `derived_with_eq`, but decides whether a
+     * recomputed value is equal to the previous one with `eq`, which is called with the previous
+     * value and the new one.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * #[derive(Clone)]
+     * struct User {
+     *     id: u32,
+     *     visits: u32,
+     * }
+     *
+     * let timeline = Timeline::new();
+     * let visits = timeline.cell(1);
+     *
+     * let user = {
+     *     let visits = visits.clone();
+     *     timeline.derived_with_eq_by(
+     *         move || User { id: 7, visits: visits.get() },
+     *         |old, new| old.id == new.id,
+     *     )
+     * };
+     *
+     * let revision = user.revision();
+     * visits.set(2);
+     *
+     * assert_eq!(user.get().visits, 2);
+     * assert_eq!(user.revision(), revision, "the user's id didn't change");
+     * ```
+     */
+    pub fn derived_with_eq_by<T: MaybeSend + 'static>(
+        &self,
+        computation: impl Fn() -> T + MaybeSync + 'static,
+        eq: impl Fn(&T, &T) -> bool + MaybeSync + 'static,
+    ) -> Derived<T> {
+        Derived::with_eq_by(self.state.clone(), computation, eq)
+    }
+
+    /**
+     * Create an effect, which runs `callback` immediately and then again whenever the timeline's
+     * scheduler flushes after a write to something `callback` read. The effect is disposed when
+     * the returned handle is dropped, unless it is detached.
+     *
+     * If `callback` panics during a flush, the panic ends the flush and unwinds out of the write
+     * that flushed. The effects after it in that flush don't run until the next one, and the
+     * next write flushes as usual.
+     */
+    pub fn effect(&self, callback: impl Fn() + MaybeSync + 'static) -> Effect {
+        Effect::new(self.state.clone(), callback)
+    }
+
+    /**
+     * Create an effect that is debounced by `delay_flushes` flushes. Like any effect it runs
+     * immediately, but afterwards a flush only runs it once nothing it read has changed for
+     * `delay_flushes` consecutive flushes, and every change during the wait starts it over. While
+     * the effect waits, each flush schedules another one, so it runs even if nothing is written
+     * again. `Effect::poll` runs it without waiting.
+     *
+     * Flushes are only worth counting when the scheduler batches writes, like `ManualScheduler`
+     * flushed once per frame: the `ImmediateScheduler` flushes again right away, so a debounced
+     * effect still runs after every write.
+     *
+     * ```
+     * use std::sync::{Arc, Mutex};
+     * use everafter::{ManualScheduler, Timeline};
+     *
+     * let timeline = Timeline::new();
+     * let scheduler = ManualScheduler::default();
+     * timeline.set_scheduler(scheduler.clone());
+     *
+     * let width = timeline.cell(100);
+     * let layouts = Arc::new(Mutex::new(vec![]));
+     *
+     * let _layout = {
+     *     let (width, layouts) = (width.clone(), layouts.clone());
+     *     timeline.effect_debounced(1, move || layouts.lock().unwrap().push(width.get()))
+     * };
+     *
+     * width.set(120);
+     * scheduler.flush();
+     * width.set(140);
+     * scheduler.flush();
+     * assert_eq!(*layouts.lock().unwrap(), vec![100], "the width is still changing");
+     *
+     * scheduler.flush();
+     * assert_eq!(*layouts.lock().unwrap(), vec![100, 140]);
+     * assert!(!scheduler.is_pending());
+     * ```
+     */
+    pub fn effect_debounced(
+        &self,
+        delay_flushes: u32,
+        callback: impl Fn() + MaybeSync + 'static,
+    ) -> Effect {
+        Effect::debounced(self.state.clone(), delay_flushes, callback)
+    }
+
+    /**
+     * The number of effects and subscriptions registered with the timeline, for diagnostics.
+     * Dropped effects stop counting once they unregister or the next flush compacts them.
+     */
+    pub fn subscription_count(&self) -> usize {
+        self.state.reaction_count()
+    }
+
+    /**
+     * Replace the scheduler that decides when effects are flushed. The default is
+     * `ImmediateScheduler`, which flushes synchronously after every write.
+     */
+    pub fn set_scheduler(&self, scheduler: impl Scheduler + 'static) {
+        self.state.set_scheduler(Arc::new(scheduler));
+    }
+
+    /**
+     * The way the timeline discovers stale deriveds. The default is `ValidationMode::Lazy`.
+     */
+    pub fn validation_mode(&self) -> ValidationMode {
+        self.state.validation_mode()
+    }
+
+    /**
+     * Switch between lazy and eager validation. Deriveds only record reverse edges while the
+     * timeline is eager, so a derived that last ran in lazy mode keeps walking its dependencies
+     * in `Derived::is_dirty` until it recomputes.
+     */
+    pub fn set_validation_mode(&self, mode: ValidationMode) {
+        self.state.set_validation_mode(mode);
+    }
+
+    /**
+     * Push the writes to `roots` through the graph instead of waiting for reads to pull them:
+     * every derived that read one of the roots, directly or through other deriveds, is brought up
+     * to date, after the deriveds it read among them. A derived that two of them read, like the
+     * bottom of a diamond, is only recomputed once. Call it with the tags of the cells a render
+     * pass wrote, from `Cell::tag`, before the pass reads the deriveds.
+     *
+     * The deriveds are found along the reverse edges recorded in `ValidationMode::Eager`, so
+     * this panics if the timeline is lazy. Deriveds that were never read aren't computed.
+     *
+     * ```
+     * use everafter::{Timeline, ValidationMode};
+     *
+     * let timeline = Timeline::new();
+     * timeline.set_validation_mode(ValidationMode::Eager);
+     *
+     * let width = timeline.cell(2);
+     * let area = {
+     *     let width = width.clone();
+     *     timeline.derived(move || width.get() * 3)
+     * };
+     * area.get();
+     *
+     * width.set(4);
+     * timeline.flush_dirty(&[width.tag().unwrap()]);
+     *
+     * assert!(!area.is_stale());
+     * assert_eq!(area.get(), 12);
+     * ```
+     */
+    pub fn flush_dirty(&self, roots: &[Tag]) {
+        self.state.flush_dirty(roots);
+    }
+
+    /**
+     * What the timeline does when one of its cells is read outside of any `ComputeStack` frame.
+     * The default is `ReadPolicy::Allow`.
+     */
+    pub fn read_policy(&self) -> ReadPolicy {
+        self.state.read_policy()
+    }
+
+    /**
+     * Choose what happens when one of the timeline's cells is read outside of any
+     * `ComputeStack` frame, to catch functions that were meant to be reactive but are called
+     * where nothing records what they read.
+     *
+     * ```
+     * use everafter::{ReadPolicy, Timeline};
+     *
+     * let timeline = Timeline::new();
+     * timeline.set_read_policy(ReadPolicy::Deny);
+     *
+     * let cell = timeline.cell(1).named("count");
+     * let error = cell.try_get().unwrap_err();
+     * assert_eq!(error.label(), "count");
+     *
+     * let doubled = {
+     *     let cell = cell.clone();
+     *     timeline.derived(move || cell.get() * 2)
+     * };
+     * assert_eq!(doubled.get(), 2);
+     * ```
+     */
+    pub fn set_read_policy(&self, policy: ReadPolicy) {
+        self.state.set_read_policy(policy);
+    }
+
+    /**
+     * Report untracked reads under `ReadPolicy::Warn` to `callback`. The callback is called with
+     * the label of the cell and where it was read, and it replaces any callback installed
+     * before. The timeline's observer hears about the reads too, whether or not there is a
+     * callback.
+     */
+    pub fn on_untracked_read(&self, callback: impl Fn(&UntrackedRead) + MaybeSync + 'static) {
+        self.state.on_untracked_read(Arc::new(callback));
+    }
+
+    /**
+     * Report the lifecycle of the timeline's nodes to `observer`: creation, recomputation,
+     * writes, flushes and drops. It replaces any observer installed before. Without an observer,
+     * each of those places only checks a flag, so the events cost nothing to leave disabled.
+     */
+    pub fn set_observer(&self, observer: Box<dyn TimelineObserver>) {
+        self.state.set_observer(Some(Arc::from(observer)));
+    }
+
+    /**
+     * Remove the observer installed with `set_observer`.
+     */
+    pub fn clear_observer(&self) {
+        self.state.set_observer(None);
+    }
+
+    /**
+     * Call `f` with every revision the timeline publishes, and the tags that were written at
+     * it, until the returned handle is dropped. Where a subscription hears about changes to
+     * what it read, this hears about the raw stream of writes, which makes it a place to log a
+     * timeline of mutations from.
+     *
+     * An unbatched write is reported on its own. A transaction or batch is reported once, when
+     * it commits, with every tag written inside it. Revisions allocated without writing a cell,
+     * like the one an `ExternalSource` gets when its data changed, are reported with no tags.
+     *
+     * ```
+     * use std::sync::{Arc, Mutex};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(0).named("count");
+     *
+     * let log = Arc::new(Mutex::new(vec![]));
+     * let handle = {
+     *     let log = log.clone();
+     *     timeline.on_bump(move |revision, tags| {
+     *         let tags: Vec<_> = tags.iter().map(|tag| tag.label().to_string()).collect();
+     *         log.lock().unwrap().push((revision, tags));
+     *     })
+     * };
+     *
+     * count.set(1);
+     * assert_eq!(*log.lock().unwrap(), [(count.revision(), vec![String::from("count")])]);
+     *
+     * drop(handle);
+     * count.set(2);
+     * assert_eq!(log.lock().unwrap().len(), 1);
+     * ```
+     *
+     * `f` runs after the write is published and before effects flush. It must not write the
+     * timeline.
+     */
+    pub fn on_bump(&self, f: impl FnMut(Revision, &[TagId]) + MaybeSend + 'static) -> BumpHandle {
+        let id = self.state.bump_listeners().add(Box::new(f));
+        BumpHandle::new(&self.state, id)
+    }
+
+    /**
+     * The time that `revision` became the timeline's current revision, or `None` if it isn't one
+     * of the last 256 revisions, or was never published, like the revision of an open
+     * transaction. The time comes from the clock installed with `set_clock`.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let cell = timeline.cell(0);
+     *
+     * let before = timeline.now();
+     * cell.set(1);
+     *
+     * let written = timeline.revision_time(timeline.now()).unwrap();
+     * assert!(written >= timeline.revision_time(before).unwrap());
+     * ```
+     */
+    #[cfg(feature = "revision-timestamps")]
+    pub fn revision_time(&self, revision: Revision) -> Option<Instant> {
+        self.state.revision_times().time(revision)
+    }
+
+    /**
+     * Read the times of new revisions from `clock` instead of from `Instant::now`. Revisions
+     * already published keep the times they were given.
+     */
+    #[cfg(feature = "revision-timestamps")]
+    pub fn set_clock(&self, clock: impl Clock + 'static) {
+        self.state.set_clock(Box::new(clock));
+    }
+
+    /**
+     * Whether writing a cell while a derived is computing panics. The default is `false`.
+     */
+    pub fn strict_writes(&self) -> bool {
+        self.state.strict_writes()
+    }
+
+    /**
+     * Panic when one of the timeline's cells is written while a derived is computing, naming the
+     * cell and the derived. A derived that writes cells makes the graph depend on the order
+     * in which things happen to be read, so this catches the mistake during development, and can
+     * be turned off in production.
+     *
+     * Writes from effects, including effects created by a derived, are still allowed. Writes
+     * inside `untrack` aren't, since they still happen while the derived is computing.
+     *
+     * ```
+     * use std::panic::{self, AssertUnwindSafe};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * timeline.set_strict_writes(true);
+     *
+     * let count = timeline.cell(0);
+     * count.set(1);
+     *
+     * let sneaky = {
+     *     let count = count.clone();
+     *     timeline.derived(move || count.set(2))
+     * };
+     *
+     * let panicked = panic::catch_unwind(AssertUnwindSafe(|| sneaky.get()));
+     * assert!(panicked.is_err());
+     * ```
+     */
+    pub fn set_strict_writes(&self, strict: bool) {
+        self.state.set_strict_writes(strict);
+    }
+
+    /**
+     * The deepest the compute stack may be when one of the timeline's deriveds or effects starts
+     * computing, set with `set_max_compute_depth`. The default is no limit.
+     */
+    pub fn max_compute_depth(&self) -> Option<usize> {
+        self.state.max_compute_depth()
+    }
+
+    /**
+     * Panic when one of the timeline's deriveds or effects would push a frame onto a compute
+     * stack that is already `depth` frames deep, naming the computation. A long enough chain of
+     * deriveds that are read for the first time recurses once per link, so a limit turns a
+     * pathological graph into a panic that says what happened, instead of overflowing the
+     * native stack. The deriveds whose computations the panic interrupted are poisoned, like
+     * after any other panic. `None` removes the limit.
+     *
+     * Every frame counts, including the untracked frames of `untrack`, see
+     * `ComputeStack::depth`.
+     *
+     * ```
+     * use std::panic::{self, AssertUnwindSafe};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * timeline.set_max_compute_depth(Some(2));
+     *
+     * let count = timeline.cell(1);
+     * let mut chain = vec![timeline.derived(move || count.get())];
+     * for _ in 0..2 {
+     *     let previous = chain.last().unwrap().clone();
+     *     chain.push(timeline.derived(move || previous.get() + 1));
+     * }
+     *
+     * let too_deep = panic::catch_unwind(AssertUnwindSafe(|| chain[2].get()));
+     * assert!(too_deep.is_err());
+     * ```
+     */
+    pub fn set_max_compute_depth(&self, depth: Option<usize>) {
+        self.state.set_max_compute_depth(depth);
+    }
+
+    /**
+     * How many times a flush goes back to run an effect in a higher lane before it finishes the
+     * lower lanes, see `Priority`. The default is 16.
+     */
+    pub fn max_preemptions(&self) -> usize {
+        self.state.max_preemptions()
+    }
+
+    /**
+     * Let each flush go back to a higher lane `preemptions` times. Once a flush used them up,
+     * effects that lower lanes invalidate in higher ones wait until the lower lanes finished,
+     * and run in the flush's next pass. With 0, every flush runs its lanes strictly in order.
+     */
+    pub fn set_max_preemptions(&self, preemptions: usize) {
+        self.state.set_max_preemptions(preemptions);
+    }
+
+    /**
+     * Whether reading a derived's cached value checks that it is consistent with what it read.
+     * The default is `false`.
+     */
+    pub fn validate_on_read(&self) -> bool {
+        self.state.validates_on_read()
+    }
+
+    /**
+     * Panic when a derived returns a cached value that is older than something it read, directly
+     * or through other deriveds, naming the stale derived and the dependency that moved past it.
+     * That happens when a derived is read after a write that the timeline's revision hasn't
+     * caught up with yet, like a write earlier in the same transaction. Checking walks the
+     * derived's dependencies on every cached read, so it is meant for development and tests.
+     *
+     * ```
+     * use std::panic::{self, AssertUnwindSafe};
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * timeline.set_validate_on_read(true);
+     *
+     * let count = timeline.cell(1);
+     * let doubled = {
+     *     let count = count.clone();
+     *     timeline.derived(move || count.get() * 2)
+     * };
+     * doubled.get();
+     *
+     * let stale = panic::catch_unwind(AssertUnwindSafe(|| {
+     *     timeline.batch(|| {
+     *         count.set(2);
+     *         doubled.get();
+     *     })
+     * }));
+     * assert!(stale.is_err());
+     * assert_eq!(doubled.get(), 4);
+     * ```
+     */
+    pub fn set_validate_on_read(&self, validate: bool) {
+        self.state.set_validate_on_read(validate);
+    }
+
+    /**
+     * Run `f` inside an untracked frame, so the enclosing computation doesn't depend on anything
+     * `f` reads. Tracked frames opened inside `f`, for example by reading a derived, still
+     * record their own reads.
+     */
+    pub fn untracked<R>(&self, f: impl FnOnce() -> R) -> R {
+        ComputeStack::untrack(f)
+    }
+
+    /**
+     * Estimate how much memory the timeline's live values take up.
+     */
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.state.memory_stats()
+    }
+
+    /**
+     * Forget the cells, deriveds and effects that were dropped, along with the reverse edges to
+     * them, and free the room they took up. The timeline only holds weak references to its
+     * values, which are owned by their handles, so whatever this forgets was already unreachable.
+     * Writes also do this a few values at a time, so calling it is only needed to free the memory
+     * right away, for example after tearing down a large part of an app.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let items: Vec<_> = (0..100).map(|i| timeline.cell(i)).collect();
+     * drop(items);
+     *
+     * assert_eq!(timeline.collect().nodes(), 100);
+     * assert_eq!(timeline.collect().nodes(), 0);
+     * ```
+     */
+    pub fn collect(&self) -> Collected {
+        self.state.collect()
+    }
+
+    /**
+     * Capture the current revision and the revisions of every live cell and derived, so they
+     * can be compared with a later snapshot using `Snapshot::diff`. While the snapshot is alive,
+     * `Cell::get_at` reads cells as they were when it was taken.
+     */
+    pub fn snapshot(&self) -> Snapshot {
+        self.state.snapshot()
+    }
+
+    /**
+     * A fork of the timeline, for trying out writes without applying them. See `Forked`.
+     */
+    pub fn fork(&self) -> Forked {
+        Forked::new(self.state.clone())
+    }
+
+    /**
+     * Keep the last `capacity` values that writes to cells created with `Cell::recorded`
+     * replaced, so `rewind_to` can restore them. A capacity of 0 stops recording and forgets
+     * the history.
+     */
+    #[cfg(feature = "history")]
+    pub fn record_history(&self, capacity: usize) {
+        self.state.record_history(capacity);
+    }
+
+    /**
+     * Restore every recorded cell to the value it had at `revision`. The values are written
+     * back at a new revision, so the timeline keeps moving forward and deriveds that read the
+     * cells recompute the next time they are read. The rewind is recorded like any other
+     * write, so it can be undone by rewinding to the revision before it.
+     *
+     * Cells created after `revision` keep their values, and cells that aren't recorded are left
+     * alone. Fails if the history isn't recorded, or if it already forgot a write after
+     * `revision`.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * timeline.record_history(100);
+     *
+     * let text = timeline.cell(String::from("a")).recorded();
+     * let length = {
+     *     let text = text.clone();
+     *     timeline.derived(move || text.get().len())
+     * };
+     *
+     * let before = timeline.now();
+     * text.set(String::from("abc"));
+     * assert_eq!(length.get(), 3);
+     *
+     * timeline.rewind_to(before).unwrap();
+     * assert_eq!(text.get(), "a");
+     * assert_eq!(length.get(), 1);
+     * assert!(timeline.now() > before);
+     * ```
+     */
+    #[cfg(feature = "history")]
+    pub fn rewind_to(&self, revision: Revision) -> Result<(), RewindError> {
+        self.state.rewind_to(revision)
+    }
+
+    /**
+     * Start logging every write to a cell created with `Cell::keyed`: the cell's key, the value
+     * it was written with, formatted with `Display`, and the write's revision. Starting again
+     * forgets the writes logged so far, and so does `reset`. See `WriteLog::replay`.
+     */
+    #[cfg(feature = "history")]
+    pub fn start_recording(&self) {
+        self.state.start_recording();
+    }
+
+    /**
+     * Stop logging writes, and return the writes logged since `start_recording`, with the value
+     * of every live derived created with `Derived::keyed`. The deriveds are read without
+     * tracking the reads, and recompute first if they are stale. The log has no writes if the
+     * timeline wasn't recording.
+     */
+    #[cfg(feature = "history")]
+    pub fn stop_recording(&self) -> WriteLog {
+        self.state.stop_recording()
+    }
+
+    /**
+     * The current value of every live cell created with `Cell::keyed`, by key, for
+     * `restore_state`. Cells that weren't keyed aren't saved, and reading them isn't tracked.
+     * The state formats as text, see `SerializedState`.
+     *
+     * ```
+     * use everafter::{SerializedState, Timeline};
+     *
+     * let timeline = Timeline::new();
+     * let name = timeline.cell(String::from("Ada")).keyed("name");
+     * let greeting = {
+     *     let name = name.clone();
+     *     timeline.derived(move || format!("hello {}", name.get()))
+     * };
+     *
+     * let saved = timeline.serialize_state().to_string();
+     *
+     * name.set(String::from("Grace"));
+     * assert_eq!(greeting.get(), "hello Grace");
+     *
+     * let skipped = timeline.restore_state(&saved.parse::<SerializedState>().unwrap());
+     * assert!(skipped.is_empty());
+     * assert_eq!(greeting.get(), "hello Ada");
+     * ```
+     */
+    #[cfg(feature = "history")]
+    pub fn serialize_state(&self) -> SerializedState {
+        SerializedState::capture(&self.state)
+    }
+
+    /**
+     * Write every value saved in `state` into the keyed cell with its key, parsing it with
+     * `FromStr`. The values are written in a single transaction, and every one advances its
+     * cell's revision, so what read the cells recomputes even if a value is equal to the one it
+     * replaced. Returns the values that were skipped because no live cell has their key, or
+     * because they didn't parse. Keyed cells that `state` has no value for keep theirs.
+     */
+    #[cfg(feature = "history")]
+    pub fn restore_state(&self, state: &SerializedState) -> Vec<Skipped> {
+        state.restore(&self.state, false)
+    }
+
+    /**
+     * The current value of every live keyed cell, like `serialize_state`, for `load_cells`.
+     */
+    #[cfg(feature = "history")]
+    pub fn serialize_cells(&self) -> SerializedState {
+        self.serialize_state()
+    }
+
+    /**
+     * Write the values saved in `state` into the keyed cells with their keys, like `set` would:
+     * a value that formats the same as the cell's current value isn't written, so only what
+     * read a cell that changed recomputes. The values that are written are written in a single
+     * transaction. Returns the values that were skipped because no live cell has their key, or
+     * because they didn't parse.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let (width, height) = (timeline.cell(2).keyed("width"), timeline.cell(3).keyed("height"));
+     * let saved = timeline.serialize_cells();
+     *
+     * width.set(5);
+     * let revision = height.revision();
+     *
+     * assert!(timeline.load_cells(&saved).is_empty());
+     * assert_eq!(width.get(), 2);
+     * assert_eq!(height.revision(), revision, "the height didn't change");
+     * ```
+     */
+    #[cfg(feature = "history")]
+    pub fn load_cells(&self, state: &SerializedState) -> Vec<Skipped> {
+        state.restore(&self.state, true)
+    }
+
+    /**
+     * A snapshot of the timeline's live cells, deriveds and effects, with the dependency edges
+     * each computation recorded the last time it ran. Each node is annotated with its current
+     * revision and whether it is stale. `Display` renders the graph in graphviz's DOT format.
+     *
+     * Building the graph doesn't validate anything, so an edge may belong to a computation that
+     * would read something else the next time it runs.
+     */
+    #[cfg(feature = "debug-graph")]
+    pub fn debug_graph(&self) -> DebugGraph {
+        self.state.debug_graph()
+    }
+
+    /**
+     * The timeline's dependency graph in graphviz's DOT format, with an edge from every
+     * dependency to the computation that read it. This is `debug_graph().to_string()`.
+     *
+     * ```
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(1).named("count");
+     * let doubled = timeline.derived(move || count.get() * 2).named("doubled");
+     * doubled.get();
+     *
+     * assert!(timeline.to_dot().contains(r#""count" -> "doubled";"#));
+     * ```
+     */
+    #[cfg(feature = "debug-graph")]
+    pub fn to_dot(&self) -> String {
+        self.debug_graph().to_string()
+    }
+
+    /**
+     * Run `f` with all of its writes coalesced into a single revision. The timeline's revision
+     * only advances when the outermost transaction closes, so computations observe every write
+     * in the transaction at once. Nested transactions join the outermost one.
+     *
+     * If `f` panics, the writes it already made are still committed together at the
+     * transaction's revision, so the timeline never observes half of a transaction.
+     */
+    pub fn transaction<R>(&self, f: impl FnOnce(&Transaction<'_>) -> R) -> R {
+        let transaction = Transaction::begin(&self.state);
+        f(&transaction)
+    }
+
+    /**
+     * Pause the timeline's reactions until the returned guard is dropped, for loading many
+     * values at once. Unlike in a transaction, every write advances the revision and is visible
+     * to readers right away, but no effects are flushed and, in eager validation mode, writes
+     * don't mark anything dirty. Reads while paused validate their dependencies instead.
+     *
+     * Dropping the outermost guard marks the dependents of every tag written while paused dirty
+     * once, and schedules a single flush, which runs each stale effect once.
+     *
+     * ```
+     * use std::sync::{
+     *     atomic::{AtomicUsize, Ordering},
+     *     Arc,
+     * };
+     * use everafter::Timeline;
+     *
+     * let timeline = Timeline::new();
+     * let count = timeline.cell(0);
+     * let runs = Arc::new(AtomicUsize::new(0));
+     *
+     * let _effect = {
+     *     let (count, runs) = (count.clone(), runs.clone());
+     *     timeline.effect(move || {
+     *         count.get();
+     *         runs.fetch_add(1, Ordering::SeqCst);
+     *     })
+     * };
+     *
+     * {
+     *     let _pause = timeline.pause();
+     *     count.set(1);
+     *     count.set(2);
+     *     assert_eq!(count.get(), 2);
+     *     assert_eq!(runs.load(Ordering::SeqCst), 1, "only the first run so far");
+     * }
+     *
+     * assert_eq!(runs.load(Ordering::SeqCst), 2);
+     * ```
+     */
+    pub fn pause(&self) -> Pause<'_> {
+        Pause::begin(&self.state)
+    }
+
+    /**
+     * Coalesce all of the writes in `f` into a single revision bump. This is `transaction` for
+     * callers that don't need the transaction handle.
+     */
+    pub fn batch(&self, f: impl FnOnce()) {
+        self.transaction(|_| f())
+    }
+
     pub fn revision<T: Debug + Clone + 'static>(
         &self,
         id: impl Into<TypedInputId<T>>,
@@ -47,34 +1234,79 @@ impl Timeline {
     pub fn setup(&mut self) -> SetupTransaction<'_> {
         SetupTransaction {
             inputs: &mut self.inputs,
-            revision: self.revision,
+            state: &self.state,
         }
     }
 
     pub fn update(&mut self) -> UpdateTransaction<'_> {
         UpdateTransaction {
             inputs: &mut self.inputs,
-            revision: self.revision,
+            state: &self.state,
         }
     }
 
     pub fn begin(&mut self) -> RenderTransaction<'_> {
         RenderTransaction {
-            revision: self.revision,
+            revision: self.state.now(),
             ctx: EvaluationContext::new(&self.inputs),
         }
     }
 }
 
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    state: &'a TimelineState,
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn begin(state: &'a TimelineState) -> Transaction<'a> {
+        state.begin_transaction();
+        Transaction { state }
+    }
+
+    /**
+     * The revision that writes in this transaction are recorded at, or `None` if nothing has
+     * been written yet.
+     */
+    pub fn revision(&self) -> Option<Revision> {
+        self.state.pending()
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        self.state.end_transaction();
+    }
+}
+
+/**
+ * A guard returned by `Timeline::pause`, which resumes the timeline when it is dropped.
+ */
+#[derive(Debug)]
+pub struct Pause<'a> {
+    state: &'a TimelineState,
+}
+
+impl<'a> Pause<'a> {
+    pub(crate) fn begin(state: &'a TimelineState) -> Pause<'a> {
+        state.begin_pause();
+        Pause { state }
+    }
+}
+
+impl<'a> Drop for Pause<'a> {
+    fn drop(&mut self) {
+        self.state.end_pause();
+    }
+}
+
 pub struct UpdateTransaction<'a> {
     inputs: &'a mut Inputs,
-    revision: Revision,
+    state: &'a TimelineState,
 }
 
 impl<'a> UpdateTransaction<'a> {
-    pub fn commit(self, timeline: &mut Timeline) {
-        timeline.revision = self.revision
-    }
+    pub fn commit(self) {}
 
     pub fn update<T: Debug + Clone + 'static>(
         &mut self,
@@ -87,9 +1319,7 @@ impl<'a> UpdateTransaction<'a> {
     }
 
     fn increment_revision(&mut self) -> Revision {
-        let revision = self.revision.increment();
-        self.revision = revision;
-        revision
+        self.state.bump()
     }
 }
 
@@ -120,8 +1350,7 @@ impl<'a> RenderTransaction<'a> {
 #[derive(Debug)]
 pub struct SetupTransaction<'a> {
     inputs: &'a mut Inputs,
-    // does not change during setup
-    revision: Revision,
+    state: &'a TimelineState,
 }
 
 impl<'a> SetupTransaction<'a> {
@@ -131,7 +1360,7 @@ impl<'a> SetupTransaction<'a> {
         &mut self,
         value: T,
     ) -> TypedInputIdWithKind<T, CellId<T>> {
-        let cell = ReactiveCell::new(value, Tag::arc(self.revision.atomic()));
+        let cell = ReactiveCell::new(value, self.state.tag(None));
         self.inputs.add_cell::<T>(cell)
     }
 
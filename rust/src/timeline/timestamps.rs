@@ -0,0 +1,196 @@
+/*!
+ * The times that revisions were published at, kept with the `revision-timestamps` feature for
+ * tools that want to know how long a value has been stale, not just that it is. Only the most
+ * recent revisions are remembered, along with the keys of the tags written at each one.
+ */
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Debug,
+    time::Instant,
+};
+
+use parking_lot::Mutex;
+
+use crate::reactive::bounds::{MaybeSend, MaybeSync};
+
+use super::{revision::Revision, NodeKey};
+
+/**
+ * The number of revisions whose times a timeline remembers.
+ */
+pub(crate) const RECENT_REVISIONS: usize = 256;
+
+/**
+ * Where a timeline gets the time that a revision was published at, set with
+ * `Timeline::set_clock`. Tests can install a clock that only moves when they tell it to.
+ */
+pub trait Clock: MaybeSend + MaybeSync {
+    fn now(&self) -> Instant;
+}
+
+/**
+ * The clock a timeline starts with, which reads `Instant::now`.
+ */
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug)]
+struct Stamp {
+    revision: Revision,
+    at: Instant,
+    // the keys of the tags written at the revision
+    written: Vec<NodeKey>,
+}
+
+pub(crate) struct RevisionTimes {
+    clock: Mutex<Box<dyn Clock>>,
+    // the most recent revisions, oldest first
+    recent: Mutex<VecDeque<Stamp>>,
+    // the newest revision that no longer fits in `recent`, if any was dropped
+    forgotten: Mutex<Option<Revision>>,
+    // the tags written at revisions that aren't published yet, with the revision of each
+    pending: Mutex<Vec<(Revision, NodeKey)>>,
+}
+
+impl RevisionTimes {
+    /**
+     * Start with the time that `initial` was published at, by the system clock.
+     */
+    pub(crate) fn new(initial: Revision) -> RevisionTimes {
+        let times = RevisionTimes {
+            clock: Mutex::new(Box::new(SystemClock)),
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_REVISIONS)),
+            forgotten: Mutex::new(None),
+            pending: Mutex::new(vec![]),
+        };
+        times.stamp(initial);
+        times
+    }
+
+    pub(crate) fn set_clock(&self, clock: Box<dyn Clock>) {
+        *self.clock.lock() = clock;
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.clock.lock().now()
+    }
+
+    /**
+     * Record that the tag with `key` was written at `revision`, which `now` hasn't reached yet.
+     */
+    pub(crate) fn written(&self, key: NodeKey, revision: Revision, now: Revision) {
+        if revision > now {
+            self.pending.lock().push((revision, key));
+        }
+    }
+
+    /**
+     * Record the time that `revision` was published at, with the tags written at it. The
+     * oldest revision is forgotten once there are more than `RECENT_REVISIONS`.
+     */
+    pub(crate) fn stamp(&self, revision: Revision) {
+        let at = self.now();
+
+        let mut written = vec![];
+        self.pending.lock().retain(|(at, key)| {
+            if *at <= revision {
+                if *at == revision && !written.contains(key) {
+                    written.push(*key);
+                }
+                false
+            } else {
+                true
+            }
+        });
+
+        let mut recent = self.recent.lock();
+
+        if recent.len() == RECENT_REVISIONS {
+            if let Some(oldest) = recent.pop_front() {
+                *self.forgotten.lock() = Some(oldest.revision);
+            }
+        }
+
+        recent.push_back(Stamp {
+            revision,
+            at,
+            written,
+        });
+    }
+
+    /**
+     * The time that `revision` was published at, if it's among the recent revisions.
+     */
+    pub(crate) fn time(&self, revision: Revision) -> Option<Instant> {
+        self.recent
+            .lock()
+            .iter()
+            .find(|stamp| stamp.revision == revision)
+            .map(|stamp| stamp.at)
+    }
+
+    /**
+     * The time of the first revision after `revision` that wrote one of the tags in `keys`, or
+     * `None` if none did or some of the revisions after `revision` were already forgotten.
+     */
+    pub(crate) fn first_written(
+        &self,
+        revision: Revision,
+        keys: &HashSet<NodeKey>,
+    ) -> Option<Instant> {
+        if matches!(*self.forgotten.lock(), Some(forgotten) if forgotten > revision) {
+            return None;
+        }
+
+        self.recent
+            .lock()
+            .iter()
+            .filter(|stamp| stamp.revision > revision)
+            .find(|stamp| stamp.written.iter().any(|key| keys.contains(key)))
+            .map(|stamp| stamp.at)
+    }
+
+    pub(crate) fn revisions(&self, out: &mut Vec<Revision>) {
+        out.extend(self.recent.lock().iter().map(|stamp| stamp.revision));
+        out.extend(*self.forgotten.lock());
+        out.extend(self.pending.lock().iter().map(|(revision, _)| *revision));
+    }
+
+    pub(crate) fn renumber(&self, renumber: &dyn Fn(Revision) -> Revision) {
+        for stamp in self.recent.lock().iter_mut() {
+            stamp.revision = renumber(stamp.revision);
+        }
+
+        for (revision, _) in self.pending.lock().iter_mut() {
+            *revision = renumber(*revision);
+        }
+
+        let mut forgotten = self.forgotten.lock();
+        *forgotten = forgotten.map(renumber);
+    }
+
+    /**
+     * Forget every revision, and stamp `initial` as if the timeline had just been created.
+     */
+    pub(crate) fn reset(&self, initial: Revision) {
+        self.recent.lock().clear();
+        *self.forgotten.lock() = None;
+        self.pending.lock().clear();
+        self.stamp(initial);
+    }
+}
+
+impl Debug for RevisionTimes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RevisionTimes")
+            .field("recent", &self.recent.lock().len())
+            .finish()
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::{ComputeStack, Dependencies};
+
+/**
+ * A future that tracks its reads across await points. Each poll of the inner future runs inside
+ * a fresh `ComputeStack` frame, and the reads from every poll are collected, so reads before and
+ * after an `.await` are all recorded even though the future may be polled from different frames,
+ * or outside of any frame.
+ *
+ * Created by `tracked_async`. The future resolves to the inner future's output together with
+ * everything it read.
+ */
+pub struct TrackedFuture<F> {
+    future: Pin<Box<F>>,
+    dependencies: Dependencies,
+}
+
+/**
+ * Create a future with `compute` and track everything it reads, including the reads in `compute`
+ * itself, until the future completes.
+ *
+ * ```
+ * use std::{future::Future, pin::Pin, task::{Context, Poll, Waker}};
+ * use everafter::{tracked_async, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let url = timeline.cell("/users");
+ * let format = timeline.cell("json");
+ *
+ * let fetch = tracked_async(|| {
+ *     let (url, format) = (url.clone(), format.clone());
+ *
+ *     async move {
+ *         let url = url.get();
+ *         // await the response here
+ *         format!("{}.{}", url, format.get())
+ *     }
+ * });
+ *
+ * let mut fetch = Box::pin(fetch);
+ * let mut cx = Context::from_waker(Waker::noop());
+ *
+ * match fetch.as_mut().poll(&mut cx) {
+ *     Poll::Ready((body, dependencies)) => {
+ *         assert_eq!(body, "/users.json");
+ *         assert_eq!(dependencies.len(), 2);
+ *     }
+ *     Poll::Pending => unreachable!(),
+ * }
+ * ```
+ */
+pub fn tracked_async<F: Future>(compute: impl FnOnce() -> F) -> TrackedFuture<F> {
+    let (future, dependencies) = ComputeStack::track(compute);
+
+    TrackedFuture {
+        future: Box::pin(future),
+        dependencies,
+    }
+}
+
+impl<F> TrackedFuture<F> {
+    /**
+     * Everything the future has read so far.
+     */
+    pub(crate) fn dependencies(&self) -> &Dependencies {
+        &self.dependencies
+    }
+}
+
+impl<F: Future> Future for TrackedFuture<F> {
+    type Output = (F::Output, Dependencies);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // a future that panics while it's polled doesn't leave its frame behind
+        let (poll, dependencies) = ComputeStack::track(|| self.future.as_mut().poll(cx));
+
+        self.dependencies.extend(dependencies);
+
+        match poll {
+            Poll::Ready(value) => Poll::Ready((value, std::mem::take(&mut self.dependencies))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
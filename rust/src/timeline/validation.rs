@@ -0,0 +1,19 @@
+/**
+ * How a timeline finds out that deriveds are stale.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ValidationMode {
+    /**
+     * Staleness is only discovered when a derived is read, or when `Derived::is_dirty` walks its
+     * dependencies. Computations don't record who read them, so tracking stays cheap.
+     */
+    #[default]
+    Lazy,
+
+    /**
+     * Computations record reverse edges to everything they read, and writes walk those edges to
+     * mark every dependent dirty right away, so `Derived::is_dirty` answers without walking the
+     * graph. Writes become proportional to the number of their dependents.
+     */
+    Eager,
+}
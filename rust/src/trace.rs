@@ -0,0 +1,723 @@
+/*!
+ * Traces of what a timeline did, in a line-delimited JSON format that other implementations,
+ * like the JS one in `main/`, can write too, so `compare` can check that they behave the same.
+ * Available with the `trace` feature.
+ *
+ * Each line is one event: the step it happened at, counting from 0, what happened, and the node
+ * it happened to, with the revisions of a write and the number of dependencies a recomputation
+ * read.
+ *
+ * ```text
+ * {"step":0,"event":"created","node":"cell/0"}
+ * {"step":1,"event":"created","node":"derived/0"}
+ * {"step":2,"event":"recomputed","node":"derived/0","consumed":1}
+ * {"step":3,"event":"written","node":"cell/0","from":1,"to":2}
+ * {"step":4,"event":"flush_scheduled"}
+ * {"step":5,"event":"effect_flushed","node":"effect/0"}
+ * {"step":6,"event":"dropped","node":"derived/0"}
+ * ```
+ *
+ * Nodes are keyed by their kind and the order they were first seen in, like `derived/0` for the
+ * first derived, rather than by ids and labels that implementations number differently. How
+ * long a recomputation took isn't traced.
+ */
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{self, Display, Formatter, Write},
+    iter::Peekable,
+    str::Chars,
+    sync::Arc,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    reactive::{ObservedNode, TimelineObserver},
+    timeline::Revision,
+};
+
+/**
+ * One line of a trace.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    Created { node: String },
+    Recomputed { node: String, consumed: usize },
+    Written { node: String, from: u64, to: u64 },
+    FlushScheduled,
+    EffectFlushed { node: String },
+    Dropped { node: String },
+}
+
+impl TraceEvent {
+    /**
+     * The key of the node the event happened to.
+     */
+    pub fn node(&self) -> Option<&str> {
+        match self {
+            TraceEvent::Created { node }
+            | TraceEvent::Recomputed { node, .. }
+            | TraceEvent::Written { node, .. }
+            | TraceEvent::EffectFlushed { node }
+            | TraceEvent::Dropped { node } => Some(node),
+            TraceEvent::FlushScheduled => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            TraceEvent::Created { .. } => "created",
+            TraceEvent::Recomputed { .. } => "recomputed",
+            TraceEvent::Written { .. } => "written",
+            TraceEvent::FlushScheduled => "flush_scheduled",
+            TraceEvent::EffectFlushed { .. } => "effect_flushed",
+            TraceEvent::Dropped { .. } => "dropped",
+        }
+    }
+
+    /**
+     * Whether the event happens in the order the program does things, in every implementation.
+     * Recomputations and flushes happen when something is read or scheduled, which
+     * implementations are free to order differently.
+     */
+    fn is_ordered(&self) -> bool {
+        matches!(
+            self,
+            TraceEvent::Created { .. } | TraceEvent::Written { .. } | TraceEvent::Dropped { .. }
+        )
+    }
+}
+
+/**
+ * The events of a timeline, in the order they happened.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn new(events: Vec<TraceEvent>) -> Trace {
+        Trace { events }
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /**
+     * The trace in the line-delimited JSON format, with a newline after every event.
+     */
+    pub fn to_json_lines(&self) -> String {
+        let mut json = String::new();
+
+        for (step, event) in self.events.iter().enumerate() {
+            write!(json, "{{\"step\":{},\"event\":\"{}\"", step, event.name()).unwrap();
+
+            if let Some(node) = event.node() {
+                json.push_str(",\"node\":");
+                write_string(&mut json, node);
+            }
+
+            match event {
+                TraceEvent::Recomputed { consumed, .. } => {
+                    write!(json, ",\"consumed\":{}", consumed).unwrap()
+                }
+                TraceEvent::Written { from, to, .. } => {
+                    write!(json, ",\"from\":{},\"to\":{}", from, to).unwrap()
+                }
+                _ => {}
+            }
+
+            json.push_str("}\n");
+        }
+
+        json
+    }
+
+    /**
+     * Read a trace in the line-delimited JSON format. Blank lines are skipped, and so are the
+     * fields of an event that the format doesn't define. Every event has to be at the step that
+     * follows the one before it.
+     */
+    pub fn parse(json: &str) -> Result<Trace, ParseError> {
+        let mut events = vec![];
+
+        for (index, line) in json.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let error = |message: String| ParseError {
+                line: index + 1,
+                message,
+            };
+
+            let mut fields = parse_object(line).map_err(error)?;
+            let step = fields.number("step").map_err(error)?;
+
+            if step != events.len() as u64 {
+                return Err(error(format!(
+                    "expected step {}, found step {}",
+                    events.len(),
+                    step
+                )));
+            }
+
+            events.push(fields.event().map_err(error)?);
+        }
+
+        Ok(Trace { events })
+    }
+}
+
+/**
+ * An observer that records a `Trace` of the timeline it's installed on. Clones share the same
+ * trace, so a test can install one clone and read the trace from another.
+ *
+ * ```
+ * use everafter::{trace::{TraceEvent, TraceObserver}, Timeline};
+ *
+ * let timeline = Timeline::new();
+ * let observer = TraceObserver::new();
+ * timeline.set_observer(Box::new(observer.clone()));
+ *
+ * let count = timeline.cell(0);
+ * count.set(1);
+ *
+ * let trace = observer.trace();
+ * assert_eq!(trace.events()[0], TraceEvent::Created { node: "cell/0".into() });
+ * assert!(trace.to_json_lines().starts_with(r#"{"step":0,"event":"created","node":"cell/0"}"#));
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct TraceObserver {
+    recording: Arc<Mutex<Recording>>,
+}
+
+#[derive(Debug, Default)]
+struct Recording {
+    trace: Trace,
+    // the key of every node seen so far, by kind and id
+    keys: HashMap<(&'static str, u64), String>,
+    // the number of nodes of each kind seen so far
+    seen: HashMap<&'static str, usize>,
+}
+
+impl Recording {
+    fn key(&mut self, node: &ObservedNode) -> String {
+        let Recording { keys, seen, .. } = self;
+
+        keys.entry((node.kind(), node.id()))
+            .or_insert_with(|| {
+                let count = seen.entry(node.kind()).or_insert(0);
+                *count += 1;
+                format!("{}/{}", node.kind(), *count - 1)
+            })
+            .clone()
+    }
+
+    fn push(&mut self, event: TraceEvent) {
+        self.trace.events.push(event);
+    }
+}
+
+fn timestamp(revision: Revision) -> u64 {
+    revision.distance(&Revision::CONSTANT)
+}
+
+impl TraceObserver {
+    pub fn new() -> TraceObserver {
+        TraceObserver::default()
+    }
+
+    /**
+     * The events recorded so far.
+     */
+    pub fn trace(&self) -> Trace {
+        self.recording.lock().trace.clone()
+    }
+}
+
+impl TimelineObserver for TraceObserver {
+    fn node_created(&self, node: &ObservedNode) {
+        let mut recording = self.recording.lock();
+        let node = recording.key(node);
+        recording.push(TraceEvent::Created { node });
+    }
+
+    fn node_recomputed(&self, node: &ObservedNode, _duration: Duration, consumed: usize) {
+        let mut recording = self.recording.lock();
+        let node = recording.key(node);
+        recording.push(TraceEvent::Recomputed { node, consumed });
+    }
+
+    fn cell_written(&self, cell: &ObservedNode, from: Revision, to: Revision) {
+        let mut recording = self.recording.lock();
+        let node = recording.key(cell);
+        recording.push(TraceEvent::Written {
+            node,
+            from: timestamp(from),
+            to: timestamp(to),
+        });
+    }
+
+    fn flush_scheduled(&self) {
+        self.recording.lock().push(TraceEvent::FlushScheduled);
+    }
+
+    fn effect_flushed(&self, effect: &ObservedNode) {
+        let mut recording = self.recording.lock();
+        let node = recording.key(effect);
+        recording.push(TraceEvent::EffectFlushed { node });
+    }
+
+    fn node_dropped(&self, node: &ObservedNode) {
+        let mut recording = self.recording.lock();
+        let node = recording.key(node);
+        recording.push(TraceEvent::Dropped { node });
+    }
+}
+
+/**
+ * A difference between two traces, found by `compare`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    kind: DivergenceKind,
+    node: Option<String>,
+    steps: (Option<usize>, Option<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /**
+     * The traces created, wrote or dropped nodes in a different order. These are the events
+     * every implementation orders the way the program does things. `None` when one trace ran out
+     * of them.
+     */
+    Order {
+        a: Option<TraceEvent>,
+        b: Option<TraceEvent>,
+    },
+    /**
+     * A node recomputed a different number of times.
+     */
+    Recomputes { a: usize, b: usize },
+    /**
+     * A cell was last written at a different revision, or only written in one of the traces.
+     */
+    FinalRevision { a: Option<u64>, b: Option<u64> },
+}
+
+impl Divergence {
+    pub fn kind(&self) -> &DivergenceKind {
+        &self.kind
+    }
+
+    /**
+     * The key of the node the traces disagree about.
+     */
+    pub fn node(&self) -> Option<&str> {
+        self.node.as_deref()
+    }
+
+    /**
+     * The step of each trace the divergence shows up at, or `None` for a trace that doesn't have
+     * the event: for an order divergence the events that differ, for recomputes the first
+     * recomputation only one of the traces has, and for a final revision the last writes.
+     */
+    pub fn steps(&self) -> (Option<usize>, Option<usize>) {
+        self.steps
+    }
+
+    fn first_step(&self) -> usize {
+        match self.steps {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(step), None) | (None, Some(step)) => step,
+            (None, None) => 0,
+        }
+    }
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let node = self.node().unwrap_or("the timeline");
+        let (a, b) = self.steps;
+
+        match &self.kind {
+            DivergenceKind::Order {
+                a: event_a,
+                b: event_b,
+            } => write!(
+                f,
+                "the traces diverge at step {:?} of a and step {:?} of b: {:?} and {:?}",
+                a, b, event_a, event_b
+            ),
+            DivergenceKind::Recomputes {
+                a: runs_a,
+                b: runs_b,
+            } => write!(
+                f,
+                "{} recomputed {} times in a and {} times in b",
+                node, runs_a, runs_b
+            ),
+            DivergenceKind::FinalRevision {
+                a: revision_a,
+                b: revision_b,
+            } => write!(
+                f,
+                "{} was last written at {:?} in a and at {:?} in b",
+                node, revision_a, revision_b
+            ),
+        }
+    }
+}
+
+/**
+ * Align two traces of the same program and report where they differ: the first created,
+ * written or dropped event they don't agree on, every node that recomputed a different number of
+ * times, and every cell that was last written at a different revision. Recomputations and
+ * flushes are only counted, since implementations may order them differently.
+ *
+ * The divergences are sorted by the first step they show up at, so the first one is where the
+ * traces start to differ.
+ */
+pub fn compare(a: &Trace, b: &Trace) -> Vec<Divergence> {
+    let mut divergences = vec![];
+
+    let ordered = |trace: &Trace| -> Vec<(usize, TraceEvent)> {
+        trace
+            .events
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(_, event)| event.is_ordered())
+            .collect()
+    };
+    let (ordered_a, ordered_b) = (ordered(a), ordered(b));
+
+    for index in 0..ordered_a.len().max(ordered_b.len()) {
+        let (event_a, event_b) = (ordered_a.get(index), ordered_b.get(index));
+
+        if event_a.map(|(_, event)| event) != event_b.map(|(_, event)| event) {
+            let node = event_a
+                .or(event_b)
+                .and_then(|(_, event)| event.node())
+                .map(String::from);
+
+            divergences.push(Divergence {
+                kind: DivergenceKind::Order {
+                    a: event_a.map(|(_, event)| event.clone()),
+                    b: event_b.map(|(_, event)| event.clone()),
+                },
+                node,
+                steps: (
+                    event_a.map(|(step, _)| *step),
+                    event_b.map(|(step, _)| *step),
+                ),
+            });
+            break;
+        }
+    }
+
+    let (recomputes_a, recomputes_b) = (recomputes(a), recomputes(b));
+
+    for node in recomputes_a.keys().chain(recomputes_b.keys()) {
+        let (steps_a, steps_b) = (steps(&recomputes_a, node), steps(&recomputes_b, node));
+
+        // each node once, when it's first reached
+        if steps_a.len() == steps_b.len() || divergences.iter().any(|d| d.is_recomputes_of(node)) {
+            continue;
+        }
+
+        let both = steps_a.len().min(steps_b.len());
+        divergences.push(Divergence {
+            kind: DivergenceKind::Recomputes {
+                a: steps_a.len(),
+                b: steps_b.len(),
+            },
+            node: Some(node.clone()),
+            steps: (steps_a.get(both).copied(), steps_b.get(both).copied()),
+        });
+    }
+
+    let (writes_a, writes_b) = (last_writes(a), last_writes(b));
+
+    for node in writes_a.keys().chain(writes_b.keys()) {
+        let (write_a, write_b) = (writes_a.get(node), writes_b.get(node));
+        let revision = |write: Option<&(usize, u64)>| write.map(|(_, revision)| *revision);
+
+        if revision(write_a) == revision(write_b)
+            || divergences.iter().any(|d| d.is_final_revision_of(node))
+        {
+            continue;
+        }
+
+        divergences.push(Divergence {
+            kind: DivergenceKind::FinalRevision {
+                a: revision(write_a),
+                b: revision(write_b),
+            },
+            node: Some(node.clone()),
+            steps: (
+                write_a.map(|(step, _)| *step),
+                write_b.map(|(step, _)| *step),
+            ),
+        });
+    }
+
+    divergences.sort_by_key(Divergence::first_step);
+    divergences
+}
+
+impl Divergence {
+    fn is_recomputes_of(&self, key: &str) -> bool {
+        matches!(self.kind, DivergenceKind::Recomputes { .. }) && self.node() == Some(key)
+    }
+
+    fn is_final_revision_of(&self, key: &str) -> bool {
+        matches!(self.kind, DivergenceKind::FinalRevision { .. }) && self.node() == Some(key)
+    }
+}
+
+/**
+ * The steps at which each node recomputed.
+ */
+fn recomputes(trace: &Trace) -> BTreeMap<String, Vec<usize>> {
+    let mut recomputes: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+
+    for (step, event) in trace.events.iter().enumerate() {
+        if let TraceEvent::Recomputed { node, .. } = event {
+            recomputes.entry(node.clone()).or_default().push(step);
+        }
+    }
+
+    recomputes
+}
+
+fn steps<'a>(recomputes: &'a BTreeMap<String, Vec<usize>>, node: &str) -> &'a [usize] {
+    recomputes.get(node).map_or(&[], Vec::as_slice)
+}
+
+/**
+ * The step and revision of each cell's last write.
+ */
+fn last_writes(trace: &Trace) -> BTreeMap<String, (usize, u64)> {
+    let mut writes = BTreeMap::new();
+
+    for (step, event) in trace.events.iter().enumerate() {
+        if let TraceEvent::Written { node, to, .. } = event {
+            writes.insert(node.clone(), (step, *to));
+        }
+    }
+
+    writes
+}
+
+/**
+ * A line of a trace that isn't an event in the format.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl ParseError {
+    /**
+     * The line the error is on, counting from 1.
+     */
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} of the trace: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn write_string(json: &mut String, value: &str) {
+    json.push('"');
+
+    for char in value.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            char if char.is_control() => write!(json, "\\u{:04x}", char as u32).unwrap(),
+            char => json.push(char),
+        }
+    }
+
+    json.push('"');
+}
+
+enum Value {
+    String(String),
+    Number(u64),
+}
+
+/**
+ * The fields of one event. Only strings and unsigned integers are parsed, since those are the
+ * only values the format has.
+ */
+struct Fields(HashMap<String, Value>);
+
+impl Fields {
+    fn number(&mut self, name: &str) -> Result<u64, String> {
+        match self.0.remove(name) {
+            Some(Value::Number(number)) => Ok(number),
+            Some(Value::String(_)) => Err(format!("{} isn't a number", name)),
+            None => Err(format!("missing {}", name)),
+        }
+    }
+
+    fn string(&mut self, name: &str) -> Result<String, String> {
+        match self.0.remove(name) {
+            Some(Value::String(string)) => Ok(string),
+            Some(Value::Number(_)) => Err(format!("{} isn't a string", name)),
+            None => Err(format!("missing {}", name)),
+        }
+    }
+
+    fn event(&mut self) -> Result<TraceEvent, String> {
+        let event = self.string("event")?;
+
+        Ok(match event.as_str() {
+            "created" => TraceEvent::Created {
+                node: self.string("node")?,
+            },
+            "recomputed" => TraceEvent::Recomputed {
+                node: self.string("node")?,
+                consumed: self.number("consumed")? as usize,
+            },
+            "written" => TraceEvent::Written {
+                node: self.string("node")?,
+                from: self.number("from")?,
+                to: self.number("to")?,
+            },
+            "flush_scheduled" => TraceEvent::FlushScheduled,
+            "effect_flushed" => TraceEvent::EffectFlushed {
+                node: self.string("node")?,
+            },
+            "dropped" => TraceEvent::Dropped {
+                node: self.string("node")?,
+            },
+            other => return Err(format!("unknown event {:?}", other)),
+        })
+    }
+}
+
+fn parse_object(line: &str) -> Result<Fields, String> {
+    let mut chars = line.chars().peekable();
+    let mut fields = HashMap::new();
+
+    expect(&mut chars, '{')?;
+
+    if skip_whitespace(&mut chars) == Some('}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let name = parse_string(&mut chars)?;
+            expect(&mut chars, ':')?;
+
+            let value = match skip_whitespace(&mut chars) {
+                Some('"') => Value::String(parse_string(&mut chars)?),
+                Some(digit) if digit.is_ascii_digit() => Value::Number(parse_number(&mut chars)?),
+                Some(other) => {
+                    return Err(format!("unexpected {:?} in the value of {}", other, name))
+                }
+                None => return Err(format!("missing the value of {}", name)),
+            };
+            fields.insert(name, value);
+
+            match skip_whitespace(&mut chars) {
+                Some(',') => {
+                    chars.next();
+                }
+                Some('}') => {
+                    chars.next();
+                    break;
+                }
+                other => return Err(format!("expected , or }} but found {:?}", other)),
+            }
+        }
+    }
+
+    match skip_whitespace(&mut chars) {
+        None => Ok(Fields(fields)),
+        Some(other) => Err(format!("unexpected {:?} after the event", other)),
+    }
+}
+
+/**
+ * Skip whitespace, and return the character after it without consuming it.
+ */
+fn skip_whitespace(chars: &mut Peekable<Chars<'_>>) -> Option<char> {
+    while chars.peek().is_some_and(|char| char.is_whitespace()) {
+        chars.next();
+    }
+
+    chars.peek().copied()
+}
+
+fn expect(chars: &mut Peekable<Chars<'_>>, expected: char) -> Result<(), String> {
+    match skip_whitespace(chars) {
+        Some(char) if char == expected => {
+            chars.next();
+            Ok(())
+        }
+        other => Err(format!("expected {:?} but found {:?}", expected, other)),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars<'_>>) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut string = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('"') => string.push('"'),
+                Some('\\') => string.push('\\'),
+                Some('/') => string.push('/'),
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let char = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("invalid escape \\u{}", hex))?;
+                    string.push(char);
+                }
+                other => return Err(format!("invalid escape {:?}", other)),
+            },
+            Some(char) => string.push(char),
+            None => return Err(String::from("unterminated string")),
+        }
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars<'_>>) -> Result<u64, String> {
+    let mut digits = String::new();
+
+    while let Some(digit) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(digit);
+        chars.next();
+    }
+
+    digits
+        .parse()
+        .map_err(|_| format!("{} isn't an unsigned integer", digits))
+}
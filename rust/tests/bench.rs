@@ -0,0 +1,33 @@
+#![cfg(feature = "bench-helpers")]
+
+use everafter::Timeline;
+
+#[test]
+fn every_shape_propagates_writes_to_its_outputs() {
+    let timeline = Timeline::new();
+
+    let shapes = vec![
+        (timeline.build_chain(10), 1, 1),
+        (timeline.build_fan_out(10), 1, 10),
+        (timeline.build_fan_in(10), 10, 1),
+        (timeline.build_diamond(4, 3), 4, 4),
+    ];
+
+    for (graph, inputs, outputs) in shapes {
+        assert_eq!(graph.inputs.len(), inputs);
+        assert_eq!(graph.outputs.len(), outputs);
+        assert_eq!(graph.read_outputs(), 0);
+
+        graph.set_inputs(1);
+        assert!(graph.outputs.iter().all(|output| output.get() > 0));
+    }
+}
+
+#[test]
+fn every_layer_of_a_diamond_reads_the_one_below_it_twice() {
+    let timeline = Timeline::new();
+    let diamond = timeline.build_diamond(3, 4);
+
+    diamond.set_inputs(1);
+    assert!(diamond.outputs.iter().all(|output| output.get() == 16));
+}
@@ -0,0 +1,413 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use everafter::{untrack, ComputeStack, Timeline};
+
+#[test]
+fn cell_get_and_set() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(String::from("hello"));
+
+    assert_eq!(cell.get(), "hello");
+
+    cell.set(String::from("goodbye"));
+    assert_eq!(cell.get(), "goodbye");
+}
+
+#[test]
+fn cell_reads_are_tracked() {
+    let timeline = Timeline::new();
+    let first = timeline.cell(1);
+    let second = timeline.cell(2);
+
+    let (sum, dependencies) = ComputeStack::track(|| first.get() + second.get());
+    assert_eq!(sum, 3);
+    assert_eq!(dependencies.len(), 2);
+
+    let revision = dependencies.revision();
+
+    second.set(10);
+    assert!(
+        dependencies.revision() > revision,
+        "writing a consumed cell makes the computation stale"
+    );
+}
+
+#[test]
+fn cell_set_to_equal_value_keeps_revision() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let revision = cell.revision();
+
+    cell.set(1);
+    assert_eq!(
+        cell.revision(),
+        revision,
+        "equal value leaves revision alone"
+    );
+
+    cell.set(2);
+    assert!(
+        cell.revision() > revision,
+        "new value advances the revision"
+    );
+}
+
+#[test]
+fn cell_reads_outside_a_frame_are_untracked() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    assert!(!ComputeStack::is_tracking());
+    assert_eq!(cell.get(), 1, "reading outside a frame returns the value");
+
+    let (_, dependencies) = ComputeStack::track(|| ());
+    assert!(
+        dependencies.is_empty(),
+        "earlier reads don't leak into later frames"
+    );
+}
+
+#[test]
+fn equal_sets_do_not_recompute_dependents() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let cell = cell.clone();
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            cell.get()
+        })
+    };
+
+    assert_eq!(derived.get(), 1);
+
+    cell.set(1);
+    assert_eq!(derived.get(), 1);
+    assert_eq!(count.load(Ordering::SeqCst), 1, "an equal set is ignored");
+
+    cell.set(2);
+    assert_eq!(derived.get(), 2);
+    assert_eq!(count.load(Ordering::SeqCst), 2, "a real change recomputes");
+
+    cell.set_always(2);
+    assert_eq!(derived.get(), 2);
+    assert_eq!(
+        count.load(Ordering::SeqCst),
+        3,
+        "set_always always invalidates"
+    );
+}
+
+#[test]
+fn set_always_works_without_partial_eq() {
+    struct Opaque(u32);
+
+    let timeline = Timeline::new();
+    let cell = timeline.cell(std::rc::Rc::new(Opaque(1)));
+    let revision = cell.revision();
+
+    cell.set_always(std::rc::Rc::new(Opaque(2)));
+    assert!(cell.revision() > revision);
+    assert_eq!(cell.get().0, 2);
+}
+
+#[test]
+fn labels_fall_back_to_ids() {
+    let timeline = Timeline::new();
+    let named = timeline.cell(0).named("count");
+    let unnamed = timeline.cell(0);
+
+    assert_eq!(named.label(), "count");
+    assert!(unnamed.label().starts_with("cell#"), "{}", unnamed.label());
+    assert_ne!(unnamed.label(), timeline.cell(0).label());
+    assert!(format!("{:?}", named).contains("\"count\""));
+}
+
+#[test]
+fn peeks_are_not_dependencies() {
+    let timeline = Timeline::new();
+    let tracked = timeline.cell(1);
+    let peeked = timeline.cell(String::from("a"));
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let (tracked, peeked, count) = (tracked.clone(), peeked.clone(), count.clone());
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            format!(
+                "{}{}{}",
+                tracked.get(),
+                peeked.peek(),
+                peeked.peek_ref().len()
+            )
+        })
+    };
+
+    assert_eq!(derived.get(), "1a1");
+
+    peeked.set(String::from("bb"));
+    assert_eq!(derived.get(), "1a1", "peeked values are not dependencies");
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    tracked.set(2);
+    assert_eq!(derived.get(), "2bb2");
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    let (_, dependencies) = ComputeStack::track(|| peeked.peek());
+    assert!(dependencies.is_empty());
+}
+
+#[test]
+fn strict_writes_allow_writes_outside_of_deriveds() {
+    let timeline = Timeline::new();
+    timeline.set_strict_writes(true);
+
+    let source = timeline.cell(1);
+    let target = timeline.cell(0);
+
+    target.set(1);
+    ComputeStack::track(|| target.set(2));
+
+    let _copy = {
+        let (source, target) = (source.clone(), target.clone());
+        timeline.effect(move || target.set(source.get()))
+    };
+
+    source.set(5);
+    assert_eq!(target.get(), 5, "effects can write cells");
+}
+
+#[test]
+fn strict_writes_flag_writes_inside_deriveds() {
+    let timeline = Timeline::new();
+    timeline.set_strict_writes(true);
+
+    let count = timeline.cell(0).named("count");
+
+    let direct = {
+        let count = count.clone();
+        timeline.derived(move || count.set(1)).named("direct")
+    };
+
+    let untracked = {
+        let count = count.clone();
+        timeline
+            .derived(move || untrack(|| count.set(2)))
+            .named("untracked")
+    };
+
+    for (derived, name) in [(direct, "direct"), (untracked, "untracked")] {
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| derived.get())).unwrap_err();
+        let message = panicked.downcast_ref::<String>().unwrap();
+
+        assert!(
+            message.starts_with(&format!("count was written while {} was computing", name)),
+            "{}",
+            message
+        );
+    }
+
+    assert_eq!(count.get(), 0);
+}
+
+#[test]
+fn deriveds_can_write_cells_without_strict_writes() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0);
+
+    let writer = {
+        let count = count.clone();
+        timeline.derived(move || count.set(1))
+    };
+
+    assert!(!timeline.strict_writes());
+    writer.get();
+    assert_eq!(count.get(), 1);
+}
+
+#[test]
+fn updates_write_the_changed_value_once() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0);
+
+    for expected in 1..=3 {
+        let before = timeline.now();
+        count.update(|count| *count += 1);
+
+        assert_eq!(count.get(), expected);
+        assert_eq!(count.revision(), timeline.now());
+        assert_eq!(timeline.now(), before.increment());
+    }
+}
+
+#[test]
+fn updates_advance_the_revision_even_when_the_value_stays_equal() {
+    // not `Clone`, so the update can't copy it
+    struct Names(Vec<String>);
+
+    let timeline = Timeline::new();
+    let names = timeline.cell(Names(vec![String::from("Yehuda")]));
+    let before = names.revision();
+
+    names.update(|names| names.0.sort());
+    let updated = names.revision();
+    assert!(updated > before);
+
+    names.update(|names| names.0.push(String::from("Tom")));
+    assert!(names.revision() > updated);
+    assert_eq!(names.with(|names| names.0.len()), 2);
+}
+
+#[test]
+fn an_update_if_changed_that_leaves_the_value_equal_doesnt_advance_the_revision() {
+    let timeline = Timeline::new();
+    let name = timeline.cell(String::from("Yehuda"));
+    let before = name.revision();
+
+    name.update_if_changed(|name| name.make_ascii_lowercase());
+    name.update_if_changed(|name| *name = name.to_uppercase());
+    let changed = name.revision();
+    assert!(changed > before);
+
+    name.update_if_changed(|name| name.make_ascii_uppercase());
+    assert_eq!(name.revision(), changed);
+    assert_eq!(name.get(), "YEHUDA");
+}
+
+#[test]
+fn a_snapshot_cant_read_a_value_that_was_updated_in_place() {
+    let timeline = Timeline::new();
+    let (updated, compared) = (timeline.cell(1), timeline.cell(1));
+
+    let snapshot = timeline.snapshot();
+    updated.update(|count| *count += 1);
+    compared.update_if_changed(|count| *count += 1);
+
+    assert_eq!(updated.get_at(&snapshot), None);
+    assert_eq!(
+        compared.get_at(&snapshot),
+        Some(1),
+        "the comparison kept a copy"
+    );
+    assert_eq!((updated.get(), compared.get()), (2, 2));
+}
+
+#[test]
+fn updates_dont_track_the_value_they_read() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0).named("count");
+
+    let (_, dependencies) = ComputeStack::track(|| count.update(|count| *count += 1));
+    assert!(dependencies.is_empty());
+    assert_eq!(count.get(), 1);
+
+    timeline.set_strict_writes(true);
+    let bumper = {
+        let count = count.clone();
+        timeline
+            .derived(move || count.update(|count| *count += 1))
+            .named("bumper")
+    };
+
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| bumper.get())).unwrap_err();
+    let message = panicked.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("count was written while bumper was computing"));
+    assert_eq!(count.get(), 1);
+}
+
+/**
+ * A string that counts how many times it was cloned.
+ */
+struct Counted {
+    text: String,
+    clones: Arc<AtomicUsize>,
+}
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        self.clones.fetch_add(1, Ordering::SeqCst);
+
+        Counted {
+            text: self.text.clone(),
+            clones: self.clones.clone(),
+        }
+    }
+}
+
+impl PartialEq for Counted {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+#[test]
+fn with_lends_the_value_without_cloning_it() {
+    let timeline = Timeline::new();
+    let clones = Arc::new(AtomicUsize::new(0));
+    let counted = |text: String| Counted {
+        text,
+        clones: clones.clone(),
+    };
+
+    let text = timeline.cell(counted("x".repeat(100_000)));
+
+    let length = {
+        let text = text.clone();
+        timeline.derived(move || text.with(|counted| counted.text.len()))
+    };
+
+    assert_eq!(length.get(), 100_000);
+
+    text.set(counted("y".repeat(10)));
+    assert!(length.is_stale(), "the read was tracked");
+    assert_eq!(length.get(), 10);
+    assert_eq!(clones.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn readonly_handles_observe_writes_through_the_writer() {
+    let timeline = Timeline::new();
+    let (count, writer) = timeline.cell(1).named("count").split();
+    let reader = count.clone();
+
+    let doubled = timeline.derived(move || reader.get() * 2);
+    assert_eq!(doubled.get(), 2);
+
+    let before = count.revision();
+    // the write half is moved into the one place that owns it
+    let store = move |value: i32| writer.set(value);
+    store(5);
+
+    assert!(count.revision() > before);
+    assert_eq!(count.peek(), 5);
+    assert_eq!(count.label(), "count");
+    assert!(doubled.is_stale(), "reads through the handle are tracked");
+    assert_eq!(doubled.get(), 10);
+}
+
+#[test]
+fn readonly_handles_share_the_cells_storage() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(String::from("a"));
+    let reader = cell.readonly();
+
+    let (value, dependencies) = ComputeStack::track(|| reader.get());
+    assert_eq!(value, "a");
+    assert_eq!(dependencies.len(), 1);
+
+    let revision = dependencies.revision();
+    cell.set(String::from("b"));
+
+    assert!(dependencies.revision() > revision);
+    assert_eq!(reader.revision(), cell.revision());
+    assert_eq!(untrack(|| reader.get()), "b");
+}
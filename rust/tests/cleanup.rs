@@ -0,0 +1,166 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use everafter::{ComputeStack, Derived, Timeline};
+
+#[test]
+fn every_value_is_cleaned_up_once() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(0);
+    let events = Arc::new(Mutex::new(vec![]));
+
+    let resource = {
+        let (input, events) = (input.clone(), events.clone());
+        let cleaned = events.clone();
+        timeline.derived_with_cleanup(
+            move || {
+                let value = input.get();
+                events.lock().unwrap().push(format!("open {}", value));
+                value
+            },
+            move |value| cleaned.lock().unwrap().push(format!("close {}", value)),
+        )
+    };
+
+    assert_eq!(resource.get(), 0);
+
+    for i in 1..=3 {
+        input.set(i);
+        assert_eq!(resource.get(), i);
+    }
+
+    // reading an up to date derived doesn't clean anything up
+    assert_eq!(resource.get(), 3);
+    drop(resource);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec!["open 0", "open 1", "close 0", "open 2", "close 1", "open 3", "close 2", "close 3"]
+    );
+}
+
+#[test]
+fn deriveds_that_were_never_read_have_nothing_to_clean_up() {
+    let timeline = Timeline::new();
+    let cleanups = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let cleanups = cleanups.clone();
+        timeline.derived_with_cleanup(
+            || 1,
+            move |_| {
+                cleanups.fetch_add(1, Ordering::SeqCst);
+            },
+        )
+    };
+
+    drop(derived);
+    assert_eq!(cleanups.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn owned_effects_are_disposed_when_their_owner_recomputes() {
+    // the computation creates effects, so everything lives on the default timeline, which it can
+    // get at without capturing a timeline
+    Timeline::with_default(|timeline| {
+        let items = timeline.cell(2);
+        let ticks = timeline.cell(0);
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let children = {
+            let (items, ticks, runs) = (items.clone(), ticks.clone(), runs.clone());
+            timeline.derived(move || {
+                let count = items.get();
+
+                for _ in 0..count {
+                    let (ticks, runs) = (ticks.clone(), runs.clone());
+                    let child = Timeline::with_default(|timeline| {
+                        timeline.effect(move || {
+                            ticks.get();
+                            runs.fetch_add(1, Ordering::SeqCst);
+                        })
+                    });
+                    ComputeStack::own(child.into_subscription());
+                }
+
+                count
+            })
+        };
+
+        assert_eq!(children.get(), 2);
+        assert_eq!(timeline.subscription_count(), 2);
+
+        ticks.set(1);
+        assert_eq!(runs.load(Ordering::SeqCst), 4, "both children ran twice");
+
+        items.set(3);
+        assert_eq!(children.get(), 3);
+        assert_eq!(
+            timeline.subscription_count(),
+            3,
+            "the first two were disposed"
+        );
+
+        ticks.set(2);
+        assert_eq!(runs.load(Ordering::SeqCst), 4 + 3 + 3);
+
+        drop(children);
+        assert_eq!(timeline.subscription_count(), 0);
+    });
+}
+
+#[test]
+#[should_panic(expected = "outside of a derived or an effect")]
+fn owning_a_subscription_needs_a_computation() {
+    let timeline = Timeline::new();
+    let effect = timeline.effect(|| {});
+    ComputeStack::own(effect.into_subscription());
+}
+
+#[test]
+// a derived is only `Send` and `Sync` with the `sync` feature
+#[allow(clippy::arc_with_non_send_sync)]
+fn cleanups_can_read_the_derived_they_belong_to() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(0);
+    let seen = Arc::new(Mutex::new(vec![]));
+    let slot: Arc<Mutex<Option<Derived<i32>>>> = Arc::new(Mutex::new(None));
+
+    let resource = {
+        let (input, seen, slot) = (input.clone(), seen.clone(), slot.clone());
+        timeline.derived_with_cleanup(
+            move || input.get(),
+            move |old| {
+                let current = slot.lock().unwrap().clone();
+                if let Some(current) = current {
+                    seen.lock().unwrap().push((old, current.get()));
+                }
+            },
+        )
+    };
+    *slot.lock().unwrap() = Some(resource.clone());
+
+    assert_eq!(resource.get(), 0);
+
+    for i in 1..=2 {
+        input.set(i);
+        assert_eq!(resource.get(), i);
+    }
+
+    // the cleanup sees the value that replaced the one it cleans up
+    assert_eq!(*seen.lock().unwrap(), vec![(0, 1), (1, 2)]);
+
+    let (_, dependencies) = ComputeStack::track(|| {
+        input.set(3);
+        resource.get()
+    });
+    assert_eq!(
+        dependencies.len(),
+        1,
+        "what the cleanup reads isn't tracked by the reader"
+    );
+
+    slot.lock().unwrap().take();
+}
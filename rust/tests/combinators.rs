@@ -0,0 +1,295 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{zip, ComputeStack, Constant, ReactiveValue, Timeline};
+
+mod common;
+use common::counter;
+
+#[test]
+fn maps_recompute_once_per_change_of_their_source() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let unrelated = timeline.cell(0);
+    let mapped_runs = counter();
+
+    let source = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 10)
+    };
+
+    let mapped = {
+        let mapped_runs = mapped_runs.clone();
+        source.clone().map(move |value| {
+            mapped_runs.fetch_add(1, Ordering::SeqCst);
+            value + 1
+        })
+    };
+
+    assert_eq!(mapped_runs.load(Ordering::SeqCst), 0, "maps are lazy");
+    assert_eq!(mapped.get(), 11);
+
+    unrelated.set(1);
+    assert_eq!(mapped.get(), 11);
+    assert_eq!(mapped_runs.load(Ordering::SeqCst), 1);
+
+    for i in 2..=4 {
+        cell.set(i);
+        assert_eq!(mapped.get(), i * 10 + 1);
+    }
+
+    assert_eq!(mapped_runs.load(Ordering::SeqCst), 4);
+
+    let (_, dependencies) = ComputeStack::track(|| mapped.get());
+    assert_eq!(dependencies.len(), 1, "only the map itself is recorded");
+}
+
+#[test]
+fn maps_of_equal_values_cut_off_with_their_source() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(4);
+    let runs = counter();
+
+    let parity = {
+        let cell = cell.clone();
+        timeline.derived_with_eq(move || cell.get() % 2 == 0)
+    };
+
+    let label = {
+        let runs = runs.clone();
+        parity.map(move |even| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            if *even {
+                "even"
+            } else {
+                "odd"
+            }
+        })
+    };
+
+    assert_eq!(label.get(), "even");
+
+    cell.set(6);
+    assert_eq!(label.get(), "even");
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    cell.set(7);
+    assert_eq!(label.get(), "odd");
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn zips_recompute_when_either_side_changes() {
+    let timeline = Timeline::new();
+    let first = timeline.cell("Ada");
+    let last = timeline.cell("Lovelace");
+    let runs = counter();
+
+    let (first_name, last_name) = {
+        let (first, last) = (first.clone(), last.clone());
+        (
+            timeline.derived(move || first.get().to_string()),
+            timeline.derived(move || last.get().to_string()),
+        )
+    };
+
+    let full = {
+        let runs = runs.clone();
+        zip(&first_name, &last_name, move |first, last| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            format!("{} {}", first, last)
+        })
+    };
+
+    assert_eq!(runs.load(Ordering::SeqCst), 0, "zips are lazy");
+    assert_eq!(full.get(), "Ada Lovelace");
+
+    first.set("Augusta");
+    assert_eq!(full.get(), "Augusta Lovelace");
+
+    last.set("King");
+    assert_eq!(full.get(), "Augusta King");
+    assert_eq!(full.get(), "Augusta King");
+
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+/**
+ * A vector that counts how many times it was cloned.
+ */
+struct Counted {
+    items: Vec<i32>,
+    clones: Arc<AtomicUsize>,
+}
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        self.clones.fetch_add(1, Ordering::SeqCst);
+
+        Counted {
+            items: self.items.clone(),
+            clones: self.clones.clone(),
+        }
+    }
+}
+
+#[test]
+fn maps_and_zips_read_their_sources_without_cloning_them() {
+    let timeline = Timeline::new();
+    let clones = counter();
+    let length = timeline.cell(3);
+
+    let items = {
+        let (length, clones) = (length.clone(), clones.clone());
+        timeline.derived(move || Counted {
+            items: (1..=length.get()).collect(),
+            clones: clones.clone(),
+        })
+    };
+
+    let count = items.clone().map(|counted| counted.items.len() as i32);
+    let sum = items
+        .clone()
+        .map(|counted| counted.items.iter().sum::<i32>());
+    let average = zip(&sum, &count, |sum, count| sum / count);
+    // the second side reads the first, so it is validated before the first is lent out
+    let last = zip(&items, &count, |counted, count| {
+        counted.items[*count as usize - 1]
+    });
+    let doubled = zip(&items, &items, |a, b| a.items.len() + b.items.len());
+
+    assert_eq!(average.get(), 2);
+    assert_eq!(last.get(), 3);
+
+    length.set(5);
+    assert_eq!(average.get(), 3);
+    assert_eq!(last.get(), 5);
+    assert_eq!(clones.load(Ordering::SeqCst), 0);
+
+    assert_eq!(doubled.get(), 10);
+    assert_eq!(
+        clones.load(Ordering::SeqCst),
+        1,
+        "a derived zipped with itself is read once by reference"
+    );
+}
+
+#[test]
+fn lightweight_maps_only_call_their_function_when_the_source_changed() {
+    let timeline = Timeline::new();
+    let celsius = timeline.cell(100);
+    let calls = counter();
+
+    let fahrenheit = {
+        let calls = calls.clone();
+        celsius.clone().map(move |c: &i32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            c * 9 / 5 + 32
+        })
+    };
+
+    assert_eq!(fahrenheit.get(), 212);
+    assert_eq!(fahrenheit.get(), 212);
+    timeline.cell(0).set(1);
+    assert_eq!(fahrenheit.get(), 212);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    celsius.set(0);
+    assert_eq!(fahrenheit.revision(), celsius.revision());
+    assert_eq!(fahrenheit.get(), 32);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn lightweight_maps_are_tracked_through_their_source() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(2);
+    let doubled = count.clone().map(|count: &i32| count * 2);
+
+    let (value, dependencies) = ComputeStack::track(|| doubled.get());
+    assert_eq!(value, 4);
+    assert_eq!(dependencies.len(), 1, "only the cell is recorded");
+
+    let (_, cached) = ComputeStack::track(|| doubled.get());
+    assert_eq!(cached.len(), 1, "a cached read is recorded too");
+
+    let label = timeline.derived(move || format!("{} items", doubled.get()));
+    assert_eq!(label.get(), "4 items");
+
+    count.set(5);
+    assert!(label.is_stale());
+    assert_eq!(label.get(), "10 items");
+}
+
+#[test]
+fn lightweight_combinators_compose() {
+    let timeline = Timeline::new();
+    let price = timeline.cell(10);
+    let quantity = timeline.cell(3);
+
+    let subtotal = {
+        let quantity = quantity.clone();
+        timeline.derived(move || quantity.get() * 2)
+    };
+
+    let total = ReactiveValue::map(subtotal, |subtotal: &i32| subtotal + 1)
+        .zip(price.clone(), |subtotal: &i32, price: &i32| {
+            subtotal * price
+        })
+        .zip(Constant::new(100), |total: &i32, limit: &i32| {
+            *total.min(limit)
+        });
+
+    assert_eq!(total.get(), 70);
+
+    quantity.set(10);
+    assert_eq!(total.get(), 100);
+
+    price.set(1);
+    assert_eq!(total.get(), 21);
+    assert_eq!(total.revision(), price.revision());
+}
+
+#[derive(Clone, Debug)]
+struct Settings {
+    theme: &'static str,
+    volume: u32,
+}
+
+#[test]
+fn selectors_only_change_when_their_part_changes() {
+    let timeline = Timeline::new();
+    let theme = timeline.cell("dark");
+    let volume = timeline.cell(5);
+
+    let settings = {
+        let (theme, volume) = (theme.clone(), volume.clone());
+        timeline.derived(move || Settings {
+            theme: theme.get(),
+            volume: volume.get(),
+        })
+    };
+
+    let selected = settings.select(|settings| &settings.theme);
+    let runs = counter();
+    let styled = {
+        let runs = runs.clone();
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            format!("theme-{}", selected.get())
+        })
+    };
+
+    assert_eq!(styled.get(), "theme-dark");
+
+    volume.set(11);
+    assert_eq!(styled.get(), "theme-dark");
+    assert_eq!(settings.get().volume, 11);
+    assert_eq!(runs.load(Ordering::SeqCst), 1, "the volume isn't selected");
+
+    theme.set("light");
+    assert_eq!(styled.get(), "theme-light");
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
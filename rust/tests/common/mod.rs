@@ -1,3 +1,25 @@
+// every test that includes the module only uses some of its helpers
+#![allow(dead_code, unused_imports)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
 mod reactive;
 
 pub use reactive::*;
+
+/**
+ * A count for a computation to bump every time it runs.
+ */
+pub fn counter() -> Arc<AtomicUsize> {
+    Arc::new(AtomicUsize::new(0))
+}
+
+/**
+ * How many times the computations sharing `counter` ran.
+ */
+pub fn runs(counter: &Arc<AtomicUsize>) -> usize {
+    counter.load(Ordering::SeqCst)
+}
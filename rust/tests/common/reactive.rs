@@ -35,7 +35,7 @@ impl Test {
             .expect("cell unexpectedly initialized with a None revision");
 
         TestReactive {
-            desc: desc,
+            desc,
             handle: cell,
             marker: PhantomData,
             last_revision: Some(revision),
@@ -51,7 +51,7 @@ impl Test {
         let derived = timeline.derived(computation);
 
         TestReactive {
-            desc: desc,
+            desc,
             handle: derived,
             marker: PhantomData,
             last_revision: None,
@@ -111,20 +111,20 @@ where
     }
 
     pub fn output(&self, desc: &'static str, test: &Test) -> TestPrimitiveOutput<T> {
-        return TestPrimitiveOutput {
+        TestPrimitiveOutput {
             desc,
             output: test.timeline.output(self.handle),
-        };
+        }
     }
 }
 
-impl<T, K> Into<TypedInputId<T>> for &TestReactive<T, K>
+impl<T, K> From<&TestReactive<T, K>> for TypedInputId<T>
 where
     T: Debug + Clone + PartialEq + 'static,
     K: IdKindFor<T>,
 {
-    fn into(self) -> TypedInputId<T> {
-        self.handle().into()
+    fn from(reactive: &TestReactive<T, K>) -> TypedInputId<T> {
+        reactive.handle().into()
     }
 }
 
@@ -0,0 +1,240 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{track_reads, untrack, Cell, ComputeStack, Timeline};
+
+#[test]
+fn reading_a_value_twice_records_it_once() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let derived = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2)
+    };
+
+    let (sum, dependencies) =
+        ComputeStack::track(|| cell.get() + derived.get() + cell.get() + derived.get());
+
+    assert_eq!(sum, 6);
+    assert_eq!(dependencies.len(), 2);
+}
+
+#[test]
+fn frames_that_read_many_values_still_record_each_once() {
+    let timeline = Timeline::new();
+    let cells: Vec<Cell<usize>> = (0..32).map(|i| timeline.cell(i)).collect();
+
+    let (sum, dependencies) = ComputeStack::track(|| {
+        let first: usize = cells.iter().map(Cell::get).sum();
+        let second: usize = cells.iter().rev().map(Cell::get).sum();
+        first + second
+    });
+
+    assert_eq!(sum, 2 * (0..32).sum::<usize>());
+    assert_eq!(dependencies.len(), 32);
+}
+
+#[test]
+fn reused_frames_start_out_empty() {
+    ComputeStack::reserve(4);
+
+    let timeline = Timeline::new();
+    let use_left = timeline.cell(true);
+    let left = timeline.cell(1);
+    let right = timeline.cell(2);
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let (use_left, left, right) = (use_left.clone(), left.clone(), right.clone());
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+
+            if use_left.get() {
+                left.get()
+            } else {
+                right.get()
+            }
+        })
+    };
+
+    assert_eq!(derived.get(), 1);
+
+    use_left.set(false);
+    assert_eq!(derived.get(), 2);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    left.set(10);
+    assert_eq!(derived.get(), 2);
+    assert_eq!(
+        count.load(Ordering::SeqCst),
+        2,
+        "the dependencies from the first run were recycled, not kept"
+    );
+
+    right.set(20);
+    assert_eq!(derived.get(), 20);
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn track_reads_reports_exactly_what_was_read() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1).named("a");
+    let b = timeline.cell(2).named("b");
+    let c = timeline.cell(3).named("c");
+
+    let reads = track_reads(|| {
+        b.get();
+        a.get();
+        b.get();
+    });
+
+    assert_eq!(reads, vec![b.tag().unwrap().id(), a.tag().unwrap().id()]);
+    assert!(!reads.contains(&c.tag().unwrap().id()));
+
+    let labels: Vec<_> = reads.iter().map(|id| id.label()).collect();
+    assert_eq!(labels, vec!["b", "a"]);
+}
+
+#[test]
+fn track_reads_nests_inside_a_tracked_region() {
+    let timeline = Timeline::new();
+    let outer = timeline.cell(1);
+    let inner = timeline.cell(2);
+
+    let doubled = {
+        let inner = inner.clone();
+        timeline.derived(move || inner.get() * 2)
+    };
+
+    let (reads, dependencies) = ComputeStack::track(|| {
+        outer.get();
+        let reads = track_reads(|| {
+            doubled.get();
+            outer.get();
+        });
+        outer.get();
+        reads
+    });
+
+    assert_eq!(reads, vec![doubled.tag().id(), outer.tag().unwrap().id()]);
+    assert_eq!(
+        dependencies.len(),
+        2,
+        "the outer frame records the forwarded reads once"
+    );
+
+    let revision = dependencies.revision();
+    inner.set(3);
+    assert!(dependencies.revision() > revision);
+}
+
+#[test]
+fn ids_of_dropped_values_are_never_reused() {
+    let timeline = Timeline::new();
+
+    let ids: Vec<_> = (0..100)
+        .map(|i| {
+            let cell = timeline.cell(i);
+            track_reads(|| {
+                cell.get();
+            })
+            .remove(0)
+        })
+        .collect();
+
+    for (i, id) in ids.iter().enumerate() {
+        assert!(!ids[i + 1..].contains(id), "{} was reused", id.label());
+    }
+}
+
+#[test]
+fn depth_counts_nested_deriveds_and_untracked_frames() {
+    let timeline = Timeline::new();
+    assert_eq!(ComputeStack::depth(), 0);
+
+    let inner = timeline.derived(|| (ComputeStack::depth(), untrack(ComputeStack::depth)));
+    let outer = {
+        let inner = inner.clone();
+        timeline.derived(move || (ComputeStack::depth(), inner.get()))
+    };
+
+    assert_eq!(outer.get(), (1, (2, 3)));
+    assert_eq!(ComputeStack::depth(), 0);
+}
+
+#[test]
+#[should_panic(
+    expected = "can't start computing, because the compute stack is already 3 frames deep"
+)]
+fn computing_past_the_max_depth_panics() {
+    let timeline = Timeline::new();
+    timeline.set_max_compute_depth(Some(3));
+
+    let count = timeline.cell(0);
+    let mut chain = vec![timeline.derived(move || count.get())];
+    for _ in 0..3 {
+        let previous = chain.last().unwrap().clone();
+        chain.push(timeline.derived(move || previous.get() + 1));
+    }
+
+    chain[3].get();
+}
+
+#[test]
+fn cache_hits_record_the_reads_of_the_call_that_computed_them() {
+    let timeline = Timeline::new();
+    let (a, b) = (timeline.cell(1), timeline.cell(2));
+
+    let ((first, second), dependencies) = ComputeStack::track(|| {
+        let first = ComputeStack::cache_in_frame("key", || a.get());
+        // the hit returns the cached value without calling its closure or reading `b`
+        let second = ComputeStack::cache_in_frame("key", || b.get());
+        (first, second)
+    });
+
+    assert_eq!((first, second), (1, 1));
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(dependencies.revision(), a.revision());
+}
+
+#[test]
+fn cached_results_last_for_one_run_of_a_computation() {
+    let timeline = Timeline::new();
+    let source = timeline.cell(1);
+    let computed = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let (source, computed) = (source.clone(), computed.clone());
+        timeline.derived(move || {
+            let expensive = || {
+                computed.fetch_add(1, Ordering::SeqCst);
+                source.get() * 10
+            };
+
+            ComputeStack::cache_in_frame(0u8, expensive)
+                + ComputeStack::cache_in_frame(0u8, expensive)
+        })
+    };
+
+    assert_eq!(derived.get(), 20);
+    assert_eq!(computed.load(Ordering::SeqCst), 1);
+
+    source.set(2);
+    assert_eq!(derived.get(), 40);
+    assert_eq!(computed.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn caching_without_a_frame_calls_the_closure_every_time() {
+    let calls = AtomicUsize::new(0);
+    let call = || calls.fetch_add(1, Ordering::SeqCst);
+
+    ComputeStack::cache_in_frame("key", call);
+    untrack(|| ComputeStack::cache_in_frame("key", call));
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
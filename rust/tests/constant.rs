@@ -0,0 +1,138 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Cell, ComputeStack, Constant, Revision, Timeline};
+
+#[test]
+fn reading_constants_records_nothing() {
+    let config = Constant::new(String::from("production"));
+    let retries = Constant::from(3);
+
+    let (value, dependencies) =
+        ComputeStack::track(|| format!("{} x{}", config.get(), retries.get_ref()));
+
+    assert_eq!(value, "production x3");
+    assert!(dependencies.is_empty());
+}
+
+#[test]
+fn deriveds_over_constants_are_computed_once() {
+    let timeline = Timeline::new();
+    let schema = Constant::new(vec!["id", "name"]);
+    let unrelated = timeline.cell(0);
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let columns = {
+        let (schema, computations) = (schema.clone(), computations.clone());
+        timeline.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            schema.get_ref().len()
+        })
+    };
+
+    assert_eq!(columns.get(), 2);
+    assert_eq!(columns.revision(), Revision::CONSTANT);
+
+    for i in 1..10 {
+        unrelated.set(i);
+        assert_eq!(columns.get(), 2);
+        assert!(!columns.is_stale());
+    }
+
+    timeline.reset();
+    assert_eq!(columns.get(), 2);
+
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn reading_a_constant_derived_records_nothing() {
+    let timeline = Timeline::new();
+    let base = Constant::new(2);
+
+    let squared = {
+        let base = base.clone();
+        timeline.derived(move || base.get() * base.get())
+    };
+
+    let cubed = {
+        let (base, squared) = (base.clone(), squared.clone());
+        timeline.derived(move || squared.get() * base.get())
+    };
+
+    let (value, dependencies) = ComputeStack::track(|| cubed.get() + squared.get());
+    assert_eq!(value, 12);
+    assert!(dependencies.is_empty(), "neither derived read a cell");
+    assert_eq!(cubed.revision(), Revision::CONSTANT);
+}
+
+#[test]
+fn deriveds_that_also_read_a_cell_stay_tracked() {
+    let timeline = Timeline::new();
+    let rate = Constant::new(10);
+    let hours = timeline.cell(2);
+
+    let pay = {
+        let (rate, hours) = (rate.clone(), hours.clone());
+        timeline.derived(move || rate.get() * hours.get())
+    };
+
+    let (value, dependencies) = ComputeStack::track(|| pay.get());
+    assert_eq!(value, 20);
+    assert_eq!(dependencies.len(), 1);
+
+    hours.set(3);
+    assert!(pay.is_stale());
+    assert_eq!(pay.get(), 30);
+}
+
+#[test]
+fn deriveds_over_constant_cells_track_nothing() {
+    let timeline = Timeline::new();
+    let width = Cell::constant(3);
+    let height = timeline.constant(4);
+
+    let area = {
+        let (width, height) = (width.clone(), height.clone());
+        timeline.derived(move || width.get() * height.get())
+    };
+
+    let (value, dependencies) = ComputeStack::track(|| area.get() + width.get());
+    assert_eq!(value, 15);
+    assert!(dependencies.is_empty());
+    assert!(area.is_const());
+    assert_eq!(width.revision(), Revision::CONSTANT);
+}
+
+#[test]
+fn constant_cells_are_not_registered_with_a_timeline() {
+    let timeline = Timeline::new();
+    let _constants: Vec<Cell<usize>> = (0..1000).map(|i| timeline.constant(i)).collect();
+    let _cell = timeline.cell(0);
+
+    assert_eq!(timeline.snapshot().len(), 1);
+}
+
+#[test]
+fn subscriptions_to_constant_cells_never_fire() {
+    let constant = Cell::constant(1);
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let subscription = {
+        let calls = calls.clone();
+        constant.subscribe(move |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    subscription.unsubscribe();
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+#[should_panic(expected = "is a constant and can't be written")]
+fn constant_cells_cannot_be_written() {
+    Cell::constant(1).named("limit").set(2);
+}
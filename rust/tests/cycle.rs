@@ -0,0 +1,157 @@
+// the slots hold deriveds, which are only `Send + Sync` with the `sync` feature
+#![allow(clippy::arc_with_non_send_sync)]
+
+use std::sync::{Arc, Mutex};
+
+use everafter::{timeline::CycleError, Derived, Timeline};
+
+type Slot = Arc<Mutex<Option<Derived<i32>>>>;
+type Errors = Arc<Mutex<Vec<CycleError>>>;
+
+fn slot() -> Slot {
+    Arc::new(Mutex::new(None))
+}
+
+fn read(slot: &Slot, errors: &Errors) -> i32 {
+    let derived = slot.lock().unwrap().clone().expect("slot was filled");
+
+    match derived.try_get() {
+        Ok(value) => value,
+        Err(cycle) => {
+            errors.lock().unwrap().push(cycle);
+            -1
+        }
+    }
+}
+
+#[test]
+fn direct_self_read_is_a_cycle() {
+    let timeline = Timeline::new();
+    let me = slot();
+    let errors = Errors::default();
+
+    let derived = {
+        let (me, errors) = (me.clone(), errors.clone());
+        timeline.derived(move || read(&me, &errors))
+    };
+    *me.lock().unwrap() = Some(derived.clone());
+
+    assert_eq!(derived.get(), -1);
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].chain(), &[derived.id(), derived.id()]);
+}
+
+#[test]
+fn mutual_reads_are_a_cycle() {
+    let timeline = Timeline::new();
+    let a_slot = slot();
+    let errors = Errors::default();
+
+    let b = {
+        let (a_slot, errors) = (a_slot.clone(), errors.clone());
+        timeline.derived(move || read(&a_slot, &errors) + 1)
+    };
+
+    let a = {
+        let b = b.clone();
+        timeline.derived(move || b.get() + 1).named("a")
+    };
+    *a_slot.lock().unwrap() = Some(a.clone());
+
+    assert_eq!(a.get(), 1);
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].chain(), &[a.id(), b.id(), a.id()]);
+    assert_eq!(errors[0].labels(), &[a.label(), b.label(), a.label()]);
+    assert_eq!(
+        errors[0].to_string(),
+        format!("cycle detected: a -> {} -> a", b.label())
+    );
+}
+
+#[test]
+#[should_panic(expected = "cycle detected")]
+fn get_panics_on_a_cycle() {
+    let timeline = Timeline::new();
+    let me: Slot = slot();
+
+    let derived = {
+        let me = me.clone();
+        timeline.derived(move || me.lock().unwrap().clone().unwrap().get())
+    };
+    *me.lock().unwrap() = Some(derived.clone());
+
+    derived.get();
+}
+
+#[test]
+fn three_node_cycles_are_reported() {
+    let timeline = Timeline::new();
+    let a_slot = slot();
+    let errors = Errors::default();
+
+    let c = {
+        let (a_slot, errors) = (a_slot.clone(), errors.clone());
+        timeline.derived(move || read(&a_slot, &errors) + 1)
+    };
+
+    let b = {
+        let c = c.clone();
+        timeline.derived(move || c.get() + 1)
+    };
+
+    let a = {
+        let b = b.clone();
+        timeline.derived(move || b.get() + 1)
+    };
+    *a_slot.lock().unwrap() = Some(a.clone());
+
+    assert_eq!(a.get(), 2);
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].chain(), &[a.id(), b.id(), c.id(), a.id()]);
+}
+
+#[test]
+fn cycles_formed_while_revalidating_are_reported() {
+    let timeline = Timeline::new();
+    let closed = timeline.cell(false);
+    let a_slot = slot();
+    let errors = Errors::default();
+
+    let c = {
+        let (closed, a_slot, errors) = (closed.clone(), a_slot.clone(), errors.clone());
+        timeline.derived(move || {
+            if closed.get() {
+                read(&a_slot, &errors)
+            } else {
+                0
+            }
+        })
+    };
+
+    let b = {
+        let c = c.clone();
+        timeline.derived(move || c.get() + 1)
+    };
+
+    let a = {
+        let b = b.clone();
+        timeline.derived(move || b.get() + 1)
+    };
+    *a_slot.lock().unwrap() = Some(a.clone());
+
+    assert_eq!(a.get(), 2);
+    assert!(errors.lock().unwrap().is_empty());
+
+    closed.set(true);
+    assert_eq!(a.get(), 1);
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].chain(), &[a.id(), b.id(), c.id(), a.id()]);
+}
@@ -0,0 +1,95 @@
+use std::thread;
+
+use everafter::{Derived, MaybeSync, Timeline, ValidationMode};
+
+const DEPTH: usize = 100_000;
+
+// far too small for one stack frame per level of the chain
+const STACK_SIZE: usize = 256 * 1024;
+
+fn on_small_stack(test: impl FnOnce() + Send + 'static) {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .expect("the test overflowed its stack or failed");
+}
+
+/**
+ * A chain of `DEPTH` deriveds over `read`, each adding one to the previous one. Every level is
+ * read as it is created, since the first computation of a chain still recurses through it.
+ */
+fn chain(timeline: &Timeline, read: impl Fn() -> u64 + MaybeSync + 'static) -> Vec<Derived<u64>> {
+    let mut chain = vec![timeline.derived(read)];
+
+    for _ in 1..DEPTH {
+        let previous = chain.last().unwrap().clone();
+        let next = timeline.derived(move || previous.get() + 1);
+        next.get();
+        chain.push(next);
+    }
+
+    chain
+}
+
+/**
+ * Drop the chain from the leaf down, so no derived drops the rest of the chain recursively.
+ */
+fn tear_down(mut chain: Vec<Derived<u64>>) {
+    while chain.pop().is_some() {}
+}
+
+#[test]
+fn deep_chains_validate_without_overflowing() {
+    on_small_stack(|| {
+        let timeline = Timeline::new();
+        let root = timeline.cell(0u64);
+
+        let chain = {
+            let root = root.clone();
+            chain(&timeline, move || root.get())
+        };
+        let leaf = chain.last().unwrap().clone();
+        assert_eq!(leaf.get(), DEPTH as u64 - 1);
+
+        root.set(1);
+        assert_eq!(leaf.get(), DEPTH as u64);
+
+        // nothing changed, so validating walks the chain without recomputing it
+        timeline.cell(0).set(1);
+        assert_eq!(leaf.get(), DEPTH as u64);
+        assert!(leaf.revision() < timeline.now());
+
+        drop(leaf);
+        tear_down(chain);
+    });
+}
+
+#[test]
+fn deep_chains_recompute_dependencies_first() {
+    on_small_stack(|| {
+        let timeline = Timeline::new();
+        timeline.set_validation_mode(ValidationMode::Eager);
+        let root = timeline.cell(0u64);
+
+        let chain = {
+            let root = root.clone();
+            chain(&timeline, move || root.get())
+        };
+
+        // marking the chain dirty walks every reverse edge
+        root.set(10);
+        let leaf = &chain[DEPTH - 1];
+        assert!(leaf.is_stale());
+
+        let middle = &chain[DEPTH / 2];
+        assert_eq!(middle.get(), 10 + DEPTH as u64 / 2);
+        assert!(leaf.is_stale(), "reading the middle left the leaf alone");
+
+        assert_eq!(leaf.get(), 10 + DEPTH as u64 - 1);
+        assert_eq!(leaf.revision(), root.revision());
+
+        tear_down(chain);
+    });
+}
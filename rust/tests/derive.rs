@@ -0,0 +1,117 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{CachedMethods, Cell, ComputeStack, Timeline};
+use everafter_derive::{cached, Reactive};
+
+#[derive(Clone, Reactive)]
+struct Rectangle {
+    #[track]
+    width: Cell<u32>,
+    #[track]
+    height: Cell<u32>,
+    label: &'static str,
+    computations: Arc<AtomicUsize>,
+    cached: CachedMethods,
+}
+
+impl Rectangle {
+    fn new(timeline: &Timeline, width: u32, height: u32) -> Rectangle {
+        Rectangle {
+            width: timeline.cell(width),
+            height: timeline.cell(height),
+            label: "rectangle",
+            computations: Arc::new(AtomicUsize::new(0)),
+            cached: timeline.cached_methods(),
+        }
+    }
+
+    #[cached]
+    fn area(&self) -> u32 {
+        self.computations.fetch_add(1, Ordering::SeqCst);
+        self.width() * self.height()
+    }
+
+    /// Cached methods can call other cached methods.
+    #[cached]
+    fn description(&self) -> String {
+        format!("{} of {}", self.label, self.area())
+    }
+
+    fn computations(&self) -> usize {
+        self.computations.load(Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn tracked_fields_get_getters_and_setters() {
+    let timeline = Timeline::new();
+    let rectangle = Rectangle::new(&timeline, 2, 3);
+
+    let (area, dependencies) = ComputeStack::track(|| rectangle.width() * rectangle.height());
+    assert_eq!(area, 6);
+    assert_eq!(dependencies.len(), 2, "getters are tracked");
+
+    let before = dependencies.revision();
+    rectangle.set_width(4);
+
+    assert_eq!(rectangle.width(), 4);
+    assert!(dependencies.revision() > before);
+
+    rectangle.set_width(4);
+    assert_eq!(
+        rectangle.width.revision(),
+        timeline.now(),
+        "equal writes are skipped"
+    );
+}
+
+#[test]
+fn cached_methods_are_memoized_until_a_field_changes() {
+    let timeline = Timeline::new();
+    let rectangle = Rectangle::new(&timeline, 2, 3);
+
+    assert_eq!(rectangle.area(), 6);
+    assert_eq!(rectangle.area(), 6);
+    assert_eq!(rectangle.computations(), 1);
+
+    rectangle.set_height(10);
+    assert_eq!(rectangle.area(), 20);
+    assert_eq!(rectangle.computations(), 2);
+
+    assert_eq!(rectangle.description(), "rectangle of 20");
+    assert_eq!(rectangle.computations(), 2);
+    assert_eq!(rectangle.cached.len(), 2);
+}
+
+#[test]
+fn clones_share_cached_methods() {
+    let timeline = Timeline::new();
+    let rectangle = Rectangle::new(&timeline, 2, 3);
+    assert_eq!(rectangle.area(), 6);
+
+    let clone = rectangle.clone();
+    assert_eq!(clone.area(), 6);
+    assert_eq!(clone.computations(), 1);
+
+    clone.set_width(5);
+    assert_eq!(rectangle.area(), 15);
+    assert_eq!(clone.area(), 15);
+    assert_eq!(rectangle.computations(), 2);
+}
+
+#[test]
+fn cached_methods_do_not_keep_their_value_alive() {
+    let timeline = Timeline::new();
+    let rectangle = Rectangle::new(&timeline, 2, 3);
+    let width = rectangle.width.clone();
+
+    assert_eq!(rectangle.description(), "rectangle of 6");
+    let cached = Arc::downgrade(&rectangle.computations);
+    drop(rectangle);
+
+    assert!(cached.upgrade().is_none(), "the deriveds were dropped");
+    width.set(4);
+}
@@ -0,0 +1,839 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::Ordering, Arc, Mutex},
+};
+
+use everafter::{ComputeStack, Derived, Timeline, Validation, ValidationMode};
+
+mod common;
+use common::{counter, runs};
+
+#[test]
+fn derived_is_memoized() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+    let count = counter();
+
+    let derived = {
+        let input = input.clone();
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            input.get() + 1
+        })
+    };
+
+    assert_eq!(runs(&count), 0, "deriveds are lazy");
+
+    assert_eq!(derived.get(), 2);
+    assert_eq!(derived.get(), 2);
+    assert_eq!(derived.get(), 2);
+    assert_eq!(runs(&count), 1, "unchanged inputs reuse the cached value");
+
+    input.set(5);
+    assert_eq!(derived.get(), 6);
+    assert_eq!(derived.get(), 6);
+    assert_eq!(runs(&count), 2, "a changed input recomputes once");
+}
+
+#[test]
+fn derived_over_derived() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+    let inner_count = counter();
+    let outer_count = counter();
+
+    let inner = {
+        let input = input.clone();
+        let count = inner_count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            input.get() * 10
+        })
+    };
+
+    let outer = {
+        let inner = inner.clone();
+        let count = outer_count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            inner.get() + 1
+        })
+    };
+
+    assert_eq!(outer.get(), 11);
+    assert_eq!(outer.get(), 11);
+    assert_eq!((runs(&inner_count), runs(&outer_count)), (1, 1));
+
+    input.set(2);
+    assert_eq!(outer.get(), 21, "transitive dependencies invalidate");
+    assert_eq!((runs(&inner_count), runs(&outer_count)), (2, 2));
+
+    assert_eq!(inner.get(), 20);
+    assert_eq!(
+        runs(&inner_count),
+        2,
+        "the inner derived was already refreshed"
+    );
+}
+
+#[test]
+fn unrelated_writes_do_not_recompute() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+    let unrelated = timeline.cell(1);
+    let count = counter();
+
+    let derived = {
+        let input = input.clone();
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            input.get()
+        })
+    };
+
+    assert_eq!(derived.get(), 1);
+
+    unrelated.set(2);
+    assert_eq!(derived.get(), 1);
+    assert_eq!(runs(&count), 1);
+}
+
+#[test]
+fn diamond_dependencies() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+    let bottom_count = counter();
+
+    let left = {
+        let input = input.clone();
+        timeline.derived(move || input.get() + 1)
+    };
+
+    let right = {
+        let input = input.clone();
+        timeline.derived(move || input.get() * 2)
+    };
+
+    let bottom = {
+        let (left, right) = (left.clone(), right.clone());
+        let count = bottom_count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            left.get() + right.get()
+        })
+    };
+
+    assert_eq!(bottom.get(), 4);
+
+    input.set(3);
+    assert_eq!(bottom.get(), 10);
+    assert_eq!(bottom.get(), 10);
+    assert_eq!(
+        runs(&bottom_count),
+        2,
+        "the shared input recomputes the bottom once"
+    );
+}
+
+#[test]
+fn dynamic_dependencies_are_replaced() {
+    let timeline = Timeline::new();
+    let flag = timeline.cell(true);
+    let a = timeline.cell("a");
+    let b = timeline.cell("b");
+    let count = counter();
+
+    let derived = {
+        let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            if flag.get() {
+                a.get()
+            } else {
+                b.get()
+            }
+        })
+    };
+
+    assert_eq!(derived.get(), "a");
+
+    flag.set(false);
+    assert_eq!(derived.get(), "b");
+    assert_eq!(runs(&count), 2);
+
+    a.set("new a");
+    assert_eq!(derived.get(), "b");
+    assert_eq!(
+        runs(&count),
+        2,
+        "the branch that wasn't taken is no longer a dependency"
+    );
+
+    b.set("new b");
+    assert_eq!(derived.get(), "new b");
+    assert_eq!(runs(&count), 3);
+}
+
+#[test]
+fn equal_values_cut_off_propagation() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(String::from("42"));
+    let parses = counter();
+    let renders = counter();
+
+    let parsed = {
+        let (input, parses) = (input.clone(), parses.clone());
+        timeline.derived_with_eq(move || {
+            parses.fetch_add(1, Ordering::SeqCst);
+            input.get().trim().parse::<i32>().unwrap()
+        })
+    };
+
+    let rendered = {
+        let (parsed, renders) = (parsed.clone(), renders.clone());
+        timeline.derived(move || {
+            renders.fetch_add(1, Ordering::SeqCst);
+            format!("<{}>", parsed.get())
+        })
+    };
+
+    assert_eq!(rendered.get(), "<42>");
+
+    input.set(String::from("  42 "));
+    assert_eq!(rendered.get(), "<42>");
+    assert_eq!(runs(&parses), 2, "the parse re-ran");
+    assert_eq!(runs(&renders), 1, "but produced an equal value");
+
+    input.set(String::from("43"));
+    assert_eq!(rendered.get(), "<43>");
+    assert_eq!(runs(&renders), 2);
+}
+
+#[test]
+fn custom_equality_cuts_off_fresh_allocations() {
+    let timeline = Timeline::new();
+    let words = timeline.cell(String::from("a b"));
+    let unrelated = timeline.cell(0);
+    let splits = counter();
+    let joins = counter();
+
+    let split = {
+        let (words, unrelated, splits) = (words.clone(), unrelated.clone(), splits.clone());
+        timeline.derived_with_eq_by(
+            move || {
+                splits.fetch_add(1, Ordering::SeqCst);
+                unrelated.get();
+                words
+                    .get()
+                    .split_whitespace()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            },
+            |old: &Vec<String>, new: &Vec<String>| old[..] == new[..],
+        )
+    };
+
+    let joined = {
+        let (split, joins) = (split.clone(), joins.clone());
+        timeline.derived(move || {
+            joins.fetch_add(1, Ordering::SeqCst);
+            split.get().join(",")
+        })
+    };
+
+    assert_eq!(joined.get(), "a,b");
+
+    // every run allocates a new `Vec`, but its contents are the same
+    unrelated.set(1);
+    words.set(String::from("a  b"));
+    assert_eq!(joined.get(), "a,b");
+    assert_eq!(runs(&splits), 2);
+    assert_eq!(runs(&joins), 1, "the downstream derived didn't recompute");
+
+    words.set(String::from("a b c"));
+    assert_eq!(joined.get(), "a,b,c");
+    assert_eq!(runs(&joins), 2);
+}
+
+#[test]
+fn custom_equality_can_compare_part_of_a_value() {
+    let timeline = Timeline::new();
+    let name = timeline.cell("first");
+    let renders = counter();
+
+    // only the id matters to dependents, even though the name is part of the value
+    let record = {
+        let name = name.clone();
+        timeline.derived_with_eq_by(move || (1, name.get()), |old, new| old.0 == new.0)
+    };
+
+    let id = {
+        let (record, renders) = (record.clone(), renders.clone());
+        timeline.derived(move || {
+            renders.fetch_add(1, Ordering::SeqCst);
+            record.get().0
+        })
+    };
+
+    assert_eq!(id.get(), 1);
+    name.set("second");
+    assert_eq!(id.get(), 1);
+    assert_eq!(runs(&renders), 1);
+    assert_eq!(record.get().1, "second", "the new value is still cached");
+}
+
+#[test]
+fn deriveds_without_eq_always_propagate() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+    let renders = counter();
+
+    let parity = {
+        let input = input.clone();
+        timeline.derived(move || input.get() % 2)
+    };
+
+    let rendered = {
+        let (parity, renders) = (parity.clone(), renders.clone());
+        timeline.derived(move || {
+            renders.fetch_add(1, Ordering::SeqCst);
+            parity.get()
+        })
+    };
+
+    assert_eq!(rendered.get(), 1);
+
+    input.set(3);
+    assert_eq!(rendered.get(), 1);
+    assert_eq!(runs(&renders), 2);
+}
+
+#[test]
+fn labels_appear_in_debug_output() {
+    let timeline = Timeline::new();
+    let total = timeline.derived(|| 1).named(String::from("total"));
+    let unnamed = timeline.derived(|| 1);
+
+    assert_eq!(total.label(), "total");
+    assert_eq!(format!("{:?}", total), "Derived<i32>(total)");
+    assert_eq!(unnamed.label(), format!("derived{}", unnamed.id()));
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DivideByZero;
+
+#[test]
+fn errors_are_cached_until_an_input_changes() {
+    let timeline = Timeline::new();
+    let numerator = timeline.cell(10);
+    let divisor = timeline.cell(0);
+    let count = counter();
+
+    let quotient = {
+        let (numerator, divisor, count) = (numerator.clone(), divisor.clone(), count.clone());
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+
+            match divisor.get() {
+                0 => Err(DivideByZero),
+                divisor => Ok(numerator.get() / divisor),
+            }
+        })
+    };
+
+    assert_eq!(quotient.get(), Err(DivideByZero));
+    assert_eq!(quotient.get(), Err(DivideByZero));
+    assert_eq!(runs(&count), 1, "the error is cached");
+
+    numerator.set(20);
+    assert_eq!(quotient.get(), Err(DivideByZero));
+    assert_eq!(runs(&count), 1, "the failing run never read the numerator");
+
+    divisor.set(5);
+    assert_eq!(quotient.get(), Ok(4));
+    assert_eq!(runs(&count), 2);
+
+    numerator.set(30);
+    assert_eq!(quotient.get(), Ok(6));
+
+    divisor.set(0);
+    assert_eq!(quotient.get(), Err(DivideByZero));
+    assert_eq!(runs(&count), 4);
+}
+
+#[test]
+fn errors_propagate_through_question_marks() {
+    let timeline = Timeline::new();
+    let divisor = timeline.cell(0);
+    let count = counter();
+
+    let quotient = {
+        let divisor = divisor.clone();
+        timeline.derived(move || match divisor.get() {
+            0 => Err(DivideByZero),
+            divisor => Ok(100 / divisor),
+        })
+    };
+
+    let doubled = {
+        let (quotient, count) = (quotient.clone(), count.clone());
+        timeline.derived(move || -> Result<i32, DivideByZero> {
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(quotient.get()? * 2)
+        })
+    };
+
+    assert_eq!(doubled.get(), Err(DivideByZero));
+    assert_eq!(doubled.get(), Err(DivideByZero));
+    assert_eq!(runs(&count), 1);
+
+    divisor.set(10);
+    assert_eq!(doubled.get(), Ok(20));
+    assert_eq!(runs(&count), 2);
+}
+
+#[test]
+fn deriveds_that_track_nothing_are_const() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(2);
+
+    let constant = timeline.derived(|| 6 * 7);
+    let tracked = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2)
+    };
+
+    assert!(
+        !constant.is_const(),
+        "nothing is known before the first computation"
+    );
+    assert!(constant.is_dirty());
+
+    assert_eq!(constant.get(), 42);
+    assert_eq!(tracked.get(), 4);
+    assert!(constant.is_const());
+    assert!(!tracked.is_const());
+
+    cell.set(3);
+    assert!(!constant.is_dirty());
+    assert!(tracked.is_dirty());
+
+    let (_, dependencies) = ComputeStack::track(|| {
+        tracked.is_dirty();
+        tracked.is_const();
+    });
+    assert!(dependencies.is_empty(), "asking doesn't read the derived");
+    assert!(tracked.is_dirty());
+
+    assert_eq!(tracked.get(), 6);
+    assert!(!tracked.is_dirty());
+}
+
+#[test]
+fn stats_count_runs_and_cache_hits() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+
+    let doubled = {
+        let input = input.clone();
+        timeline.derived(move || input.get() * 2)
+    };
+
+    let stats = doubled.stats();
+    assert_eq!((stats.runs(), stats.hits()), (0, 0));
+    assert_eq!(stats.recomputed_at(), None);
+
+    for _ in 0..5 {
+        assert_eq!(doubled.get(), 2);
+    }
+
+    let stats = doubled.stats();
+    assert_eq!(stats.runs(), 1);
+    assert_eq!(stats.hits(), 4);
+    assert_eq!(stats.recomputed_at(), Some(timeline.now()));
+
+    timeline.cell(0).set(1);
+    assert_eq!(doubled.get(), 2);
+    assert_eq!(doubled.stats().runs(), 1, "validating isn't a run");
+    assert_eq!(doubled.stats().hits(), 5);
+
+    input.set(2);
+    doubled.revision();
+    let stats = doubled.stats();
+    assert_eq!(stats.runs(), 2);
+    assert_eq!(stats.hits(), 5, "validating for a dependent isn't a read");
+    assert_eq!(stats.recomputed_at(), Some(input.revision()));
+}
+
+#[test]
+fn stats_count_reads_from_dependents() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+
+    let inner = {
+        let input = input.clone();
+        timeline.derived(move || input.get() + 1)
+    };
+
+    let (first, second) = {
+        let (a, b) = (inner.clone(), inner.clone());
+        (
+            timeline.derived(move || a.get() * 2),
+            timeline.derived(move || b.get() * 3),
+        )
+    };
+
+    assert_eq!(first.get() + second.get(), 10);
+    assert_eq!(inner.stats().runs(), 1);
+    assert_eq!(
+        inner.stats().hits(),
+        1,
+        "the second dependent hit the cache"
+    );
+}
+
+#[test]
+fn frozen_deriveds_recompute_once_when_unfrozen() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+
+    let doubled = {
+        let input = input.clone();
+        timeline.derived(move || input.get() * 2)
+    };
+    let label = {
+        let doubled = doubled.clone();
+        timeline.derived(move || doubled.get().to_string())
+    };
+
+    assert_eq!(label.get(), "2");
+
+    doubled.freeze();
+    input.set(2);
+    input.set(3);
+    assert!(!doubled.is_stale(), "a frozen derived won't recompute");
+    assert_eq!(doubled.get(), 2);
+    assert_eq!(label.get(), "2", "dependents see the frozen value");
+    assert_eq!(doubled.stats().runs(), 1);
+
+    doubled.unfreeze();
+    assert_eq!(doubled.stats().runs(), 2, "unfreezing caught up right away");
+    assert_eq!(doubled.get(), 6);
+    assert_eq!(label.get(), "6");
+    assert_eq!(doubled.stats().runs(), 2);
+
+    input.set(4);
+    assert_eq!(doubled.get(), 8, "tracking resumes after unfreezing");
+}
+
+#[test]
+fn unfreezing_an_unchanged_derived_does_not_recompute() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+
+    let doubled = {
+        let input = input.clone();
+        timeline.derived(move || input.get() * 2)
+    };
+
+    doubled.freeze();
+    assert_eq!(doubled.get(), 2, "the first read still computes");
+    assert!(doubled.is_frozen());
+
+    timeline.cell(0).set(1);
+    doubled.unfreeze();
+    assert!(!doubled.is_frozen());
+    assert_eq!(doubled.get(), 2);
+    assert_eq!(doubled.stats().runs(), 1);
+}
+
+#[test]
+fn a_derived_dropped_while_it_is_being_validated_finishes() {
+    for mode in [ValidationMode::Lazy, ValidationMode::Eager] {
+        let timeline = Timeline::new();
+        timeline.set_validation_mode(mode);
+        let cell = timeline.cell(1);
+        // handles are only `Send` with the `sync` feature
+        #[allow(clippy::arc_with_non_send_sync)]
+        let registry: Arc<Mutex<Vec<Derived<i32>>>> = Arc::new(Mutex::new(vec![]));
+
+        // reading the leaf empties the registry, which drops the middle derived that read it
+        let leaf = {
+            let (registry, cell) = (registry.clone(), cell.clone());
+            timeline.derived(move || {
+                registry.lock().unwrap().clear();
+                cell.get()
+            })
+        };
+        registry.lock().unwrap().push({
+            let leaf = leaf.clone();
+            timeline.derived(move || leaf.get() * 2)
+        });
+
+        let outer = {
+            let registry = registry.clone();
+            timeline.derived(move || {
+                let middle = registry.lock().unwrap().first().cloned();
+                middle.map_or(-1, |middle| middle.get())
+            })
+        };
+
+        assert_eq!(outer.get(), 2);
+        assert!(registry.lock().unwrap().is_empty());
+
+        // the dropped derived never invalidates the outer one again
+        cell.set(5);
+        assert!(!outer.is_dirty(), "{:?}", mode);
+        assert_eq!(outer.get(), 2);
+        assert_eq!(outer.stats().runs(), 1);
+        assert_eq!(leaf.get(), 5, "the leaf still tracks the cell");
+    }
+}
+
+#[test]
+fn reading_a_derived_inside_its_own_with_panics_instead_of_waiting() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let doubled = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2).named("doubled")
+    };
+    let next = {
+        let doubled = doubled.clone();
+        timeline.derived(move || doubled.get() + 1)
+    };
+
+    let message = |panicked: Box<dyn std::any::Any + Send>| match panicked.downcast::<String>() {
+        Ok(message) => *message,
+        Err(_) => String::from("a panic without a message"),
+    };
+
+    let direct = panic::catch_unwind(AssertUnwindSafe(|| doubled.with(|_| doubled.get())));
+    let direct = message(direct.unwrap_err());
+    assert!(
+        direct.starts_with("doubled was read inside its own `Derived::with`"),
+        "{}",
+        direct
+    );
+
+    // the stale reader validates `doubled`, which has to lock it
+    cell.set(2);
+    let nested = panic::catch_unwind(AssertUnwindSafe(|| doubled.with(|_| next.get())));
+    assert!(message(nested.unwrap_err()).contains("inside its own `Derived::with`"));
+
+    // `next` panicked while it validated, which poisons it like any other panic in a computation
+    assert_eq!(doubled.with(|value| *value), 4, "the lock was released");
+    assert_eq!(doubled.get(), 4);
+}
+
+#[test]
+fn a_branch_only_tracks_the_side_it_took() {
+    let timeline = Timeline::new();
+    let flag = timeline.cell(true);
+    let (a, b) = (timeline.cell(1), timeline.cell(2));
+
+    let chosen = {
+        let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+        timeline.branch(move || flag.get(), move || a.get(), move || b.get())
+    };
+    assert_eq!(chosen.get(), 1);
+
+    b.set(20);
+    assert_eq!(chosen.get(), 1);
+    assert_eq!(
+        chosen.stats().runs(),
+        1,
+        "the inactive branch doesn't recompute"
+    );
+
+    flag.set(false);
+    assert_eq!(chosen.get(), 20);
+    assert_eq!(chosen.dependency_count(), 2);
+
+    a.set(10);
+    assert_eq!(chosen.get(), 20);
+    assert_eq!(
+        chosen.stats().runs(),
+        2,
+        "the branch that was left is no longer tracked"
+    );
+
+    b.set(30);
+    assert_eq!(chosen.get(), 30);
+    assert_eq!(chosen.stats().runs(), 3);
+}
+
+#[test]
+fn lazy_deriveds_run_on_read_and_eager_ones_run_on_flush() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1);
+    let (lazy_runs, eager_runs) = (counter(), counter());
+
+    let lazy = {
+        let (count, runs) = (count.clone(), lazy_runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            count.get() * 2
+        })
+    };
+    let eager = {
+        let (count, runs) = (count.clone(), eager_runs.clone());
+        timeline.eager_derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            count.get() * 2
+        })
+    };
+
+    assert_eq!((runs(&lazy_runs), runs(&eager_runs)), (0, 1));
+
+    count.set(2);
+    assert_eq!((runs(&lazy_runs), runs(&eager_runs)), (0, 2));
+
+    assert_eq!((lazy.get(), eager.get()), (4, 4));
+    assert_eq!((runs(&lazy_runs), runs(&eager_runs)), (1, 2));
+    assert_eq!(eager.stats().hits(), 1, "the flush doesn't count as a read");
+}
+
+#[test]
+fn eager_deriveds_retrack_their_dependencies() {
+    let timeline = Timeline::new();
+    let flag = timeline.cell(true);
+    let (a, b) = (timeline.cell(1), timeline.cell(2));
+    let runs_counter = counter();
+
+    let chosen = {
+        let (flag, a, b, runs) = (flag.clone(), a.clone(), b.clone(), runs_counter.clone());
+        timeline.eager_derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            if flag.get() {
+                a.get()
+            } else {
+                b.get()
+            }
+        })
+    };
+
+    b.set(20);
+    assert_eq!(runs(&runs_counter), 1);
+
+    flag.set(false);
+    assert_eq!(runs(&runs_counter), 2);
+    b.set(30);
+    assert_eq!(runs(&runs_counter), 3);
+    assert_eq!(chosen.get(), 30);
+
+    drop(chosen);
+    b.set(40);
+    assert_eq!(
+        runs(&runs_counter),
+        3,
+        "a dropped eager derived stops recomputing"
+    );
+}
+
+#[test]
+fn validate_only_reports_staleness_without_running_any_computation() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1);
+    let unrelated = timeline.cell(0);
+    let computed = counter();
+
+    let parity = {
+        let (count, computed) = (count.clone(), computed.clone());
+        timeline.derived_with_eq(move || {
+            computed.fetch_add(1, Ordering::SeqCst);
+            count.get() % 2
+        })
+    };
+    let label = {
+        let (parity, computed) = (parity.clone(), computed.clone());
+        timeline.derived(move || {
+            computed.fetch_add(1, Ordering::SeqCst);
+            if parity.get() == 0 {
+                "even"
+            } else {
+                "odd"
+            }
+        })
+    };
+
+    assert_eq!(
+        label.validate_only(),
+        Validation::Stale {
+            dirty_dependencies: 0
+        }
+    );
+    assert_eq!(runs(&computed), 0);
+
+    assert_eq!(label.get(), "odd");
+    assert_eq!(runs(&computed), 2);
+
+    unrelated.set(1);
+    assert_eq!(label.validate_only(), Validation::Clean);
+
+    // parity recomputes the same value, but finding that out means running it
+    count.set(3);
+    assert_eq!(
+        label.validate_only(),
+        Validation::Stale {
+            dirty_dependencies: 1
+        }
+    );
+    assert_eq!(
+        parity.validate_only(),
+        Validation::Stale {
+            dirty_dependencies: 1
+        }
+    );
+    assert_eq!(runs(&computed), 2);
+
+    assert_eq!(label.get(), "odd");
+    assert_eq!(runs(&computed), 3);
+    assert_eq!(label.validate_only(), Validation::Clean);
+}
+
+#[test]
+fn derived_with_clones_up_to_six_handles_for_the_computation() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(2).readonly();
+    let three_cell = timeline.cell(3);
+    let c = timeline.derived_with(&three_cell, |cell| cell.get());
+    let (d, e, f) = (timeline.cell(4), timeline.cell(5), timeline.cell(6));
+
+    let one = timeline.derived_with(&a, |a| a.get());
+    let single = timeline.derived_with((&a,), |(a,)| a.get());
+    let two = timeline.derived_with((&a, &b), |(a, b)| a.get() + b.get());
+    let three = timeline.derived_with((&a, &b, &c), |(a, b, c)| a.get() + b.get() + c.get());
+    let four = timeline.derived_with((&a, &b, &c, &d), |(a, b, c, d)| {
+        a.get() + b.get() + c.get() + d.get()
+    });
+    let five = timeline.derived_with((&a, &b, &c, &d, &e), |(a, b, c, d, e)| {
+        a.get() + b.get() + c.get() + d.get() + e.get()
+    });
+    let six = timeline.derived_with((&a, &b, &c, &d, &e, &f), |(a, b, c, d, e, f)| {
+        a.get() + b.get() + c.get() + d.get() + e.get() + f.get()
+    });
+
+    let sums = || {
+        [
+            one.get(),
+            single.get(),
+            two.get(),
+            three.get(),
+            four.get(),
+            five.get(),
+            six.get(),
+        ]
+    };
+    assert_eq!(sums(), [1, 1, 3, 6, 10, 15, 21]);
+
+    a.set(10);
+    f.set(60);
+    assert_eq!(sums(), [10, 10, 12, 15, 19, 24, 84]);
+    assert_eq!(six.dependency_count(), 6);
+}
@@ -0,0 +1,186 @@
+use std::{
+    future::Future,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use everafter::Timeline;
+
+/**
+ * Returns `Pending` the first time it is polled, like a fetch that isn't done yet.
+ */
+#[derive(Default)]
+struct Fetch {
+    polled: bool,
+}
+
+impl Future for Fetch {
+    type Output = &'static str;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<&'static str> {
+        if self.polled {
+            Poll::Ready("response")
+        } else {
+            self.polled = true;
+            Poll::Pending
+        }
+    }
+}
+
+/**
+ * Poll `future` until it is ready. Every future in these tests makes progress each time it is
+ * polled, so there is nothing to wait for.
+ */
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+            return value;
+        }
+    }
+}
+
+#[test]
+fn a_cell_read_after_an_await_invalidates_the_derived() {
+    let timeline = Timeline::new();
+    let url = timeline.cell("/users");
+    let format = timeline.cell("json");
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let body = {
+        let (url, format, runs) = (url.clone(), format.clone(), runs.clone());
+        timeline.derived_async(move || {
+            let (url, format, runs) = (url.clone(), format.clone(), runs.clone());
+
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                let url = url.get();
+                let response = Fetch::default().await;
+                format!("{} {} as {}", url, response, format.get())
+            }
+        })
+    };
+
+    assert_eq!(block_on(body.resolve()), "/users response as json");
+    assert_eq!(block_on(body.resolve()), "/users response as json");
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    assert!(!body.is_stale());
+
+    format.set("xml");
+    assert!(body.is_stale(), "the read after the await is tracked");
+    assert_eq!(block_on(body.resolve()), "/users response as xml");
+    assert_eq!(runs.load(Ordering::SeqCst), 2, "the whole future ran again");
+
+    url.set("/posts");
+    assert!(body.is_stale(), "the read before the await is tracked");
+    assert_eq!(block_on(body.resolve()), "/posts response as xml");
+    assert_eq!(body.runs(), 3);
+}
+
+#[test]
+fn a_change_during_a_run_starts_it_over() {
+    let timeline = Timeline::new();
+    let url = timeline.cell("/users");
+    let started = Arc::new(AtomicUsize::new(0));
+
+    let body = {
+        let (url, started) = (url.clone(), started.clone());
+        timeline.derived_async(move || {
+            let (url, started) = (url.clone(), started.clone());
+
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                let url = url.get();
+                Fetch::default().await;
+                url
+            }
+        })
+    };
+
+    let mut resolve = pin!(body.resolve());
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(resolve.as_mut().poll(&mut cx).is_pending());
+
+    url.set("/posts");
+    assert!(resolve.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(started.load(Ordering::SeqCst), 2);
+
+    assert_eq!(resolve.as_mut().poll(&mut cx), Poll::Ready("/posts"));
+    assert_eq!(body.runs(), 1, "the first run never completed");
+}
+
+#[test]
+fn synchronous_readers_see_each_completed_run() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1);
+
+    let doubled = {
+        let count = count.clone();
+        timeline.derived_async(move || {
+            let count = count.clone();
+
+            async move {
+                Fetch::default().await;
+                count.get() * 2
+            }
+        })
+    };
+
+    let label = {
+        let doubled = doubled.clone();
+        timeline.derived(move || match doubled.get() {
+            Some(doubled) => doubled.to_string(),
+            None => "loading".to_string(),
+        })
+    };
+
+    assert_eq!(label.get(), "loading");
+    assert_eq!(block_on(doubled.resolve()), 2);
+    assert_eq!(label.get(), "2");
+
+    count.set(5);
+    assert_eq!(label.get(), "2", "nothing awaited the new value yet");
+
+    block_on(doubled.resolve());
+    assert_eq!(label.get(), "10");
+}
+
+#[test]
+fn awaiting_the_derived_is_tracked() {
+    let timeline = Timeline::new();
+    let name = timeline.cell("alice");
+
+    let user = {
+        let name = name.clone();
+        timeline.derived_async(move || {
+            let name = name.clone();
+            async move { name.get().to_uppercase() }
+        })
+    };
+
+    let greeting = {
+        let user = user.clone();
+        timeline.derived_async(move || {
+            let user = user.clone();
+
+            async move {
+                let user = user.resolve().await;
+                Fetch::default().await;
+                format!("hello, {}", user)
+            }
+        })
+    };
+
+    assert_eq!(block_on(greeting.resolve()), "hello, ALICE");
+
+    name.set("bob");
+    assert_eq!(block_on(greeting.resolve()), "hello, BOB");
+    assert_eq!(user.runs(), 2);
+    assert_eq!(greeting.runs(), 2);
+}
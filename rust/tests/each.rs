@@ -0,0 +1,141 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{each, ListChange, Timeline};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Todo {
+    id: u32,
+    title: &'static str,
+}
+
+fn todo(id: u32, title: &'static str) -> Todo {
+    Todo { id, title }
+}
+
+#[test]
+fn the_first_computation_inserts_every_element() {
+    let timeline = Timeline::new();
+    let todos = timeline.vec(vec![todo(1, "write"), todo(2, "test")]);
+
+    let titles = each(&todos, |todo| todo.id, |todo| todo.title.to_uppercase());
+    let list = titles.get();
+
+    assert_eq!(
+        list.iter().collect::<Vec<_>>(),
+        vec![(&1, &"WRITE".to_string()), (&2, &"TEST".to_string())]
+    );
+    assert_eq!(
+        list.changes(),
+        &[ListChange::Inserted(0), ListChange::Inserted(1)]
+    );
+}
+
+#[test]
+fn reordering_only_moves_outputs() {
+    let timeline = Timeline::new();
+    let todos = timeline.vec(vec![todo(1, "write"), todo(2, "test"), todo(3, "ship")]);
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let rows = {
+        let calls = calls.clone();
+        each(
+            &todos,
+            |todo| todo.id,
+            move |todo| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Arc::new(todo.title.to_string())
+            },
+        )
+    };
+
+    let before = rows.get();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // rotate the list left by one
+    let first = todos.remove(0);
+    todos.push(first);
+
+    let after = rows.get();
+    assert_eq!(
+        after.changes(),
+        &[
+            ListChange::Moved(1, 0),
+            ListChange::Moved(2, 1),
+            ListChange::Moved(0, 2),
+        ]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3, "nothing was mapped again");
+    assert!(Arc::ptr_eq(before.get(0).unwrap(), after.get(2).unwrap()));
+}
+
+#[test]
+fn changed_values_are_mapped_again() {
+    let timeline = Timeline::new();
+    let todos = timeline.vec(vec![todo(1, "write"), todo(2, "test")]);
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let titles = {
+        let calls = calls.clone();
+        each(
+            &todos,
+            |todo| todo.id,
+            move |todo| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                todo.title
+            },
+        )
+    };
+
+    titles.get();
+    todos.set(1, todo(2, "test more"));
+
+    let list = titles.get();
+    assert_eq!(list.get(1), Some(&"test more"));
+    assert_eq!(
+        list.changes(),
+        &[
+            ListChange::Removed(1),
+            ListChange::Retained(0),
+            ListChange::Inserted(1),
+        ]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn insertions_and_removals_are_reported() {
+    let timeline = Timeline::new();
+    let todos = timeline.vec(vec![todo(1, "write"), todo(2, "test"), todo(3, "ship")]);
+    let titles = each(&todos, |todo| todo.id, |todo| todo.title);
+
+    titles.get();
+    todos.remove(1);
+    todos.push(todo(4, "celebrate"));
+
+    let list = titles.get();
+    assert_eq!(
+        list.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+        vec![1, 3, 4]
+    );
+    assert_eq!(
+        list.changes(),
+        &[
+            ListChange::Removed(1),
+            ListChange::Retained(0),
+            ListChange::Moved(2, 1),
+            ListChange::Inserted(2),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "more than one element with the key 1")]
+fn duplicate_keys_panic() {
+    let timeline = Timeline::new();
+    let todos = timeline.vec(vec![todo(1, "write"), todo(1, "test")]);
+
+    each(&todos, |todo| todo.id, |todo| todo.title).get();
+}
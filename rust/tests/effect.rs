@@ -0,0 +1,336 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use everafter::{Flush, Scheduler, Timeline};
+
+#[test]
+fn effects_run_immediately_and_on_writes() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let _effect = {
+        let (cell, seen) = (cell.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(cell.get()))
+    };
+
+    assert_eq!(*seen.lock().unwrap(), vec![1]);
+
+    cell.set(2);
+    cell.set(2);
+    cell.set(3);
+
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn effects_ignore_unrelated_writes() {
+    let timeline = Timeline::new();
+    let read = timeline.cell(1);
+    let unrelated = timeline.cell(1);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let _effect = {
+        let (read, runs) = (read.clone(), runs.clone());
+        timeline.effect(move || {
+            read.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    unrelated.set(2);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    read.set(2);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn effects_run_once_per_transaction() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(2);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let _effect = {
+        let (a, b, seen) = (a.clone(), b.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(a.get() + b.get()))
+    };
+
+    timeline.batch(|| {
+        a.set(10);
+        b.set(20);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![3],
+            "nothing runs before the commit"
+        );
+    });
+
+    assert_eq!(*seen.lock().unwrap(), vec![3, 30]);
+}
+
+#[test]
+fn disposed_effects_do_not_run() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let effect = {
+        let (cell, runs) = (cell.clone(), runs.clone());
+        timeline.effect(move || {
+            cell.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    effect.dispose();
+    assert!(effect.is_disposed());
+
+    cell.set(2);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn reentrant_writes_are_queued() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(0);
+    let doubled = timeline.cell(0);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let _double = {
+        let (input, doubled) = (input.clone(), doubled.clone());
+        timeline.effect(move || doubled.set(input.get() * 2))
+    };
+
+    let _log = {
+        let (doubled, seen) = (doubled.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(doubled.get()))
+    };
+
+    input.set(1);
+    input.set(5);
+
+    assert_eq!(doubled.get(), 10);
+    assert_eq!(*seen.lock().unwrap(), vec![0, 2, 10]);
+}
+
+#[test]
+fn effects_can_write_other_cells_while_they_run() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(0);
+
+    let _copy = {
+        let (a, b) = (a.clone(), b.clone());
+        timeline.effect(move || b.set(a.get() + 1))
+    };
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let _count = {
+        let (b, runs) = (b.clone(), runs.clone());
+        b.subscribe(move |_| {
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    a.set(5);
+
+    assert_eq!(b.get(), 6);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn computations_can_write_cells_while_they_are_read() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1);
+    let reads = timeline.cell(0);
+
+    let doubled = {
+        let (input, reads) = (input.clone(), reads.clone());
+        timeline.derived(move || {
+            reads.set(reads.peek() + 1);
+            input.get() * 2
+        })
+    };
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let _log = {
+        let (reads, seen) = (reads.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(reads.get()))
+    };
+
+    assert_eq!(doubled.get(), 2);
+    input.set(2);
+    assert_eq!(doubled.get(), 4);
+
+    assert_eq!(*seen.lock().unwrap(), vec![0, 1, 2]);
+}
+
+#[test]
+fn effects_that_write_their_own_input_settle() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+
+    let _clamp = {
+        let cell = cell.clone();
+        timeline.effect(move || {
+            if cell.get() > 10 {
+                cell.set(10);
+            }
+        })
+    };
+
+    cell.set(50);
+    assert_eq!(cell.get(), 10);
+}
+
+#[derive(Default, Clone)]
+struct Manual {
+    queue: Arc<Mutex<Vec<Flush>>>,
+}
+
+impl Manual {
+    fn run(&self) {
+        let flushes: Vec<Flush> = self.queue.lock().unwrap().drain(..).collect();
+
+        for flush in flushes {
+            flush.run();
+        }
+    }
+}
+
+impl Scheduler for Manual {
+    fn schedule(&self, flush: Flush) {
+        self.queue.lock().unwrap().push(flush);
+    }
+}
+
+#[test]
+fn schedulers_decide_when_to_flush() {
+    let timeline = Timeline::new();
+    let scheduler = Manual::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let cell = timeline.cell(1);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let _effect = {
+        let (cell, seen) = (cell.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(cell.get()))
+    };
+
+    cell.set(2);
+    cell.set(3);
+    assert_eq!(*seen.lock().unwrap(), vec![1]);
+
+    scheduler.run();
+    assert_eq!(*seen.lock().unwrap(), vec![1, 3]);
+}
+
+#[test]
+fn polling_runs_stale_effects() {
+    let timeline = Timeline::new();
+    let scheduler = Manual::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let counter = timeline.cell(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let effect = {
+        let (counter, runs) = (counter.clone(), runs.clone());
+        timeline.effect(move || {
+            counter.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    assert_eq!(runs.load(Ordering::SeqCst), 1, "effects run on creation");
+    assert!(!effect.poll(), "nothing changed yet");
+
+    counter.set(1);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    assert!(effect.poll());
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    scheduler.run();
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        2,
+        "a polled effect isn't stale when the flush runs"
+    );
+}
+
+#[test]
+fn dropping_an_effect_disposes_it() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let effect = {
+        let (cell, runs) = (cell.clone(), runs.clone());
+        timeline.effect(move || {
+            cell.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    drop(effect);
+    cell.set(2);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn detached_effects_outlive_their_handle() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    {
+        let (cell, seen) = (cell.clone(), seen.clone());
+        timeline
+            .effect(move || seen.lock().unwrap().push(cell.get()))
+            .detach();
+    }
+
+    cell.set(2);
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn an_effect_that_panics_doesnt_stop_later_flushes() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let _exploding = {
+        let cell = cell.clone();
+        timeline.effect(move || {
+            if cell.get() == 1 {
+                panic!("exploded");
+            }
+        })
+    };
+
+    let _logging = {
+        let (cell, seen) = (cell.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(cell.get()))
+    };
+
+    let exploded = panic::catch_unwind(AssertUnwindSafe(|| cell.set(1)));
+    assert!(exploded.is_err());
+
+    cell.set(2);
+    cell.set(3);
+    assert_eq!(*seen.lock().unwrap().last().unwrap(), 3);
+    assert!(
+        seen.lock().unwrap().contains(&2),
+        "the write after the panic ran the effects"
+    );
+}
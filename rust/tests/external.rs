@@ -0,0 +1,86 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{ComputeStack, Timeline};
+
+#[test]
+fn bumping_invalidates_deriveds_that_tracked_the_tag() {
+    let timeline = Timeline::new();
+    let tag = timeline.external_tag().named("socket");
+    let source = Arc::new(AtomicUsize::new(1));
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let received = {
+        let (tag, source, computations) = (tag.clone(), source.clone(), computations.clone());
+        timeline.derived(move || {
+            tag.track();
+            computations.fetch_add(1, Ordering::SeqCst);
+            source.load(Ordering::SeqCst)
+        })
+    };
+
+    assert_eq!(received.get(), 1);
+    assert!(!received.is_stale());
+
+    source.store(2, Ordering::SeqCst);
+    assert_eq!(
+        received.get(),
+        1,
+        "the derived wasn't told about the change"
+    );
+
+    tag.bump();
+    assert!(received.is_stale());
+    assert_eq!(received.get(), 2);
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+    let invalidation = received
+        .last_invalidation()
+        .expect("the tag invalidated it");
+    assert_eq!(invalidation.cause().label(), "socket");
+}
+
+#[test]
+fn bumps_advance_the_timeline() {
+    let timeline = Timeline::new();
+    let tag = timeline.external_tag();
+    let cell = timeline.cell(0);
+
+    let (_, dependencies) = ComputeStack::track(|| tag.track());
+    assert_eq!(dependencies.len(), 1);
+
+    cell.set(1);
+    let before = timeline.now();
+
+    tag.bump();
+    assert!(timeline.now() > before);
+    assert_eq!(tag.revision(), timeline.now());
+    assert!(tag.revision() > cell.revision());
+}
+
+#[test]
+fn bumps_inside_a_transaction_commit_together() {
+    let timeline = Timeline::new();
+    let tag = timeline.external_tag();
+    let cell = timeline.cell(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let _effect = {
+        let (tag, cell, runs) = (tag.clone(), cell.clone(), runs.clone());
+        timeline.effect(move || {
+            tag.track();
+            cell.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    timeline.batch(|| {
+        tag.bump();
+        cell.set(1);
+    });
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_eq!(tag.revision(), cell.revision());
+}
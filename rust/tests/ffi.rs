@@ -0,0 +1,151 @@
+use std::{ffi::c_void, ptr};
+
+use everafter::ffi::{
+    everafter_cell_get, everafter_cell_new, everafter_cell_set, everafter_derived_get,
+    everafter_derived_new, everafter_free, everafter_timeline_new, EverafterHandle,
+    EverafterStatus,
+};
+
+/**
+ * What the host's computation reads, and how often it ran.
+ */
+struct Doubled {
+    cell: EverafterHandle,
+    runs: usize,
+}
+
+extern "C" fn doubled(user_data: *mut c_void) -> i64 {
+    let doubled = unsafe { &mut *(user_data as *mut Doubled) };
+    doubled.runs += 1;
+
+    let mut value = 0;
+    match unsafe { everafter_cell_get(doubled.cell, &mut value) } {
+        EverafterStatus::Ok => value * 2,
+        _ => -1,
+    }
+}
+
+fn timeline() -> EverafterHandle {
+    let mut timeline = 0;
+    assert_eq!(
+        unsafe { everafter_timeline_new(&mut timeline) },
+        EverafterStatus::Ok
+    );
+    timeline
+}
+
+fn get(derived: EverafterHandle) -> i64 {
+    let mut value = 0;
+    assert_eq!(
+        unsafe { everafter_derived_get(derived, &mut value) },
+        EverafterStatus::Ok
+    );
+    value
+}
+
+#[test]
+fn a_host_computation_tracks_the_cells_it_reads() {
+    let timeline = timeline();
+
+    let mut cell = 0;
+    assert_eq!(
+        unsafe { everafter_cell_new(timeline, 1, &mut cell) },
+        EverafterStatus::Ok
+    );
+
+    let mut state = Doubled { cell, runs: 0 };
+    let mut derived = 0;
+    assert_eq!(
+        unsafe {
+            everafter_derived_new(
+                timeline,
+                doubled,
+                &mut state as *mut Doubled as *mut c_void,
+                &mut derived,
+            )
+        },
+        EverafterStatus::Ok
+    );
+
+    assert_eq!(get(derived), 2);
+    assert_eq!(get(derived), 2);
+    assert_eq!(state.runs, 1);
+
+    assert_eq!(everafter_cell_set(cell, 10), EverafterStatus::Ok);
+    assert_eq!(get(derived), 20);
+    assert_eq!(state.runs, 2);
+
+    assert_eq!(everafter_cell_set(cell, 10), EverafterStatus::Ok);
+    assert_eq!(get(derived), 20);
+    assert_eq!(state.runs, 2, "writing an equal value doesn't invalidate");
+
+    assert_eq!(everafter_free(derived), EverafterStatus::Ok);
+    assert_eq!(everafter_free(cell), EverafterStatus::Ok);
+    assert_eq!(everafter_free(timeline), EverafterStatus::Ok);
+}
+
+#[test]
+fn bad_handles_and_pointers_are_reported_as_statuses() {
+    let timeline = timeline();
+    let mut cell = 0;
+    unsafe { everafter_cell_new(timeline, 1, &mut cell) };
+
+    let mut value = 0;
+    assert_eq!(
+        unsafe { everafter_cell_get(0, &mut value) },
+        EverafterStatus::NullHandle
+    );
+    assert_eq!(
+        unsafe { everafter_cell_get(cell, ptr::null_mut()) },
+        EverafterStatus::NullPointer
+    );
+    assert_eq!(
+        unsafe { everafter_derived_get(cell, &mut value) },
+        EverafterStatus::WrongKind
+    );
+    assert_eq!(everafter_cell_set(timeline, 1), EverafterStatus::WrongKind);
+
+    assert_eq!(everafter_free(cell), EverafterStatus::Ok);
+    assert_eq!(
+        unsafe { everafter_cell_get(cell, &mut value) },
+        EverafterStatus::InvalidHandle
+    );
+    assert_eq!(everafter_cell_set(cell, 2), EverafterStatus::InvalidHandle);
+    assert_eq!(everafter_free(cell), EverafterStatus::InvalidHandle);
+    assert_eq!(everafter_free(0), EverafterStatus::NullHandle);
+}
+
+/**
+ * A computation that reads the derived it computes.
+ */
+extern "C" fn cyclic(user_data: *mut c_void) -> i64 {
+    let derived = unsafe { *(user_data as *const EverafterHandle) };
+    let mut value = 0;
+
+    match unsafe { everafter_derived_get(derived, &mut value) } {
+        EverafterStatus::Ok => value,
+        status => -(status as i64),
+    }
+}
+
+#[test]
+fn panics_dont_unwind_into_the_host() {
+    let timeline = timeline();
+    let mut handle: EverafterHandle = 0;
+    let mut derived = 0;
+
+    unsafe {
+        everafter_derived_new(
+            timeline,
+            cyclic,
+            &mut handle as *mut EverafterHandle as *mut c_void,
+            &mut derived,
+        )
+    };
+    handle = derived;
+
+    let mut value = 0;
+    let status = unsafe { everafter_derived_get(handle, &mut value) };
+    assert_eq!(value, -(EverafterStatus::Panicked as i64));
+    assert_eq!(status, EverafterStatus::Ok);
+}
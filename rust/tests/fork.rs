@@ -0,0 +1,193 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use everafter::{ReactiveValue, Timeline};
+
+#[test]
+fn forked_writes_are_invisible_until_commit() {
+    let timeline = Timeline::new();
+    let (first, last) = (timeline.cell("Ada"), timeline.cell("Lovelace"));
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let full = {
+        let (first, last, runs) = (first.clone(), last.clone(), runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            format!("{} {}", first.get(), last.get())
+        })
+    };
+    assert_eq!(full.get(), "Ada Lovelace");
+
+    let fork = timeline.fork();
+    fork.set(&first, "Grace");
+    fork.set(&last, "Hopper");
+
+    assert_eq!(fork.run(|| full.get()), "Grace Hopper");
+    assert_eq!(fork.run(|| full.get()), "Grace Hopper");
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        2,
+        "the fork caches its own value"
+    );
+    assert_eq!(fork.run(|| first.peek()), "Grace");
+
+    assert_eq!(first.get(), "Ada");
+    assert_eq!(full.get(), "Ada Lovelace");
+    assert!(!full.is_stale(), "the derived's cached value is untouched");
+
+    let before = timeline.now();
+    fork.commit();
+
+    assert_eq!(
+        timeline.bumps_since(before),
+        1,
+        "committed as a single revision"
+    );
+    assert_eq!(full.get(), "Grace Hopper");
+}
+
+#[test]
+fn dropped_forks_are_discarded() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1);
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+    let mapped = count.clone().map(|count| count * 10);
+
+    let before = timeline.now();
+    {
+        let fork = timeline.fork();
+        fork.set(&count, 5);
+
+        assert_eq!(fork.run(|| (doubled.get(), mapped.get())), (10, 50));
+        assert!(!fork.is_empty());
+    }
+
+    assert_eq!(timeline.now(), before);
+    assert_eq!((count.get(), doubled.get(), mapped.get()), (1, 2, 10));
+
+    let fork = timeline.fork();
+    assert_eq!(
+        fork.run(|| count.get()),
+        1,
+        "a new fork starts from the timeline"
+    );
+}
+
+#[test]
+fn reads_outside_the_fork_fall_through_to_the_timeline() {
+    let timeline = Timeline::new();
+    let (edited, untouched) = (timeline.cell(1), timeline.cell(100));
+    let untouched_runs = Arc::new(AtomicUsize::new(0));
+
+    let plus_one = {
+        let (untouched, runs) = (untouched.clone(), untouched_runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            untouched.get() + 1
+        })
+    };
+    plus_one.get();
+
+    let fork = timeline.fork();
+    fork.set(&edited, 2);
+
+    // written while the fork is open, and read through it
+    untouched.set(200);
+    assert_eq!(fork.run(|| (edited.get(), plus_one.get())), (2, 201));
+    assert_eq!(plus_one.get(), 201);
+    assert_eq!(
+        untouched_runs.load(Ordering::SeqCst),
+        3,
+        "the stale derived ran once in the fork and once in the timeline"
+    );
+}
+
+#[test]
+fn peeking_by_reference_inside_a_fork_sees_its_writes() {
+    let timeline = Timeline::new();
+    let (edited, untouched) = (timeline.cell(String::from("draft")), timeline.cell(1));
+
+    let fork = timeline.fork();
+    fork.set(&edited, String::from("final"));
+
+    let peeked = fork.run(|| (edited.peek_ref().clone(), *untouched.peek_ref()));
+    assert_eq!(peeked, (String::from("final"), 1));
+    assert_eq!(*edited.peek_ref(), "draft", "outside the fork");
+
+    let snapshot = timeline.snapshot();
+    assert_eq!(
+        fork.run(|| edited.get_at(&snapshot)),
+        Some(String::from("draft")),
+        "snapshots are of the timeline"
+    );
+}
+
+#[test]
+fn writing_the_timeline_inside_a_fork_panics() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+    let fork = timeline.fork();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| fork.run(|| cell.set(1))));
+    assert!(result.is_err());
+    assert_eq!(cell.get(), 0);
+
+    fork.set(&cell, 2);
+    fork.commit();
+    assert_eq!(cell.get(), 2, "the fork still commits after the panic");
+}
+
+#[test]
+fn forks_recompute_once_the_timeline_is_written() {
+    let timeline = Timeline::new();
+    let (a, b) = (timeline.cell(1), timeline.cell(10));
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let sum = {
+        let (a, b, runs) = (a.clone(), b.clone(), runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            a.get() + b.get()
+        })
+    };
+    let scaled = {
+        let sum = sum.clone();
+        timeline.derived(move || sum.get() * 10)
+    };
+
+    let fork = timeline.fork();
+    fork.set(&a, 2);
+    assert_eq!(fork.run(|| (sum.get(), scaled.get())), (12, 120));
+
+    b.set(20);
+    assert_eq!(fork.run(|| (sum.get(), scaled.get())), (22, 220));
+
+    let computed = runs.load(Ordering::SeqCst);
+    assert_eq!(fork.run(|| sum.get()), 22);
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        computed,
+        "the value is cached again until the next write"
+    );
+
+    timeline.batch(|| {
+        b.set(30);
+        assert_eq!(fork.run(|| sum.get()), 32);
+        b.set(40);
+        assert_eq!(
+            fork.run(|| sum.get()),
+            42,
+            "a transaction's writes aren't cached over"
+        );
+    });
+
+    assert_eq!(sum.get(), 41, "the timeline kept its own values");
+}
@@ -0,0 +1,100 @@
+/*!
+ * The `ComputeStack` fuzz driver on fixed inputs: one for each operation, and pseudo-random ones
+ * like the ones `cargo fuzz run compute_stack_frames` generates.
+ */
+
+use everafter::fuzz::{compute_stack_frames, quiet_deliberate_panics};
+
+const CASES: u64 = 500;
+const LENGTH: usize = 256;
+
+// operations, see `compute_stack_frames`
+const PUSH: u8 = 0;
+const UNTRACK: u8 = 1;
+const POP: u8 = 2;
+const READ: u8 = 3;
+const READ_DERIVED: u8 = 4;
+const WRITE: u8 = 5;
+const PANIC_IN_FRAME: u8 = 6;
+const TRACK_READS: u8 = 7;
+
+/**
+ * The byte for `op` on the cell or derived `index`.
+ */
+fn op(op: u8, index: u8) -> u8 {
+    index << 3 | op
+}
+
+#[test]
+fn reads_land_in_the_innermost_tracked_frame() {
+    compute_stack_frames(&[
+        op(READ, 0),
+        PUSH,
+        op(READ, 1),
+        op(READ, 1),
+        PUSH,
+        op(READ, 2),
+        op(TRACK_READS, 3),
+        POP,
+        UNTRACK,
+        op(READ, 0),
+        POP,
+        op(READ, 3),
+        POP,
+        POP,
+    ]);
+}
+
+#[test]
+fn deriveds_that_push_frames_leave_the_stack_balanced() {
+    compute_stack_frames(&[
+        PUSH,
+        op(READ_DERIVED, 0),
+        op(READ_DERIVED, 1),
+        op(WRITE, 1),
+        op(READ_DERIVED, 1),
+        UNTRACK,
+        op(WRITE, 0),
+        op(READ_DERIVED, 1),
+        POP,
+        op(READ_DERIVED, 0),
+    ]);
+}
+
+#[test]
+fn a_panic_unwinds_only_its_own_frame() {
+    quiet_deliberate_panics();
+    compute_stack_frames(&[
+        PUSH,
+        op(READ, 0),
+        op(PANIC_IN_FRAME, 1),
+        op(WRITE, 3),
+        op(READ_DERIVED, 2),
+        op(READ_DERIVED, 2),
+        PUSH,
+        op(PANIC_IN_FRAME, 2),
+        op(WRITE, 3),
+        op(READ_DERIVED, 2),
+        POP,
+        op(READ, 1),
+    ]);
+}
+
+#[test]
+fn pseudo_random_inputs_keep_the_stack_balanced() {
+    quiet_deliberate_panics();
+
+    for seed in 0..CASES {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        let data: Vec<u8> = (0..LENGTH)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+
+        compute_stack_frames(&data);
+    }
+}
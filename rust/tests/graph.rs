@@ -0,0 +1,169 @@
+#![cfg(feature = "debug-graph")]
+
+use everafter::Timeline;
+
+#[test]
+fn the_graph_has_an_edge_from_each_dependency() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1).named("count");
+
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2).named("doubled")
+    };
+
+    assert_eq!(doubled.get(), 2);
+
+    let dot = timeline.debug_graph().to_string();
+    assert!(dot.starts_with("digraph {"));
+    assert!(dot.contains(r#""count" -> "doubled";"#), "{}", dot);
+}
+
+#[test]
+fn nodes_are_annotated_with_their_revision_and_staleness() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1).named("count");
+
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2).named("doubled")
+    };
+
+    doubled.get();
+    count.set(2);
+
+    let graph = timeline.debug_graph();
+    let node = |label: &str| {
+        graph
+            .nodes()
+            .iter()
+            .find(|node| node.label() == label)
+            .cloned()
+            .unwrap()
+    };
+
+    assert_eq!(node("count").revision(), count.revision());
+    assert!(node("doubled").is_stale());
+    assert!(graph.to_string().contains("style=dashed"));
+
+    doubled.get();
+    let graph = timeline.debug_graph();
+    assert!(graph.nodes().iter().all(|node| !node.is_stale()));
+}
+
+#[test]
+fn edges_follow_the_last_computation() {
+    let timeline = Timeline::new();
+    let flag = timeline.cell(true).named("flag");
+    let left = timeline.cell("left").named("left");
+    let right = timeline.cell("right").named("right");
+
+    let choice = {
+        let (flag, left, right) = (flag.clone(), left.clone(), right.clone());
+        timeline.derived(move || if flag.get() { left.get() } else { right.get() })
+    }
+    .named("choice");
+
+    let effect = {
+        let choice = choice.clone();
+        timeline.effect(move || {
+            choice.get();
+        })
+    };
+
+    let edges = |timeline: &Timeline| {
+        let graph = timeline.debug_graph();
+        let mut edges: Vec<(String, String)> = graph
+            .edges()
+            .into_iter()
+            .map(|(from, to)| (from.label().to_string(), to.label().to_string()))
+            .collect();
+        edges.sort();
+        edges
+    };
+
+    let effect_label = format!("effect{}", effect.id());
+    let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+
+    assert_eq!(
+        edges(&timeline),
+        vec![
+            edge("choice", &effect_label),
+            edge("flag", "choice"),
+            edge("left", "choice"),
+        ]
+    );
+
+    flag.set(false);
+    assert_eq!(
+        edges(&timeline),
+        vec![
+            edge("choice", &effect_label),
+            edge("flag", "choice"),
+            edge("right", "choice"),
+        ]
+    );
+
+    drop((effect, choice));
+    assert_eq!(edges(&timeline), vec![]);
+    assert_eq!(timeline.debug_graph().nodes().len(), 3);
+}
+
+#[test]
+fn dot_nodes_show_their_label_id_and_revision() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1).named("count");
+    let unnamed = timeline.cell(2);
+
+    let sum = {
+        let (count, unnamed) = (count.clone(), unnamed.clone());
+        timeline.derived(move || count.get() + unnamed.get())
+    };
+
+    sum.get();
+    count.set(5);
+
+    let graph = timeline.debug_graph();
+    let id = |label: &str| {
+        graph
+            .nodes()
+            .iter()
+            .find(|node| node.label() == label)
+            .unwrap()
+            .id()
+    };
+
+    let dot = timeline.to_dot();
+    let count_label = format!(
+        r#"label="count (cell#{})\n{}""#,
+        id("count"),
+        count.revision()
+    );
+    let unnamed_label = format!(r#"label="{}\n{}""#, unnamed.label(), unnamed.revision());
+
+    assert!(dot.contains(&count_label), "{}", dot);
+    assert!(dot.contains(&unnamed_label), "{}", dot);
+    assert!(
+        dot.contains(&format!(r#""{}" -> "{}";"#, unnamed.label(), sum.label())),
+        "{}",
+        dot
+    );
+}
+
+#[test]
+fn dropped_deriveds_leave_the_graph() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1).named("count");
+
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2).named("doubled")
+    };
+    doubled.get();
+    assert!(timeline.to_dot().contains("doubled"));
+
+    drop(doubled);
+    let graph = timeline.debug_graph();
+    assert!(graph.nodes().iter().all(|node| node.label() != "doubled"));
+    assert!(!timeline.to_dot().contains("doubled"));
+}
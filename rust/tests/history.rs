@@ -0,0 +1,137 @@
+use everafter::Timeline;
+
+#[test]
+fn rewinding_restores_cells_and_their_dependents() {
+    let timeline = Timeline::new();
+    timeline.record_history(16);
+
+    let count = timeline.cell(1).named("count").recorded();
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+
+    count.set(2);
+    let at_two = timeline.now();
+    count.set(3);
+    count.set(4);
+    assert_eq!(doubled.get(), 8);
+
+    let before = timeline.now();
+    timeline.rewind_to(at_two).unwrap();
+
+    assert!(timeline.now() > before, "rewinding is a new revision");
+    assert_eq!(
+        timeline.bumps_since(before),
+        1,
+        "restored in a single write"
+    );
+    assert_eq!(count.get(), 2);
+    assert!(doubled.is_stale());
+    assert_eq!(doubled.get(), 4);
+
+    // the rewind is recorded, so it can be undone
+    timeline.rewind_to(before).unwrap();
+    assert_eq!(count.get(), 4);
+    assert_eq!(doubled.get(), 8);
+}
+
+#[test]
+fn rewinding_restores_every_recorded_cell_at_once() {
+    let timeline = Timeline::new();
+    timeline.record_history(16);
+
+    let first = timeline.cell("a").recorded();
+    let second = timeline.cell(10).recorded();
+    let untouched = timeline.cell(100);
+    let start = timeline.now();
+
+    timeline.batch(|| {
+        first.set("b");
+        second.set(20);
+    });
+    first.set("c");
+    untouched.set(200);
+
+    timeline.rewind_to(start).unwrap();
+    assert_eq!((first.get(), second.get()), ("a", 10));
+    assert_eq!(
+        untouched.get(),
+        200,
+        "cells that aren't recorded keep their values"
+    );
+}
+
+#[test]
+fn rewinding_restores_a_value_that_was_updated_in_place() {
+    let timeline = Timeline::new();
+    timeline.record_history(16);
+
+    let items = timeline.cell(vec![1, 2]).recorded();
+    let start = timeline.now();
+
+    items.update(|items| items.push(3));
+    items.update(|items| items.clear());
+    assert!(items.get().is_empty());
+
+    timeline.rewind_to(start).unwrap();
+    assert_eq!(items.get(), [1, 2]);
+}
+
+#[test]
+fn rewinding_past_the_history_fails() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0).recorded();
+    let start = timeline.now();
+
+    cell.set(1);
+    let error = timeline.rewind_to(start).unwrap_err();
+    assert_eq!(error.oldest(), None, "nothing is recorded yet");
+
+    timeline.record_history(2);
+    let recording = timeline.now();
+    assert!(
+        timeline.rewind_to(start).is_err(),
+        "the write before recording is lost"
+    );
+
+    for value in 2..6 {
+        cell.set(value);
+    }
+
+    let error = timeline.rewind_to(recording).unwrap_err();
+    assert_eq!(error.revision(), recording);
+    assert!(error.oldest().unwrap() > recording);
+    assert_eq!(cell.get(), 5, "a failed rewind restores nothing");
+
+    timeline.rewind_to(error.oldest().unwrap()).unwrap();
+    assert_eq!(cell.get(), 3);
+}
+
+#[test]
+#[cfg(feature = "testing")]
+fn rewinding_survives_renumbering() {
+    let timeline = Timeline::new();
+    timeline.record_history(16);
+
+    let cell = timeline.cell(0).recorded();
+    // a cell that isn't recorded, whose revision is renumbered with the rest of the timeline
+    let checkpoint = timeline.cell(0);
+
+    cell.set(1);
+    timeline.fast_forward(u64::MAX - 4);
+    cell.set(2);
+    checkpoint.set(1);
+
+    let before = timeline.now();
+    cell.set(3);
+    cell.set(4);
+    cell.set(5);
+    assert!(
+        timeline.now() < before,
+        "the timeline renumbered its revisions"
+    );
+
+    timeline.rewind_to(checkpoint.revision()).unwrap();
+    assert_eq!(cell.get(), 2);
+}
@@ -0,0 +1,99 @@
+use everafter::Timeline;
+
+#[test]
+fn the_written_dependency_is_the_cause() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1).named("a");
+    let b = timeline.cell(2).named("b");
+    let c = timeline.cell(3).named("c");
+
+    let sum = {
+        let (a, b, c) = (a.clone(), b.clone(), c.clone());
+        timeline.derived(move || a.get() + b.get() + c.get())
+    };
+
+    assert_eq!(sum.get(), 6);
+    assert_eq!(
+        sum.last_invalidation(),
+        None,
+        "the first computation isn't an invalidation"
+    );
+
+    b.set(20);
+    assert_eq!(sum.get(), 24);
+
+    let invalidation = sum.last_invalidation().unwrap();
+    assert_eq!(invalidation.chain().len(), 1);
+    assert_eq!(invalidation.cause().label(), "b");
+    assert_eq!(invalidation.cause().revision(), b.revision());
+
+    c.set(30);
+    assert_eq!(
+        sum.last_invalidation().unwrap().cause().label(),
+        "b",
+        "the invalidation is only recorded once the derived is read"
+    );
+
+    sum.get();
+    assert_eq!(sum.last_invalidation().unwrap().cause().label(), "c");
+}
+
+#[test]
+fn chains_lead_back_to_the_write() {
+    let timeline = Timeline::new();
+    let x = timeline.cell(1).named("x");
+    let unrelated = timeline.cell(1).named("unrelated");
+
+    let y = {
+        let x = x.clone();
+        timeline.derived(move || x.get() + 1).named("y")
+    };
+
+    let z = {
+        let (unrelated, y) = (unrelated.clone(), y.clone());
+        timeline
+            .derived(move || unrelated.get() + y.get())
+            .named("z")
+    };
+
+    assert_eq!(z.get(), 3);
+
+    x.set(10);
+    assert_eq!(z.get(), 12);
+
+    let invalidation = z.last_invalidation().unwrap();
+    let labels: Vec<&str> = invalidation
+        .chain()
+        .iter()
+        .map(|step| step.label())
+        .collect();
+    assert_eq!(labels, vec!["x", "y"]);
+    assert!(invalidation
+        .chain()
+        .iter()
+        .all(|step| step.revision() == x.revision()));
+    assert_eq!(
+        invalidation.to_string(),
+        format!("x ({0}) → y ({0})", x.revision())
+    );
+}
+
+#[test]
+fn tracked_collections_report_their_label() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2, 3]);
+
+    let first = {
+        let list = list.clone();
+        timeline.derived(move || list.get(0))
+    };
+
+    first.get();
+    list.set(0, 10);
+    first.get();
+
+    assert_eq!(
+        first.last_invalidation().unwrap().cause().label(),
+        list.label()
+    );
+}
@@ -0,0 +1,192 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Derived, Timeline, TrackedMap};
+
+mod common;
+use common::runs;
+
+fn watch(
+    timeline: &Timeline,
+    map: &TrackedMap<String, i32>,
+    key: &'static str,
+) -> (Derived<Option<i32>>, Arc<AtomicUsize>) {
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let (map, count) = (map.clone(), count.clone());
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            map.get(key)
+        })
+    };
+
+    (derived, count)
+}
+
+#[test]
+fn keys_invalidate_independently() {
+    let timeline = Timeline::new();
+    let map = timeline.map(vec![(String::from("a"), 1), (String::from("b"), 2)]);
+
+    let (a, a_runs) = watch(&timeline, &map, "a");
+    let (b, b_runs) = watch(&timeline, &map, "b");
+
+    assert_eq!(a.get(), Some(1));
+    assert_eq!(b.get(), Some(2));
+
+    map.insert(String::from("b"), 20);
+    assert_eq!(a.get(), Some(1));
+    assert_eq!(b.get(), Some(20));
+    assert_eq!(runs(&a_runs), 1, "`a` doesn't depend on `b`");
+    assert_eq!(runs(&b_runs), 2);
+
+    map.insert(String::from("c"), 3);
+    assert_eq!(a.get(), Some(1));
+    assert_eq!(runs(&a_runs), 1, "new keys don't invalidate other keys");
+}
+
+#[test]
+fn absent_keys_are_tracked() {
+    let timeline = Timeline::new();
+    let map: TrackedMap<String, i32> = timeline.map(vec![]);
+
+    let (missing, missing_runs) = watch(&timeline, &map, "missing");
+    assert_eq!(missing.get(), None);
+
+    map.insert(String::from("other"), 1);
+    assert_eq!(missing.get(), None);
+    assert_eq!(runs(&missing_runs), 1);
+
+    map.insert(String::from("missing"), 2);
+    assert_eq!(
+        missing.get(),
+        Some(2),
+        "inserting the key invalidates the reader"
+    );
+}
+
+#[test]
+fn removing_and_reinserting_a_key() {
+    let timeline = Timeline::new();
+    let map = timeline.map(vec![(String::from("key"), 1)]);
+
+    let (key, key_runs) = watch(&timeline, &map, "key");
+    let present = {
+        let map = map.clone();
+        timeline.derived(move || map.contains_key("key"))
+    };
+
+    assert_eq!(key.get(), Some(1));
+    assert!(present.get());
+
+    assert_eq!(map.remove("key"), Some(1));
+    assert_eq!(key.get(), None);
+    assert!(!present.get());
+
+    assert_eq!(
+        map.remove("key"),
+        None,
+        "removing a missing key does nothing"
+    );
+    assert_eq!(key.get(), None);
+    assert_eq!(runs(&key_runs), 2);
+
+    map.insert(String::from("key"), 3);
+    assert_eq!(key.get(), Some(3));
+    assert!(present.get());
+    assert_eq!(runs(&key_runs), 3);
+}
+
+#[test]
+fn length_depends_on_the_structure() {
+    let timeline = Timeline::new();
+    let map = timeline.map(vec![(String::from("a"), 1)]);
+
+    let len = {
+        let map = map.clone();
+        timeline.derived(move || map.len())
+    };
+
+    assert_eq!(len.get(), 1);
+
+    let revision = len.revision();
+    map.insert(String::from("a"), 10);
+    assert_eq!(len.revision(), revision, "updating a key isn't structural");
+
+    map.insert(String::from("b"), 2);
+    assert_eq!(len.get(), 2);
+
+    map.remove("a");
+    assert_eq!(len.get(), 1);
+    assert!(!map.is_empty());
+}
+
+#[test]
+fn defaults_for_missing_keys_recompute_when_the_key_appears() {
+    let timeline = Timeline::new();
+    let settings: TrackedMap<String, String> = timeline.map(vec![]);
+
+    let config = {
+        let settings = settings.clone();
+        timeline.derived(move || {
+            settings
+                .get("config")
+                .unwrap_or_else(|| String::from("default"))
+        })
+    };
+
+    assert_eq!(config.get(), "default");
+
+    settings.insert(String::from("config"), String::from("custom"));
+    assert_eq!(config.get(), "custom");
+
+    settings.remove("config");
+    assert_eq!(
+        config.get(),
+        "default",
+        "removing a depended-on key invalidates"
+    );
+}
+
+#[test]
+fn keys_depend_on_the_structure_and_iter_on_every_entry() {
+    let timeline = Timeline::new();
+    let map = timeline.map(vec![(String::from("a"), 1), (String::from("b"), 2)]);
+    let key_runs = Arc::new(AtomicUsize::new(0));
+
+    let keys = {
+        let (map, key_runs) = (map.clone(), key_runs.clone());
+        timeline.derived(move || {
+            key_runs.fetch_add(1, Ordering::SeqCst);
+            map.keys().collect::<Vec<_>>().join(",")
+        })
+    };
+
+    let total = {
+        let map = map.clone();
+        timeline.derived(move || map.iter().map(|(_, value)| value).sum::<i32>())
+    };
+
+    assert_eq!(keys.get(), "a,b");
+    assert_eq!(total.get(), 3);
+
+    map.insert(String::from("b"), 20);
+    assert_eq!(keys.get(), "a,b");
+    assert_eq!(total.get(), 21);
+    assert_eq!(
+        runs(&key_runs),
+        1,
+        "updating a value doesn't change the keys"
+    );
+
+    map.insert(String::from("c"), 30);
+    assert_eq!(keys.get(), "a,b,c");
+    assert_eq!(total.get(), 51);
+
+    map.remove("a");
+    assert_eq!(keys.get(), "b,c");
+    assert_eq!(total.get(), 50);
+}
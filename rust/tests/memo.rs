@@ -0,0 +1,187 @@
+// memos are only `Send` and `Sync` with the `sync` feature
+#![allow(clippy::arc_with_non_send_sync)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use everafter::{ComputeStack, Memo, Timeline};
+
+#[test]
+fn arguments_are_cached_separately() {
+    let timeline = Timeline::new();
+    let prefix = timeline.cell("hello");
+    let computed = Arc::new(Mutex::new(vec![]));
+
+    let greet = {
+        let (prefix, computed) = (prefix.clone(), computed.clone());
+        timeline.memo(move |name: &String| {
+            computed.lock().unwrap().push(name.clone());
+            format!("{} {}", prefix.get(), name)
+        })
+    };
+
+    assert_eq!(greet.get(&"alice".to_string()), "hello alice");
+    assert_eq!(greet.get(&"bob".to_string()), "hello bob");
+    assert_eq!(greet.get(&"alice".to_string()), "hello alice");
+    assert_eq!(greet.get(&"bob".to_string()), "hello bob");
+
+    assert_eq!(greet.len(), 2);
+    assert_eq!(*computed.lock().unwrap(), vec!["alice", "bob"]);
+}
+
+#[test]
+fn only_the_arguments_whose_dependencies_changed_recompute() {
+    let timeline = Timeline::new();
+    let cells = vec![timeline.cell(10), timeline.cell(20)];
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let read = {
+        let (cells, computations) = (cells.clone(), computations.clone());
+        timeline.memo(move |index: &usize| {
+            computations.fetch_add(1, Ordering::SeqCst);
+            cells[*index].get()
+        })
+    };
+
+    assert_eq!(read.get(&0), 10);
+    assert_eq!(read.get(&1), 20);
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+    cells[1].set(21);
+
+    assert_eq!(read.get(&0), 10);
+    assert_eq!(
+        computations.load(Ordering::SeqCst),
+        2,
+        "0 didn't read cell 1"
+    );
+
+    assert_eq!(read.get(&1), 21);
+    assert_eq!(computations.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn reading_a_memo_tracks_the_argument_that_was_read() {
+    let timeline = Timeline::new();
+    let cells = vec![timeline.cell(1), timeline.cell(2)];
+
+    let read = {
+        let cells = cells.clone();
+        timeline.memo(move |index: &usize| cells[*index].get())
+    };
+
+    let (value, dependencies) = ComputeStack::track(|| read.get(&0));
+    assert_eq!(value, 1);
+    assert_eq!(dependencies.len(), 1);
+
+    let before = dependencies.revision();
+    cells[1].set(20);
+    assert_eq!(dependencies.revision(), before);
+
+    cells[0].set(10);
+    assert!(dependencies.revision() > before);
+}
+
+#[test]
+fn memos_can_read_their_own_other_arguments() {
+    let timeline = Timeline::new();
+    let base = timeline.cell(1u64);
+
+    let fib: Arc<Mutex<Option<Memo<u64, u64>>>> = Arc::new(Mutex::new(None));
+    let memo = {
+        let (base, fib) = (base.clone(), fib.clone());
+        timeline.memo(move |n: &u64| {
+            if *n < 2 {
+                return base.get();
+            }
+
+            let fib = fib.lock().unwrap().clone().expect("the memo was stored");
+            fib.get(&(n - 1)) + fib.get(&(n - 2))
+        })
+    };
+    *fib.lock().unwrap() = Some(memo.clone());
+
+    assert_eq!(memo.get(&10), 89);
+    assert_eq!(memo.len(), 11);
+
+    base.set(2);
+    assert_eq!(memo.get(&10), 178);
+
+    // the computation refers back to the memo, so break the cycle
+    fib.lock().unwrap().take();
+}
+
+#[test]
+fn the_least_recently_used_argument_is_evicted() {
+    let timeline = Timeline::new();
+    let computed = Arc::new(Mutex::new(vec![]));
+
+    let square = {
+        let computed = computed.clone();
+        timeline
+            .memo(move |n: &u32| {
+                computed.lock().unwrap().push(*n);
+                n * n
+            })
+            .with_capacity(3)
+    };
+
+    for n in 1..=3 {
+        square.get(&n);
+    }
+
+    // 1 is used over and over, so it is never the least recently used
+    for n in 4..=8 {
+        square.get(&1);
+        square.get(&n);
+    }
+
+    assert_eq!(square.len(), 3);
+    assert!(square.contains(&1));
+    assert!(square.contains(&7));
+    assert!(square.contains(&8));
+    assert!(!square.contains(&6));
+
+    assert_eq!(square.get(&2), 4);
+    assert_eq!(
+        *computed.lock().unwrap(),
+        vec![1, 2, 3, 4, 5, 6, 7, 8, 2],
+        "2 was evicted, so it is computed again"
+    );
+}
+
+#[test]
+fn evicted_arguments_are_dropped() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let add = {
+        let cell = cell.clone();
+        timeline
+            .memo(move |n: &u32| n + cell.get())
+            .with_capacity(2)
+    };
+
+    for n in 0..100 {
+        assert_eq!(add.get(&n), n + 1);
+    }
+
+    // the cell and the two deriveds that are still cached
+    assert_eq!(timeline.snapshot().len(), 3);
+}
+
+#[test]
+fn shrinking_a_memo_evicts_what_no_longer_fits() {
+    let timeline = Timeline::new();
+    let square = timeline.memo(|n: &u32| n * n);
+
+    for n in 0..10 {
+        square.get(&n);
+    }
+
+    let square = square.with_capacity(4);
+    assert_eq!(square.len(), 4);
+    assert!((6..10).all(|n| square.contains(&n)));
+}
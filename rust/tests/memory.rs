@@ -0,0 +1,151 @@
+use everafter::{Cell, Derived, Timeline, ValidationMode};
+
+/**
+ * A derived that reads `size` cells while the returned flag is true, and only the flag and the
+ * first cell otherwise.
+ */
+fn narrowing(timeline: &Timeline, size: usize) -> (Cell<bool>, Cell<usize>, Derived<usize>) {
+    let wide = timeline.cell(true);
+    let cells: Vec<_> = (0..size).map(|i| timeline.cell(i)).collect();
+
+    let derived = {
+        let wide = wide.clone();
+        let cells = cells.clone();
+        timeline.derived(move || {
+            if wide.get() {
+                cells.iter().map(|cell| cell.get()).sum()
+            } else {
+                cells[0].get()
+            }
+        })
+    };
+
+    (wide, cells[0].clone(), derived)
+}
+
+#[test]
+fn dependency_storage_shrinks_when_reads_drop() {
+    let timeline = Timeline::new();
+    let (wide, _, derived) = narrowing(&timeline, 1000);
+
+    derived.get();
+    assert_eq!(derived.dependency_count(), 1001);
+    assert!(timeline.memory_stats().tag_slots() >= 1001);
+
+    wide.set(false);
+    derived.get();
+    assert_eq!(derived.dependency_count(), 2);
+    assert!(timeline.memory_stats().tag_slots() < 16);
+}
+
+#[test]
+fn reused_storage_is_shrunk_too() {
+    let timeline = Timeline::new();
+    let (wide, first, derived) = narrowing(&timeline, 200);
+    derived.get();
+
+    // the storage of the wide run goes back to the pool once the narrow run replaces it
+    wide.set(false);
+    derived.get();
+
+    // and the next run is handed that storage again
+    first.set(1);
+    derived.get();
+    assert_eq!(derived.dependency_count(), 2);
+    assert!(timeline.memory_stats().tag_slots() < 16);
+}
+
+#[test]
+fn memory_stats_count_live_values() {
+    let timeline = Timeline::new();
+    let empty = timeline.memory_stats();
+
+    let (wide, first, derived) = narrowing(&timeline, 10);
+    derived.get();
+
+    let stats = timeline.memory_stats();
+    assert_eq!(stats.nodes(), empty.nodes() + 12);
+    assert!(stats.bytes() > empty.bytes());
+
+    drop((wide, first, derived));
+    assert_eq!(timeline.memory_stats().nodes(), empty.nodes());
+}
+
+#[test]
+fn dropped_dependents_dont_grow_the_edges_of_what_they_read() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let source = timeline.cell(0);
+
+    let churn = |times: usize| {
+        for _ in 0..times {
+            let source = source.clone();
+            let derived = timeline.derived(move || source.get() + 1);
+            derived.get();
+        }
+    };
+
+    churn(100);
+    let settled = timeline.memory_stats().bytes();
+
+    churn(10_000);
+    assert_eq!(timeline.memory_stats().bytes(), settled);
+}
+
+/**
+ * Create `count` deriveds that read `source`, and drop them all at once.
+ */
+fn create_and_drop(timeline: &Timeline, source: &Cell<usize>, count: usize) {
+    let deriveds: Vec<_> = (0..count)
+        .map(|_| {
+            let source = source.clone();
+            let derived = timeline.derived(move || source.get() + 1);
+            derived.get();
+            derived
+        })
+        .collect();
+
+    drop(deriveds);
+}
+
+#[test]
+fn collecting_after_dropping_deriveds_returns_to_the_baseline() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let source = timeline.cell(0);
+    let baseline = timeline.memory_stats();
+
+    create_and_drop(&timeline, &source, 10_000);
+
+    let collected = timeline.collect();
+    assert_eq!(collected.nodes(), 10_000);
+    assert!(collected.bytes() > 100_000);
+
+    let after = timeline.memory_stats();
+    assert_eq!(after.nodes(), baseline.nodes());
+    assert!(after.bytes() <= baseline.bytes() + 1024);
+
+    assert_eq!(timeline.collect().nodes(), 0);
+}
+
+#[test]
+fn writes_sweep_the_dropped_deriveds_without_collecting() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let source = timeline.cell(0);
+    let unrelated = timeline.cell(0);
+    let baseline = timeline.memory_stats();
+
+    create_and_drop(&timeline, &source, 10_000);
+
+    for i in 0..5_000 {
+        unrelated.set(i + 1);
+    }
+
+    assert!(timeline.memory_stats().bytes() <= baseline.bytes() + 1024);
+    assert_eq!(
+        timeline.collect().nodes(),
+        0,
+        "the writes already swept them"
+    );
+}
@@ -0,0 +1,115 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use everafter::{Derived, Timeline};
+
+mod common;
+use common::{counter, runs};
+
+/**
+ * A derived that counts how often it reads `output`.
+ */
+fn reader<T: Clone + Send + Sync + 'static>(
+    timeline: &Timeline,
+    output: &Derived<T>,
+) -> (Derived<T>, Arc<AtomicUsize>) {
+    let count = counter();
+    let derived = {
+        let (output, count) = (output.clone(), count.clone());
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            output.get()
+        })
+    };
+
+    (derived, count)
+}
+
+#[test]
+fn one_run_feeds_every_output_and_readers_only_see_their_own_changes() {
+    let timeline = Timeline::new();
+    let source = timeline.cell(String::from("a b"));
+    let parses = counter();
+
+    let parsed = {
+        let (source, parses) = (source.clone(), parses.clone());
+        timeline
+            .multi_derived(move |outputs| {
+                parses.fetch_add(1, Ordering::SeqCst);
+                let source = source.get();
+                let words: Vec<String> = source
+                    .split_whitespace()
+                    .filter(|word| *word != "!")
+                    .map(String::from)
+                    .collect();
+
+                outputs.set("count", words.len());
+                outputs.set("errors", source.matches('!').count());
+                outputs.set("words", words);
+            })
+            .named("parsed")
+    };
+
+    let words = parsed.output::<Vec<String>>("words");
+    let count = parsed.output::<usize>("count");
+    let errors = parsed.output::<usize>("errors");
+    assert_eq!(words.label(), "parsed.words");
+
+    let (words_reader, words_runs) = reader(&timeline, &words);
+    let (count_reader, count_runs) = reader(&timeline, &count);
+    let (errors_reader, errors_runs) = reader(&timeline, &errors);
+
+    assert_eq!(words_reader.get(), ["a", "b"]);
+    assert_eq!(count_reader.get(), 2);
+    assert_eq!(errors_reader.get(), 0);
+    assert_eq!(runs(&parses), 1, "one run feeds all three outputs");
+
+    source.set(String::from("a b !"));
+    assert_eq!(errors_reader.get(), 1);
+    assert_eq!(words_reader.get(), ["a", "b"]);
+    assert_eq!(count_reader.get(), 2);
+    assert_eq!(runs(&parses), 2);
+    assert_eq!(
+        (runs(&words_runs), runs(&count_runs), runs(&errors_runs)),
+        (1, 1, 2),
+        "only the reader of the output that changed recomputed"
+    );
+
+    source.set(String::from("b a !"));
+    assert_eq!(words_reader.get(), ["b", "a"]);
+    assert_eq!(count_reader.get(), 2);
+    assert_eq!(errors_reader.get(), 1);
+    assert_eq!(runs(&parses), 3);
+    assert_eq!(
+        (runs(&words_runs), runs(&count_runs), runs(&errors_runs)),
+        (2, 1, 2)
+    );
+}
+
+#[test]
+fn reading_a_missing_output_panics() {
+    let timeline = Timeline::new();
+    let parsed = timeline
+        .multi_derived(|outputs| outputs.set("tokens", 1))
+        .named("parsed");
+
+    let missing = parsed.output::<usize>("errors");
+    let mistyped = parsed.output::<String>("tokens");
+
+    for (output, expected) in [
+        (
+            missing.map(|_| ()),
+            "parsed didn't set an output called errors",
+        ),
+        (mistyped.map(|_| ()), "the output tokens of parsed isn't a "),
+    ] {
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| output.get())).unwrap_err();
+        let message = panicked.downcast_ref::<String>().unwrap();
+        assert!(message.contains(expected), "{}", message);
+    }
+}
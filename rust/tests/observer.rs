@@ -0,0 +1,189 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use everafter::{BumpHandle, ObserverEvent, RecordingObserver, Revision, Timeline};
+
+/**
+ * The events, with the durations of recomputations zeroed so they can be compared.
+ */
+fn untimed(events: Vec<ObserverEvent>) -> Vec<ObserverEvent> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            ObserverEvent::Recomputed { node, consumed, .. } => ObserverEvent::Recomputed {
+                node,
+                duration: Duration::ZERO,
+                consumed,
+            },
+            event => event,
+        })
+        .collect()
+}
+
+#[test]
+fn a_write_and_a_pull_report_the_write_then_the_recomputation() {
+    let timeline = Timeline::new();
+    let recording = RecordingObserver::new();
+    timeline.set_observer(Box::new(recording.clone()));
+
+    let count = timeline.cell(1);
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+    assert_eq!(doubled.get(), 2);
+
+    let created = recording.take();
+    let (cell, derived) = match &created[..] {
+        [ObserverEvent::Created(cell), ObserverEvent::Created(derived), ObserverEvent::Recomputed { .. }] => {
+            (cell.clone(), derived.clone())
+        }
+        events => panic!("unexpected events: {:?}", events),
+    };
+    assert_eq!((cell.kind(), derived.kind()), ("cell", "derived"));
+
+    let before = count.revision();
+    count.set(5);
+    assert_eq!(doubled.get(), 10);
+
+    assert_eq!(
+        untimed(recording.take()),
+        vec![
+            ObserverEvent::Written {
+                cell,
+                from: before,
+                to: count.revision(),
+            },
+            ObserverEvent::Recomputed {
+                node: derived,
+                duration: Duration::ZERO,
+                consumed: 1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn effects_report_scheduled_flushes_and_drops() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0);
+
+    let effect = {
+        let count = count.clone();
+        timeline.effect(move || {
+            count.get();
+        })
+    };
+
+    let recording = RecordingObserver::new();
+    timeline.set_observer(Box::new(recording.clone()));
+
+    count.set(1);
+    drop(effect);
+
+    let events = untimed(recording.take());
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            ObserverEvent::Written { cell, .. } => format!("written {}", cell.kind()),
+            ObserverEvent::FlushScheduled => String::from("scheduled"),
+            ObserverEvent::Recomputed { node, .. } => format!("recomputed {}", node.kind()),
+            ObserverEvent::EffectFlushed(node) => format!("flushed {}", node.kind()),
+            ObserverEvent::Dropped(node) => format!("dropped {}", node.kind()),
+            ObserverEvent::Created(node) => format!("created {}", node.kind()),
+        })
+        .collect();
+
+    assert_eq!(
+        kinds,
+        [
+            "written cell",
+            "scheduled",
+            "recomputed effect",
+            "flushed effect",
+            "dropped effect",
+        ]
+    );
+}
+
+#[test]
+fn cleared_observers_see_nothing() {
+    let timeline = Timeline::new();
+    let recording = RecordingObserver::new();
+    timeline.set_observer(Box::new(recording.clone()));
+    timeline.clear_observer();
+
+    let count = timeline.cell(0);
+    count.set(1);
+
+    assert!(recording.events().is_empty());
+}
+
+type Bumps = Arc<Mutex<Vec<(Revision, Vec<String>)>>>;
+
+/**
+ * Listen to the timeline's bumps, recording each revision with the labels of its tags.
+ */
+fn record_bumps(timeline: &Timeline) -> (BumpHandle, Bumps) {
+    let bumps = Arc::new(Mutex::new(vec![]));
+    let handle = {
+        let bumps = bumps.clone();
+        timeline.on_bump(move |revision, tags| {
+            let tags = tags.iter().map(|tag| tag.label().to_string()).collect();
+            bumps.lock().unwrap().push((revision, tags));
+        })
+    };
+
+    (handle, bumps)
+}
+
+#[test]
+fn every_unbatched_write_is_reported_as_its_own_bump() {
+    let timeline = Timeline::new();
+    let cells: Vec<_> = (0..5)
+        .map(|i| timeline.cell(0).named(format!("cell {}", i)))
+        .collect();
+    let (_handle, bumps) = record_bumps(&timeline);
+
+    for cell in &cells {
+        cell.set(1);
+    }
+
+    let bumps = bumps.lock().unwrap();
+    assert_eq!(bumps.len(), cells.len());
+
+    for ((revision, tags), cell) in bumps.iter().zip(&cells) {
+        assert_eq!(*revision, cell.revision());
+        assert_eq!(*tags, [cell.label()]);
+    }
+
+    assert!(bumps.windows(2).all(|pair| pair[0].0 < pair[1].0));
+}
+
+#[test]
+fn a_batch_is_reported_once_with_every_tag_it_wrote() {
+    let timeline = Timeline::new();
+    let first = timeline.cell(0).named("first");
+    let second = timeline.cell(0).named("second");
+    let (handle, bumps) = record_bumps(&timeline);
+
+    timeline.batch(|| {
+        first.set(1);
+        second.set(1);
+        first.set(2);
+    });
+
+    assert_eq!(
+        *bumps.lock().unwrap(),
+        [(
+            timeline.now(),
+            vec![String::from("first"), String::from("second")]
+        )]
+    );
+
+    drop(handle);
+    first.set(3);
+    assert_eq!(bumps.lock().unwrap().len(), 1);
+}
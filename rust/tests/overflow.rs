@@ -0,0 +1,99 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Revision, Timeline};
+
+#[test]
+fn running_out_of_revisions_renumbers_them_in_order() {
+    let timeline = Timeline::new();
+    let first = timeline.cell(1);
+    let second = timeline.cell(2);
+    let untouched = timeline.cell(3);
+    let constant = timeline.constant(4);
+
+    timeline.fast_forward(u64::MAX - 4);
+    first.set(10);
+    second.set(20);
+
+    let computations = Arc::new(AtomicUsize::new(0));
+    let sum = {
+        let (first, second) = (first.clone(), second.clone());
+        let computations = computations.clone();
+        timeline.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            first.get() + second.get()
+        })
+    };
+
+    assert_eq!(sum.get(), 30);
+
+    let third = timeline.cell(0);
+    third.set(1);
+    assert_eq!(
+        timeline.now().to_string(),
+        format!("r{}", u64::MAX - 1),
+        "the last revision is still usable"
+    );
+
+    // the next write can't increment past the last revision
+    third.set(2);
+
+    assert_eq!(
+        timeline.now().to_string(),
+        "r5",
+        "the live revisions are numbered from the initial revision"
+    );
+    assert!(untouched.revision() < first.revision());
+    assert!(first.revision() < second.revision());
+    assert!(second.revision() < third.revision());
+    assert_eq!(third.revision(), timeline.now());
+    assert_eq!(constant.revision(), Revision::CONSTANT);
+
+    assert_eq!(sum.get(), 30);
+    assert_eq!(
+        computations.load(Ordering::SeqCst),
+        1,
+        "renumbering doesn't invalidate anything"
+    );
+    assert_eq!(sum.revision(), second.revision());
+
+    second.set(200);
+    assert_eq!(sum.get(), 210);
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+    assert_eq!(sum.revision(), timeline.now());
+}
+
+#[test]
+fn collections_are_renumbered_with_their_timeline() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2]);
+    let map = timeline.map(vec![("a", 1)]);
+
+    timeline.fast_forward(u64::MAX - 2);
+    list.push(3);
+    map.insert("b", 2);
+
+    let total = {
+        let (list, map) = (list.clone(), map.clone());
+        timeline.derived(move || list.len() + map.len())
+    };
+    assert_eq!(total.get(), 5);
+
+    timeline.cell(0).set(1);
+    assert!(list.revision() < map.revision());
+    assert!(map.revision() < timeline.now());
+
+    list.push(4);
+    assert_eq!(total.get(), 6);
+    assert_eq!(list.revision(), timeline.now());
+}
+
+#[test]
+#[should_panic(expected = "can't fast forward")]
+fn fast_forward_only_moves_forward() {
+    let timeline = Timeline::new();
+    timeline.fast_forward(10);
+    timeline.fast_forward(5);
+}
@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+use everafter::{with_owner, Cell, Owner, Timeline};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+fn log() -> Log {
+    Arc::new(Mutex::new(vec![]))
+}
+
+fn entries(log: &Log) -> Vec<String> {
+    log.lock().unwrap().clone()
+}
+
+/**
+ * Create an effect on the default timeline, which logs `name` when its run is disposed.
+ */
+fn child(name: String, log: &Log) {
+    let log = log.clone();
+    Timeline::with_default(|timeline| {
+        timeline.effect(move || {
+            let (name, log) = (name.clone(), log.clone());
+            Owner::current()
+                .unwrap()
+                .on_cleanup(move || log.lock().unwrap().push(name));
+        })
+    });
+}
+
+#[test]
+fn a_recomputing_parent_disposes_the_effects_its_last_run_created() {
+    // the computation creates effects, so everything lives on the default timeline, which it can
+    // get at without capturing a timeline
+    Timeline::with_default(|timeline| {
+        let run: Cell<u32> = timeline.cell(1);
+        let disposed = log();
+
+        let parent = {
+            let (run, disposed) = (run.clone(), disposed.clone());
+            let on_drop = disposed.clone();
+            timeline.derived_with_cleanup(
+                move || {
+                    let run = run.get();
+                    child(format!("child {}.1", run), &disposed);
+                    child(format!("child {}.2", run), &disposed);
+                    run
+                },
+                move |run| on_drop.lock().unwrap().push(format!("parent {}", run)),
+            )
+        };
+
+        assert_eq!(parent.get(), 1);
+        assert_eq!(
+            timeline.subscription_count(),
+            2,
+            "the parent keeps its children"
+        );
+
+        for next in 2..=3 {
+            run.set(next);
+            assert_eq!(parent.get(), next);
+        }
+
+        assert_eq!(timeline.subscription_count(), 2);
+        assert_eq!(
+            entries(&disposed),
+            [
+                "child 1.2",
+                "child 1.1",
+                "parent 1",
+                "child 2.2",
+                "child 2.1",
+                "parent 2"
+            ]
+        );
+
+        disposed.lock().unwrap().clear();
+        drop(parent);
+        assert_eq!(timeline.subscription_count(), 0);
+        assert_eq!(entries(&disposed), ["child 3.2", "child 3.1", "parent 3"]);
+    });
+}
+
+#[test]
+fn effects_dispose_their_own_children_first() {
+    Timeline::with_default(|timeline| {
+        let disposed = log();
+
+        let outer = {
+            let disposed = disposed.clone();
+            timeline.effect(move || {
+                for name in ["a", "b"] {
+                    let disposed = disposed.clone();
+                    Timeline::with_default(|timeline| {
+                        timeline.effect(move || {
+                            let owner = Owner::current().unwrap();
+                            let log = disposed.clone();
+                            owner.on_cleanup(move || log.lock().unwrap().push(name.into()));
+                            child(format!("{} grandchild", name), &disposed);
+                        })
+                    });
+                }
+            })
+        };
+
+        assert_eq!(timeline.subscription_count(), 5);
+
+        outer.dispose();
+        assert_eq!(
+            entries(&disposed),
+            ["b grandchild", "b", "a grandchild", "a"]
+        );
+        assert_eq!(timeline.subscription_count(), 0);
+    });
+}
+
+#[test]
+fn with_owner_lets_later_work_join_a_run() {
+    Timeline::with_default(|timeline| {
+        let page: Cell<u32> = timeline.cell(1);
+        let seen = log();
+
+        let loader = {
+            let page = page.clone();
+            timeline.derived(move || {
+                page.get();
+                Owner::current().unwrap()
+            })
+        };
+
+        let owner = loader.get();
+        assert!(Owner::current().is_none());
+        assert_eq!(owner.id(), loader.id());
+
+        // what `with_owner` reads is still tracked by the computation it runs in
+        let resumed = {
+            let (owner, page, seen) = (owner.clone(), page.clone(), seen.clone());
+            timeline.derived(move || {
+                with_owner(&owner, || {
+                    let seen = seen.clone();
+                    owner.on_cleanup(move || seen.lock().unwrap().push("resumed".into()));
+                    page.get()
+                })
+            })
+        };
+        assert_eq!(resumed.get(), 1);
+        assert!(entries(&seen).is_empty());
+
+        page.set(2);
+        loader.get();
+        assert!(owner.is_disposed());
+        assert_eq!(entries(&seen), ["resumed"]);
+
+        assert_eq!(resumed.get(), 2, "the resumed derived depends on the page");
+        assert_eq!(
+            entries(&seen),
+            ["resumed", "resumed"],
+            "a disposed owner cleans up right away"
+        );
+    });
+}
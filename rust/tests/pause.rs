@@ -0,0 +1,120 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Cell, Effect, Timeline, ValidationMode};
+
+/**
+ * An effect that reads every cell, along with the number of times it ran.
+ */
+fn summing(timeline: &Timeline, cells: &[Cell<usize>]) -> (Effect, Arc<AtomicUsize>) {
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let effect = {
+        let (cells, runs) = (cells.to_vec(), runs.clone());
+        timeline.effect(move || {
+            cells.iter().map(|cell| cell.get()).sum::<usize>();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    (effect, runs)
+}
+
+#[test]
+fn effects_run_once_when_the_timeline_resumes() {
+    let timeline = Timeline::new();
+    let cells: Vec<_> = (0..10).map(|_| timeline.cell(0)).collect();
+    let (_effect, runs) = summing(&timeline, &cells);
+    let start = timeline.now();
+
+    {
+        let _pause = timeline.pause();
+
+        for i in 0..1000 {
+            cells[i % 10].set(i + 1);
+        }
+
+        assert_eq!(timeline.bumps_since(start), 1000, "every write advanced");
+        assert_eq!(cells[9].get(), 1000, "values are visible while paused");
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn only_the_outermost_pause_resumes() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+    let (_effect, runs) = summing(&timeline, std::slice::from_ref(&cell));
+
+    let outer = timeline.pause();
+    {
+        let _inner = timeline.pause();
+        cell.set(1);
+    }
+
+    cell.set(2);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    drop(outer);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    cell.set(3);
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        3,
+        "writes flush again after resuming"
+    );
+}
+
+#[test]
+fn resuming_without_writes_flushes_nothing() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+    let (_effect, runs) = summing(&timeline, std::slice::from_ref(&cell));
+
+    drop(timeline.pause());
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn eager_deriveds_stay_correct_while_paused() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let cell = timeline.cell(1);
+
+    let doubled = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2)
+    };
+    let quadrupled = {
+        let doubled = doubled.clone();
+        timeline.derived(move || doubled.get() * 2)
+    };
+
+    assert_eq!(quadrupled.get(), 4);
+
+    {
+        let _pause = timeline.pause();
+        assert_eq!(timeline.validation_mode(), ValidationMode::Eager);
+
+        cell.set(2);
+        assert_eq!(
+            quadrupled.get(),
+            8,
+            "reads validate instead of trusting marks"
+        );
+
+        cell.set(3);
+    }
+
+    assert_eq!(
+        quadrupled.get(),
+        12,
+        "resuming marked what the last write invalidated"
+    );
+    assert_eq!(doubled.get(), 6);
+}
@@ -0,0 +1,151 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{SerializedState, Skipped, Timeline};
+
+#[test]
+fn restoring_a_saved_state_recomputes_what_read_it() {
+    let timeline = Timeline::new();
+    let first = timeline.cell(String::from("Ada")).keyed("first");
+    let visits = timeline.cell(3_u32).keyed("visits");
+    let unkeyed = timeline.cell(1);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let summary = {
+        let (first, visits, runs) = (first.clone(), visits.clone(), runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            format!("{} visited {} times", first.get(), visits.get())
+        })
+    };
+    assert_eq!(summary.get(), "Ada visited 3 times");
+
+    let state = timeline.serialize_state();
+    assert_eq!(
+        state.iter().collect::<Vec<_>>(),
+        [("first", "Ada"), ("visits", "3")],
+        "only keyed cells are saved"
+    );
+
+    first.set(String::from("Grace"));
+    visits.set(10);
+    unkeyed.set(2);
+    assert_eq!(summary.get(), "Grace visited 10 times");
+
+    let saved = state.to_string();
+    let before = timeline.now();
+    let skipped = timeline.restore_state(&saved.parse().unwrap());
+
+    assert!(skipped.is_empty(), "{:?}", skipped);
+    assert_eq!(
+        timeline.bumps_since(before),
+        1,
+        "restored in one transaction"
+    );
+    assert_eq!(summary.get(), "Ada visited 3 times");
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        unkeyed.get(),
+        2,
+        "cells that weren't saved keep their values"
+    );
+
+    let revision = first.revision();
+    timeline.restore_state(&state);
+    assert!(
+        first.revision() > revision,
+        "restoring an equal value still advances the revision"
+    );
+    assert_eq!(summary.get(), "Ada visited 3 times");
+}
+
+#[test]
+fn values_without_a_cell_to_restore_them_into_are_skipped() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0_i32).keyed("count");
+
+    let mut state = SerializedState::new();
+    state.insert("count", "not a number");
+    state.insert("gone", "1");
+
+    let skipped = timeline.restore_state(&state);
+    assert_eq!(skipped.len(), 2);
+    assert!(matches!(&skipped[0], Skipped::Unparsable { key, .. } if key == "count"));
+    assert_eq!(
+        skipped[1],
+        Skipped::Missing {
+            key: String::from("gone")
+        }
+    );
+    assert_eq!(count.get(), 0);
+}
+
+#[test]
+fn saved_states_escape_what_would_split_their_lines() {
+    let timeline = Timeline::new();
+    let text = timeline
+        .cell(String::from("two\tcolumns\nand two\\lines"))
+        .keyed("key with\ttab");
+
+    let saved = timeline.serialize_state().to_string();
+    assert_eq!(saved.lines().count(), 1);
+
+    text.set(String::new());
+    timeline.restore_state(&saved.parse().unwrap());
+    assert_eq!(text.get(), "two\tcolumns\nand two\\lines");
+
+    let error = "no separator".parse::<SerializedState>().unwrap_err();
+    assert_eq!(error.line(), 1);
+    assert!("a\tb\\x".parse::<SerializedState>().is_err());
+}
+
+#[test]
+fn loading_cells_only_writes_the_values_that_changed() {
+    let timeline = Timeline::new();
+    let (a, b, c) = (
+        timeline.cell(1).keyed("a"),
+        timeline.cell(2).keyed("b"),
+        timeline.cell(String::from("three")).keyed("c"),
+    );
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let product = {
+        let (a, b, runs) = (a.clone(), b.clone(), runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            a.get() * b.get()
+        })
+    };
+    let label = {
+        let (c, product) = (c.clone(), product.clone());
+        timeline.derived(move || format!("{} is {}", c.get(), product.get()))
+    };
+    assert_eq!(label.get(), "three is 2");
+
+    let saved = timeline.serialize_cells();
+    assert_eq!(saved, timeline.serialize_state());
+
+    a.set(10);
+    b.set(20);
+    assert_eq!(label.get(), "three is 200");
+
+    let (before, unchanged) = (timeline.now(), c.revision());
+    let computed = runs.load(Ordering::SeqCst);
+
+    assert!(timeline.load_cells(&saved).is_empty());
+    assert_eq!(timeline.bumps_since(before), 1, "loaded in one transaction");
+    assert_eq!(c.revision(), unchanged, "an unchanged value isn't written");
+    assert_eq!((a.get(), b.get()), (1, 2));
+    assert_eq!(label.get(), "three is 2");
+    assert_eq!(runs.load(Ordering::SeqCst), computed + 1);
+
+    let revision = timeline.now();
+    assert!(timeline.load_cells(&saved).is_empty());
+    assert_eq!(
+        timeline.now(),
+        revision,
+        "loading the same values writes nothing"
+    );
+}
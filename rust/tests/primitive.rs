@@ -51,10 +51,10 @@ fn primitive_derived() {
     test.assert_changed(&mut derived, "initially");
 
     // initialize outputs
-    let mut output1 = derived.output("output1", &mut test);
-    let mut output2 = derived.output("output2", &mut test);
+    let mut output1 = derived.output("output1", &test);
+    let mut output2 = derived.output("output2", &test);
 
-    test.assert_unchanged(&mut derived, "after initialization");
+    test.assert_unchanged(&derived, "after initialization");
 
     // render
     let mut transaction = test.begin();
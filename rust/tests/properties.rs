@@ -0,0 +1,381 @@
+/*!
+ * Random sequences of operations on a timeline, checking the invariants of revisions and
+ * validation after every step. A failing sequence is shrunk by dropping operations for as long
+ * as it still fails, and the panic message prints the operations that are left, which turn
+ * into a regression test that calls `run`, like `reads_through_dropped_handles_stay_correct`.
+ */
+
+use std::{
+    fmt::Debug,
+    panic::{self, AssertUnwindSafe},
+};
+
+use everafter::{Cell, Derived, Revision, Timeline, ValidationMode};
+
+const CASES: u64 = 200;
+const STEPS: usize = 60;
+
+/**
+ * Every index in an operation wraps around the number of live cells or deriveds, so an
+ * operation still means something once the operations before it are shrunk away. An operation
+ * on an empty list does nothing.
+ */
+#[derive(Debug, Clone)]
+enum Op {
+    Cell(i64),
+    Write { cell: usize, value: i64 },
+    // when `branch` is set and the first input is even, the derived only reads that input
+    Derived { inputs: Vec<Input>, branch: bool },
+    Read(usize),
+    DropCell(usize),
+    DropDerived(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Input {
+    Cell(usize),
+    Derived(usize),
+}
+
+#[derive(Clone)]
+enum Source {
+    Cell(Cell<i64>),
+    Derived(Derived<i64>),
+}
+
+impl Source {
+    fn get(&self) -> i64 {
+        match self {
+            Source::Cell(cell) => cell.get(),
+            Source::Derived(derived) => derived.get(),
+        }
+    }
+}
+
+/**
+ * The live values of a sequence, each with the last revision it was seen at.
+ */
+struct Graph {
+    timeline: Timeline,
+    now: Revision,
+    cells: Vec<(Cell<i64>, Revision)>,
+    deriveds: Vec<(Derived<i64>, Revision)>,
+}
+
+impl Graph {
+    fn new(mode: ValidationMode) -> Graph {
+        let timeline = Timeline::new();
+        timeline.set_validation_mode(mode);
+
+        Graph {
+            now: timeline.now(),
+            timeline,
+            cells: vec![],
+            deriveds: vec![],
+        }
+    }
+
+    fn apply(&mut self, op: &Op) -> Result<(), String> {
+        match op {
+            Op::Cell(value) => {
+                let cell = self.timeline.cell(*value);
+                let revision = cell.revision();
+                self.cells.push((cell, revision));
+            }
+            Op::Write { cell, value } => {
+                if let Some((cell, _)) = pick(&self.cells, *cell) {
+                    cell.set(*value);
+                }
+            }
+            Op::Derived { inputs, branch } => {
+                let sources: Vec<_> = inputs
+                    .iter()
+                    .filter_map(|input| self.source(*input))
+                    .collect();
+                let branch = *branch;
+
+                let derived = self.timeline.derived(move || {
+                    if let (true, Some(first)) = (branch, sources.first()) {
+                        let first = first.get();
+                        if first % 2 == 0 {
+                            return first;
+                        }
+                    }
+
+                    sources.iter().fold(0i64, |sum, source| {
+                        sum.wrapping_mul(31).wrapping_add(source.get())
+                    })
+                });
+
+                let revision = derived.recorded_revision();
+                self.deriveds.push((derived, revision));
+            }
+            Op::Read(derived) => {
+                if let Some((derived, _)) = pick(&self.deriveds, *derived) {
+                    check_value(derived)?;
+                }
+            }
+            Op::DropCell(cell) => {
+                if !self.cells.is_empty() {
+                    self.cells.remove(cell % self.cells.len());
+                }
+            }
+            Op::DropDerived(derived) => {
+                if !self.deriveds.is_empty() {
+                    self.deriveds.remove(derived % self.deriveds.len());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn source(&self, input: Input) -> Option<Source> {
+        match input {
+            Input::Cell(cell) => {
+                pick(&self.cells, cell).map(|(cell, _)| Source::Cell(cell.clone()))
+            }
+            Input::Derived(derived) => {
+                pick(&self.deriveds, derived).map(|(derived, _)| Source::Derived(derived.clone()))
+            }
+        }
+    }
+
+    /**
+     * The invariants that hold after every step. None of these checks bring a derived up to
+     * date, so they don't change which deriveds the sequence leaves stale.
+     */
+    fn check(&mut self) -> Result<(), String> {
+        let now = self.timeline.now();
+        if now < self.now {
+            return Err(format!(
+                "the timeline went back from {:?} to {:?}",
+                self.now, now
+            ));
+        }
+        self.now = now;
+
+        for (cell, seen) in &mut self.cells {
+            monotone(cell.label(), seen, cell.revision())?;
+        }
+
+        for (derived, seen) in &mut self.deriveds {
+            let recorded = derived.recorded_revision();
+            monotone(derived.label(), seen, recorded)?;
+
+            if !derived.is_stale() {
+                if let Some(newer) = derived
+                    .dependency_revisions()
+                    .into_iter()
+                    .find(|revision| *revision > recorded)
+                {
+                    return Err(format!(
+                        "{} is clean at {:?} but a dependency is at {:?}",
+                        derived.label(),
+                        recorded,
+                        newer
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn pick<T>(list: &[T], index: usize) -> Option<&T> {
+    if list.is_empty() {
+        None
+    } else {
+        Some(&list[index % list.len()])
+    }
+}
+
+fn monotone(label: &str, seen: &mut Revision, revision: Revision) -> Result<(), String> {
+    if revision < *seen {
+        return Err(format!(
+            "{} went back from {:?} to {:?}",
+            label, seen, revision
+        ));
+    }
+
+    *seen = revision;
+    Ok(())
+}
+
+fn check_value(derived: &Derived<i64>) -> Result<(), String> {
+    let cached = derived.get();
+    let fresh = derived.force_recompute_for_test();
+
+    if cached == fresh {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} cached {} but recomputing it gives {}",
+            derived.label(),
+            cached,
+            fresh
+        ))
+    }
+}
+
+/**
+ * Apply `ops` to a fresh timeline, checking the invariants after every step and every value at
+ * the end. A panic counts as a failure.
+ */
+fn run(ops: &[Op], mode: ValidationMode) -> Result<(), String> {
+    let ran = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut graph = Graph::new(mode);
+
+        for (step, op) in ops.iter().enumerate() {
+            graph
+                .apply(op)
+                .and_then(|()| graph.check())
+                .map_err(|error| format!("step {} ({:?}): {}", step, op, error))?;
+        }
+
+        for (derived, _) in &graph.deriveds {
+            check_value(derived).map_err(|error| format!("at the end: {}", error))?;
+        }
+
+        Ok(())
+    }));
+
+    ran.unwrap_or_else(|payload| Err(format!("panicked: {}", message(&*payload))))
+}
+
+fn message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("a panic without a message")
+    }
+}
+
+/**
+ * Remove operations one at a time for as long as the sequence keeps failing.
+ */
+fn shrink(mut ops: Vec<Op>, mut error: String, mode: ValidationMode) -> (Vec<Op>, String) {
+    'shrinking: loop {
+        for index in 0..ops.len() {
+            let mut candidate = ops.clone();
+            candidate.remove(index);
+
+            if let Err(candidate_error) = run(&candidate, mode) {
+                ops = candidate;
+                error = candidate_error;
+                continue 'shrinking;
+            }
+        }
+
+        return (ops, error);
+    }
+}
+
+/**
+ * A xorshift generator, so every case can be reproduced from its seed.
+ */
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> usize {
+        (self.next() % n) as usize
+    }
+
+    fn op(&mut self) -> Op {
+        // small values, so writes often store a value the cell already has
+        match self.below(12) {
+            0 | 1 => Op::Cell(self.below(4) as i64),
+            2..=5 => Op::Write {
+                cell: self.below(64),
+                value: self.below(4) as i64,
+            },
+            6 | 7 => Op::Derived {
+                inputs: (0..self.below(4))
+                    .map(|_| {
+                        if self.below(3) == 0 {
+                            Input::Derived(self.below(64))
+                        } else {
+                            Input::Cell(self.below(64))
+                        }
+                    })
+                    .collect(),
+                branch: self.below(2) == 0,
+            },
+            8..=10 => Op::Read(self.below(64)),
+            _ if self.below(2) == 0 => Op::DropCell(self.below(64)),
+            _ => Op::DropDerived(self.below(64)),
+        }
+    }
+}
+
+fn check_random_sequences(mode: ValidationMode) {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let ops: Vec<_> = (0..STEPS).map(|_| rng.op()).collect();
+
+        if let Err(error) = run(&ops, mode) {
+            let (ops, error) = shrink(ops, error, mode);
+            fail(seed, mode, &ops, &error);
+        }
+    }
+}
+
+fn fail(seed: u64, mode: impl Debug, ops: &[Op], error: &str) -> ! {
+    panic!(
+        "seed {} in {:?} mode fails after shrinking to {} operations: {}\n\n{:?}",
+        seed,
+        mode,
+        ops.len(),
+        error,
+        ops
+    )
+}
+
+#[test]
+fn lazy_validation_holds_its_invariants() {
+    check_random_sequences(ValidationMode::Lazy);
+}
+
+#[test]
+fn eager_validation_holds_its_invariants() {
+    check_random_sequences(ValidationMode::Eager);
+}
+
+#[test]
+fn reads_through_dropped_handles_stay_correct() {
+    let ops = vec![
+        Op::Cell(1),
+        Op::Cell(2),
+        Op::Derived {
+            inputs: vec![Input::Cell(0), Input::Cell(1)],
+            branch: false,
+        },
+        Op::Derived {
+            inputs: vec![Input::Derived(0), Input::Cell(1)],
+            branch: true,
+        },
+        Op::Read(1),
+        Op::DropDerived(0),
+        Op::DropCell(0),
+        Op::Write { cell: 0, value: 3 },
+        Op::Read(0),
+    ];
+
+    for mode in [ValidationMode::Lazy, ValidationMode::Eager] {
+        assert_eq!(run(&ops, mode), Ok(()));
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex};
+
+use everafter::{untrack, ComputeStack, ReadPolicy, Timeline, TimelineObserver, UntrackedRead};
+
+#[test]
+fn untracked_reads_are_allowed_by_default() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    assert_eq!(timeline.read_policy(), ReadPolicy::Allow);
+    assert_eq!(cell.get(), 1);
+    assert_eq!(cell.try_get(), Ok(1));
+}
+
+#[test]
+fn warnings_report_the_label_and_the_caller() {
+    let timeline = Timeline::new();
+    let reads = Arc::new(Mutex::new(vec![]));
+
+    timeline.set_read_policy(ReadPolicy::Warn);
+    {
+        let reads = reads.clone();
+        timeline.on_untracked_read(move |read| {
+            reads.lock().unwrap().push((
+                read.label().to_string(),
+                read.location().file().to_string(),
+                read.location().line(),
+            ))
+        });
+    }
+
+    let cell = timeline.cell(1).named("count");
+    let line = line!() + 1;
+    assert_eq!(cell.get(), 1);
+
+    // reads inside frames, deliberately untracked reads and peeks aren't reported
+    ComputeStack::track(|| cell.get());
+    untrack(|| cell.get());
+    cell.peek();
+
+    assert_eq!(
+        *reads.lock().unwrap(),
+        vec![("count".to_string(), file!().to_string(), line)]
+    );
+}
+
+/**
+ * An observer that keeps the label of every untracked read it hears about.
+ */
+#[derive(Clone, Default)]
+struct Reads(Arc<Mutex<Vec<String>>>);
+
+impl TimelineObserver for Reads {
+    fn untracked_read(&self, read: &UntrackedRead) {
+        self.0.lock().unwrap().push(read.label().to_string());
+    }
+}
+
+#[test]
+fn warnings_reach_the_observer_with_or_without_a_callback() {
+    let timeline = Timeline::new();
+    let reads = Reads::default();
+    timeline.set_observer(Box::new(reads.clone()));
+    timeline.set_read_policy(ReadPolicy::Warn);
+
+    let cell = timeline.cell(1).named("count");
+    cell.get();
+
+    let called = Arc::new(Mutex::new(0));
+    {
+        let called = called.clone();
+        timeline.on_untracked_read(move |_| *called.lock().unwrap() += 1);
+    }
+    cell.get();
+
+    assert_eq!(*reads.0.lock().unwrap(), ["count", "count"]);
+    assert_eq!(*called.lock().unwrap(), 1);
+}
+
+#[test]
+fn denied_reads_fail_outside_of_frames() {
+    let timeline = Timeline::new();
+    timeline.set_read_policy(ReadPolicy::Deny);
+
+    let cell = timeline.cell(1).named("count");
+    let error = cell.try_get().unwrap_err();
+    assert_eq!(error.label(), "count");
+    assert_eq!(error.location().file(), file!());
+    assert!(error
+        .to_string()
+        .starts_with("count was read outside of any tracking frame"));
+
+    let doubled = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2)
+    };
+
+    assert_eq!(doubled.get(), 2);
+    assert_eq!(untrack(|| cell.try_get()), Ok(1));
+    assert_eq!(
+        timeline.constant(5).try_get(),
+        Ok(5),
+        "constants aren't tracked"
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "count was read outside of any tracking frame")]
+fn denied_reads_panic_in_debug_builds() {
+    let timeline = Timeline::new();
+    timeline.set_read_policy(ReadPolicy::Deny);
+
+    timeline.cell(1).named("count").get();
+}
+
+#[test]
+fn the_policy_belongs_to_the_cells_timeline() {
+    let strict = Timeline::new();
+    strict.set_read_policy(ReadPolicy::Deny);
+
+    let relaxed = Timeline::new();
+
+    assert!(strict.cell(1).try_get().is_err());
+    assert!(relaxed.cell(1).try_get().is_ok());
+}
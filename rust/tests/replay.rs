@@ -0,0 +1,234 @@
+use everafter::{Cell, Derived, ReplayError, Timeline};
+
+/**
+ * The cells and deriveds of a small graph, keyed so a write log can be replayed onto another
+ * copy of it.
+ */
+struct Graph {
+    a: Cell<i32>,
+    b: Cell<i32>,
+    name: Cell<String>,
+    sum: Derived<i32>,
+    summary: Derived<String>,
+}
+
+fn graph(timeline: &Timeline, combine: fn(i32, i32) -> i32) -> Graph {
+    let a = timeline.cell(0).keyed("a");
+    let b = timeline.cell(0).keyed("b");
+    let name = timeline.cell(String::from("total")).keyed("name");
+
+    let sum = {
+        let (a, b) = (a.clone(), b.clone());
+        timeline
+            .derived(move || combine(a.get(), b.get()))
+            .keyed("sum")
+    };
+
+    let summary = {
+        let (name, sum) = (name.clone(), sum.clone());
+        timeline
+            .derived(move || format!("{}: {}", name.get(), sum.get()))
+            .keyed("summary")
+    };
+
+    Graph {
+        a,
+        b,
+        name,
+        sum,
+        summary,
+    }
+}
+
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[test]
+fn a_replayed_log_produces_the_same_deriveds() {
+    let timeline = Timeline::new();
+    let recorded = graph(&timeline, add);
+    assert_eq!(recorded.summary.get(), "total: 0");
+
+    timeline.start_recording();
+
+    for i in 1..=8 {
+        recorded.a.set(i);
+        recorded.b.set(i * 10);
+    }
+
+    recorded.name.set(String::from("sum"));
+
+    timeline.batch(|| {
+        recorded.a.set(100);
+        recorded.b.set(-5);
+        recorded.name.set(String::from("after the batch"));
+    });
+
+    // an equal value isn't a write, so it isn't logged
+    recorded.a.set(100);
+
+    let log = timeline.stop_recording();
+    assert_eq!(log.writes().len(), 20);
+    assert_eq!(recorded.summary.get(), "after the batch: 95");
+    assert_eq!(
+        log.outputs().collect::<Vec<_>>(),
+        [("sum", "95"), ("summary", "after the batch: 95")]
+    );
+
+    let batched = &log.writes()[17..];
+    assert!(batched
+        .iter()
+        .all(|write| write.revision() == batched[0].revision()));
+    assert_eq!(batched[2].key(), "name");
+    assert_eq!(batched[2].value(), "after the batch");
+
+    for pair in log.writes()[..18].windows(2) {
+        assert!(pair[0].revision() < pair[1].revision());
+    }
+
+    let fresh = Timeline::new();
+    let replayed = graph(&fresh, add);
+    log.replay(&fresh).unwrap();
+
+    assert_eq!(replayed.a.get(), 100);
+    assert_eq!(replayed.b.get(), -5);
+    assert_eq!(replayed.sum.get(), recorded.sum.get());
+    assert_eq!(replayed.summary.get(), recorded.summary.get());
+}
+
+#[test]
+fn a_replay_reports_every_derived_that_differs() {
+    let timeline = Timeline::new();
+    let recorded = graph(&timeline, add);
+
+    timeline.start_recording();
+    recorded.a.set(3);
+    recorded.b.set(4);
+    let log = timeline.stop_recording();
+
+    let fresh = Timeline::new();
+    let replayed = graph(&fresh, |a, b| a * b);
+    let error = log.replay(&fresh).unwrap_err();
+
+    let mismatches = match &error {
+        ReplayError::Mismatched(mismatches) => mismatches,
+        other => panic!("expected mismatches, got {:?}", other),
+    };
+
+    let diff: Vec<_> = mismatches
+        .iter()
+        .map(|mismatch| (mismatch.key(), mismatch.recorded(), mismatch.replayed()))
+        .collect();
+    assert_eq!(
+        diff,
+        [
+            ("sum", "7", Some("12")),
+            ("summary", "total: 7", Some("total: 12"))
+        ]
+    );
+
+    let message = error.to_string();
+    assert!(
+        message.contains(r#"sum: recorded "7", replayed "12""#),
+        "{}",
+        message
+    );
+
+    drop(replayed);
+    let without_deriveds = Timeline::new();
+    let _a = without_deriveds.cell(0).keyed("a");
+    let _b = without_deriveds.cell(0).keyed("b");
+
+    match log.replay(&without_deriveds).unwrap_err() {
+        ReplayError::Mismatched(mismatches) => {
+            assert_eq!(mismatches.len(), 2);
+            assert_eq!(mismatches[0].replayed(), None);
+        }
+        other => panic!("expected mismatches, got {:?}", other),
+    }
+}
+
+#[test]
+fn a_replay_fails_without_the_cells_it_writes() {
+    let timeline = Timeline::new();
+    let recorded = graph(&timeline, add);
+
+    timeline.start_recording();
+    recorded.a.set(1);
+    recorded.b.set(2);
+    let log = timeline.stop_recording();
+
+    let fresh = Timeline::new();
+    let a = fresh.cell(0).keyed("a");
+
+    assert_eq!(
+        log.replay(&fresh),
+        Err(ReplayError::MissingCell {
+            key: String::from("b")
+        })
+    );
+    assert_eq!(a.get(), 1, "the writes before it stay applied");
+
+    let unparsable = Timeline::new();
+    let _a = unparsable.cell(false).keyed("a");
+
+    match log.replay(&unparsable).unwrap_err() {
+        ReplayError::Unparsable { key, value, .. } => assert_eq!((&*key, &*value), ("a", "1")),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn a_replay_checks_that_revisions_advance_in_the_same_order() {
+    let timeline = Timeline::new();
+    let recorded = graph(&timeline, add);
+
+    timeline.start_recording();
+    recorded.a.set(1);
+    recorded.b.set(2);
+    let log = timeline.stop_recording();
+
+    let fresh = Timeline::new();
+    let _replayed = graph(&fresh, add);
+
+    // inside a transaction, the writes that were separate share one revision
+    let result = fresh.transaction(|_| log.replay(&fresh));
+    assert_eq!(
+        result,
+        Err(ReplayError::OutOfOrder {
+            key: String::from("b"),
+            index: 1
+        })
+    );
+}
+
+#[test]
+fn only_writes_while_recording_are_logged() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0).keyed("count");
+
+    count.set(1);
+    assert!(timeline.stop_recording().writes().is_empty());
+
+    timeline.start_recording();
+    count.set(2);
+    timeline.start_recording();
+    count.set(3);
+
+    let log = timeline.stop_recording();
+    let values: Vec<_> = log.writes().iter().map(|write| write.value()).collect();
+    assert_eq!(values, ["3"]);
+    assert_eq!(log.writes()[0].revision(), count.revision());
+
+    count.set(4);
+    assert!(timeline.stop_recording().writes().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "\"count\" already keys a live cell")]
+fn a_key_names_one_live_cell() {
+    let timeline = Timeline::new();
+    let _count = timeline.cell(0).keyed("count");
+    let _other = timeline.cell(1).keyed("count");
+}
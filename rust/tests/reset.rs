@@ -0,0 +1,128 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Revision, Timeline};
+
+// a stand-in for a `#[test]` that shares the timeline with other tests
+fn scenario(timeline: &Timeline) -> Vec<Revision> {
+    timeline.reset();
+
+    let cell = timeline.cell(1);
+    let mut revisions = vec![timeline.now(), cell.revision()];
+
+    cell.set(2);
+    timeline.batch(|| cell.set(3));
+    revisions.push(cell.revision());
+    revisions.push(timeline.now());
+
+    revisions
+}
+
+#[test]
+fn sequential_tests_that_reset_see_identical_revisions() {
+    let timeline = Timeline::new();
+
+    let first = scenario(&timeline);
+    let second = scenario(&timeline);
+
+    assert_eq!(first, second);
+    assert_eq!(first[0], Revision::initial());
+}
+
+#[test]
+fn held_cells_rebaseline_instead_of_breaking() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let list = timeline.vec(vec![1]);
+    let constant = timeline.constant(10);
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let sum = {
+        let (cell, list, constant) = (cell.clone(), list.clone(), constant.clone());
+        let computations = computations.clone();
+        timeline.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            cell.get() + list.len() as i32 + constant.get()
+        })
+    };
+
+    assert_eq!(sum.get(), 12);
+
+    // the derived is stale when the timeline is reset, and must not keep its old value
+    cell.set(2);
+    list.push(2);
+    timeline.reset();
+
+    assert_eq!(timeline.now(), Revision::initial());
+    assert_eq!(cell.revision(), Revision::initial());
+    assert_eq!(list.revision(), Revision::initial());
+    assert_eq!(constant.revision(), Revision::CONSTANT);
+
+    assert_eq!(sum.get(), 14);
+    assert_eq!(sum.get(), 14);
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+    cell.set(3);
+    assert_eq!(sum.get(), 15);
+    assert!(cell.revision() > Revision::initial());
+}
+
+#[test]
+fn reset_disposes_effects_and_subscriptions() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let effect = {
+        let (cell, runs) = (cell.clone(), runs.clone());
+        timeline.effect(move || {
+            cell.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+    timeline
+        .effect({
+            let cell = cell.clone();
+            move || {
+                cell.get();
+            }
+        })
+        .detach();
+    let _subscription = cell.subscribe(|_| panic!("subscriptions are disposed by a reset"));
+
+    assert_eq!(timeline.subscription_count(), 3);
+    timeline.reset();
+
+    assert_eq!(timeline.subscription_count(), 0);
+    assert!(effect.is_disposed());
+
+    cell.set(1);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn snapshots_after_a_reset_read_the_new_values() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let old = timeline.snapshot();
+    cell.set(2);
+    cell.set(3);
+    timeline.reset();
+
+    let frame = timeline.snapshot();
+    cell.set(4);
+    cell.set(5);
+
+    assert_eq!(cell.get_at(&frame), Some(3));
+    drop(old);
+}
+
+#[test]
+#[should_panic(expected = "can't be reset inside a transaction")]
+fn reset_panics_inside_a_transaction() {
+    let timeline = Timeline::new();
+    timeline.batch(|| timeline.reset());
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use everafter::{Revision, Timeline};
+
+#[test]
+fn timelines_start_at_the_initial_revision() {
+    let timeline = Timeline::new();
+    assert_eq!(timeline.now(), Revision::initial());
+
+    timeline.cell(1);
+    assert_eq!(
+        timeline.now(),
+        Revision::initial(),
+        "creating a cell is not a write"
+    );
+}
+
+#[test]
+fn every_write_is_strictly_later() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let before = timeline.now();
+    cell.set(2);
+    let after = timeline.now();
+
+    assert!(after > before);
+    assert_eq!(cell.revision(), after);
+
+    cell.set(2);
+    assert_eq!(
+        timeline.now(),
+        after,
+        "an equal write doesn't advance the timeline"
+    );
+}
+
+#[test]
+fn snapshots_answer_whether_anything_changed() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let mut cache: Option<(Revision, String)> = None;
+    let mut builds = 0;
+
+    let mut resource = |timeline: &Timeline| match &cache {
+        Some((revision, value)) if *revision == timeline.now() => value.clone(),
+        _ => {
+            builds += 1;
+            let value = format!("resource@{}", cell.get());
+            cache = Some((timeline.now(), value.clone()));
+            value
+        }
+    };
+
+    assert_eq!(resource(&timeline), "resource@1");
+    assert_eq!(resource(&timeline), "resource@1");
+
+    timeline.cell(0).set(1);
+    cell.set(3);
+    assert_eq!(resource(&timeline), "resource@3");
+    assert_eq!(builds, 2);
+}
+
+#[test]
+fn constant_is_older_than_everything() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    cell.set(2);
+
+    assert!(Revision::CONSTANT < Revision::initial());
+    assert!(Revision::CONSTANT < cell.revision());
+    assert!(Revision::UNINITIALIZED > timeline.now());
+    assert!(Revision::CONSTANT.is_constant());
+
+    assert_eq!(Revision::CONSTANT.to_string(), "constant");
+    assert_eq!(Revision::UNINITIALIZED.to_string(), "uninitialized");
+    assert_eq!(Revision::initial().to_string(), "r1");
+}
+
+#[test]
+fn revisions_can_be_used_as_keys() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let mut seen = HashSet::new();
+
+    seen.insert(timeline.now());
+    cell.set(2);
+    seen.insert(timeline.now());
+    seen.insert(cell.revision());
+
+    assert_eq!(seen.len(), 2);
+}
+
+#[test]
+fn deriveds_over_constants_never_recompute() {
+    let timeline = Timeline::new();
+    let width = timeline.constant(3);
+    let height = timeline.constant(4);
+    let unrelated = timeline.cell(0);
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let area = {
+        let (width, height) = (width.clone(), height.clone());
+        let computations = computations.clone();
+        timeline.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            width.get() * height.get()
+        })
+    };
+
+    assert_eq!(area.get(), 12);
+    assert_eq!(area.revision(), Revision::CONSTANT);
+
+    unrelated.set(1);
+    unrelated.set(2);
+
+    assert_eq!(area.get(), 12);
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+#[should_panic(expected = "is a constant and can't be written")]
+fn constants_cannot_be_written() {
+    let timeline = Timeline::new();
+    let constant = timeline.constant(1);
+    constant.set(2);
+}
+
+#[test]
+fn bumps_since_counts_every_write() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let start = timeline.now();
+
+    cell.set(2);
+    cell.set(3);
+    cell.set(4);
+    assert_eq!(timeline.bumps_since(start), 3);
+    assert_eq!(start.distance(&timeline.now()), 3);
+    assert_eq!(timeline.now().distance(&start), 3);
+
+    cell.set(4);
+    assert_eq!(
+        timeline.bumps_since(start),
+        3,
+        "an equal write doesn't advance the timeline"
+    );
+}
+
+#[test]
+fn a_batch_is_one_bump() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let start = timeline.now();
+
+    timeline.batch(|| {
+        cell.set(2);
+        cell.set(3);
+        cell.set(4);
+    });
+
+    assert_eq!(timeline.bumps_since(start), 1);
+    assert_eq!(Revision::initial().distance(&Revision::initial()), 0);
+}
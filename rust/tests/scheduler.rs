@@ -0,0 +1,367 @@
+use std::sync::{Arc, Mutex};
+
+use everafter::{Cell, DeferredScheduler, Effect, ManualScheduler, Priority, Timeline};
+
+fn record(
+    timeline: &Timeline,
+    name: &'static str,
+    cells: &[&Cell<i32>],
+    log: &Arc<Mutex<Vec<&'static str>>>,
+) -> Effect {
+    let cells: Vec<Cell<i32>> = cells.iter().map(|cell| (*cell).clone()).collect();
+    let log = log.clone();
+
+    timeline.effect(move || {
+        for cell in &cells {
+            cell.get();
+        }
+
+        log.lock().unwrap().push(name);
+    })
+}
+
+fn take(log: &Arc<Mutex<Vec<&'static str>>>) -> Vec<&'static str> {
+    std::mem::take(&mut *log.lock().unwrap())
+}
+
+#[test]
+fn flushing_only_runs_affected_effects() {
+    let timeline = Timeline::new();
+    let scheduler = ManualScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let (a, b, c) = (timeline.cell(1), timeline.cell(2), timeline.cell(3));
+    let log = Arc::new(Mutex::new(vec![]));
+
+    let _first = record(&timeline, "first", &[&a, &b], &log);
+    let _second = record(&timeline, "second", &[&b, &c], &log);
+    assert_eq!(take(&log), vec!["first", "second"]);
+
+    a.set(10);
+    scheduler.flush();
+    assert_eq!(take(&log), vec!["first"]);
+
+    c.set(30);
+    scheduler.flush();
+    assert_eq!(take(&log), vec!["second"]);
+
+    b.set(20);
+    scheduler.flush();
+    assert_eq!(take(&log), vec!["first", "second"], "`b` is shared");
+
+    scheduler.flush();
+    assert_eq!(take(&log), Vec::<&str>::new(), "nothing is stale");
+}
+
+#[test]
+fn effects_run_once_per_flush_in_registration_order() {
+    let timeline = Timeline::new();
+    let scheduler = ManualScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let (a, b) = (timeline.cell(1), timeline.cell(2));
+    let log = Arc::new(Mutex::new(vec![]));
+
+    let _late = record(&timeline, "late", &[&b], &log);
+    let _early = record(&timeline, "early", &[&a, &b], &log);
+    take(&log);
+
+    a.set(10);
+    b.set(20);
+    a.set(100);
+    assert!(scheduler.is_pending());
+    assert_eq!(take(&log), Vec::<&str>::new());
+
+    scheduler.flush();
+    assert_eq!(take(&log), vec!["late", "early"]);
+    assert!(!scheduler.is_pending());
+}
+
+#[test]
+fn dropped_effects_are_not_flushed() {
+    let timeline = Timeline::new();
+    let scheduler = ManualScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let cell = timeline.cell(1);
+    let log = Arc::new(Mutex::new(vec![]));
+
+    let _kept = record(&timeline, "kept", &[&cell], &log);
+    let dropped = record(&timeline, "dropped", &[&cell], &log);
+    take(&log);
+
+    cell.set(2);
+    drop(dropped);
+    scheduler.flush();
+    assert_eq!(take(&log), vec!["kept"]);
+}
+
+fn debounced(timeline: &Timeline, delay: u32, cell: &Cell<i32>) -> (Effect, Arc<Mutex<Vec<i32>>>) {
+    let runs = Arc::new(Mutex::new(vec![]));
+
+    let effect = {
+        let (cell, runs) = (cell.clone(), runs.clone());
+        timeline.effect_debounced(delay, move || runs.lock().unwrap().push(cell.get()))
+    };
+
+    (effect, runs)
+}
+
+#[test]
+fn debounced_effects_wait_until_their_dependencies_settle() {
+    let timeline = Timeline::new();
+    let scheduler = ManualScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+    let cell = timeline.cell(0);
+
+    let (_effect, runs) = debounced(&timeline, 2, &cell);
+    assert_eq!(
+        *runs.lock().unwrap(),
+        vec![0],
+        "effects run when they are created"
+    );
+
+    for value in 1..=3 {
+        cell.set(value);
+        scheduler.flush();
+    }
+
+    assert_eq!(
+        *runs.lock().unwrap(),
+        vec![0],
+        "each bump restarted the wait"
+    );
+
+    // waiting keeps the flushes coming without any writes
+    assert!(scheduler.is_pending());
+    scheduler.flush();
+    assert_eq!(*runs.lock().unwrap(), vec![0]);
+
+    scheduler.flush();
+    assert_eq!(*runs.lock().unwrap(), vec![0, 3]);
+    assert!(!scheduler.is_pending());
+}
+
+#[test]
+fn debounced_effects_can_be_polled_early() {
+    let timeline = Timeline::new();
+    let scheduler = ManualScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+    let cell = timeline.cell(0);
+
+    let (effect, runs) = debounced(&timeline, 5, &cell);
+    cell.set(1);
+    scheduler.flush();
+
+    assert!(effect.poll());
+    assert_eq!(*runs.lock().unwrap(), vec![0, 1]);
+
+    // the pending flush finds nothing stale, and stops scheduling more
+    scheduler.flush();
+    assert!(!scheduler.is_pending());
+}
+
+#[test]
+fn the_immediate_scheduler_does_not_debounce() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+
+    let (_effect, runs) = debounced(&timeline, 3, &cell);
+    cell.set(1);
+    cell.set(2);
+
+    assert_eq!(*runs.lock().unwrap(), vec![0, 1, 2]);
+}
+
+#[test]
+fn deferred_flushes_run_writers_before_their_readers() {
+    let timeline = Timeline::new();
+    let scheduler = DeferredScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let width = timeline.cell(100);
+    let layout = timeline.cell(0);
+    let log = Arc::new(Mutex::new(vec![]));
+
+    // the child reads what the parent writes through a derived, and was registered first
+    let padded = {
+        let layout = layout.clone();
+        timeline.derived(move || layout.get() - 10)
+    };
+    let _child = {
+        let (width, padded, log) = (width.clone(), padded.clone(), log.clone());
+        timeline.effect(move || {
+            log.lock()
+                .unwrap()
+                .push(("child", width.get(), padded.get()));
+        })
+    };
+    let _parent = {
+        let (width, layout, log) = (width.clone(), layout.clone(), log.clone());
+        timeline.effect(move || {
+            log.lock().unwrap().push(("parent", width.get(), 0));
+            layout.set(width.get() / 2);
+        })
+    };
+
+    scheduler.flush().unwrap();
+    log.lock().unwrap().clear();
+
+    width.set(300);
+    width.set(400);
+    assert!(scheduler.is_pending());
+    assert!(log.lock().unwrap().is_empty(), "writes are coalesced");
+
+    scheduler.flush().unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![("parent", 400, 0), ("child", 400, 190)]
+    );
+    assert!(
+        !scheduler.is_pending(),
+        "the flush ran what the parent's write invalidated"
+    );
+}
+
+#[test]
+fn deferred_flushes_stop_effects_that_keep_invalidating_each_other() {
+    let timeline = Timeline::new();
+    let scheduler = DeferredScheduler::with_max_passes(10);
+    timeline.set_scheduler(scheduler.clone());
+
+    let (ping, pong) = (timeline.cell(0), timeline.cell(0));
+    let bounce = |from: &Cell<i32>, to: &Cell<i32>, name: &'static str| {
+        let (from, to) = (from.clone(), to.clone());
+        timeline.effect(move || to.set(from.get() + 1)).named(name)
+    };
+
+    let _ping = bounce(&pong, &ping, "ping");
+    let _pong = bounce(&ping, &pong, "pong");
+    scheduler.flush().unwrap_err();
+
+    pong.set(100);
+    let error = scheduler.flush().unwrap_err();
+    assert_eq!(error.effect(), "ping");
+    assert_eq!(error.passes(), 10);
+    assert!(error.to_string().contains("ping"));
+
+    assert!(
+        scheduler.is_pending(),
+        "the stale effect is left for the next flush"
+    );
+    assert!(ping.get() > 100);
+}
+
+type Log = Arc<Mutex<Vec<&'static str>>>;
+
+/**
+ * An effect in `priority` that reads `source`, logs its name and, if it has a `target`, writes
+ * the next number to it.
+ */
+fn lane(
+    timeline: &Timeline,
+    priority: Priority,
+    name: &'static str,
+    source: &Cell<i32>,
+    target: Option<&Cell<i32>>,
+    log: &Log,
+) -> Effect {
+    let (source, target, log) = (source.clone(), target.cloned(), log.clone());
+
+    timeline
+        .effect(move || {
+            let value = source.get();
+            log.lock().unwrap().push(name);
+
+            if let Some(target) = &target {
+                target.set(target.peek() + value);
+            }
+        })
+        .with_priority(priority)
+}
+
+#[test]
+fn flushes_run_lanes_in_priority_order() {
+    let timeline = Timeline::new();
+    let source = timeline.cell(0);
+    let log = Log::default();
+
+    let _effects = [
+        lane(&timeline, Priority::Idle, "idle", &source, None, &log),
+        lane(&timeline, Priority::Render, "render", &source, None, &log),
+        lane(&timeline, Priority::Sync, "sync", &source, None, &log),
+        lane(&timeline, Priority::Idle, "idle again", &source, None, &log),
+    ];
+    log.lock().unwrap().clear();
+
+    source.set(1);
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["sync", "render", "idle", "idle again"]
+    );
+}
+
+#[test]
+fn invalidating_a_higher_lane_preempts_the_lower_one() {
+    let timeline = Timeline::new();
+    let (source, layout) = (timeline.cell(0), timeline.cell(0));
+    let log = Log::default();
+
+    let _effects = [
+        lane(&timeline, Priority::Sync, "layout", &layout, None, &log),
+        lane(&timeline, Priority::Render, "render", &source, None, &log),
+        lane(
+            &timeline,
+            Priority::Idle,
+            "resize",
+            &source,
+            Some(&layout),
+            &log,
+        ),
+        lane(&timeline, Priority::Idle, "analytics", &source, None, &log),
+    ];
+    log.lock().unwrap().clear();
+
+    source.set(1);
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["render", "resize", "layout", "analytics"]
+    );
+}
+
+#[test]
+fn lower_lanes_finish_once_the_preemptions_are_used_up() {
+    let timeline = Timeline::new();
+    timeline.set_max_preemptions(1);
+
+    let (source, layout) = (timeline.cell(0), timeline.cell(0));
+    let log = Log::default();
+
+    let _effects = [
+        lane(&timeline, Priority::Sync, "layout", &layout, None, &log),
+        lane(
+            &timeline,
+            Priority::Idle,
+            "first",
+            &source,
+            Some(&layout),
+            &log,
+        ),
+        lane(
+            &timeline,
+            Priority::Idle,
+            "second",
+            &source,
+            Some(&layout),
+            &log,
+        ),
+        lane(&timeline, Priority::Idle, "third", &source, None, &log),
+    ];
+    log.lock().unwrap().clear();
+
+    source.set(1);
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["first", "layout", "second", "third", "layout"]
+    );
+}
@@ -0,0 +1,158 @@
+use everafter::Timeline;
+
+fn labels(changed: &[everafter::ChangedId]) -> Vec<&str> {
+    changed.iter().map(|id| id.label()).collect()
+}
+
+#[test]
+fn diffs_report_only_the_values_that_changed() {
+    let timeline = Timeline::new();
+    let cells: Vec<_> = (0..6)
+        .map(|i| timeline.cell(i).named(format!("cell-{}", i)))
+        .collect();
+
+    let before = timeline.snapshot();
+    assert_eq!(before.len(), 6);
+
+    timeline.batch(|| {
+        cells[1].set(10);
+        cells[3].set(30);
+    });
+    cells[4].set(40);
+    cells[5].set(5);
+
+    let after = timeline.snapshot();
+    assert!(after.revision() > before.revision());
+    assert_eq!(
+        labels(&before.diff(&after)),
+        vec!["cell-1", "cell-3", "cell-4"]
+    );
+    assert!(after.diff(&after).is_empty());
+}
+
+#[test]
+fn deriveds_change_once_they_are_read() {
+    let timeline = Timeline::new();
+    let input = timeline.cell(1).named("input");
+
+    let doubled = {
+        let input = input.clone();
+        timeline.derived(move || input.get() * 2).named("doubled")
+    };
+
+    doubled.get();
+    let before = timeline.snapshot();
+
+    input.set(2);
+    assert_eq!(
+        labels(&before.diff(&timeline.snapshot())),
+        vec!["input"],
+        "taking a snapshot doesn't recompute anything"
+    );
+
+    doubled.get();
+    let after = timeline.snapshot();
+    assert_eq!(labels(&before.diff(&after)), vec!["input", "doubled"]);
+    assert_eq!(after.diff(&before).len(), 2, "diffs are symmetric");
+}
+
+#[test]
+fn dropped_values_are_left_out() {
+    let timeline = Timeline::new();
+    let kept = timeline.cell(1).named("kept");
+    let dropped = timeline.cell(1).named("dropped");
+    let before = timeline.snapshot();
+
+    dropped.set(2);
+    kept.set(2);
+    drop(dropped);
+
+    let after = timeline.snapshot();
+    assert_eq!(after.len(), 1);
+    assert_eq!(labels(&before.diff(&after)), vec!["kept"]);
+
+    let created = timeline.cell(1).named("created");
+    created.set(2);
+    assert_eq!(
+        labels(&after.diff(&timeline.snapshot())),
+        Vec::<&str>::new(),
+        "values created after the first snapshot weren't in it"
+    );
+}
+
+#[test]
+fn short_lived_values_do_not_accumulate() {
+    let timeline = Timeline::new();
+
+    for i in 0..10_000 {
+        let cell = timeline.cell(i);
+        let derived = {
+            let cell = cell.clone();
+            timeline.derived(move || cell.get())
+        };
+        drop((cell, derived));
+    }
+
+    assert_eq!(timeline.snapshot().len(), 0);
+}
+
+#[test]
+fn readers_holding_a_snapshot_see_a_consistent_world() {
+    let timeline = Timeline::new();
+    let width = timeline.cell(1);
+    let height = timeline.cell(2);
+
+    let frame = timeline.snapshot();
+
+    width.set(10);
+    timeline.batch(|| {
+        width.set(100);
+        height.set(200);
+    });
+    height.set(2000);
+
+    assert_eq!(width.get_at(&frame), Some(1));
+    assert_eq!(height.get_at(&frame), Some(2));
+
+    let later = timeline.snapshot();
+    height.set(3);
+
+    assert_eq!(width.get_at(&later), Some(100));
+    assert_eq!(height.get_at(&later), Some(2000));
+    assert_eq!(height.get_at(&frame), Some(2));
+
+    assert_eq!((width.get(), height.get()), (100, 3));
+}
+
+#[test]
+fn values_written_after_every_snapshot_is_dropped_are_current() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let frame = timeline.snapshot();
+    let clone = frame.clone();
+    cell.set(2);
+    drop(frame);
+
+    assert_eq!(cell.get_at(&clone), Some(1), "clones share the pin");
+    drop(clone);
+
+    cell.set(3);
+    let frame = timeline.snapshot();
+    cell.set(4);
+
+    assert_eq!(cell.get_at(&frame), Some(3));
+}
+
+#[test]
+fn cells_created_after_a_snapshot_have_no_value_at_it() {
+    let timeline = Timeline::new();
+    let existing = timeline.cell(0);
+
+    let frame = timeline.snapshot();
+    existing.set(1);
+    let created = timeline.cell("new");
+
+    assert_eq!(created.get_at(&frame), None);
+    assert_eq!(existing.get_at(&frame), Some(0));
+}
@@ -0,0 +1,140 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use everafter::{ExternalSource, Revision, Timeline, ValidationMode};
+
+/**
+ * A source whose data and revision the test changes by hand, like a file watcher would.
+ */
+struct MockSource {
+    state: Mutex<(Revision, u32)>,
+}
+
+impl MockSource {
+    fn new(value: u32) -> MockSource {
+        MockSource {
+            state: Mutex::new((Revision::initial(), value)),
+        }
+    }
+
+    fn set(&self, value: u32) {
+        self.state.lock().unwrap().1 = value;
+    }
+
+    fn advance(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = state.0.increment();
+    }
+}
+
+impl ExternalSource for MockSource {
+    type Value = u32;
+
+    fn current_revision(&self) -> Revision {
+        self.state.lock().unwrap().0
+    }
+
+    fn read(&self) -> u32 {
+        self.state.lock().unwrap().1
+    }
+}
+
+#[test]
+fn deriveds_poll_the_source_revision() {
+    let timeline = Timeline::new();
+    let source = timeline.register_external(MockSource::new(1)).named("file");
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let doubled = {
+        let (source, computations) = (source.clone(), computations.clone());
+        timeline.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            source.get() * 2
+        })
+    };
+
+    assert_eq!(doubled.get(), 2);
+    assert_eq!(doubled.get(), 2);
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+
+    source.source().set(5);
+    assert_eq!(
+        doubled.get(),
+        2,
+        "the data changed, but the source's revision didn't"
+    );
+
+    source.source().advance();
+    assert!(doubled.is_stale());
+    assert_eq!(doubled.get(), 10);
+    assert_eq!(doubled.get(), 10);
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+    let invalidation = doubled
+        .last_invalidation()
+        .expect("the source invalidated it");
+    assert_eq!(invalidation.cause().label(), "file");
+}
+
+#[test]
+fn changes_reach_deriveds_that_only_read_the_source_indirectly() {
+    let timeline = Timeline::new();
+    let source = timeline.register_external(MockSource::new(1));
+    let offset = timeline.cell(10);
+
+    let inner = {
+        let source = source.clone();
+        timeline.derived(move || source.get() + 1)
+    };
+
+    let outer = {
+        let (inner, offset) = (inner.clone(), offset.clone());
+        timeline.derived(move || inner.get() + offset.get())
+    };
+
+    assert_eq!(outer.get(), 12);
+
+    source.source().set(2);
+    source.source().advance();
+    assert_eq!(outer.get(), 13);
+
+    offset.set(20);
+    assert_eq!(outer.get(), 23);
+}
+
+#[test]
+fn a_change_advances_the_timeline_once_it_is_noticed() {
+    let timeline = Timeline::new();
+    let source = timeline.register_external(MockSource::new(1));
+    let start = timeline.now();
+
+    source.source().advance();
+    source.source().advance();
+    assert_eq!(timeline.now(), start, "nothing has polled the source yet");
+
+    assert!(source.revision() > start);
+    assert_eq!(timeline.bumps_since(start), 1);
+    assert_eq!(source.revision(), timeline.now());
+}
+
+#[test]
+fn eager_validation_still_polls_the_source() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let source = timeline.register_external(MockSource::new(3));
+
+    let squared = {
+        let source = source.clone();
+        timeline.derived(move || source.get() * source.get())
+    };
+
+    assert_eq!(squared.get(), 9);
+    assert!(!squared.is_stale());
+
+    source.source().set(4);
+    source.source().advance();
+    assert!(squared.is_stale());
+    assert_eq!(squared.get(), 16);
+}
@@ -0,0 +1,184 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use everafter::{ManualScheduler, Timeline};
+
+#[test]
+fn short_lived_effects_do_not_grow_the_registry() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    for i in 0..10_000 {
+        let effect = {
+            let (cell, runs) = (cell.clone(), runs.clone());
+            timeline.effect(move || {
+                cell.get();
+                runs.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        assert_eq!(timeline.subscription_count(), 1);
+
+        if i % 2 == 0 {
+            drop(effect);
+        } else {
+            effect.dispose();
+        }
+    }
+
+    assert_eq!(timeline.subscription_count(), 0);
+    assert_eq!(runs.load(Ordering::SeqCst), 10_000);
+
+    cell.set(1);
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        10_000,
+        "dropped effects never run again"
+    );
+}
+
+#[test]
+fn cell_subscriptions_see_each_change() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let subscription = {
+        let seen = seen.clone();
+        cell.subscribe(move |value| seen.lock().unwrap().push(*value))
+    };
+
+    assert_eq!(*seen.lock().unwrap(), Vec::<i32>::new());
+
+    cell.set(2);
+    cell.set(2);
+    cell.set(3);
+    assert_eq!(*seen.lock().unwrap(), vec![2, 3]);
+    assert_eq!(timeline.subscription_count(), 1);
+
+    drop(subscription);
+    cell.set(4);
+    assert_eq!(*seen.lock().unwrap(), vec![2, 3]);
+    assert_eq!(timeline.subscription_count(), 0);
+}
+
+#[test]
+fn unsubscribing_is_idempotent() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let subscription = {
+        let runs = runs.clone();
+        cell.subscribe(move |_| {
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    subscription.unsubscribe();
+    subscription.unsubscribe();
+
+    cell.set(2);
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+    assert_eq!(timeline.subscription_count(), 0);
+}
+
+#[test]
+fn effects_can_be_kept_as_subscriptions() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let subscription = {
+        let (cell, seen) = (cell.clone(), seen.clone());
+        timeline
+            .effect(move || seen.lock().unwrap().push(cell.get()))
+            .into_subscription()
+    };
+
+    cell.set(2);
+    drop(subscription);
+    cell.set(3);
+
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn derived_subscriptions_fire_once_per_change() {
+    let timeline = Timeline::new();
+    let scheduler = ManualScheduler::default();
+    timeline.set_scheduler(scheduler.clone());
+
+    let cell = timeline.cell(1);
+    let changes = Arc::new(AtomicUsize::new(0));
+
+    let parity = {
+        let cell = cell.clone();
+        timeline.derived_with_eq(move || cell.get() % 2)
+    };
+
+    let subscription = {
+        let changes = changes.clone();
+        parity.subscribe(move || {
+            changes.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    cell.set(1);
+    scheduler.flush();
+    assert_eq!(changes.load(Ordering::SeqCst), 0, "writing the same value");
+
+    cell.set(3);
+    scheduler.flush();
+    assert_eq!(
+        changes.load(Ordering::SeqCst),
+        0,
+        "recomputing an equal value"
+    );
+
+    cell.set(4);
+    cell.set(6);
+    scheduler.flush();
+    assert_eq!(changes.load(Ordering::SeqCst), 1, "one flush, one change");
+
+    cell.set(7);
+    assert_eq!(
+        changes.load(Ordering::SeqCst),
+        1,
+        "nothing fires before a flush"
+    );
+    scheduler.flush();
+    assert_eq!(changes.load(Ordering::SeqCst), 2);
+
+    drop(subscription);
+    cell.set(8);
+    scheduler.flush();
+    assert_eq!(changes.load(Ordering::SeqCst), 2);
+    assert_eq!(timeline.subscription_count(), 0);
+}
+
+#[test]
+fn derived_subscriptions_fire_for_every_recomputation_without_eq() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let changes = Arc::new(AtomicUsize::new(0));
+
+    let parity = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() % 2)
+    };
+
+    let _subscription = {
+        let changes = changes.clone();
+        parity.subscribe(move || {
+            changes.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+
+    cell.set(3);
+    cell.set(5);
+    assert_eq!(changes.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "sync")]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use everafter::{Cell, Derived, Effect, Timeline};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn handles_are_send_and_sync() {
+    assert_send_sync::<Cell<String>>();
+    assert_send_sync::<Derived<String>>();
+    assert_send_sync::<Effect>();
+}
+
+const WRITERS: usize = 4;
+const WRITES: usize = 10_000;
+
+#[test]
+fn concurrent_writers_and_a_validating_reader() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0usize);
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let doubled = {
+        let (cell, computations) = (cell.clone(), computations.clone());
+        timeline.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            cell.get() * 2
+        })
+    };
+
+    let done = Arc::new(AtomicBool::new(false));
+
+    let reader = {
+        let (doubled, done) = (doubled.clone(), done.clone());
+        thread::spawn(move || {
+            let mut last = doubled.revision();
+
+            while !done.load(Ordering::SeqCst) {
+                assert_eq!(doubled.get() % 2, 0);
+
+                let revision = doubled.revision();
+                assert!(revision >= last, "revisions never go backwards");
+                last = revision;
+            }
+        })
+    };
+
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|writer| {
+            let cell = cell.clone();
+            thread::spawn(move || {
+                for i in 1..=WRITES {
+                    cell.set_always(writer * WRITES + i);
+                }
+            })
+        })
+        .collect();
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    done.store(true, Ordering::SeqCst);
+    reader.join().unwrap();
+
+    assert_eq!(
+        doubled.get(),
+        cell.get() * 2,
+        "once the writers are done, the reader sees the last write"
+    );
+    assert!(computations.load(Ordering::SeqCst) <= WRITERS * WRITES + 2);
+}
+
+#[test]
+fn two_threads_read_and_write_shared_cells() {
+    let timeline = Timeline::new();
+    let shared = timeline.shared();
+    let (left, right) = (shared.cell(0usize), shared.cell(0usize));
+
+    let threads: Vec<_> = vec![(left.clone(), right.clone()), (right.clone(), left.clone())]
+        .into_iter()
+        .map(|(mine, theirs)| {
+            let shared = shared.clone();
+
+            thread::spawn(move || {
+                // each thread tracks into its own frame, so this derived only ever depends on
+                // the two cells
+                let total = {
+                    let (mine, theirs) = (mine.clone(), theirs.clone());
+                    shared.derived(move || mine.get() + theirs.get())
+                };
+
+                for i in 1..=WRITES {
+                    mine.set(i);
+                    assert!(total.get() >= i);
+                }
+
+                total
+            })
+        })
+        .collect();
+
+    let totals: Vec<Derived<usize>> = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect();
+
+    for total in totals {
+        assert_eq!(total.get(), 2 * WRITES);
+    }
+
+    assert_eq!(left.get() + right.get(), 2 * WRITES);
+}
+
+#[test]
+fn concurrent_updates_never_lose_an_increment() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(0usize);
+    let before = timeline.now();
+
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|_| {
+            let count = count.clone();
+            thread::spawn(move || {
+                for _ in 0..WRITES {
+                    count.update(|count| *count += 1);
+                }
+            })
+        })
+        .collect();
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    assert_eq!(count.get(), WRITERS * WRITES);
+    assert_eq!(
+        timeline.now().distance(&before),
+        (WRITERS * WRITES) as u64,
+        "every update is one write"
+    );
+}
+
+#[test]
+fn snapshots_read_a_consistent_frame_while_another_thread_writes() {
+    let timeline = Timeline::new();
+    let shared = timeline.shared();
+    let (credit, debit) = (shared.cell(0i64), shared.cell(0i64));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let writer = thread::spawn({
+        let (credit, debit, done) = (credit.clone(), debit.clone(), done.clone());
+        let shared = shared.clone();
+
+        move || {
+            for i in 1..=WRITES as i64 {
+                shared.batch(|| {
+                    credit.set(i);
+                    debit.set(-i);
+                });
+            }
+
+            done.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let mut frames = 0;
+    while !done.load(Ordering::SeqCst) || frames == 0 {
+        let frame = shared.snapshot();
+        let credit = credit.get_at(&frame).unwrap();
+        let debit = debit.get_at(&frame).unwrap();
+
+        assert_eq!(credit + debit, 0, "a frame never sees half of a batch");
+        frames += 1;
+    }
+
+    writer.join().unwrap();
+    assert_eq!(credit.get(), WRITES as i64);
+}
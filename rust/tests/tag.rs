@@ -0,0 +1,181 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{ComputeStack, Revision, Tag, Timeline, ValidationMode};
+
+#[test]
+fn a_combined_revision_is_the_newest_member() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let external = timeline.external_tag();
+
+    let doubled = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2)
+    };
+
+    let combined = Tag::combine(&[cell.tag().unwrap(), external.tag(), doubled.tag()]);
+    assert_eq!(combined.tags().len(), 3);
+
+    external.bump();
+    assert_eq!(combined.revision(), external.revision());
+
+    cell.set(2);
+    assert_eq!(combined.revision(), cell.revision());
+    assert_eq!(doubled.revision(), cell.revision());
+
+    assert_eq!(Tag::combine(&[]).revision(), Revision::CONSTANT);
+}
+
+#[test]
+fn bumping_any_member_invalidates_a_dependent() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let external = timeline.external_tag().named("socket");
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let combined = Tag::combine(&[cell.tag().unwrap(), external.tag()]);
+
+    let composite = {
+        let (combined, computations) = (combined.clone(), computations.clone());
+        timeline.derived(move || {
+            combined.track();
+            computations.fetch_add(1, Ordering::SeqCst)
+        })
+    };
+
+    assert_eq!(composite.get(), 0);
+    assert_eq!(composite.get(), 0);
+
+    external.bump();
+    assert_eq!(composite.get(), 1);
+    assert_eq!(
+        composite.last_invalidation().unwrap().cause().label(),
+        "socket"
+    );
+
+    cell.set(2);
+    assert_eq!(composite.get(), 2);
+
+    let (_, dependencies) = ComputeStack::track(|| combined.track());
+    assert_eq!(dependencies.len(), 2);
+}
+
+#[test]
+fn constants_have_no_tag() {
+    let timeline = Timeline::new();
+    assert!(timeline.constant(1).tag().is_none());
+}
+
+#[test]
+fn a_constant_tag_never_changes() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let constant = Tag::constant();
+    assert_eq!(constant.revision(), Revision::CONSTANT);
+
+    let (_, dependencies) = ComputeStack::track(|| constant.track());
+    assert!(
+        dependencies.is_empty(),
+        "tracking a constant records nothing"
+    );
+
+    let tag = cell.tag().unwrap().max(&constant);
+    cell.set(2);
+    assert_eq!(tag.revision(), cell.revision());
+}
+
+#[test]
+fn a_combined_tag_is_a_single_dependency() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let external = timeline.external_tag();
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let doubled = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 2)
+    };
+
+    let combined = Tag::combine(&[doubled.tag(), external.tag()]).tag();
+
+    let (_, dependencies) = ComputeStack::track(|| combined.track());
+    assert_eq!(dependencies.len(), 1);
+
+    let composite = {
+        let (combined, computations) = (combined.clone(), computations.clone());
+        timeline.derived(move || {
+            combined.track();
+            computations.fetch_add(1, Ordering::SeqCst)
+        })
+    };
+
+    assert_eq!(composite.get(), 0);
+    external.bump();
+    assert_eq!(composite.get(), 1);
+
+    // validating the combined tag brings the derived member up to date
+    cell.set(2);
+    assert_eq!(combined.revision(), cell.revision());
+    assert_eq!(doubled.revision(), cell.revision());
+    assert_eq!(composite.get(), 2);
+    assert_eq!(composite.get(), 2);
+}
+
+#[test]
+fn combined_tags_nest() {
+    let timeline = Timeline::new();
+    let (x, y, z) = (timeline.cell(1), timeline.cell(2), timeline.cell(3));
+
+    let xy = Tag::combine(&[x.tag().unwrap(), y.tag().unwrap()]).tag();
+    let xyz = xy.max(&z.tag().unwrap());
+
+    z.set(30);
+    assert_eq!(xyz.revision(), z.revision());
+    assert!(xy.revision() < z.revision(), "z isn't a member of xy");
+
+    y.set(20);
+    assert_eq!(xyz.revision(), y.revision());
+    assert_eq!(xy.revision(), y.revision());
+}
+
+#[test]
+fn an_empty_combined_tag_is_constant() {
+    let timeline = Timeline::new();
+    let empty = Tag::combine(&[]).tag();
+    assert_eq!(empty.revision(), Revision::CONSTANT);
+
+    let derived = {
+        let empty = empty.clone();
+        timeline.derived(move || empty.track())
+    };
+
+    derived.get();
+    assert!(derived.is_const());
+
+    timeline.cell(0).set(1);
+    assert_eq!(empty.revision(), Revision::CONSTANT);
+}
+
+#[test]
+fn eager_validation_marks_through_a_combined_tag() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let cell = timeline.cell(1);
+    let unrelated = timeline.cell(1);
+
+    let combined = Tag::combine(&[cell.tag().unwrap(), Tag::constant()]).tag();
+    let reader = {
+        let combined = combined.clone();
+        timeline.derived(move || combined.track())
+    };
+
+    reader.get();
+    unrelated.set(2);
+    assert!(!reader.is_stale());
+
+    cell.set(2);
+    assert!(reader.is_stale());
+}
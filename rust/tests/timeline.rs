@@ -0,0 +1,90 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use everafter::Timeline;
+
+#[test]
+fn timelines_advance_independently() {
+    let first = Timeline::new();
+    let second = Timeline::new();
+
+    let a = first.cell(0);
+    let b = second.cell(0);
+
+    let start = second.now();
+
+    for i in 1..=5 {
+        a.set(i);
+    }
+
+    assert_eq!(second.now(), start, "writes on the first timeline");
+    assert!(first.now() > second.now());
+
+    b.set(1);
+    assert!(second.now() > start);
+}
+
+#[test]
+fn writes_never_invalidate_computations_on_another_timeline() {
+    let first = Timeline::new();
+    let second = Timeline::new();
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let title = first.cell("untitled");
+    let other = second.cell("other");
+
+    let heading = {
+        let (title, computations) = (title.clone(), computations.clone());
+        first.derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            title.get().to_uppercase()
+        })
+    };
+
+    assert_eq!(heading.get(), "UNTITLED");
+
+    other.set("changed");
+    assert!(!heading.is_stale());
+    assert_eq!(heading.get(), "UNTITLED");
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+
+    title.set("draft");
+    assert_eq!(heading.get(), "DRAFT");
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn the_default_timeline_is_shared_on_its_thread() {
+    let before = Timeline::with_default(|timeline| timeline.now());
+    let cell = Timeline::with_default(|timeline| timeline.cell(1));
+
+    cell.set(2);
+    assert!(Timeline::with_default(|timeline| timeline.now()) > before);
+
+    let elsewhere = thread::spawn(|| Timeline::with_default(|timeline| timeline.now()))
+        .join()
+        .unwrap();
+    assert_eq!(
+        elsewhere,
+        Timeline::new().now(),
+        "every thread starts its own"
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "which belongs to another timeline")]
+fn reading_a_value_from_another_timeline_panics() {
+    let first = Timeline::new();
+    let second = Timeline::new();
+
+    let foreign = second.cell(1).named("foreign");
+    let derived = first.derived(move || foreign.get());
+
+    derived.get();
+}
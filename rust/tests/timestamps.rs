@@ -0,0 +1,121 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use everafter::{Clock, Timeline};
+
+/**
+ * A clock that only moves when `advance` is called.
+ */
+#[derive(Clone)]
+struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    fn install(timeline: &Timeline) -> MockClock {
+        let clock = MockClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        };
+        timeline.set_clock(clock.clone());
+        clock
+    }
+
+    fn advance(&self, millis: u64) {
+        *self.now.lock().unwrap() += Duration::from_millis(millis);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[test]
+fn each_revision_is_stamped_with_the_time_it_was_published() {
+    let timeline = Timeline::new();
+    let clock = MockClock::install(&timeline);
+    let cell = timeline.cell(0);
+    let initial = timeline.now();
+
+    cell.set(1);
+    let first = timeline.now();
+    clock.advance(10);
+    cell.set(2);
+
+    let first = timeline.revision_time(first).unwrap();
+    let second = timeline.revision_time(timeline.now()).unwrap();
+    assert_eq!(second - first, Duration::from_millis(10));
+
+    timeline.batch(|| {
+        cell.set(3);
+        assert_eq!(timeline.revision_time(cell.revision()), None);
+    });
+    assert!(timeline.revision_time(timeline.now()).is_some());
+
+    for i in 0..300 {
+        cell.set(4 + i);
+    }
+    assert_eq!(timeline.revision_time(initial), None);
+}
+
+#[test]
+fn a_derived_is_stale_since_the_first_write_to_what_it_read() {
+    let timeline = Timeline::new();
+    let clock = MockClock::install(&timeline);
+    let count = timeline.cell(1);
+    let unrelated = timeline.cell(0);
+
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+    let label = {
+        let doubled = doubled.clone();
+        timeline.derived(move || format!("{}", doubled.get()))
+    };
+
+    assert_eq!(label.get(), "2");
+    assert_eq!(label.staleness_age(), None);
+
+    clock.advance(5);
+    unrelated.set(1);
+    assert_eq!(label.staleness_age(), None);
+
+    clock.advance(10);
+    count.set(2);
+    clock.advance(20);
+    count.set(3);
+    clock.advance(30);
+
+    assert_eq!(label.staleness_age(), Some(Duration::from_millis(50)));
+    assert_eq!(doubled.staleness_age(), Some(Duration::from_millis(50)));
+
+    assert_eq!(label.get(), "6");
+    assert_eq!(label.staleness_age(), None);
+    assert_eq!(doubled.staleness_age(), None);
+}
+
+#[test]
+fn a_derived_computed_before_the_remembered_revisions_has_no_age() {
+    let timeline = Timeline::new();
+    MockClock::install(&timeline);
+    let count = timeline.cell(0);
+    let unrelated = timeline.cell(0);
+
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+    doubled.get();
+
+    for i in 0..300 {
+        unrelated.set(i + 1);
+    }
+    count.set(1);
+
+    assert!(doubled.is_stale());
+    assert_eq!(doubled.staleness_age(), None);
+}
@@ -0,0 +1,139 @@
+use everafter::{
+    trace::{compare, DivergenceKind, Trace, TraceEvent, TraceObserver},
+    Timeline,
+};
+
+/**
+ * A counter, its double and an effect that reads the double, written twice and then torn down.
+ */
+fn counter() -> Trace {
+    let timeline = Timeline::new();
+    let observer = TraceObserver::new();
+    timeline.set_observer(Box::new(observer.clone()));
+
+    let count = timeline.cell(1);
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+    assert_eq!(doubled.get(), 2);
+
+    let effect = {
+        let doubled = doubled.clone();
+        timeline.effect(move || {
+            doubled.get();
+        })
+    };
+
+    count.set(2);
+    count.set(3);
+    assert_eq!(doubled.get(), 6);
+
+    drop(effect);
+    drop(doubled);
+    observer.trace()
+}
+
+const COUNTER: &str = include_str!("fixtures/trace/counter.jsonl");
+// the same program, traced by an implementation that reports an effect before its first run
+const COUNTER_PEER: &str = include_str!("fixtures/trace/counter.peer.jsonl");
+// an implementation that didn't recompute the double after the second write
+const COUNTER_STALE: &str = include_str!("fixtures/trace/counter.stale.jsonl");
+
+#[test]
+fn a_recorded_trace_round_trips_through_json_lines() {
+    let trace = counter();
+    assert_eq!(trace.to_json_lines(), COUNTER);
+    assert_eq!(Trace::parse(COUNTER).unwrap(), trace);
+}
+
+#[test]
+fn traces_that_only_order_recomputations_differently_match() {
+    let peer = Trace::parse(COUNTER_PEER).unwrap();
+    assert_ne!(peer, counter());
+    assert_eq!(compare(&counter(), &peer), vec![]);
+}
+
+#[test]
+fn a_missing_recomputation_is_reported_at_the_node_and_step() {
+    let stale = Trace::parse(COUNTER_STALE).unwrap();
+    let divergences = compare(&counter(), &stale);
+
+    assert_eq!(divergences.len(), 1, "{:?}", divergences);
+    let divergence = &divergences[0];
+    assert_eq!(divergence.node(), Some("derived/0"));
+    assert_eq!(
+        divergence.kind(),
+        &DivergenceKind::Recomputes { a: 3, b: 2 }
+    );
+    assert_eq!(divergence.steps(), (Some(12), None));
+    assert_eq!(
+        divergence.to_string(),
+        "derived/0 recomputed 3 times in a and 2 times in b"
+    );
+}
+
+#[test]
+fn the_first_out_of_order_event_is_reported_with_a_different_final_revision() {
+    let a = Trace::parse(
+        r#"{"step":0,"event":"created","node":"cell/0"}
+{"step":1,"event":"written","node":"cell/0","from":1,"to":2}
+{"step":2,"event":"dropped","node":"cell/0"}"#,
+    )
+    .unwrap();
+    let b = Trace::parse(
+        r#"{"step":0,"event":"created","node":"cell/0"}
+{"step":1,"event":"flush_scheduled"}
+{"step":2,"event":"dropped","node":"cell/0"}"#,
+    )
+    .unwrap();
+
+    let divergences = compare(&a, &b);
+    assert_eq!(divergences.len(), 2, "{:?}", divergences);
+
+    assert_eq!(divergences[0].node(), Some("cell/0"));
+    assert_eq!(divergences[0].steps(), (Some(1), Some(2)));
+    assert_eq!(
+        divergences[0].kind(),
+        &DivergenceKind::Order {
+            a: Some(TraceEvent::Written {
+                node: "cell/0".into(),
+                from: 1,
+                to: 2
+            }),
+            b: Some(TraceEvent::Dropped {
+                node: "cell/0".into()
+            }),
+        }
+    );
+
+    assert_eq!(
+        divergences[1].kind(),
+        &DivergenceKind::FinalRevision {
+            a: Some(2),
+            b: None
+        }
+    );
+    assert_eq!(divergences[1].steps(), (Some(1), None));
+}
+
+#[test]
+fn parsing_reports_the_line_of_a_bad_event() {
+    let error = Trace::parse(
+        r#"{"step":0,"event":"created","node":"cell/0"}
+
+{"step":2,"event":"created","node":"cell/1"}"#,
+    )
+    .unwrap_err();
+    assert_eq!(error.line(), 3);
+    assert_eq!(
+        error.to_string(),
+        "line 3 of the trace: expected step 1, found step 2"
+    );
+
+    let error = Trace::parse(r#"{"step":0,"event":"renamed","node":"cell/0"}"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        r#"line 1 of the trace: unknown event "renamed""#
+    );
+}
@@ -0,0 +1,112 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use everafter::{timeline::Dependencies, tracked_async, ComputeStack, Timeline};
+
+/**
+ * Returns `Pending` the first time it is polled, like a fetch that isn't done yet.
+ */
+#[derive(Default)]
+struct Fetch {
+    polled: bool,
+}
+
+impl Future for Fetch {
+    type Output = &'static str;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<&'static str> {
+        if self.polled {
+            Poll::Ready("response")
+        } else {
+            self.polled = true;
+            Poll::Pending
+        }
+    }
+}
+
+fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+    Pin::new(future).poll(&mut Context::from_waker(Waker::noop()))
+}
+
+#[test]
+fn reads_on_both_sides_of_an_await_are_tracked() {
+    let timeline = Timeline::new();
+    let url = timeline.cell("/users");
+    let format = timeline.cell("json");
+    let unrelated = timeline.cell(0);
+
+    let mut future = tracked_async(|| {
+        let (url, format) = (url.clone(), format.clone());
+
+        async move {
+            let url = url.get();
+            let response = Fetch::default().await;
+            format!("{} {} as {}", url, response, format.get())
+        }
+    });
+
+    assert!(poll(&mut future).is_pending());
+
+    // reads between polls belong to whoever is reading, not to the future
+    let ((), outer) = ComputeStack::track(|| {
+        unrelated.get();
+    });
+    assert_eq!(outer.len(), 1);
+
+    let (body, dependencies): (String, Dependencies) = match poll(&mut future) {
+        Poll::Ready(ready) => ready,
+        Poll::Pending => panic!("the fetch finishes on the second poll"),
+    };
+
+    assert_eq!(body, "/users response as json");
+    assert_eq!(dependencies.len(), 2);
+
+    let revision = dependencies.revision();
+    unrelated.set(1);
+    assert_eq!(dependencies.revision(), revision);
+
+    format.set("xml");
+    assert!(
+        dependencies.revision() > revision,
+        "the read after the await is tracked"
+    );
+
+    let revision = dependencies.revision();
+    url.set("/posts");
+    assert!(
+        dependencies.revision() > revision,
+        "the read before the await is tracked"
+    );
+}
+
+#[test]
+fn polling_inside_a_frame_does_not_leak_reads() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let mut future = tracked_async(|| {
+        let cell = cell.clone();
+        async move {
+            Fetch::default().await;
+            cell.get()
+        }
+    });
+
+    let (first, outer) = ComputeStack::track(|| poll(&mut future));
+    assert!(first.is_pending());
+    assert!(outer.is_empty());
+
+    let (second, outer) = ComputeStack::track(|| poll(&mut future));
+    assert!(outer.is_empty(), "the future's reads stay in its own frame");
+
+    match second {
+        Poll::Ready((value, dependencies)) => {
+            assert_eq!(value, 1);
+            assert_eq!(dependencies.len(), 1);
+        }
+        Poll::Pending => panic!("the fetch finishes on the second poll"),
+    }
+}
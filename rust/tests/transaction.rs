@@ -0,0 +1,181 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use everafter::Timeline;
+
+#[test]
+fn transaction_recomputes_once() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(2);
+    let c = timeline.cell(3);
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let sum = {
+        let (a, b, c) = (a.clone(), b.clone(), c.clone());
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            a.get() + b.get() + c.get()
+        })
+    };
+
+    assert_eq!(sum.get(), 6);
+
+    timeline.transaction(|_| {
+        a.set(10);
+        b.set(20);
+        c.set(30);
+    });
+
+    assert_eq!(sum.get(), 60);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn transaction_writes_share_a_revision() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(2);
+
+    let revision = timeline.transaction(|transaction| {
+        assert_eq!(transaction.revision(), None, "no writes yet");
+
+        a.set(10);
+        timeline.transaction(|_| b.set(20));
+
+        transaction.revision()
+    });
+
+    assert_eq!(Some(a.revision()), revision);
+    assert_eq!(Some(b.revision()), revision, "nested transactions coalesce");
+}
+
+#[test]
+fn transaction_commits_on_panic() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(2);
+
+    let sum = {
+        let (a, b) = (a.clone(), b.clone());
+        timeline.derived(move || a.get() + b.get())
+    };
+
+    assert_eq!(sum.get(), 3);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        timeline.transaction(|_| {
+            a.set(10);
+            b.set(20);
+            panic!("failed halfway through a transaction");
+        })
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(a.revision(), b.revision());
+    assert_eq!(sum.get(), 30, "writes before the panic are committed");
+
+    let revision = a.revision();
+    a.set(100);
+    assert!(
+        a.revision() > revision,
+        "the timeline keeps advancing afterwards"
+    );
+}
+
+#[test]
+fn batch_recomputes_once() {
+    let timeline = Timeline::new();
+    let a = timeline.cell(1);
+    let b = timeline.cell(2);
+    let c = timeline.cell(3);
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let product = {
+        let (a, b, c) = (a.clone(), b.clone(), c.clone());
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            a.get() * b.get() * c.get()
+        })
+    };
+
+    assert_eq!(product.get(), 6);
+
+    timeline.batch(|| {
+        a.set(2);
+        timeline.batch(|| b.set(3));
+        c.set(4);
+    });
+
+    assert_eq!(product.get(), 24);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    assert_eq!(a.revision(), b.revision());
+    assert_eq!(
+        b.revision(),
+        c.revision(),
+        "nested batches bump the revision once"
+    );
+    assert_eq!(
+        timeline.cell(0).revision(),
+        c.revision(),
+        "the batch's revision is the timeline's current revision"
+    );
+}
+
+#[test]
+fn validate_on_read_panics_at_a_stale_read_inside_a_transaction() {
+    let timeline = Timeline::new();
+    timeline.set_validate_on_read(true);
+
+    let count = timeline.cell(1).named("count");
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2).named("doubled")
+    };
+    let label = {
+        let doubled = doubled.clone();
+        timeline.derived(move || format!("{}!", doubled.get()))
+    };
+    assert_eq!(label.get(), "2!");
+
+    let stale = catch_unwind(AssertUnwindSafe(|| {
+        timeline.batch(|| {
+            count.set(2);
+            label.get();
+        })
+    }));
+
+    let message = stale.unwrap_err();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(
+        message.contains("doubled was computed at") && message.contains("count changed at"),
+        "{}",
+        message
+    );
+    assert_eq!(label.get(), "4!");
+}
+
+#[test]
+fn reads_inside_a_transaction_see_the_values_from_before_it() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1);
+    let doubled = {
+        let count = count.clone();
+        timeline.derived(move || count.get() * 2)
+    };
+    assert_eq!(doubled.get(), 2);
+
+    timeline.batch(|| {
+        count.set(2);
+        assert_eq!(doubled.get(), 2, "the write isn't observed yet");
+    });
+
+    assert_eq!(doubled.get(), 4);
+}
@@ -0,0 +1,157 @@
+use std::{
+    num::ParseIntError,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use everafter::{ComputeStack, Timeline};
+
+#[test]
+fn errors_are_cached_until_a_dependency_changes() {
+    let timeline = Timeline::new();
+    let input = timeline.cell("one");
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let parsed = {
+        let (input, computations) = (input.clone(), computations.clone());
+        timeline.try_derived(move || {
+            computations.fetch_add(1, Ordering::SeqCst);
+            input.get().parse::<u32>()
+        })
+    };
+
+    let first = parsed.get().unwrap_err();
+    let second = parsed.get().unwrap_err();
+    assert!(Arc::ptr_eq(&first, &second), "the error wasn't cloned");
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+
+    input.set("1");
+    assert_eq!(parsed.get().unwrap(), 1);
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn try_map_short_circuits_on_errors() {
+    let timeline = Timeline::new();
+    let input = timeline.cell("2");
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let parsed = {
+        let input = input.clone();
+        timeline.try_derived(move || input.get().parse::<u32>())
+    };
+
+    let halved = {
+        let calls = calls.clone();
+        parsed.try_map(move |n| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            if n % 2 == 0 {
+                Ok(n / 2)
+            } else {
+                "odd".parse::<u32>()
+            }
+        })
+    };
+
+    assert_eq!(halved.get().unwrap(), 1);
+
+    input.set("two");
+    let error = halved.get().unwrap_err();
+    assert!(Arc::ptr_eq(&error, &parsed.get().unwrap_err()));
+    assert_eq!(calls.load(Ordering::SeqCst), 1, "the error skipped `f`");
+
+    input.set("3");
+    assert!(halved.get().is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn with_reads_without_cloning() {
+    let timeline = Timeline::new();
+    let input = timeline.cell("7");
+
+    let parsed = {
+        let input = input.clone();
+        timeline.try_derived(move || input.get().parse::<u32>())
+    };
+
+    let (is_ok, dependencies) = ComputeStack::track(|| parsed.with(|result| result.is_ok()));
+    assert!(is_ok);
+    assert_eq!(dependencies.len(), 1);
+
+    let describe = |result: &Result<u32, Arc<ParseIntError>>| match result {
+        Ok(n) => n.to_string(),
+        Err(error) => error.to_string(),
+    };
+
+    input.set("");
+    assert_eq!(
+        parsed.with(describe),
+        "cannot parse integer from empty string"
+    );
+}
+
+#[test]
+fn a_panicking_computation_poisons_its_derived() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let exploding = {
+        let cell = cell.clone();
+        timeline.derived(move || -> i32 {
+            if cell.get() > 1 {
+                panic!("too big");
+            }
+
+            cell.get()
+        })
+    };
+
+    let outer = {
+        let exploding = exploding.clone();
+        timeline.derived(move || exploding.get() + 1)
+    };
+
+    assert_eq!(outer.get(), 2);
+    cell.set(2);
+
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| outer.get()));
+    assert!(panicked.is_err());
+    assert!(!ComputeStack::is_tracking(), "every frame was popped");
+
+    let again = panic::catch_unwind(AssertUnwindSafe(|| exploding.get())).unwrap_err();
+    let message = again.downcast_ref::<String>().unwrap();
+    assert!(message.contains("was poisoned by a panic in its computation: too big"));
+}
+
+#[test]
+fn unrelated_computations_keep_working_after_a_poison() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let poisoned = timeline.derived(|| -> i32 { panic!("broken") });
+    let healthy = {
+        let cell = cell.clone();
+        timeline.derived(move || cell.get() * 10)
+    };
+
+    let (_, dependencies) = ComputeStack::track(|| {
+        let caught = panic::catch_unwind(AssertUnwindSafe(|| poisoned.get()));
+        assert!(caught.is_err());
+
+        healthy.get()
+    });
+
+    assert_eq!(
+        dependencies.len(),
+        1,
+        "the enclosing frame still records reads"
+    );
+    assert_eq!(healthy.get(), 10);
+
+    cell.set(2);
+    assert_eq!(healthy.get(), 20);
+}
@@ -0,0 +1,112 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{untrack, ComputeStack, Timeline};
+
+#[test]
+fn untracked_reads_do_not_invalidate() {
+    let timeline = Timeline::new();
+    let tracked = timeline.cell(1);
+    let ignored = timeline.cell(100);
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let derived = {
+        let (tracked, ignored) = (tracked.clone(), ignored.clone());
+        let count = count.clone();
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            tracked.get() + untrack(|| ignored.get())
+        })
+    };
+
+    assert_eq!(derived.get(), 101);
+
+    ignored.set(200);
+    assert_eq!(derived.get(), 101, "the untracked read is not a dependency");
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    tracked.set(2);
+    assert_eq!(derived.get(), 202);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn untrack_restores_tracking() {
+    let timeline = Timeline::new();
+    let before = timeline.cell(1);
+    let during = timeline.cell(2);
+    let after = timeline.cell(3);
+
+    let (_, dependencies) = ComputeStack::track(|| {
+        before.get();
+
+        untrack(|| {
+            assert!(!ComputeStack::is_tracking());
+            during.get();
+        });
+
+        assert!(ComputeStack::is_tracking());
+        after.get();
+    });
+
+    assert_eq!(dependencies.len(), 2);
+}
+
+#[test]
+fn tracked_frames_inside_untrack_record_reads() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let (inner, outer) = ComputeStack::track(|| untrack(|| ComputeStack::track(|| cell.get()).1));
+
+    assert!(outer.is_empty());
+    assert_eq!(inner.len(), 1);
+}
+
+#[test]
+fn deriveds_read_inside_untracked_blocks_track_their_own_reads() {
+    let timeline = Timeline::new();
+    let tracked = timeline.cell(1);
+    let peeked = timeline.cell(10);
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let inner = {
+        let peeked = peeked.clone();
+        timeline.derived(move || peeked.get() * 2)
+    };
+
+    let outer = {
+        let (tracked, inner, count) = (tracked.clone(), inner.clone(), count.clone());
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            tracked.get() + untrack(|| inner.get())
+        })
+    };
+
+    assert_eq!(outer.get(), 21);
+
+    peeked.set(20);
+    assert_eq!(
+        outer.get(),
+        21,
+        "the untracked read is not a dependency of outer"
+    );
+    assert_eq!(inner.get(), 40, "but inner still tracked its own read");
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    tracked.set(2);
+    assert_eq!(outer.get(), 42);
+}
+
+#[test]
+fn timeline_untracked_hides_reads() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    let (value, dependencies) = ComputeStack::track(|| timeline.untracked(|| cell.get()));
+
+    assert_eq!(value, 1);
+    assert!(dependencies.is_empty());
+}
@@ -0,0 +1,167 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Cell, Derived, Timeline, ValidationMode};
+
+fn sum(
+    timeline: &Timeline,
+    cells: Vec<Cell<i32>>,
+    computations: &Arc<AtomicUsize>,
+) -> Derived<i32> {
+    let computations = computations.clone();
+
+    timeline.derived(move || {
+        computations.fetch_add(1, Ordering::SeqCst);
+        cells.iter().map(Cell::get).sum()
+    })
+}
+
+#[test]
+fn timelines_are_lazy_by_default() {
+    let timeline = Timeline::new();
+    assert_eq!(timeline.validation_mode(), ValidationMode::Lazy);
+
+    let cell = timeline.cell(1);
+    let computations = Arc::new(AtomicUsize::new(0));
+    let total = sum(&timeline, vec![cell.clone()], &computations);
+
+    assert!(total.is_stale(), "a derived that never ran is stale");
+    assert_eq!(total.get(), 1);
+    assert!(!total.is_stale());
+
+    cell.set(2);
+    assert!(total.is_stale());
+    assert_eq!(
+        computations.load(Ordering::SeqCst),
+        1,
+        "is_stale doesn't recompute"
+    );
+
+    assert_eq!(total.get(), 2);
+    assert!(!total.is_stale());
+}
+
+#[test]
+fn eager_writes_mark_every_dependent_dirty() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+
+    let (a, b) = (timeline.cell(1), timeline.cell(2));
+    let computations = Arc::new(AtomicUsize::new(0));
+    let left = sum(&timeline, vec![a.clone()], &computations);
+    let right = sum(&timeline, vec![b.clone()], &computations);
+    let top = {
+        let (left, right) = (left.clone(), right.clone());
+        timeline.derived(move || left.get() + right.get())
+    };
+
+    assert_eq!(top.get(), 3);
+    assert!(!top.is_stale() && !left.is_stale() && !right.is_stale());
+
+    a.set(10);
+    assert!(left.is_stale());
+    assert!(top.is_stale(), "dirtiness propagates through deriveds");
+    assert!(!right.is_stale());
+    assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+    assert_eq!(top.get(), 12);
+    assert!(!top.is_stale() && !left.is_stale());
+}
+
+#[test]
+fn eager_mode_works_with_collections_and_transactions() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+
+    let list = timeline.vec(vec![1, 2]);
+    let length = {
+        let list = list.clone();
+        timeline.derived(move || list.len())
+    };
+
+    assert_eq!(length.get(), 2);
+
+    timeline.batch(|| {
+        list.push(3);
+        assert!(length.is_stale());
+    });
+
+    assert!(length.is_stale());
+    assert_eq!(length.get(), 3);
+    assert!(!length.is_stale());
+}
+
+#[test]
+fn deriveds_from_a_lazy_period_are_not_trusted_in_eager_mode() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+    let computations = Arc::new(AtomicUsize::new(0));
+    let inner = sum(&timeline, vec![cell.clone()], &computations);
+
+    assert_eq!(inner.get(), 1);
+
+    timeline.set_validation_mode(ValidationMode::Eager);
+    let outer = {
+        let inner = inner.clone();
+        timeline.derived(move || inner.get() * 2)
+    };
+    assert_eq!(outer.get(), 2);
+
+    // `inner` recorded no reverse edges, so the write can't reach `outer` eagerly
+    cell.set(5);
+    assert!(inner.is_stale());
+    assert!(outer.is_stale());
+
+    assert_eq!(outer.get(), 10);
+
+    timeline.set_validation_mode(ValidationMode::Lazy);
+    cell.set(6);
+    timeline.set_validation_mode(ValidationMode::Eager);
+    assert!(outer.is_stale());
+    assert_eq!(outer.get(), 12);
+}
+
+#[test]
+fn flush_dirty_recomputes_each_derived_in_a_diamond_once() {
+    let timeline = Timeline::new();
+    timeline.set_validation_mode(ValidationMode::Eager);
+
+    let top = timeline.cell(1);
+    let left = {
+        let top = top.clone();
+        timeline.derived(move || top.get() + 1)
+    };
+    let right = {
+        let top = top.clone();
+        timeline.derived(move || top.get() * 2)
+    };
+    let bottom = {
+        let (left, right) = (left.clone(), right.clone());
+        timeline.derived(move || left.get() + right.get())
+    };
+    assert_eq!(bottom.get(), 4);
+
+    let runs = || [&left, &right, &bottom].map(|derived| derived.stats().runs());
+    assert_eq!(runs(), [1, 1, 1]);
+
+    top.set(5);
+    timeline.flush_dirty(&[top.tag().unwrap()]);
+    assert_eq!(runs(), [2, 2, 2], "every derived ran exactly once");
+
+    assert_eq!(bottom.get(), 16);
+    assert_eq!(runs(), [2, 2, 2], "reading afterwards doesn't recompute");
+
+    timeline.flush_dirty(&[top.tag().unwrap()]);
+    assert_eq!(runs(), [2, 2, 2], "nothing changed since the last flush");
+}
+
+#[test]
+#[should_panic(expected = "ValidationMode::Eager")]
+fn flush_dirty_needs_reverse_edges() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(1);
+
+    timeline.flush_dirty(&[cell.tag().unwrap()]);
+}
@@ -0,0 +1,219 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use everafter::{Derived, Timeline, TrackedVec};
+
+mod common;
+use common::{counter, runs};
+
+fn watch(
+    timeline: &Timeline,
+    list: &TrackedVec<i32>,
+    index: usize,
+) -> (Derived<Option<i32>>, Arc<AtomicUsize>) {
+    let count = counter();
+
+    let derived = {
+        let (list, count) = (list.clone(), count.clone());
+        timeline.derived(move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            list.get(index)
+        })
+    };
+
+    (derived, count)
+}
+
+#[test]
+fn indices_invalidate_independently() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![0, 1, 2, 3, 4, 5]);
+
+    let (first, first_runs) = watch(&timeline, &list, 0);
+    let (last, last_runs) = watch(&timeline, &list, 5);
+
+    assert_eq!(first.get(), Some(0));
+    assert_eq!(last.get(), Some(5));
+
+    list.set(5, 50);
+    assert_eq!(first.get(), Some(0));
+    assert_eq!(last.get(), Some(50));
+    assert_eq!(runs(&first_runs), 1, "index 0 wasn't written");
+    assert_eq!(runs(&last_runs), 2);
+
+    list.set(0, 10);
+    assert_eq!(first.get(), Some(10));
+    assert_eq!(last.get(), Some(50));
+    assert_eq!(runs(&first_runs), 2);
+    assert_eq!(runs(&last_runs), 2, "index 5 wasn't written");
+}
+
+#[test]
+fn pushing_only_invalidates_the_length() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2]);
+
+    let (first, first_runs) = watch(&timeline, &list, 0);
+    let (third, third_runs) = watch(&timeline, &list, 2);
+
+    let len = {
+        let list = list.clone();
+        timeline.derived(move || list.len())
+    };
+
+    assert_eq!(first.get(), Some(1));
+    assert_eq!(third.get(), None);
+    assert_eq!(len.get(), 2);
+
+    list.push(3);
+
+    assert_eq!(first.get(), Some(1));
+    assert_eq!(
+        third.get(),
+        Some(3),
+        "reads past the end depend on the length"
+    );
+    assert_eq!(len.get(), 3);
+    assert_eq!(runs(&first_runs), 1);
+    assert_eq!(runs(&third_runs), 2);
+}
+
+#[test]
+fn inserting_and_removing_shift_later_indices() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2, 3, 4]);
+
+    let (first, first_runs) = watch(&timeline, &list, 0);
+    let (third, third_runs) = watch(&timeline, &list, 2);
+
+    assert_eq!(first.get(), Some(1));
+    assert_eq!(third.get(), Some(3));
+
+    list.insert(1, 10);
+    assert_eq!(first.get(), Some(1));
+    assert_eq!(third.get(), Some(2));
+    assert_eq!(runs(&first_runs), 1, "index 0 is before the insertion");
+    assert_eq!(runs(&third_runs), 2);
+
+    assert_eq!(list.remove(1), 10);
+    assert_eq!(first.get(), Some(1));
+    assert_eq!(third.get(), Some(3));
+    assert_eq!(runs(&first_runs), 1);
+    assert_eq!(runs(&third_runs), 3);
+}
+
+#[test]
+fn shrinking_below_a_tracked_index() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2, 3]);
+
+    let (last, _) = watch(&timeline, &list, 2);
+    assert_eq!(last.get(), Some(3));
+
+    assert_eq!(list.pop(), Some(3));
+    assert_eq!(last.get(), None);
+
+    list.push(4);
+    assert_eq!(last.get(), Some(4));
+
+    list.remove(0);
+    assert_eq!(
+        last.get(),
+        None,
+        "removing an earlier element shrinks the vec too"
+    );
+    assert_eq!(list.len(), 2);
+}
+
+#[test]
+fn iteration_depends_on_the_length_and_visited_elements() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2, 3, 4]);
+    let sum_runs = counter();
+    let prefix_runs = counter();
+
+    let sum = {
+        let (list, sum_runs) = (list.clone(), sum_runs.clone());
+        timeline.derived(move || {
+            sum_runs.fetch_add(1, Ordering::SeqCst);
+            list.iter().sum::<i32>()
+        })
+    };
+
+    let prefix = {
+        let (list, prefix_runs) = (list.clone(), prefix_runs.clone());
+        timeline.derived(move || {
+            prefix_runs.fetch_add(1, Ordering::SeqCst);
+            list.iter().take(2).collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(sum.get(), 10);
+    assert_eq!(prefix.get(), vec![1, 2]);
+
+    list.set(3, 40);
+    assert_eq!(sum.get(), 46);
+    assert_eq!(prefix.get(), vec![1, 2]);
+    assert_eq!(runs(&prefix_runs), 1, "the prefix never visited index 3");
+
+    list.push(5);
+    assert_eq!(sum.get(), 51);
+    assert_eq!(prefix.get(), vec![1, 2]);
+    assert_eq!(runs(&sum_runs), 3);
+    assert_eq!(runs(&prefix_runs), 2, "iterators depend on the length");
+}
+
+#[test]
+fn iterating_sees_element_writes_that_reading_the_length_doesnt() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![1, 2, 3]);
+    let sum_runs = counter();
+    let len_runs = counter();
+
+    let sum = {
+        let (list, sum_runs) = (list.clone(), sum_runs.clone());
+        timeline.derived(move || {
+            sum_runs.fetch_add(1, Ordering::SeqCst);
+            list.iter().sum::<i32>()
+        })
+    };
+
+    let len = {
+        let (list, len_runs) = (list.clone(), len_runs.clone());
+        timeline.derived(move || {
+            len_runs.fetch_add(1, Ordering::SeqCst);
+            list.len()
+        })
+    };
+
+    assert_eq!((sum.get(), len.get()), (6, 3));
+
+    list.set(0, 10);
+    assert_eq!((sum.get(), len.get()), (15, 3));
+    assert_eq!((runs(&sum_runs), runs(&len_runs)), (2, 1));
+
+    list.push(4);
+    assert_eq!((sum.get(), len.get()), (19, 4));
+    assert_eq!((runs(&sum_runs), runs(&len_runs)), (3, 2));
+}
+
+#[test]
+fn vec_operations_mirror_vec() {
+    let timeline = Timeline::new();
+    let list = timeline.vec(vec![]);
+
+    assert!(list.is_empty());
+
+    list.push('b');
+    list.insert(0, 'a');
+    list.push('c');
+    assert_eq!(list.iter().collect::<String>(), "abc");
+
+    assert_eq!(list.remove(1), 'b');
+    assert_eq!(list.pop(), Some('c'));
+    assert_eq!(list.pop(), Some('a'));
+    assert_eq!(list.pop(), None);
+    assert_eq!(list.len(), 0);
+}
@@ -0,0 +1,81 @@
+use std::sync::{Arc, Mutex};
+
+use everafter::Timeline;
+
+#[test]
+fn a_watch_starts_with_the_current_value_and_skips_equal_ones() {
+    let timeline = Timeline::new();
+    let count = timeline.cell(1);
+    let parity = {
+        let count = count.clone();
+        timeline.derived(move || count.get() % 2)
+    };
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let watch = {
+        let seen = seen.clone();
+        parity.watch(move |parity| seen.lock().unwrap().push(*parity))
+    };
+
+    count.set(3);
+    count.set(4);
+    count.set(6);
+    assert_eq!(*seen.lock().unwrap(), [1, 0]);
+
+    watch.unwatch();
+    count.set(7);
+    assert_eq!(*seen.lock().unwrap(), [1, 0]);
+}
+
+#[test]
+fn three_writes_in_a_transaction_deliver_one_value() {
+    let timeline = Timeline::new();
+    let cell = timeline.cell(0);
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let _watch = {
+        let seen = seen.clone();
+        cell.watch(move |value| seen.lock().unwrap().push(*value))
+    };
+
+    timeline.transaction(|_| {
+        cell.set(1);
+        cell.set(2);
+        cell.set(3);
+    });
+
+    assert_eq!(*seen.lock().unwrap(), [0, 3]);
+}
+
+#[test]
+fn constant_cells_deliver_their_only_value() {
+    let timeline = Timeline::new();
+    let constant = timeline.constant(5);
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let _watch = {
+        let seen = seen.clone();
+        constant.watch(move |value| seen.lock().unwrap().push(*value))
+    };
+
+    assert_eq!(*seen.lock().unwrap(), [5]);
+}
+
+#[cfg(feature = "channel")]
+#[test]
+fn a_channel_receives_one_message_per_transaction() {
+    use std::sync::mpsc;
+
+    let timeline = Timeline::new();
+    let cell = timeline.cell(String::from("a"));
+    let (sender, receiver) = mpsc::channel();
+    let _watch = cell.watch_into(sender);
+
+    timeline.transaction(|_| {
+        cell.set(String::from("b"));
+        cell.set(String::from("c"));
+        cell.set(String::from("d"));
+    });
+
+    assert_eq!(receiver.try_iter().collect::<Vec<_>>(), ["a", "d"]);
+}
@@ -0,0 +1,112 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use everafter::{Cell, Timeline, WritableDerived};
+
+fn fahrenheit(timeline: &Timeline, celsius: &Cell<f64>) -> WritableDerived<f64> {
+    let (read, write) = (celsius.clone(), celsius.clone());
+    timeline
+        .derived_with_setter(
+            move || read.get() * 9.0 / 5.0 + 32.0,
+            move |fahrenheit| write.set((fahrenheit - 32.0) * 5.0 / 9.0),
+        )
+        .named("fahrenheit")
+}
+
+#[test]
+fn setting_writes_upstream_and_the_next_read_reflects_it() {
+    let timeline = Timeline::new();
+    let celsius = timeline.cell(100.0);
+    let fahrenheit = fahrenheit(&timeline, &celsius);
+    assert_eq!(fahrenheit.label(), "fahrenheit");
+    assert_eq!(fahrenheit.get(), 212.0);
+
+    fahrenheit.set(-40.0);
+    assert_eq!(celsius.get(), -40.0);
+    assert_eq!(fahrenheit.get(), -40.0);
+
+    celsius.set(0.0);
+    assert_eq!(fahrenheit.get(), 32.0);
+
+    let revision = fahrenheit.revision();
+    fahrenheit.set(32.0);
+    assert_eq!(
+        timeline.now(),
+        revision,
+        "the setter wrote the value celsius had"
+    );
+}
+
+#[test]
+fn a_write_that_leaves_the_computed_value_equal_doesnt_invalidate_readers() {
+    let timeline = Timeline::new();
+    let name = timeline.cell(String::from("alice"));
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let lowercase = {
+        let (read, write) = (name.clone(), name.clone());
+        timeline.derived_with_setter(
+            move || read.get().to_lowercase(),
+            move |value| write.set(value),
+        )
+    };
+
+    let greeting = {
+        let (lowercase, runs) = (lowercase.clone(), runs.clone());
+        timeline.derived(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            format!("hello {}", lowercase.get())
+        })
+    };
+
+    assert_eq!(greeting.get(), "hello alice");
+    let revision = lowercase.revision();
+
+    lowercase.set(String::from("ALICE"));
+    assert_eq!(name.get(), "ALICE");
+    assert_eq!(greeting.get(), "hello alice");
+    assert_eq!(lowercase.revision(), revision);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    lowercase.set(String::from("Bob"));
+    assert_eq!(greeting.get(), "hello bob");
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn an_effect_can_set_it_without_depending_on_what_the_setter_writes() {
+    let timeline = Timeline::new();
+    let celsius = timeline.cell(0.0);
+    let fahrenheit = fahrenheit(&timeline, &celsius);
+    let input = timeline.cell(50.0);
+    let runs = Arc::new(AtomicUsize::new(0));
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    let _bind = {
+        let (fahrenheit, input, runs) = (fahrenheit.clone(), input.clone(), runs.clone());
+        timeline.effect(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            fahrenheit.set(input.get());
+        })
+    };
+
+    let _display = {
+        let (fahrenheit, seen) = (fahrenheit.clone(), seen.clone());
+        timeline.effect(move || seen.lock().unwrap().push(fahrenheit.get()))
+    };
+
+    assert_eq!(celsius.get(), 10.0);
+
+    input.set(212.0);
+    assert_eq!(celsius.get(), 100.0);
+
+    celsius.set(0.0);
+    assert_eq!(*seen.lock().unwrap(), vec![50.0, 212.0, 32.0]);
+    assert_eq!(
+        runs.load(Ordering::SeqCst),
+        2,
+        "writing celsius didn't rerun the binding"
+    );
+}